@@ -1,5 +1,5 @@
 use std::{
-    collections::BTreeMap,
+    collections::{BTreeMap, BTreeSet},
     error::Error,
     fs::File,
     io::BufWriter,
@@ -8,40 +8,122 @@ use std::{
     sync::Arc,
 };
 
-use prjunnamed_generic::{chain_rebalance, tree_rebalance};
-use prjunnamed_netlist::{Design, Target};
-
-fn process(design: &mut Design) {
-    match design.target() {
-        None => {
-            prjunnamed_generic::decision(design);
-            prjunnamed_generic::canonicalize(design);
-            design.rewrite(&[
-                &prjunnamed_generic::LowerLt,
-                &prjunnamed_generic::LowerMul,
-                &prjunnamed_generic::LowerShift,
-            ]);
-            prjunnamed_generic::canonicalize(design);
-            design.rewrite(&[
-                &prjunnamed_generic::LowerEq,
-                &prjunnamed_generic::LowerMux,
-                &prjunnamed_generic::SimpleAigOpt,
-                &prjunnamed_generic::Normalize,
-            ]);
-            chain_rebalance(design);
-            prjunnamed_generic::canonicalize(design);
-            tree_rebalance(design);
+use prjunnamed_netlist::{Cell, Design, PassManager, Target};
+
+/// Extracts state machines like the `fsm` pass, and reports them.
+fn report_fsms(design: &mut Design) {
+    for fsm in prjunnamed_generic::fsm(design) {
+        eprint!("{fsm}");
+    }
+}
+
+fn report_registers(design: &Design) {
+    for shift_register in prjunnamed_generic::shift_registers(design) {
+        eprint!("{shift_register}");
+    }
+    for counter in prjunnamed_generic::counters(design) {
+        eprint!("{counter}");
+    }
+}
+
+fn report_clock_domains(design: &Design) {
+    // a design with a single clock domain has no crossings, and nothing worth reporting
+    let report = prjunnamed_generic::clock_domains(design);
+    if report.domains.len() > 1 {
+        eprint!("{report}");
+    }
+}
+
+fn check_comb_loops(design: &Design) -> Result<(), Box<dyn Error>> {
+    let comb_loops = design.find_comb_loops();
+    if comb_loops.is_empty() {
+        return Ok(());
+    }
+    for comb_loop in &comb_loops {
+        eprint!("{comb_loop}");
+    }
+    Err(format!("design contains {} combinational loop(s)", comb_loops.len()).into())
+}
+
+fn ports(design: &Design) -> BTreeSet<String> {
+    let mut ports = BTreeSet::from_iter(design.iter_ios().map(|(name, _)| format!("io {name}")));
+    for cell_ref in design.iter_cells() {
+        match &*cell_ref.get() {
+            Cell::Input(name, _) => ports.insert(format!("input {name}")),
+            Cell::Output(name, _) => ports.insert(format!("output {name}")),
+            _ => continue,
+        };
+    }
+    ports
+}
+
+/// Checks that a pass preserved the ports of the design and did not introduce combinational loops. This is a sanity
+/// check of the structure of the design only; it does not check that the pass preserved its function.
+fn check_structure(before: &Design, after: &Design) -> Result<(), String> {
+    let (ports_before, ports_after) = (ports(before), ports(after));
+    if let Some(port) = ports_before.symmetric_difference(&ports_after).next() {
+        return Err(format!("ports changed ({port})"));
+    }
+    let (loops_before, loops_after) = (before.find_comb_loops().len(), after.find_comb_loops().len());
+    if loops_after > loops_before {
+        return Err(format!("introduced {} combinational loop(s)", loops_after - loops_before));
+    }
+    Ok(())
+}
+
+fn process(
+    design: &mut Design,
+    pipeline: Option<Vec<String>>,
+    structure_check: bool,
+    time_passes: bool,
+    fsms_report: bool,
+    registers_report: bool,
+) -> Result<(), Box<dyn Error>> {
+    let target = design.target();
+    let mut passes = PassManager::new();
+    match target {
+        None => prjunnamed_generic::register_passes(&mut passes),
+        Some(ref target) => target.register_passes(&mut passes),
+    }
+    passes.register("fsm-report", report_fsms);
+    passes.register("report-registers", |design| report_registers(design));
+    passes.register("report-clock-domains", |design| report_clock_domains(design));
+    if structure_check {
+        passes.set_check(check_structure);
+    }
+    let pipeline = pipeline.unwrap_or_else(|| {
+        let (mut prologue, mut flow) = match target {
+            None => (vec![], prjunnamed_generic::GENERIC_PASSES.to_vec()),
+            Some(ref target) => (vec!["unname"], target.synthesis_passes()),
+        };
+        if registers_report {
+            prologue.push("report-registers");
         }
-        Some(ref target) => {
-            prjunnamed_generic::unname(design);
-            target.synthesize(design).expect("synthesis failed")
+        prologue.push("report-clock-domains");
+        if fsms_report {
+            for name in &mut flow {
+                if *name == "fsm" {
+                    *name = "fsm-report";
+                }
+            }
+        }
+        Vec::from_iter(prologue.iter().chain(flow.iter()).map(|name| name.to_string()))
+    });
+    let result = passes.run(design, &pipeline);
+    if time_passes {
+        eprintln!("pass times:");
+        for record in passes.records() {
+            eprintln!("{record}");
         }
     }
+    Ok(result?)
 }
 
 fn read_input(target: Option<Arc<dyn Target>>, name: String) -> Result<Design, Box<dyn Error>> {
     if name.ends_with(".uir") {
         Ok(prjunnamed_netlist::parse(target, &std::fs::read_to_string(name)?)?)
+    } else if name.ends_with(".uirb") {
+        Ok(prjunnamed_netlist::read_binary(target, File::open(name)?)?)
     } else if name.ends_with(".json") {
         let designs = prjunnamed_yosys_json::import(target, &mut File::open(name)?)?;
         assert_eq!(designs.len(), 1, "can only convert single-module Yosys JSON to Unnamed IR");
@@ -57,6 +139,7 @@ fn read_input(target: Option<Arc<dyn Target>>, name: String) -> Result<Design, B
 enum OutputType {
     YosysJson,
     UIR,
+    UIRBinary,
     GraphvizDot,
     GraphvizSvg,
 }
@@ -65,6 +148,8 @@ impl OutputType {
     fn for_filename(name: &str) -> Self {
         if name.ends_with(".uir") || name.is_empty() {
             Self::UIR
+        } else if name.ends_with(".uirb") {
+            Self::UIRBinary
         } else if name.ends_with(".json") {
             Self::YosysJson
         } else if name.ends_with(".dot") {
@@ -96,6 +181,7 @@ fn write_output(mut design: Design, name: String, export: bool) -> Result<(), Bo
 
     match output_type {
         OutputType::UIR => write!(output()?, "{design}")?,
+        OutputType::UIRBinary => prjunnamed_netlist::write_binary(&design, output()?)?,
         OutputType::YosysJson => {
             let designs = BTreeMap::from([("top".to_owned(), design)]);
             prjunnamed_yosys_json::export(&mut output()?, designs)?;
@@ -131,12 +217,48 @@ fn run() -> Result<(), Box<dyn Error>> {
     let mut input = String::new();
     let mut output = String::new();
     let mut target = None::<String>;
+    let mut target_options = Vec::<String>::new();
     let mut export = false;
+    let mut passes = None::<String>;
+    let mut structure_check = false;
+    let mut time_passes = false;
+    let mut fsms_report = false;
+    let mut registers_report = false;
     {
         let mut parser = argparse::ArgumentParser::new();
         parser.refer(&mut version).add_option(&["--version"], argparse::StoreTrue, "Display version");
         parser.refer(&mut target).add_option(&["-t", "--target"], argparse::StoreOption, "Target platform");
+        parser.refer(&mut target_options).add_option(
+            &["-O", "--target-option"],
+            argparse::Collect,
+            "Target option (e.g. device=iCE40UP5K)",
+        );
         parser.refer(&mut export).add_option(&["-e", "--export"], argparse::StoreTrue, "Export target cells");
+        parser.refer(&mut passes).add_option(
+            &["-p", "--passes"],
+            argparse::StoreOption,
+            "Comma separated passes to run instead of the default flow (e.g. decision,canonicalize)",
+        );
+        parser.refer(&mut structure_check).add_option(
+            &["--check-structure"],
+            argparse::StoreTrue,
+            "Check that each pass preserves ports and does not introduce combinational loops",
+        );
+        parser.refer(&mut time_passes).add_option(
+            &["--time-passes"],
+            argparse::StoreTrue,
+            "Report the time taken by each pass and the cells it changed",
+        );
+        parser.refer(&mut fsms_report).add_option(
+            &["--report-fsms"],
+            argparse::StoreTrue,
+            "Report the state machines extracted and re-encoded by the fsm pass",
+        );
+        parser.refer(&mut registers_report).add_option(
+            &["--report-registers"],
+            argparse::StoreTrue,
+            "Report the shift registers and counters found in the design",
+        );
         parser.refer(&mut input).required().add_argument("INPUT", argparse::Store, "Input file");
         parser.refer(&mut output).add_argument("OUTPUT", argparse::Store, "Output file");
         parser.parse_args_or_exit();
@@ -147,8 +269,15 @@ fn run() -> Result<(), Box<dyn Error>> {
         return Ok(());
     }
 
+    let mut options = BTreeMap::new();
+    for option in target_options {
+        let Some((name, value)) = option.split_once('=') else {
+            return Err(format!("target option {option:?} must be of the form NAME=VALUE").into());
+        };
+        options.insert(name.to_owned(), value.to_owned());
+    }
     let target = match target {
-        Some(name) => Some(prjunnamed_netlist::create_target(name.as_str(), BTreeMap::new())?),
+        Some(name) => Some(prjunnamed_netlist::create_target(name.as_str(), options)?),
         None => None,
     };
 
@@ -156,7 +285,9 @@ fn run() -> Result<(), Box<dyn Error>> {
     if let Some(target) = design.target() {
         target.import(&mut design)?;
     }
-    process(&mut design);
+    check_comb_loops(&design)?;
+    let pipeline = passes.map(|passes| Vec::from_iter(passes.split(',').map(|name| name.trim().to_owned())));
+    process(&mut design, pipeline, structure_check, time_passes, fsms_report, registers_report)?;
     write_output(design, output, export)?;
     Ok(())
 }
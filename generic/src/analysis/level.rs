@@ -6,6 +6,31 @@ pub struct LevelAnalysis {
     levels: RefCell<HashMap<Net, u32>>,
 }
 
+/// Computes the levels of the outputs of `cell` from the levels of its inputs, and passes them to `set_level`.
+fn cell_levels(
+    design: &Design,
+    cell: &Cell,
+    output: &Value,
+    level: impl Fn(Net) -> u32,
+    mut set_level: impl FnMut(Net, u32),
+) {
+    if let Cell::Not(input) = cell {
+        for (onet, inet) in output.iter().zip(input) {
+            set_level(onet, level(inet));
+        }
+    } else if !cell.has_state(design) {
+        let mut max_level = 0;
+        cell.visit(|net| {
+            if !net.is_const() {
+                max_level = max_level.max(level(net) + 1);
+            }
+        });
+        for net in output {
+            set_level(net, max_level);
+        }
+    };
+}
+
 impl LevelAnalysis {
     pub fn new() -> Self {
         LevelAnalysis { levels: Default::default() }
@@ -14,28 +39,39 @@ impl LevelAnalysis {
     pub fn get(&self, net: Net) -> u32 {
         self.levels.borrow().get(&net).copied().unwrap_or(0)
     }
+
+    /// Returns the largest change in level of the nets replaced by the pending changes of `design`, without applying
+    /// them. The result is negative if the changes decrease the level of every replaced net.
+    pub fn pending_level_delta(&self, design: &Design) -> i64 {
+        let mut pending_levels = HashMap::new();
+        for (cell, output) in design.pending_cells() {
+            let level = |net: Net| {
+                let net = design.map_net_new(net);
+                pending_levels.get(&net).copied().unwrap_or_else(|| self.get(net))
+            };
+            let mut output_levels = vec![];
+            cell_levels(design, &cell, &output, level, |net, level| output_levels.push((net, level)));
+            pending_levels.extend(output_levels);
+        }
+        let level = |net: Net| pending_levels.get(&net).copied().unwrap_or_else(|| self.get(net));
+        let deltas =
+            design.pending_net_replacements().into_iter().map(|(from, to)| level(to) as i64 - self.get(from) as i64);
+        deltas.max().unwrap_or(0)
+    }
 }
 
 impl RewriteRuleset for LevelAnalysis {
     fn cell_added(&self, design: &Design, cell: &Cell, output: &Value) {
         let mut levels = self.levels.borrow_mut();
-        if let Cell::Not(input) = cell {
-            for (onet, inet) in output.iter().zip(input) {
-                let ilevel = levels.get(&inet).copied().unwrap_or(0);
-                levels.insert(onet, ilevel);
-            }
-        } else if !cell.has_state(design) {
-            let mut level = 0;
-            cell.visit(|net| {
-                if !net.is_const() {
-                    let l = levels.get(&net).copied().unwrap_or(0);
-                    level = level.max(l + 1);
-                }
-            });
-            for net in output {
-                levels.insert(net, level);
-            }
-        };
+        let mut output_levels = vec![];
+        cell_levels(
+            design,
+            cell,
+            output,
+            |net| levels.get(&net).copied().unwrap_or(0),
+            |net, level| output_levels.push((net, level)),
+        );
+        levels.extend(output_levels);
     }
 
     fn net_replaced(&self, _design: &Design, from: Net, to: Net) {
@@ -0,0 +1,435 @@
+//! Clock domain analysis.
+//!
+//! This analysis groups the stateful cells of a design by the clock that updates them, and finds the combinational
+//! paths carrying data from one clock domain to the synchronous inputs of another. A single bit may only cross
+//! domains through a two-flop synchronizer, since the first flip-flop sampling it can become metastable; the bits
+//! of a bus cannot cross domains this way at all, since each of them may settle in a different cycle.
+//!
+//! The domains are those of `dff` cells and of `memory` write ports and synchronous read ports. A clock is
+//! a [`ControlNet`], so the rising and the falling edge of a net are different domains. The contents of a memory
+//! belong to the domains of its write ports and are seen through its asynchronous read ports. Synchronous read ports
+//! of a memory written in another domain are not reported as crossings, since dual-clock memories are the usual way
+//! of passing data between domains. Inputs, IO buffers, and instances do not belong to any domain.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt::Display;
+
+use prjunnamed_netlist::{Cell, ControlNet, Design, Net, Value};
+
+use crate::registers::collect_names;
+
+/// A clock domain found by [`clock_domains`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClockDomain {
+    /// Names of the clock net in the source code.
+    pub names: Vec<String>,
+    pub clock: ControlNet,
+    /// Outputs of the flip-flops and synchronous memory read ports clocked by `clock`.
+    pub outputs: Value,
+    /// The number of memory write ports clocked by `clock`.
+    pub write_ports: usize,
+}
+
+/// The kind of a [`ClockCrossing`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrossingKind {
+    /// A single bit sampled by the first flip-flop of a two-flop synchronizer.
+    Synchronized,
+    /// A single bit sampled without a synchronizer.
+    UnsynchronizedBit,
+    /// Several bits sampled by the same flip-flop or memory port.
+    UnsynchronizedBus,
+}
+
+/// Data from one clock domain sampled by a flip-flop or memory port in another.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClockCrossing {
+    /// Names of the outputs of the destination in the source code.
+    pub names: Vec<String>,
+    /// The clock of the domain the data comes from.
+    pub source: ControlNet,
+    /// The clock of the destination.
+    pub destination: ControlNet,
+    /// The outputs of the stateful cells in the source domain that the data depends on.
+    pub sources: Value,
+    /// The synchronous inputs of the destination that depend on the source domain: data, enable, and reset of
+    /// a flip-flop, or address, data, and mask of a memory port.
+    pub inputs: Value,
+    pub kind: CrossingKind,
+}
+
+impl ClockCrossing {
+    pub fn width(&self) -> usize {
+        self.inputs.len()
+    }
+}
+
+/// The clock domains of a design and the crossings between them, as found by [`clock_domains`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClockDomainReport {
+    pub domains: Vec<ClockDomain>,
+    pub crossings: Vec<ClockCrossing>,
+}
+
+impl ClockDomainReport {
+    pub fn domain(&self, clock: ControlNet) -> Option<&ClockDomain> {
+        self.domains.iter().find(|domain| domain.clock == clock)
+    }
+
+    /// Returns the crossings that do not go through a two-flop synchronizer.
+    pub fn unsynchronized(&self) -> impl Iterator<Item = &ClockCrossing> {
+        self.crossings.iter().filter(|crossing| crossing.kind != CrossingKind::Synchronized)
+    }
+}
+
+/// Calls `f` with the inputs of `cell` that bit `offset` of its output depends on.
+fn bit_inputs(cell: &Cell, offset: usize, mut f: impl FnMut(Net)) {
+    match cell {
+        Cell::Buf(arg) | Cell::Not(arg) => f(arg[offset]),
+        Cell::And(arg1, arg2) | Cell::Or(arg1, arg2) | Cell::Xor(arg1, arg2) => {
+            f(arg1[offset]);
+            f(arg2[offset]);
+        }
+        Cell::Mux(arg1, arg2, arg3) => {
+            f(*arg1);
+            f(arg2[offset]);
+            f(arg3[offset]);
+        }
+        _ => cell.visit(f),
+    }
+}
+
+/// Returns the union of the domains of `nets`.
+fn union(
+    net_domains: &HashMap<Net, BTreeSet<ControlNet>>,
+    nets: impl IntoIterator<Item = Net>,
+) -> BTreeSet<ControlNet> {
+    let mut domains = BTreeSet::new();
+    for net in nets {
+        domains.extend(net_domains.get(&net).into_iter().flatten());
+    }
+    domains
+}
+
+/// Returns the domains of each net that depends on a stateful cell clocked in some domain.
+fn net_domains(design: &Design) -> HashMap<Net, BTreeSet<ControlNet>> {
+    let mut net_domains: HashMap<Net, BTreeSet<ControlNet>> = HashMap::new();
+    // the outputs of flip-flops and synchronous read ports are the roots of the analysis, and must be known before
+    // any combinational cell is visited, since they may be part of a cycle
+    for cell_ref in design.iter_cells() {
+        let output = cell_ref.output();
+        match &*cell_ref.get() {
+            Cell::Dff(flip_flop) if !flip_flop.clock.is_const() => {
+                for net in output.iter() {
+                    net_domains.insert(net, BTreeSet::from([flip_flop.clock]));
+                }
+            }
+            Cell::Memory(memory) => {
+                for (port_index, port) in memory.read_ports.iter().enumerate() {
+                    let Some(flip_flop) = port.flip_flop.as_ref().filter(|flip_flop| !flip_flop.clock.is_const())
+                    else {
+                        continue;
+                    };
+                    for net in output.slice(memory.read_port_output_slice(port_index)).iter() {
+                        net_domains.insert(net, BTreeSet::from([flip_flop.clock]));
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+    for cell_ref in design.iter_cells_topo() {
+        let output = cell_ref.output();
+        let cell = cell_ref.get();
+        match &*cell {
+            Cell::Memory(memory) => {
+                let mut contents = BTreeSet::new();
+                for port in &memory.write_ports {
+                    match port.clock {
+                        Some(clock) if !clock.is_const() => {
+                            contents.insert(clock);
+                        }
+                        Some(_) => (),
+                        None => {
+                            let mut nets = vec![];
+                            port.visit(|net| nets.push(net));
+                            contents.extend(union(&net_domains, nets));
+                        }
+                    }
+                }
+                for (port_index, port) in memory.read_ports.iter().enumerate() {
+                    if port.flip_flop.is_some() {
+                        continue;
+                    }
+                    let mut domains = contents.clone();
+                    domains.extend(union(&net_domains, port.addr.iter()));
+                    if domains.is_empty() {
+                        continue;
+                    }
+                    for net in output.slice(memory.read_port_output_slice(port_index)).iter() {
+                        net_domains.insert(net, domains.clone());
+                    }
+                }
+            }
+            _ if cell.has_state(design) => (),
+            Cell::Buf(..) | Cell::Not(..) | Cell::And(..) | Cell::Or(..) | Cell::Xor(..) | Cell::Mux(..) => {
+                for (offset, net) in output.iter().enumerate() {
+                    let mut inputs = vec![];
+                    bit_inputs(&cell, offset, |net| inputs.push(net));
+                    let domains = union(&net_domains, inputs);
+                    if !domains.is_empty() {
+                        net_domains.insert(net, domains);
+                    }
+                }
+            }
+            _ => {
+                let mut inputs = vec![];
+                cell.visit(|net| inputs.push(net));
+                let domains = union(&net_domains, inputs);
+                if !domains.is_empty() {
+                    for net in output.iter() {
+                        net_domains.insert(net, domains.clone());
+                    }
+                }
+            }
+        }
+    }
+    net_domains
+}
+
+/// Returns the outputs of the stateful cells clocked by `source` that `inputs` depend on.
+fn trace_sources(
+    design: &Design,
+    net_domains: &HashMap<Net, BTreeSet<ControlNet>>,
+    source: ControlNet,
+    inputs: &[Net],
+) -> Value {
+    let mut sources = BTreeSet::new();
+    let mut visited = HashSet::new();
+    let mut stack = Vec::from(inputs);
+    while let Some(net) = stack.pop() {
+        if !visited.insert(net) || !net_domains.get(&net).is_some_and(|domains| domains.contains(&source)) {
+            continue;
+        }
+        let Ok((cell_ref, offset)) = design.find_cell(net) else { continue };
+        match &*cell_ref.get() {
+            Cell::Dff(_) => {
+                sources.insert(net);
+            }
+            Cell::Memory(memory) => {
+                let port_index = (0..memory.read_ports.len())
+                    .find(|&port_index| memory.read_port_output_slice(port_index).contains(&offset))
+                    .unwrap();
+                let port = &memory.read_ports[port_index];
+                if port.flip_flop.is_some() || memory.write_ports.iter().any(|port| port.clock == Some(source)) {
+                    sources.insert(net);
+                } else {
+                    stack.extend(port.addr.iter());
+                    for port in memory.write_ports.iter().filter(|port| port.clock.is_none()) {
+                        port.visit(|net| stack.push(net));
+                    }
+                }
+            }
+            cell => bit_inputs(cell, offset, |net| stack.push(net)),
+        }
+    }
+    Value::from_iter(sources)
+}
+
+/// Returns whether `input`, sampled by a flip-flop clocked by `destination` whose output is `output`, comes directly
+/// from a flip-flop clocked by `source`, and `output` is only used as the data of another flip-flop clocked by
+/// `destination`.
+fn is_synchronizer(design: &Design, source: ControlNet, destination: ControlNet, input: Net, output: Net) -> bool {
+    let Ok((cell_ref, _)) = design.find_cell(input) else { return false };
+    let Cell::Dff(flip_flop) = &*cell_ref.get() else { return false };
+    if flip_flop.clock != source {
+        return false;
+    }
+    let mut uses = design
+        .uses(output)
+        .into_iter()
+        .filter(|(cell_ref, _)| !matches!(&*cell_ref.get(), Cell::Name(..) | Cell::Debug(..)));
+    let (Some((user, offset)), None) = (uses.next(), uses.next()) else { return false };
+    let Cell::Dff(flip_flop) = &*user.get() else { return false };
+    // the data of a flip-flop is visited before its control nets
+    flip_flop.clock == destination && offset < flip_flop.data.len()
+}
+
+/// Finds the clock domains of `design` and the crossings between them.
+///
+/// The crossings are grouped by destination and source domain: the data, enable, and reset of a `dff` cell, or the
+/// inputs of a memory port, that depend on the same source domain are a single crossing. A crossing of a single bit
+/// is synchronized if it is the data of a bit of a `dff` cell that comes directly from a flip-flop in the source
+/// domain, and whose output is only used as the data of another flip-flop in the destination domain.
+pub fn clock_domains(design: &Design) -> ClockDomainReport {
+    let mut domains: BTreeMap<ControlNet, ClockDomain> = BTreeMap::new();
+    let new_domain = |clock| ClockDomain { names: vec![], clock, outputs: Value::new(), write_ports: 0 };
+    // (destination clock, inputs, outputs, data of the `dff` cell)
+    let mut destinations = vec![];
+    for cell_ref in design.iter_cells() {
+        let output = cell_ref.output();
+        match &*cell_ref.get() {
+            Cell::Dff(flip_flop) if !flip_flop.clock.is_const() => {
+                domains
+                    .entry(flip_flop.clock)
+                    .or_insert_with(|| new_domain(flip_flop.clock))
+                    .outputs
+                    .extend(output.iter());
+                let mut inputs = Vec::from_iter(flip_flop.data.iter());
+                for control in [flip_flop.enable, flip_flop.reset] {
+                    if !control.is_const() {
+                        inputs.push(control.net());
+                    }
+                }
+                destinations.push((flip_flop.clock, inputs, output, Some(flip_flop.data.clone())));
+            }
+            Cell::Memory(memory) => {
+                for port in &memory.write_ports {
+                    let Some(clock) = port.clock.filter(|clock| !clock.is_const()) else { continue };
+                    domains.entry(clock).or_insert_with(|| new_domain(clock)).write_ports += 1;
+                    let mut inputs = vec![];
+                    port.visit(|net| inputs.push(net));
+                    inputs.retain(|&net| net != clock.net());
+                    destinations.push((clock, inputs, Value::new(), None));
+                }
+                for (port_index, port) in memory.read_ports.iter().enumerate() {
+                    let Some(flip_flop) = port.flip_flop.as_ref().filter(|flip_flop| !flip_flop.clock.is_const())
+                    else {
+                        continue;
+                    };
+                    let output = output.slice(memory.read_port_output_slice(port_index));
+                    domains
+                        .entry(flip_flop.clock)
+                        .or_insert_with(|| new_domain(flip_flop.clock))
+                        .outputs
+                        .extend(output.iter());
+                    let mut inputs = Vec::from_iter(port.addr.iter());
+                    for control in [flip_flop.enable, flip_flop.reset] {
+                        if !control.is_const() {
+                            inputs.push(control.net());
+                        }
+                    }
+                    destinations.push((flip_flop.clock, inputs, output, None));
+                }
+            }
+            _ => (),
+        }
+    }
+
+    let net_domains = net_domains(design);
+    let mut crossings = vec![];
+    let mut crossing_destinations = vec![];
+    let mut owner = HashMap::new();
+    for (index, (destination, inputs, output, data)) in destinations.iter().enumerate() {
+        let mut by_source: BTreeMap<ControlNet, Vec<Net>> = BTreeMap::new();
+        for &net in inputs {
+            for &source in net_domains.get(&net).into_iter().flatten().filter(|&source| source != destination) {
+                let inputs = by_source.entry(source).or_default();
+                if !inputs.contains(&net) {
+                    inputs.push(net);
+                }
+            }
+        }
+        for (source, inputs) in by_source {
+            let kind = match (&inputs[..], data) {
+                ([input], Some(data)) => {
+                    let synchronized = data
+                        .iter()
+                        .position(|net| net == *input)
+                        .is_some_and(|offset| is_synchronizer(design, source, *destination, *input, output[offset]));
+                    if synchronized { CrossingKind::Synchronized } else { CrossingKind::UnsynchronizedBit }
+                }
+                ([_], None) => CrossingKind::UnsynchronizedBit,
+                _ => CrossingKind::UnsynchronizedBus,
+            };
+            crossings.push(ClockCrossing {
+                names: vec![],
+                source,
+                destination: *destination,
+                sources: trace_sources(design, &net_domains, source, &inputs),
+                inputs: Value::from(inputs),
+                kind,
+            });
+            crossing_destinations.push(index);
+        }
+        for net in output.iter() {
+            owner.insert(net, index);
+        }
+    }
+    let names = collect_names(design, &owner, destinations.len());
+    for (crossing, index) in crossings.iter_mut().zip(crossing_destinations) {
+        crossing.names = names[index].clone();
+    }
+
+    let mut domains = Vec::from_iter(domains.into_values());
+    let mut clock_owner = HashMap::new();
+    for (index, domain) in domains.iter().enumerate() {
+        clock_owner.entry(domain.clock.net()).or_insert(index);
+    }
+    let mut clock_names = collect_names(design, &clock_owner, domains.len());
+    for cell_ref in design.iter_cells() {
+        let Cell::Input(name, 1) = &*cell_ref.get() else { continue };
+        let Some(&index) = clock_owner.get(&cell_ref.output().unwrap_net()) else { continue };
+        if !clock_names[index].contains(name) {
+            clock_names[index].insert(0, name.clone());
+        }
+    }
+    for domain in &mut domains {
+        domain.names = clock_names[clock_owner[&domain.clock.net()]].clone();
+    }
+    ClockDomainReport { domains, crossings }
+}
+
+fn write_clock(f: &mut std::fmt::Formatter<'_>, names: &[String], clock: ControlNet) -> std::fmt::Result {
+    match names.first() {
+        Some(name) => write!(f, "{name:?}")?,
+        None => write!(f, "{}", clock.net())?,
+    }
+    match clock {
+        ControlNet::Pos(_) => write!(f, " (rising edge)"),
+        ControlNet::Neg(_) => write!(f, " (falling edge)"),
+    }
+}
+
+impl Display for ClockDomain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "clock domain ")?;
+        write_clock(f, &self.names, self.clock)?;
+        write!(f, ": {} bits", self.outputs.len())?;
+        if self.write_ports > 0 {
+            write!(f, ", {} memory write ports", self.write_ports)?;
+        }
+        writeln!(f)
+    }
+}
+
+impl Display for CrossingKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CrossingKind::Synchronized => write!(f, "synchronized crossing"),
+            CrossingKind::UnsynchronizedBit => write!(f, "unsynchronized crossing"),
+            CrossingKind::UnsynchronizedBus => write!(f, "unsynchronized bus crossing"),
+        }
+    }
+}
+
+impl Display for ClockDomainReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for domain in &self.domains {
+            write!(f, "{domain}")?;
+        }
+        let names = |clock| self.domain(clock).map(|domain| &domain.names[..]).unwrap_or_default();
+        for crossing in &self.crossings {
+            write!(f, "{} from ", crossing.kind)?;
+            write_clock(f, names(crossing.source), crossing.source)?;
+            write!(f, " to ")?;
+            write_clock(f, names(crossing.destination), crossing.destination)?;
+            write!(f, ": {} bits", crossing.width())?;
+            if let Some(name) = crossing.names.first() {
+                write!(f, " into {name:?}")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
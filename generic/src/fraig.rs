@@ -0,0 +1,344 @@
+//! Functional reduction of equivalent nets.
+//!
+//! The `fraig` pass finds nets computed by bitwise logic cells (`buf`, `not`, `and`, `or`, `xor`, `mux`, and `aig`)
+//! that are functionally equivalent to an earlier net, to its complement, or to a constant, even if they are
+//! computed by structurally different cones of logic. Candidates are found by simulating random input vectors,
+//! and each candidate is proven with a SAT solver before the net is replaced.
+//!
+//! The outputs of all other cells, as well as undefined constants, are treated as free variables. Every use of
+//! an undefined constant is a distinct free variable, since each one may be chosen independently; a net is only
+//! replaced with a net computed from undefined constants if they are equal for every choice. Since proving
+//! an equivalence can take exponential time, each proof is limited to a number of conflicts, and the pass stops
+//! trying once it has attempted a number of proofs (or, optionally, once it exceeds a time limit); the candidates
+//! that could not be proven are left alone.
+
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use prjunnamed_netlist::{Cell, ControlNet, Design, Net, Trit};
+
+use crate::sat::{Lit, SatResult, Solver};
+
+/// Number of 64-bit words of random input vectors simulated for each net.
+const WORDS: usize = 4;
+
+#[derive(Debug, Clone, Copy)]
+enum Gate {
+    Buf(Net),
+    Not(Net),
+    And(Net, Net),
+    Or(Net, Net),
+    Xor(Net, Net),
+    Mux(Net, Net, Net),
+    Aig(ControlNet, ControlNet),
+}
+
+impl Gate {
+    fn from_cell(cell: &Cell, bit: usize) -> Option<Gate> {
+        Some(match cell {
+            Cell::Buf(arg) => Gate::Buf(arg[bit]),
+            Cell::Not(arg) => Gate::Not(arg[bit]),
+            Cell::And(arg1, arg2) => Gate::And(arg1[bit], arg2[bit]),
+            Cell::Or(arg1, arg2) => Gate::Or(arg1[bit], arg2[bit]),
+            Cell::Xor(arg1, arg2) => Gate::Xor(arg1[bit], arg2[bit]),
+            Cell::Mux(arg1, arg2, arg3) => Gate::Mux(*arg1, arg2[bit], arg3[bit]),
+            Cell::Aig(arg1, arg2) => Gate::Aig(*arg1, *arg2),
+            _ => return None,
+        })
+    }
+
+    fn inputs(self) -> Vec<Net> {
+        match self {
+            Gate::Buf(arg) | Gate::Not(arg) => vec![arg],
+            Gate::And(arg1, arg2) | Gate::Or(arg1, arg2) | Gate::Xor(arg1, arg2) => vec![arg1, arg2],
+            Gate::Mux(arg1, arg2, arg3) => vec![arg1, arg2, arg3],
+            Gate::Aig(arg1, arg2) => vec![arg1.net(), arg2.net()],
+        }
+    }
+}
+
+/// A xorshift generator; the pass does not need high quality randomness, but it must be deterministic.
+struct Random(u64);
+
+impl Random {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+type Signature = [u64; WORDS];
+
+struct Network {
+    gates: HashMap<Net, Gate>,
+    /// Nets in a topological order, including the free variables used by the gates.
+    order: Vec<Net>,
+    signatures: HashMap<Net, Signature>,
+}
+
+impl Network {
+    fn new(design: &Design) -> Self {
+        let mut network = Network { gates: HashMap::new(), order: vec![], signatures: HashMap::new() };
+        let mut random = Random(0x2545f4914f6cdd1d);
+        for cell_ref in design.iter_cells_topo() {
+            let cell = cell_ref.get();
+            for (bit, net) in cell_ref.output().iter().enumerate() {
+                let Some(gate) = Gate::from_cell(&cell, bit) else { break };
+                let inputs = Vec::from_iter(gate.inputs().into_iter().map(|net| network.simulate(net, &mut random)));
+                let signature = std::array::from_fn(|index| {
+                    let word = |input: usize| inputs[input][index];
+                    match gate {
+                        Gate::Buf(_) => word(0),
+                        Gate::Not(_) => !word(0),
+                        Gate::And(..) => word(0) & word(1),
+                        Gate::Or(..) => word(0) | word(1),
+                        Gate::Xor(..) => word(0) ^ word(1),
+                        Gate::Mux(..) => (word(0) & word(1)) | (!word(0) & word(2)),
+                        Gate::Aig(arg1, arg2) => {
+                            let invert = |control_net: ControlNet| if control_net.is_negative() { !0 } else { 0 };
+                            (word(0) ^ invert(arg1)) & (word(1) ^ invert(arg2))
+                        }
+                    }
+                });
+                network.gates.insert(net, gate);
+                network.order.push(net);
+                network.signatures.insert(net, signature);
+            }
+        }
+        network
+    }
+
+    fn simulate(&mut self, net: Net, random: &mut Random) -> Signature {
+        match net.as_const() {
+            Some(Trit::Zero) => [0; WORDS],
+            Some(Trit::One) => [!0; WORDS],
+            Some(Trit::Undef) => std::array::from_fn(|_| random.next()),
+            None => *self.signatures.entry(net).or_insert_with(|| {
+                // the net is either a free variable, or a gate in a combinational loop; only the former is
+                // a valid candidate for equivalence
+                self.order.push(net);
+                std::array::from_fn(|_| random.next())
+            }),
+        }
+    }
+
+    /// Groups the nets into classes of candidate equivalences. Each class contains nets along with whether they
+    /// are inverted relative to the first one. The first class is the class of constant zero.
+    fn classes(&self) -> Vec<Vec<(Net, bool)>> {
+        // classes are kept in the order of their first net so that the replacements are deterministic
+        let mut classes = vec![vec![(Net::ZERO, false)]];
+        let mut indices: HashMap<Signature, usize> = HashMap::from([([0; WORDS], 0)]);
+        for &net in &self.order {
+            let mut signature = self.signatures[&net];
+            let invert = signature[0] & 1 != 0;
+            if invert {
+                signature = signature.map(|word| !word);
+            }
+            let index = *indices.entry(signature).or_insert_with(|| {
+                classes.push(vec![]);
+                classes.len() - 1
+            });
+            classes[index].push((net, invert));
+        }
+        classes.retain(|class| class.len() > 1);
+        classes
+    }
+
+    /// Encodes the cone of `net` into the solver. Returns `None` if the cone includes a combinational loop.
+    fn encode(
+        &self,
+        solver: &mut Solver,
+        lits: &mut HashMap<Net, Lit>,
+        visiting: &mut HashSet<Net>,
+        net: Net,
+    ) -> Option<Lit> {
+        if net == Net::UNDEF {
+            // not cached, since each use of an undefined constant is independent
+            return Some(solver.add_var());
+        }
+        if let Some(&lit) = lits.get(&net) {
+            return Some(lit);
+        }
+        let lit = match self.gates.get(&net) {
+            Some(&gate) => {
+                if !visiting.insert(net) {
+                    return None;
+                }
+                let mut inputs = vec![];
+                for input in gate.inputs() {
+                    inputs.push(self.encode(solver, lits, visiting, input)?);
+                }
+                visiting.remove(&net);
+                let y = solver.add_var();
+                match gate {
+                    Gate::Buf(_) | Gate::Not(_) => {
+                        let a = inputs[0] ^ matches!(gate, Gate::Not(_));
+                        solver.add_clause(&[!y, a]);
+                        solver.add_clause(&[y, !a]);
+                    }
+                    Gate::And(..) | Gate::Or(..) | Gate::Aig(..) => {
+                        // `or` is an `and` with inverted inputs and output
+                        let (a, b, y) = match gate {
+                            Gate::And(..) => (inputs[0], inputs[1], y),
+                            Gate::Or(..) => (!inputs[0], !inputs[1], !y),
+                            Gate::Aig(arg1, arg2) => {
+                                (inputs[0] ^ arg1.is_negative(), inputs[1] ^ arg2.is_negative(), y)
+                            }
+                            _ => unreachable!(),
+                        };
+                        solver.add_clause(&[!y, a]);
+                        solver.add_clause(&[!y, b]);
+                        solver.add_clause(&[y, !a, !b]);
+                    }
+                    Gate::Xor(..) => {
+                        let (a, b) = (inputs[0], inputs[1]);
+                        solver.add_clause(&[!y, a, b]);
+                        solver.add_clause(&[!y, !a, !b]);
+                        solver.add_clause(&[y, !a, b]);
+                        solver.add_clause(&[y, a, !b]);
+                    }
+                    Gate::Mux(..) => {
+                        let (s, a, b) = (inputs[0], inputs[1], inputs[2]);
+                        solver.add_clause(&[!s, !a, y]);
+                        solver.add_clause(&[!s, a, !y]);
+                        solver.add_clause(&[s, !b, y]);
+                        solver.add_clause(&[s, b, !y]);
+                    }
+                }
+                y
+            }
+            None => {
+                let lit = solver.add_var();
+                match net.as_const() {
+                    Some(Trit::Zero) => solver.add_clause(&[!lit]),
+                    Some(Trit::One) => solver.add_clause(&[lit]),
+                    _ => (),
+                }
+                lit
+            }
+        };
+        lits.insert(net, lit);
+        Some(lit)
+    }
+
+    /// Proves that `net1` and `net2` (inverted if `invert` is true) are equivalent.
+    fn prove(&self, net1: Net, net2: Net, invert: bool, conflict_limit: u64) -> SatResult {
+        let mut solver = Solver::new();
+        let mut lits = HashMap::new();
+        let mut visiting = HashSet::new();
+        let Some(lit1) = self.encode(&mut solver, &mut lits, &mut visiting, net1) else { return SatResult::Unknown };
+        let Some(lit2) = self.encode(&mut solver, &mut lits, &mut visiting, net2) else { return SatResult::Unknown };
+        // the nets are equivalent if they cannot differ
+        let lit2 = lit2 ^ invert;
+        solver.add_clause(&[lit1, lit2]);
+        solver.add_clause(&[!lit1, !lit2]);
+        solver.solve(conflict_limit)
+    }
+}
+
+/// Configures and runs the [`fraig`] pass.
+#[derive(Debug, Clone)]
+pub struct Fraig {
+    conflict_limit: u64,
+    proof_limit: usize,
+    time_limit: Option<Duration>,
+}
+
+impl Fraig {
+    pub fn new() -> Self {
+        Fraig { conflict_limit: 1000, proof_limit: 10000, time_limit: None }
+    }
+
+    /// Sets the number of conflicts after which the SAT solver gives up on proving a candidate equivalence.
+    pub fn with_conflict_limit(mut self, conflict_limit: u64) -> Self {
+        self.conflict_limit = conflict_limit;
+        self
+    }
+
+    /// Sets the number of candidate equivalences after which no more are checked.
+    pub fn with_proof_limit(mut self, proof_limit: usize) -> Self {
+        self.proof_limit = proof_limit;
+        self
+    }
+
+    /// Sets the time after which no more candidate equivalences are checked. There is no time limit by default,
+    /// since it makes the result of the pass depend on the speed of the machine.
+    pub fn with_time_limit(mut self, time_limit: Duration) -> Self {
+        self.time_limit = Some(time_limit);
+        self
+    }
+
+    /// Replaces functionally equivalent nets, and returns the number of replaced nets.
+    pub fn run(&self, design: &mut Design) -> usize {
+        let deadline = self.time_limit.map(|time_limit| Instant::now() + time_limit);
+        let mut proofs = 0;
+        let network = Network::new(design);
+        let mut replaced = 0;
+        'classes: for class in network.classes() {
+            let mut representatives = vec![class[0]];
+            for &(net, invert) in &class[1..] {
+                if !network.gates.contains_key(&net) {
+                    // free variables can be representatives, but never get replaced
+                    representatives.push((net, invert));
+                    continue;
+                }
+                let mut is_replaced = false;
+                for &(representative, representative_invert) in &representatives {
+                    let invert = invert ^ representative_invert;
+                    if invert && matches!(network.gates[&net], Gate::Not(arg) if arg == representative) {
+                        is_replaced = true;
+                        break;
+                    }
+                    if proofs == self.proof_limit {
+                        if cfg!(feature = "trace") {
+                            eprintln!(">fraig out of proofs");
+                        }
+                        break 'classes;
+                    }
+                    if deadline.is_some_and(|deadline| Instant::now() > deadline) {
+                        if cfg!(feature = "trace") {
+                            eprintln!(">fraig out of time");
+                        }
+                        break 'classes;
+                    }
+                    proofs += 1;
+                    match network.prove(net, representative, invert, self.conflict_limit) {
+                        SatResult::Unsat => {
+                            let new_net = match (representative.as_const(), invert) {
+                                (Some(_), true) => Net::ONE,
+                                (_, true) => design.add_not1(representative),
+                                (_, false) => representative,
+                            };
+                            if cfg!(feature = "trace") {
+                                eprintln!(">fraig {} => {}", design.display_net(net), design.display_net(new_net));
+                            }
+                            design.replace_net(net, new_net);
+                            replaced += 1;
+                            is_replaced = true;
+                            break;
+                        }
+                        SatResult::Sat | SatResult::Unknown => (),
+                    }
+                }
+                if !is_replaced {
+                    representatives.push((net, invert));
+                }
+            }
+        }
+        design.compact();
+        replaced
+    }
+}
+
+impl Default for Fraig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Replaces functionally equivalent nets with the default limits.
+pub fn fraig(design: &mut Design) {
+    Fraig::new().run(design);
+}
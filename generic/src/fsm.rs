@@ -0,0 +1,506 @@
+//! State machine extraction and re-encoding.
+//!
+//! The `fsm` pass finds `dff` cells holding the state of a state machine: their next state is selected by a chain
+//! of `assign` cells from the state codes (constants) and the current state, and their output is only examined by
+//! `match` cells, by `eq` cells comparing it with a constant, and by names. Each of these registers is replaced
+//! with one using the encoding requested by a [`MetaItem::EnumEncoding`] item attached to the `dff` cell, or else
+//! the encoding preferred by the target.
+//!
+//! The states of a state machine are the constants that can be loaded into the register: its initial value, its
+//! reset values, and the updates of the `assign` chain. Since the register never holds any other value, the codes
+//! that are not states are don't-cares when examining the register.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Display;
+
+use prjunnamed_netlist::{
+    AssignCell, Cell, CellRef, Const, ControlNet, Design, EnumEncoding, FlipFlop, MatchCell, MetaItem, MetaItemRef,
+    Net, Trit, Value,
+};
+
+/// A state of an extracted state machine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FsmState {
+    /// Name of the state, if the state register has an enumeration metadata item that includes it.
+    pub name: Option<String>,
+    /// Code of the state in the original encoding.
+    pub old_code: Const,
+    /// Code of the state in the new encoding.
+    pub new_code: Const,
+}
+
+/// A state machine extracted and re-encoded by the [`fsm`] pass.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fsm {
+    /// Names of the state register in the source code.
+    pub names: Vec<String>,
+    pub encoding: EnumEncoding,
+    pub states: Vec<FsmState>,
+    /// Transitions between states, as pairs of indices into `states`. Transitions that are not ruled out by
+    /// the `match` cells examining the state are included, even if they depend on other inputs.
+    pub transitions: BTreeSet<(usize, usize)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum NextState {
+    Hold,
+    Code(Const),
+}
+
+struct Candidate<'a> {
+    dff: CellRef<'a>,
+    flip_flop: FlipFlop,
+    state: Value,
+    /// Updates of the `assign` chain, from the highest to the lowest priority.
+    chain: Vec<(Net, NextState)>,
+    chain_cells: Vec<CellRef<'a>>,
+    /// Value of the next state if no update in the chain is enabled.
+    default: NextState,
+    /// Cells examining the state, keyed by their index.
+    readers: BTreeMap<usize, CellRef<'a>>,
+    codes: Vec<Const>,
+}
+
+fn find_users(design: &Design) -> HashMap<Net, BTreeSet<CellRef<'_>>> {
+    let mut users: HashMap<Net, BTreeSet<CellRef>> = HashMap::new();
+    for cell_ref in design.iter_cells() {
+        cell_ref.visit(|net| {
+            users.entry(net).or_default().insert(cell_ref);
+        });
+    }
+    users
+}
+
+fn defined_const(value: &Value) -> Option<Const> {
+    value.as_const().filter(|value| !value.has_undef())
+}
+
+fn extract<'a>(
+    design: &'a Design,
+    users: &HashMap<Net, BTreeSet<CellRef<'a>>>,
+    dff: CellRef<'a>,
+) -> Option<Candidate<'a>> {
+    let Cell::Dff(flip_flop) = &*dff.get() else { return None };
+    let state = dff.output();
+    let is_state = |net: Net| state.iter().any(|state_net| state_net == net);
+    if state.is_empty() {
+        return None;
+    }
+    for control in [flip_flop.clock, flip_flop.clear, flip_flop.reset, flip_flop.enable] {
+        if is_state(control.net()) {
+            return None;
+        }
+    }
+
+    // Walk the `assign` chain from its end (the highest priority update) to its start.
+    let mut chain = vec![];
+    let mut chain_cells = vec![];
+    let mut value = flip_flop.data.clone();
+    let default = loop {
+        if value == state {
+            break NextState::Hold;
+        }
+        if value.as_const().is_some() {
+            break NextState::Code(defined_const(&value)?);
+        }
+        let (cell_ref, 0) = design.find_cell(value[0]).ok()? else { return None };
+        if cell_ref.output() != value {
+            return None;
+        }
+        let Cell::Assign(AssignCell { value: base, enable, update, offset: 0 }) = &*cell_ref.get() else { return None };
+        if is_state(*enable) || update.len() != state.len() {
+            return None;
+        }
+        let next = if *update == state { NextState::Hold } else { NextState::Code(defined_const(update)?) };
+        chain.push((*enable, next));
+        chain_cells.push(cell_ref);
+        value = base.clone();
+    };
+
+    // The intermediate values of the chain are only used by the chain itself.
+    for (index, cell_ref) in chain_cells.iter().enumerate() {
+        let allowed = if index == 0 { dff } else { chain_cells[index - 1] };
+        for net in cell_ref.output().iter() {
+            if users.get(&net).into_iter().flatten().any(|&user| user != allowed) {
+                return None;
+            }
+        }
+    }
+
+    // The state is only examined by cells that can be rewritten for another encoding.
+    let mut readers = BTreeMap::new();
+    for net in state.iter() {
+        for &user in users.get(&net).into_iter().flatten() {
+            if user == dff || chain_cells.contains(&user) {
+                continue;
+            }
+            match &*user.get() {
+                Cell::Match(match_cell) if !is_state(match_cell.enable) => (),
+                Cell::Eq(arg1, arg2) if *arg1 == state && defined_const(arg2).is_some() => (),
+                Cell::Eq(arg1, arg2) if *arg2 == state && defined_const(arg1).is_some() => (),
+                Cell::Name(_, value) | Cell::Debug(_, value) if *value == state => (),
+                _ => return None,
+            }
+            readers.insert(user.debug_index(), user);
+        }
+    }
+
+    let mut codes = vec![];
+    let mut add_code = |code: Const| {
+        if !codes.contains(&code) {
+            codes.push(code);
+        }
+    };
+    if flip_flop.has_reset() {
+        add_code(Some(flip_flop.reset_value.clone()).filter(|value| !value.has_undef())?);
+    }
+    if flip_flop.has_clear() {
+        add_code(Some(flip_flop.clear_value.clone()).filter(|value| !value.has_undef())?);
+    }
+    if !flip_flop.init_value.is_undef() {
+        add_code(Some(flip_flop.init_value.clone()).filter(|value| !value.has_undef())?);
+    }
+    for next in std::iter::once(&default).chain(chain.iter().rev().map(|(_, next)| next)) {
+        if let NextState::Code(code) = next {
+            add_code(code.clone());
+        }
+    }
+    if codes.len() < 2 {
+        return None;
+    }
+
+    Some(Candidate { dff, flip_flop: flip_flop.clone(), state, chain, chain_cells, default, readers, codes })
+}
+
+/// Returns the path of an identifier through its scopes, e.g. `top.uart.state`.
+fn ident_path(item: MetaItemRef) -> Option<String> {
+    let MetaItem::Ident { name, scope } = item.get() else { return None };
+    let mut path = name.get().to_string();
+    let mut scope = scope;
+    loop {
+        match scope.get() {
+            MetaItem::NamedScope { name, parent, .. } => {
+                path = format!("{}.{path}", &*name.get());
+                scope = parent;
+            }
+            MetaItem::IndexedScope { index, parent, .. } => {
+                path = format!("[{index}].{path}");
+                scope = parent;
+            }
+            _ => break,
+        }
+    }
+    Some(path)
+}
+
+fn encode(encoding: EnumEncoding, count: usize, index: usize) -> Const {
+    let width = (usize::BITS - (count - 1).leading_zeros()).max(1) as usize;
+    match encoding {
+        EnumEncoding::Binary => Const::from_uint(index as u128, width),
+        EnumEncoding::Gray => Const::from_uint((index ^ (index >> 1)) as u128, width),
+        EnumEncoding::OneHot => Const::one_hot(count, index),
+    }
+}
+
+impl<'a> Candidate<'a> {
+    fn cells(&self) -> impl Iterator<Item = CellRef<'a>> + '_ {
+        std::iter::once(self.dff).chain(self.chain_cells.iter().copied()).chain(self.readers.values().copied())
+    }
+
+    fn code_index(&self, code: &Const) -> usize {
+        self.codes.iter().position(|state_code| state_code == code).unwrap()
+    }
+
+    /// Evaluates a `match` cell alternative against the state register holding `code`.
+    fn match_alternative(&self, value: &Value, alternative: &Const, code: &Const) -> Trit {
+        let mut result = Trit::One;
+        for (net, pattern) in value.iter().zip(alternative.iter()) {
+            let actual = match self.state.iter().position(|state_net| state_net == net) {
+                Some(bit) => code[bit],
+                None => net.as_const().unwrap_or(Trit::Undef),
+            };
+            match (pattern, actual) {
+                (Trit::Undef, _) => (),
+                (_, Trit::Undef) => result = result & Trit::Undef,
+                _ if pattern == actual => (),
+                _ => return Trit::Zero,
+            }
+        }
+        result
+    }
+
+    /// Evaluates `net` when the state register holds `code`, as far as it is determined by the state
+    /// and by the logic at most `depth` levels deep.
+    fn evaluate(&self, design: &Design, net: Net, code: &Const, depth: usize) -> Trit {
+        if let Some(trit) = net.as_const() {
+            return trit;
+        }
+        let Ok((cell_ref, output_index)) = design.find_cell(net) else { return Trit::Undef };
+        if depth == 0 {
+            return Trit::Undef;
+        }
+        let evaluate = |net: Net| self.evaluate(design, net, code, depth - 1);
+        match &*cell_ref.get() {
+            Cell::Match(match_cell) => {
+                let mut result = evaluate(match_cell.enable);
+                for (index, alternatives) in match_cell.patterns.iter().enumerate().take(output_index + 1) {
+                    let mut matches = Trit::Zero;
+                    for alternative in alternatives {
+                        matches = matches | self.match_alternative(&match_cell.value, alternative, code);
+                    }
+                    // the outputs of a `match` cell are prioritized
+                    result = result & if index == output_index { matches } else { !matches };
+                }
+                result
+            }
+            Cell::Not(arg) => !evaluate(arg[output_index]),
+            Cell::And(arg1, arg2) => evaluate(arg1[output_index]) & evaluate(arg2[output_index]),
+            Cell::Or(arg1, arg2) => evaluate(arg1[output_index]) | evaluate(arg2[output_index]),
+            Cell::Aig(arg1, arg2) => {
+                let evaluate_control = |arg: ControlNet| match arg {
+                    ControlNet::Pos(net) => evaluate(net),
+                    ControlNet::Neg(net) => !evaluate(net),
+                };
+                evaluate_control(*arg1) & evaluate_control(*arg2)
+            }
+            _ => Trit::Undef,
+        }
+    }
+
+    fn transitions(&self, design: &Design) -> BTreeSet<(usize, usize)> {
+        let mut transitions = BTreeSet::new();
+        for (index, code) in self.codes.iter().enumerate() {
+            let target = |next: &NextState| match next {
+                NextState::Hold => index,
+                NextState::Code(next_code) => self.code_index(next_code),
+            };
+            let mut is_exhaustive = false;
+            for (enable, next) in &self.chain {
+                match self.evaluate(design, *enable, code, 8) {
+                    Trit::Zero => continue,
+                    Trit::One => is_exhaustive = true,
+                    Trit::Undef => (),
+                }
+                transitions.insert((index, target(next)));
+                if is_exhaustive {
+                    break;
+                }
+            }
+            if !is_exhaustive {
+                transitions.insert((index, target(&self.default)));
+            }
+        }
+        transitions
+    }
+
+    /// Returns the description of the state machine and the first net of the new state register.
+    fn rewrite(self, design: &'a Design, default_encoding: EnumEncoding) -> (Fsm, Net) {
+        let mut encoding = None;
+        let mut members = vec![];
+        let mut names = vec![];
+        for item in self.dff.metadata().iter() {
+            match item.get() {
+                MetaItem::EnumEncoding { encoding: item_encoding, members: item_members } => {
+                    encoding = encoding.or(item_encoding);
+                    members.extend(item_members.into_iter().map(|(name, value)| (name.get().to_string(), value)));
+                }
+                MetaItem::Ident { .. } => names.extend(ident_path(item)),
+                _ => (),
+            }
+        }
+        for reader in self.readers.values() {
+            if let Cell::Name(name, _) | Cell::Debug(name, _) = &*reader.get()
+                && !names.contains(name)
+            {
+                names.push(name.clone());
+            }
+        }
+        let encoding = encoding.unwrap_or(default_encoding);
+        let transitions = self.transitions(design);
+
+        let count = self.codes.len();
+        let new_codes = Vec::from_iter((0..count).map(|index| encode(encoding, count, index)));
+        // the register only ever holds one of the new codes, so one-hot states can be recognized by a single bit
+        let patterns = Vec::from_iter((0..count).map(|index| match encoding {
+            EnumEncoding::OneHot => {
+                let mut pattern = Const::undef(count);
+                pattern[index] = Trit::One;
+                pattern
+            }
+            EnumEncoding::Binary | EnumEncoding::Gray => new_codes[index].clone(),
+        }));
+        let width = new_codes[0].len();
+
+        let new_state_void = design.add_void(width);
+        let new_next = |next: &NextState| match next {
+            NextState::Hold => new_state_void.clone(),
+            NextState::Code(code) => Value::from(&new_codes[self.code_index(code)]),
+        };
+        let mut data = new_next(&self.default);
+        for ((enable, next), &cell_ref) in self.chain.iter().zip(self.chain_cells.iter()).rev() {
+            let _guard = design.use_metadata_from(&[cell_ref]);
+            data = design.add_assign(AssignCell { value: data, enable: *enable, update: new_next(next), offset: 0 });
+        }
+        let new_value = |value: &Const| match value.has_undef() {
+            true => Const::undef(width),
+            false => new_codes[self.code_index(value)].clone(),
+        };
+        let flip_flop = &self.flip_flop;
+        let new_state = {
+            let _guard = design.use_metadata_from(&[self.dff]);
+            design.add_dff(FlipFlop {
+                data,
+                clock: flip_flop.clock,
+                clear: flip_flop.clear,
+                reset: flip_flop.reset,
+                enable: flip_flop.enable,
+                reset_over_enable: flip_flop.reset_over_enable,
+                clear_value: if flip_flop.has_clear() {
+                    new_value(&flip_flop.clear_value)
+                } else {
+                    Const::undef(width)
+                },
+                reset_value: if flip_flop.has_reset() {
+                    new_value(&flip_flop.reset_value)
+                } else {
+                    Const::undef(width)
+                },
+                // an undefined initial value may be refined to any state
+                init_value: if flip_flop.init_value.is_undef() {
+                    new_codes[0].clone()
+                } else {
+                    new_value(&flip_flop.init_value)
+                },
+            })
+        };
+        design.replace_value(&new_state_void, &new_state);
+
+        let state_patterns = |matches: &dyn Fn(&Const) -> bool| {
+            Vec::from_iter((0..count).filter(|&index| matches(&self.codes[index])).map(|index| patterns[index].clone()))
+        };
+        let mut decoded = None;
+        for &reader in self.readers.values() {
+            let _guard = design.use_metadata_from(&[reader]);
+            match &*reader.get() {
+                Cell::Match(match_cell) => {
+                    let is_state = |net: Net| self.state.iter().any(|state_net| state_net == net);
+                    let other =
+                        Vec::from_iter((0..match_cell.value.len()).filter(|&bit| !is_state(match_cell.value[bit])));
+                    let mut value = Value::from_iter(other.iter().map(|&bit| match_cell.value[bit]));
+                    value.extend(&new_state);
+                    let patterns = Vec::from_iter(match_cell.patterns.iter().map(|alternatives| {
+                        let mut new_alternatives = vec![];
+                        for alternative in alternatives {
+                            let other_pattern = Const::from_iter(other.iter().map(|&bit| alternative[bit]));
+                            let is_match = |code: &Const| {
+                                self.match_alternative(&match_cell.value, alternative, code) != Trit::Zero
+                            };
+                            for state_pattern in state_patterns(&is_match) {
+                                new_alternatives.push(other_pattern.concat(state_pattern));
+                            }
+                        }
+                        new_alternatives
+                    }));
+                    reader.replace(Cell::Match(MatchCell { value, enable: match_cell.enable, patterns }));
+                }
+                Cell::Eq(arg1, arg2) => {
+                    let other = defined_const(if *arg1 == self.state { arg2 } else { arg1 }).unwrap();
+                    let patterns = vec![state_patterns(&|code| *code == other)];
+                    reader.replace(Cell::Match(MatchCell { value: new_state.clone(), enable: Net::ONE, patterns }));
+                }
+                Cell::Name(name, _) | Cell::Debug(name, _) => {
+                    let decoded = decoded.get_or_insert_with(|| {
+                        let patterns = Vec::from_iter(patterns.iter().map(|pattern| vec![pattern.clone()]));
+                        let matches =
+                            design.add_match(MatchCell { value: new_state.clone(), enable: Net::ONE, patterns });
+                        let mut decoded = Value::undef(self.state.len());
+                        for (index, code) in self.codes.iter().enumerate() {
+                            decoded = design.add_assign(AssignCell {
+                                value: decoded,
+                                enable: matches[index],
+                                update: Value::from(code),
+                                offset: 0,
+                            });
+                        }
+                        decoded
+                    });
+                    reader.replace(match &*reader.get() {
+                        Cell::Name(..) => Cell::Name(name.clone(), decoded.clone()),
+                        _ => Cell::Debug(name.clone(), decoded.clone()),
+                    });
+                }
+                _ => unreachable!(),
+            }
+        }
+        self.dff.unalive();
+        for cell_ref in &self.chain_cells {
+            cell_ref.unalive();
+        }
+
+        let states = Vec::from_iter(self.codes.into_iter().zip(new_codes).map(|(old_code, new_code)| FsmState {
+            name: members.iter().find(|(_, value)| *value == old_code).map(|(name, _)| name.clone()),
+            old_code,
+            new_code,
+        }));
+        (Fsm { names, encoding, states, transitions }, new_state[0])
+    }
+}
+
+/// Extracts state machines, and re-encodes them using the encoding requested in the design or preferred by
+/// the target. Returns a description of each of the extracted state machines.
+pub fn fsm(design: &mut Design) -> Vec<Fsm> {
+    let default_encoding = design.target().map(|target| target.enum_encoding()).unwrap_or(EnumEncoding::Binary);
+    let mut fsms = vec![];
+    let mut new_states = BTreeSet::new();
+    loop {
+        // A cell may examine several state registers; these are rewritten one at a time.
+        let mut is_deferred = false;
+        let users = find_users(design);
+        let mut rewritten = BTreeSet::new();
+        for cell_ref in design.iter_cells() {
+            let Some(candidate) = extract(design, &users, cell_ref) else { continue };
+            if new_states.contains(&candidate.state[0]) {
+                continue;
+            }
+            if candidate.cells().any(|cell_ref| rewritten.contains(&cell_ref.debug_index())) {
+                is_deferred = true;
+                continue;
+            }
+            rewritten.extend(candidate.cells().map(|cell_ref| cell_ref.debug_index()));
+            let (fsm, new_state) = candidate.rewrite(design, default_encoding);
+            new_states.insert(new_state);
+            if cfg!(feature = "trace") {
+                eprint!(">extracted:\n{fsm}");
+            }
+            fsms.push(fsm);
+        }
+        design.apply();
+        if !is_deferred {
+            break;
+        }
+    }
+    design.compact();
+    fsms
+}
+
+impl Display for Fsm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.names.first() {
+            Some(name) => write!(f, "state machine {name:?}")?,
+            None => write!(f, "state machine")?,
+        }
+        writeln!(f, " ({} states, {} encoding):", self.states.len(), self.encoding)?;
+        for (index, state) in self.states.iter().enumerate() {
+            write!(f, "  state {index}")?;
+            if let Some(name) = &state.name {
+                write!(f, " {name:?}")?;
+            }
+            write!(f, ": {} => {} ->", state.old_code, state.new_code)?;
+            for &(_, to_index) in self.transitions.range((index, 0)..(index + 1, 0)) {
+                write!(f, " {to_index}")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
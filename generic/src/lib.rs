@@ -1,27 +1,43 @@
-use prjunnamed_netlist::Design;
+use prjunnamed_netlist::{Design, PassManager};
 
 mod rewrite;
 mod unname;
 mod decision;
+mod fsm;
 mod simplify;
 mod merge;
 mod split;
 mod lower_arith;
+mod multiplier;
 mod iobuf_insert;
 mod chain_rebalance;
 mod tree_rebalance;
+mod retime;
+mod fraig;
+mod share;
+mod memory_infer;
+mod registers;
+mod clock_domains;
+mod sat;
 mod analysis;
 
 pub use unname::unname;
 pub use decision::decision;
+pub use fsm::{fsm, Fsm, FsmState};
 pub use lower_arith::lower_arith;
 pub use iobuf_insert::iobuf_insert;
 pub use analysis::level::LevelAnalysis;
 pub use rewrite::normalize::Normalize;
 pub use rewrite::aig::SimpleAigOpt;
-pub use rewrite::lower::{LowerMux, LowerEq, LowerLt, LowerMul, LowerShift};
+pub use rewrite::lower::{LowerMux, LowerEq, LowerLt, LowerMul, LowerShift, LowerCount};
 pub use chain_rebalance::chain_rebalance;
 pub use tree_rebalance::tree_rebalance;
+pub use retime::retime;
+pub use fraig::{fraig, Fraig};
+pub use share::share;
+pub use memory_infer::memory_infer;
+pub use registers::{shift_registers, counters, ShiftRegister, Counter};
+pub use clock_domains::{clock_domains, ClockDomain, ClockCrossing, CrossingKind, ClockDomainReport};
 
 pub fn canonicalize(design: &mut Design) {
     for iter in 1.. {
@@ -39,3 +55,49 @@ pub fn canonicalize(design: &mut Design) {
         }
     }
 }
+
+/// Passes run when synthesizing a design without a target.
+pub const GENERIC_PASSES: &[&str] = &[
+    "fsm",
+    "memory-infer",
+    "share",
+    "decision",
+    "canonicalize",
+    "lower-operators",
+    "canonicalize",
+    "lower-logic",
+    "fraig",
+    "chain-rebalance",
+    "canonicalize",
+    "tree-rebalance",
+];
+
+/// Registers the generic passes with `passes`.
+pub fn register_passes(passes: &mut PassManager) {
+    passes.register("unname", unname);
+    passes.register("decision", decision);
+    passes.register("fsm", |design| {
+        fsm(design);
+    });
+    passes.register("memory-infer", |design| {
+        memory_infer(design);
+    });
+    passes.register("share", share);
+    passes.register("canonicalize", canonicalize);
+    passes.register("lower-arith", lower_arith);
+    passes.register("iobuf-insert", iobuf_insert);
+    passes.register("lower-operators", |design| {
+        let architecture = design.target().map(|target| target.mul_architecture()).unwrap_or_default();
+        design.rewrite(&[&LowerLt, &LowerMul::new(architecture), &LowerShift, &LowerCount]);
+    });
+    passes.register("lower-logic", |design| {
+        design.rewrite(&[&Normalize, &LowerEq, &LowerMux, &SimpleAigOpt]);
+    });
+    passes.register("normalize", |design| {
+        design.rewrite(&[&Normalize]);
+    });
+    passes.register("fraig", fraig);
+    passes.register("chain-rebalance", chain_rebalance);
+    passes.register("tree-rebalance", tree_rebalance);
+    passes.register("retime", retime);
+}
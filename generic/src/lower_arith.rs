@@ -1,4 +1,6 @@
-use prjunnamed_netlist::{Design, Cell, Value, Net};
+use prjunnamed_netlist::{Design, Cell, Value, Net, lower_clz, lower_ctz, lower_popcnt};
+
+use crate::multiplier::lower_mul;
 
 fn add_horiz_or(design: &Design, value: Value) -> Net {
     let mut nets = Vec::from_iter(value.iter());
@@ -39,6 +41,7 @@ fn lower_shift(
 }
 
 pub fn lower_arith(design: &mut Design) {
+    let mul_architecture = design.target().map(|target| target.mul_architecture()).unwrap_or_default();
     for cell_ref in design.iter_cells() {
         let _guard = design.use_metadata_from(&[cell_ref]);
         let new_cell = match &*cell_ref.get() {
@@ -77,17 +80,10 @@ pub fn lower_arith(design: &mut Design) {
                 let shift = |value: &Value, amount| value.slice(amount..).concat(Value::undef(amount));
                 lower_shift(design, a, b, *stride, shift, Value::undef(a.len()))
             }
-            Cell::Mul(a, b) => {
-                let mut value = Value::zero(a.len());
-                for (index, bit) in b.iter().enumerate() {
-                    value = design.add_adc(
-                        value,
-                        Value::zero(index).concat(design.add_mux(bit, a, Value::zero(a.len()))),
-                        Net::ZERO,
-                    );
-                }
-                Cell::Buf(value.slice(..a.len()))
-            }
+            Cell::Mul(a, b) => Cell::Buf(lower_mul(&|cell| design.add_cell(cell), mul_architecture, a, b)),
+            Cell::Popcnt(a) => Cell::Buf(lower_popcnt(&|cell| design.add_cell(cell), a)),
+            Cell::Clz(a) => Cell::Buf(lower_clz(&|cell| design.add_cell(cell), a)),
+            Cell::Ctz(a) => Cell::Buf(lower_ctz(&|cell| design.add_cell(cell), a)),
             Cell::UDiv(..)
             | Cell::UMod(..)
             | Cell::SDivTrunc(..)
@@ -0,0 +1,324 @@
+//! Inference of memories from register arrays.
+//!
+//! The `memory_infer` pass finds groups of flip-flops that are written by a single address-decoded port and
+//! read through multiplexers, and replaces them with a `memory` cell, which the target may map to a block RAM.
+//!
+//! The flip-flops of a group share their clock and data input, and each of them is enabled by a conjunction of
+//! the same terms (the write enable) and a term that compares the same write address to the index of the row,
+//! either using an `eq` cell or an output of a `match` cell. The rows must be numbered from zero without gaps.
+//!
+//! Every use of the flip-flops must be a read port, which is either an `xshr` cell that selects a row of
+//! the concatenated rows (with a stride equal to the row width, or a smaller stride and a shifted address), or
+//! a complete tree of `mux` cells that selects row `n` when the select inputs, starting with the ones closest
+//! to the rows, are equal to `n`.
+
+use std::collections::HashMap;
+
+use prjunnamed_netlist::{
+    Cell, CellRef, Const, ControlNet, Design, Memory, MemoryReadPort, MemoryWritePort, Net, Trit, Value,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct WritePort {
+    clock: ControlNet,
+    data: Value,
+    addr: Value,
+    enable: Vec<Net>,
+}
+
+struct ReadPort<'a> {
+    addr: Value,
+    output: Value,
+    /// The cell computing the output.
+    root: CellRef<'a>,
+    /// The cells that use the rows directly.
+    readers: Vec<CellRef<'a>>,
+    /// The `mux` cells between the readers and the root. Only the root may be used elsewhere.
+    internal: Vec<CellRef<'a>>,
+}
+
+/// Collects the terms of the conjunction computed by `net`.
+fn conjuncts(design: &Design, net: Net, terms: &mut Vec<Net>) {
+    if let Ok((cell_ref, _)) = design.find_cell(net) {
+        match &*cell_ref.get() {
+            Cell::And(arg1, arg2) if arg1.len() == 1 => {
+                conjuncts(design, arg1[0], terms);
+                conjuncts(design, arg2[0], terms);
+                return;
+            }
+            Cell::Aig(ControlNet::Pos(arg1), ControlNet::Pos(arg2)) => {
+                conjuncts(design, *arg1, terms);
+                conjuncts(design, *arg2, terms);
+                return;
+            }
+            _ => (),
+        }
+    }
+    if net != Net::ONE && !terms.contains(&net) {
+        terms.push(net);
+    }
+}
+
+/// If `net` is set when `addr` is equal to `row` (and `enable` is set), returns `(addr, row, enable)`.
+fn decode_row(design: &Design, net: Net) -> Option<(Value, usize, Option<Net>)> {
+    let (cell_ref, offset) = design.find_cell(net).ok()?;
+    let defined = |value: &Const| value.iter().all(|trit| trit != Trit::Undef);
+    match &*cell_ref.get() {
+        Cell::Eq(arg1, arg2) => {
+            let (addr, row) = match (arg1.as_const(), arg2.as_const()) {
+                (None, Some(row)) => (arg1.clone(), row),
+                (Some(row), None) => (arg2.clone(), row),
+                _ => return None,
+            };
+            Some((addr, u64::try_from(&row).ok()? as usize, None))
+        }
+        Cell::Match(match_cell) => {
+            // an output is only set if none of the previous patterns match, which is implied if they are all
+            // distinct constants
+            let [pattern] = &match_cell.patterns[offset][..] else { return None };
+            for alternatives in &match_cell.patterns[..offset] {
+                if alternatives.iter().any(|alternative| !defined(alternative) || alternative == pattern) {
+                    return None;
+                }
+            }
+            let row = u64::try_from(pattern).ok()? as usize;
+            let enable = Some(match_cell.enable).filter(|&enable| enable != Net::ONE);
+            Some((match_cell.value.clone(), row, enable))
+        }
+        _ => None,
+    }
+}
+
+struct Inference<'a> {
+    design: &'a Design,
+    users: HashMap<Net, Vec<CellRef<'a>>>,
+}
+
+impl<'a> Inference<'a> {
+    fn new(design: &'a Design) -> Self {
+        let mut users: HashMap<Net, Vec<CellRef<'a>>> = HashMap::new();
+        for cell_ref in design.iter_cells() {
+            cell_ref.visit(|net| {
+                let net_users = users.entry(net).or_default();
+                if net_users.last() != Some(&cell_ref) {
+                    net_users.push(cell_ref);
+                }
+            });
+        }
+        Inference { design, users }
+    }
+
+    /// Returns the cells other than `debug` cells that use `value`.
+    fn users(&self, value: &Value) -> Vec<CellRef<'a>> {
+        let mut users = vec![];
+        for net in value {
+            for &cell_ref in self.users.get(&net).into_iter().flatten() {
+                if !matches!(&*cell_ref.get(), Cell::Debug(..)) && !users.contains(&cell_ref) {
+                    users.push(cell_ref);
+                }
+            }
+        }
+        users
+    }
+
+    /// Decodes a tree of `mux` cells selecting between `rows`. Returns the select inputs, starting with the ones
+    /// closest to the rows, the indices of the rows selected by each value of the select inputs, the `mux` cells
+    /// using the rows, and the other `mux` cells.
+    #[allow(clippy::type_complexity)]
+    fn decode_tree(
+        &self,
+        rows: &HashMap<Value, usize>,
+        value: &Value,
+    ) -> Option<(Vec<Net>, Vec<usize>, Vec<CellRef<'a>>, Vec<CellRef<'a>>)> {
+        if let Some(&row) = rows.get(value) {
+            return Some((vec![], vec![row], vec![], vec![]));
+        }
+        let (cell_ref, 0) = self.design.find_cell(value[0]).ok()? else { return None };
+        if cell_ref.output() != *value {
+            return None;
+        }
+        let Cell::Mux(select, arg1, arg2) = &*cell_ref.get() else { return None };
+        let (selects1, order1, readers1, internal1) = self.decode_tree(rows, arg1)?;
+        let (mut selects, mut order, mut readers, mut internal) = self.decode_tree(rows, arg2)?;
+        if selects != selects1 {
+            return None;
+        }
+        selects.push(*select);
+        order.extend(order1);
+        if selects.len() == 1 {
+            readers.push(cell_ref);
+        } else {
+            readers.extend(readers1);
+            internal.extend(internal1);
+            internal.push(cell_ref);
+        }
+        Some((selects, order, readers, internal))
+    }
+
+    /// Recognizes a read port whose output is computed by `cell_ref` from `rows`.
+    fn read_port(&self, rows: &[Value], cell_ref: CellRef<'a>) -> Option<ReadPort<'a>> {
+        let width = rows[0].len();
+        match &*cell_ref.get() {
+            Cell::XShr(value, amount, stride) => {
+                let stride = *stride as usize;
+                let concatenated = Value::from_iter(rows.iter().flat_map(|row| row.iter()));
+                if value.len() < concatenated.len()
+                    || value.slice(..concatenated.len()) != concatenated
+                    || value.slice(concatenated.len()..).iter().any(|net| net != Net::UNDEF)
+                {
+                    return None;
+                }
+                let addr = if stride == width {
+                    amount.clone()
+                } else if stride != 0 && width.is_multiple_of(stride) && (width / stride).is_power_of_two() {
+                    let shift = (width / stride).ilog2() as usize;
+                    if amount.len() < shift || amount.slice(..shift).iter().any(|net| net != Net::ZERO) {
+                        return None;
+                    }
+                    amount.slice(shift..)
+                } else {
+                    return None;
+                };
+                let output = cell_ref.output();
+                if !self.users(&output.slice(width..)).is_empty() {
+                    return None;
+                }
+                Some(ReadPort {
+                    addr,
+                    output: output.slice(..width),
+                    root: cell_ref,
+                    readers: vec![cell_ref],
+                    internal: vec![],
+                })
+            }
+            Cell::Mux(..) => {
+                let row_index = HashMap::from_iter(rows.iter().enumerate().map(|(index, row)| (row.clone(), index)));
+                // find the outermost `mux` cell that completes a tree
+                let mut chain = vec![cell_ref];
+                loop {
+                    let output = chain.last().unwrap().output();
+                    let [user] = self.users(&output)[..] else { break };
+                    match &*user.get() {
+                        Cell::Mux(_, arg1, arg2) if *arg1 == output || *arg2 == output => chain.push(user),
+                        _ => break,
+                    }
+                }
+                for &root in chain.iter().rev() {
+                    let Some((selects, order, readers, internal)) = self.decode_tree(&row_index, &root.output()) else {
+                        continue;
+                    };
+                    if order.iter().enumerate().any(|(index, &row)| index != row) || order.len() != rows.len() {
+                        continue;
+                    }
+                    return Some(ReadPort {
+                        addr: Value::from(selects),
+                        output: root.output(),
+                        root,
+                        readers,
+                        internal,
+                    });
+                }
+                None
+            }
+            _ => None,
+        }
+    }
+
+    /// Replaces the flip-flops in `rows` (ordered by row index) with a memory if all of their uses are read ports.
+    fn infer(&self, write_port: &WritePort, flip_flops: &[CellRef<'a>]) -> bool {
+        let rows = Vec::from_iter(flip_flops.iter().map(|cell_ref| cell_ref.output()));
+        let width = write_port.data.len();
+        let mut read_ports: Vec<ReadPort> = vec![];
+        for row in &rows {
+            for user in self.users(row) {
+                if read_ports.iter().any(|read_port| read_port.readers.contains(&user)) {
+                    continue;
+                }
+                let Some(read_port) = self.read_port(&rows, user) else { return false };
+                read_ports.push(read_port);
+            }
+        }
+        if read_ports.is_empty() {
+            return false;
+        }
+        for read_port in &read_ports {
+            for &cell_ref in read_port.readers.iter().chain(&read_port.internal) {
+                if cell_ref != read_port.root
+                    && self.users(&cell_ref.output()).iter().any(|user| !read_port.internal.contains(user))
+                {
+                    return false;
+                }
+            }
+        }
+
+        let design = self.design;
+        let mut enable = write_port.enable.iter().copied();
+        let first = enable.next().unwrap_or(Net::ONE);
+        let enable = enable.fold(first, |acc, term| design.add_and(acc, term)[0]);
+        let mut init_value = Const::new();
+        for cell_ref in flip_flops {
+            let Cell::Dff(flip_flop) = &*cell_ref.get() else { unreachable!() };
+            init_value.extend(flip_flop.init_value.iter());
+        }
+        let output = design.add_memory(Memory {
+            depth: rows.len(),
+            width,
+            init_value,
+            write_ports: vec![MemoryWritePort {
+                addr: write_port.addr.clone(),
+                data: write_port.data.clone(),
+                mask: enable.repeat(width),
+                clock: Some(write_port.clock),
+            }],
+            read_ports: Vec::from_iter(
+                read_ports.iter().map(|read_port| MemoryReadPort::new_asynchronous(read_port.addr.clone(), width)),
+            ),
+        });
+        for (index, read_port) in read_ports.iter().enumerate() {
+            design.replace_value(&read_port.output, output.slice(index * width..(index + 1) * width));
+        }
+        true
+    }
+}
+
+/// Replaces arrays of flip-flops that are written through an address decoder and read through multiplexers
+/// with `memory` cells. Returns `true` if any memories were inferred.
+pub fn memory_infer(design: &mut Design) -> bool {
+    let inference = Inference::new(design);
+    let mut groups: HashMap<WritePort, Vec<(usize, CellRef)>> = HashMap::new();
+    for cell_ref in design.iter_cells() {
+        let Cell::Dff(flip_flop) = &*cell_ref.get() else { continue };
+        if flip_flop.has_clear() || flip_flop.has_reset() || flip_flop.data.is_empty() {
+            continue;
+        }
+        let ControlNet::Pos(enable) = flip_flop.enable else { continue };
+        let mut terms = vec![];
+        conjuncts(design, enable, &mut terms);
+        let decoded = Vec::from_iter(terms.iter().filter_map(|&term| Some((term, decode_row(design, term)?))));
+        let [(decoder, (addr, row, extra))] = &decoded[..] else { continue };
+        terms.retain(|term| term != decoder);
+        terms.extend(extra);
+        terms.sort();
+        terms.dedup();
+        let write_port =
+            WritePort { clock: flip_flop.clock, data: flip_flop.data.clone(), addr: addr.clone(), enable: terms };
+        groups.entry(write_port).or_default().push((*row, cell_ref));
+    }
+
+    let mut groups = Vec::from_iter(groups);
+    groups.sort_by_key(|(_, flip_flops)| flip_flops.iter().map(|&(_, cell_ref)| cell_ref).min());
+    let mut inferred = false;
+    for (write_port, mut flip_flops) in groups {
+        flip_flops.sort_by_key(|&(row, _)| row);
+        if flip_flops.len() < 2 || flip_flops.iter().enumerate().any(|(index, &(row, _))| index != row) {
+            continue;
+        }
+        let flip_flops = Vec::from_iter(flip_flops.into_iter().map(|(_, cell_ref)| cell_ref));
+        if inference.infer(&write_port, &flip_flops) {
+            inferred = true;
+        }
+    }
+    drop(inference);
+    design.compact();
+    inferred
+}
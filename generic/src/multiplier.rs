@@ -0,0 +1,178 @@
+//! Lowering of `mul` cells.
+//!
+//! The product is computed as a sum of partial products, each of which is shifted and truncated to the width of
+//! the output. The partial products are either the operand `a` gated by each bit of the operand `b`, or `a`
+//! multiplied by each radix-4 Booth digit of `b`. They are summed either by a chain of adders, or by a Wallace
+//! tree of carry-save adders that reduces them to two values, followed by a single adder.
+//!
+//! If either operand is a constant, the partial products are the other operand shifted by the position of each
+//! nonzero digit of the canonical signed digit form of the constant, which has the fewest nonzero digits.
+
+use prjunnamed_netlist::{Cell, Const, MulArchitecture, Net, Trit, Value};
+
+/// Returns the digits of the canonical signed digit form of `value`, starting with the least significant one,
+/// truncated to the width of `value`.
+fn csd_digits(value: &Const) -> Vec<i8> {
+    let bits = Vec::from_iter(value.iter().map(|trit| trit == Trit::One));
+    let bit = |index: usize| bits.get(index).copied().unwrap_or(false);
+    // the digits are the differences between the bits of `3 * value` and of `value`, shifted right by one
+    let mut triple = vec![];
+    let mut carry = false;
+    for index in 0..bits.len() + 2 {
+        let (addend1, addend2) = (bit(index), index.checked_sub(1).is_some_and(bit));
+        triple.push(addend1 ^ addend2 ^ carry);
+        carry = (addend1 && addend2) || (carry && (addend1 ^ addend2));
+    }
+    Vec::from_iter((0..bits.len()).map(|index| triple[index + 1] as i8 - bit(index + 1) as i8))
+}
+
+/// Shifts `value` left by `amount`, keeping the width.
+fn shift(value: &Value, amount: usize) -> Value {
+    Value::zero(amount).concat(value.slice(..value.len() - amount))
+}
+
+/// Returns the partial products of `a` multiplied by the constant `b`.
+fn csd_partial_products(add_cell: &dyn Fn(Cell) -> Value, a: &Value, b: &Const) -> Vec<Value> {
+    let mut partial_products = vec![];
+    // `-(a << position)` is `!(a << position) + (1 << position)`, and the digits are at distinct positions
+    let mut correction = Const::zero(a.len());
+    for (position, digit) in csd_digits(b).into_iter().enumerate() {
+        match digit {
+            1 => partial_products.push(shift(a, position)),
+            -1 => {
+                let inverted = add_cell(Cell::Not(a.slice(..a.len() - position)));
+                partial_products.push(Value::zero(position).concat(inverted));
+                correction[position] = Trit::One;
+            }
+            _ => (),
+        }
+    }
+    if correction.iter().any(|trit| trit == Trit::One) {
+        partial_products.push(Value::from(correction));
+    }
+    partial_products
+}
+
+/// Returns the partial products of `a` multiplied by each bit of `b`.
+fn simple_partial_products(add_cell: &dyn Fn(Cell) -> Value, a: &Value, b: &Value) -> Vec<Value> {
+    Vec::from_iter(b.iter().enumerate().map(|(position, bit)| {
+        let width = a.len() - position;
+        Value::zero(position).concat(add_cell(Cell::Mux(bit, a.slice(..width), Value::zero(width))))
+    }))
+}
+
+/// Returns the partial products of `a` multiplied by each radix-4 Booth digit of `b`.
+///
+/// The digits treat `b` as a signed number, which does not change the truncated product.
+fn booth_partial_products(add_cell: &dyn Fn(Cell) -> Value, a: &Value, b: &Value) -> Vec<Value> {
+    let bit = |index: usize| b[index.min(b.len() - 1)];
+    let mut partial_products = vec![];
+    // a negative digit selects `!(a * -digit) + 1`, and the digits are at distinct positions
+    let mut corrections = Value::zero(a.len());
+    for position in (0..a.len()).step_by(2) {
+        let width = a.len() - position;
+        let low = if position == 0 { Net::ZERO } else { bit(position - 1) };
+        let (middle, high) = (bit(position), bit(position + 1));
+        // the digit is `-2 * high + middle + low`
+        let one = add_cell(Cell::Xor(middle.into(), low.into()));
+        let not_one = add_cell(Cell::Not(one.clone()));
+        let two = add_cell(Cell::And(not_one, add_cell(Cell::Xor(high.into(), middle.into()))));
+        let doubled = shift(&a.slice(..width), 1);
+        let selected = add_cell(Cell::Mux(two[0], doubled, Value::zero(width)));
+        let selected = add_cell(Cell::Mux(one[0], a.slice(..width), selected));
+        let inverted = add_cell(Cell::Xor(selected, high.repeat(width)));
+        partial_products.push(Value::zero(position).concat(inverted));
+        corrections[position] = high;
+    }
+    partial_products.push(corrections);
+    partial_products
+}
+
+/// Sums `values` using a chain of adders.
+fn sum_chain(add_cell: &dyn Fn(Cell) -> Value, width: usize, values: Vec<Value>) -> Value {
+    let mut values = values.into_iter();
+    let Some(mut sum) = values.next() else { return Value::zero(width) };
+    for value in values {
+        sum = add_cell(Cell::Adc(sum, value, Net::ZERO)).slice(..width);
+    }
+    sum
+}
+
+/// Sums `values` using a Wallace tree of carry-save adders followed by a single adder.
+fn sum_tree(add_cell: &dyn Fn(Cell) -> Value, width: usize, values: Vec<Value>) -> Value {
+    let mut columns = vec![vec![]; width];
+    for value in &values {
+        for (index, net) in value.iter().enumerate() {
+            if net != Net::ZERO {
+                columns[index].push(net);
+            }
+        }
+    }
+    while columns.iter().any(|column| column.len() > 2) {
+        // all of the full adders of a stage are built from the same few cells
+        let (mut addends1, mut addends2, mut addends3) = (vec![], vec![], vec![]);
+        let mut positions = vec![];
+        let mut next_columns = vec![vec![]; width];
+        for (index, column) in columns.iter().enumerate() {
+            let mut chunks = column.chunks_exact(3);
+            for chunk in &mut chunks {
+                addends1.push(chunk[0]);
+                addends2.push(chunk[1]);
+                addends3.push(chunk[2]);
+                positions.push(index);
+            }
+            next_columns[index].extend(chunks.remainder());
+        }
+        let (addends1, addends2, addends3) = (Value::from(addends1), Value::from(addends2), Value::from(addends3));
+        let partial_sum = add_cell(Cell::Xor(addends1.clone(), addends2.clone()));
+        let sum = add_cell(Cell::Xor(partial_sum.clone(), addends3.clone()));
+        let generate = add_cell(Cell::And(addends1, addends2));
+        let propagate = add_cell(Cell::And(partial_sum, addends3));
+        let carry = add_cell(Cell::Or(generate, propagate));
+        for (adder, &index) in positions.iter().enumerate() {
+            next_columns[index].push(sum[adder]);
+            if index + 1 < width {
+                next_columns[index + 1].push(carry[adder]);
+            }
+        }
+        columns = next_columns;
+    }
+    let row = |row: usize| Value::from_iter(columns.iter().map(|column| column.get(row).copied().unwrap_or(Net::ZERO)));
+    add_cell(Cell::Adc(row(0), row(1), Net::ZERO)).slice(..width)
+}
+
+/// Lowers `a * b` (truncated to the width of the operands) using the cells created by `add_cell`.
+pub(crate) fn lower_mul(
+    add_cell: &dyn Fn(Cell) -> Value,
+    architecture: MulArchitecture,
+    a: &Value,
+    b: &Value,
+) -> Value {
+    let width = a.len();
+    let defined = |value: &Value| value.as_const().filter(|value| value.iter().all(|trit| trit != Trit::Undef));
+    let partial_products = if let Some(b_const) = defined(b) {
+        csd_partial_products(add_cell, a, &b_const)
+    } else if let Some(a_const) = defined(a) {
+        csd_partial_products(add_cell, b, &a_const)
+    } else {
+        match architecture {
+            MulArchitecture::Array => {
+                let mut value = Value::zero(width);
+                for (index, bit) in b.iter().enumerate() {
+                    value = add_cell(Cell::Adc(
+                        value,
+                        Value::zero(index).concat(add_cell(Cell::Mux(bit, a.clone(), Value::zero(width)))),
+                        Net::ZERO,
+                    ));
+                }
+                return value.slice(..width);
+            }
+            MulArchitecture::Wallace => simple_partial_products(add_cell, a, b),
+            MulArchitecture::BoothArray | MulArchitecture::BoothWallace => booth_partial_products(add_cell, a, b),
+        }
+    };
+    match architecture {
+        MulArchitecture::Array | MulArchitecture::BoothArray => sum_chain(add_cell, width, partial_products),
+        MulArchitecture::Wallace | MulArchitecture::BoothWallace => sum_tree(add_cell, width, partial_products),
+    }
+}
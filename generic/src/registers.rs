@@ -0,0 +1,271 @@
+//! Shift register and counter recognition.
+//!
+//! These analyses find groups of `dff` cells that targets may implement with dedicated resources: shift registers,
+//! which can be mapped to LUT-based shift register primitives or to FIFOs, and counters, which should be kept on
+//! the carry chain. They expect enables and resets to be folded into the flip-flops, as done by [`canonicalize`].
+//!
+//! [`canonicalize`]: crate::canonicalize
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+
+use prjunnamed_netlist::{Cell, CellRef, Const, ControlNet, Design, FlipFlop, Net, Trit, Value};
+
+type Controls = (ControlNet, ControlNet, ControlNet, ControlNet, bool);
+
+fn controls(flip_flop: &FlipFlop) -> Controls {
+    let reset_over_enable = flip_flop.has_reset() && flip_flop.has_enable() && flip_flop.reset_over_enable;
+    (flip_flop.clock, flip_flop.clear, flip_flop.reset, flip_flop.enable, reset_over_enable)
+}
+
+/// Returns a flip-flop with the controls of `flip_flop` and the bits at `offsets`.
+fn select_bits(flip_flop: &FlipFlop, offsets: &[usize]) -> FlipFlop {
+    FlipFlop {
+        data: Value::from_iter(offsets.iter().map(|&offset| flip_flop.data[offset])),
+        clear_value: Const::from_iter(offsets.iter().map(|&offset| flip_flop.clear_value[offset])),
+        reset_value: Const::from_iter(offsets.iter().map(|&offset| flip_flop.reset_value[offset])),
+        init_value: Const::from_iter(offsets.iter().map(|&offset| flip_flop.init_value[offset])),
+        ..flip_flop.clone()
+    }
+}
+
+/// Returns the names of the `name` and `debug` cells whose value consists only of nets that `owner` maps to
+/// the same index.
+pub(crate) fn collect_names(design: &Design, owner: &HashMap<Net, usize>, count: usize) -> Vec<Vec<String>> {
+    let mut names = vec![vec![]; count];
+    for cell_ref in design.iter_cells() {
+        let (Cell::Name(name, value) | Cell::Debug(name, value)) = &*cell_ref.get() else { continue };
+        let mut indices = value.iter().map(|net| owner.get(&net));
+        let Some(Some(&index)) = indices.next() else { continue };
+        if indices.all(|other| other == Some(&index)) && !names[index].contains(name) {
+            names[index].push(name.clone());
+        }
+    }
+    names
+}
+
+/// The number of uses of each net, not including names.
+fn use_counts(design: &Design) -> HashMap<Net, usize> {
+    let mut use_counts: HashMap<Net, usize> = HashMap::new();
+    for cell_ref in design.iter_cells() {
+        if matches!(&*cell_ref.get(), Cell::Name(..) | Cell::Debug(..)) {
+            continue;
+        }
+        cell_ref.visit(|net| *use_counts.entry(net).or_default() += 1);
+    }
+    use_counts
+}
+
+/// A shift register recognized by [`shift_registers`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShiftRegister {
+    /// Names of the shift register in the source code.
+    pub names: Vec<String>,
+    /// The flip-flops of each stage, from the first to the last. All of them have the same controls, and the data
+    /// of each stage except for the first one is the output of the previous stage.
+    pub stages: Vec<FlipFlop>,
+    /// The outputs of each stage. The outputs of every stage except for the last one are only used by
+    /// the next stage.
+    pub outputs: Vec<Value>,
+}
+
+impl ShiftRegister {
+    /// The value shifted into the first stage.
+    pub fn input(&self) -> &Value {
+        &self.stages[0].data
+    }
+
+    /// The output of the last stage.
+    pub fn output(&self) -> &Value {
+        self.outputs.last().unwrap()
+    }
+
+    pub fn width(&self) -> usize {
+        self.outputs[0].len()
+    }
+}
+
+/// Finds the shift registers in `design`.
+///
+/// A shift register is a chain of two or more flip-flop bits with the same controls, where the data of each bit is
+/// the output of the previous one, and the output of every bit except for the last one is used only by the next
+/// one. Chains whose bits are in the same `dff` cells are grouped into a single shift register with several bits
+/// per stage. Rings of flip-flops (e.g. rotating registers) are not shift registers, since they have no input.
+pub fn shift_registers(design: &Design) -> Vec<ShiftRegister> {
+    let use_counts = use_counts(design);
+    let mut flip_flop_bits: HashMap<Net, (CellRef, usize)> = HashMap::new();
+    for cell_ref in design.iter_cells() {
+        if let Cell::Dff(_) = &*cell_ref.get() {
+            for (offset, net) in cell_ref.output().iter().enumerate() {
+                flip_flop_bits.insert(net, (cell_ref, offset));
+            }
+        }
+    }
+
+    // the next bit of each bit in a chain
+    let mut next_bits: HashMap<Net, Net> = HashMap::new();
+    let mut has_prev_bit: HashSet<Net> = HashSet::new();
+    for cell_ref in design.iter_cells() {
+        let Cell::Dff(flip_flop) = &*cell_ref.get() else { continue };
+        for (offset, data) in flip_flop.data.iter().enumerate() {
+            let Some(&(prev_cell_ref, _)) = flip_flop_bits.get(&data) else { continue };
+            let Cell::Dff(prev_flip_flop) = &*prev_cell_ref.get() else { unreachable!() };
+            if controls(prev_flip_flop) == controls(flip_flop) && use_counts.get(&data) == Some(&1) {
+                next_bits.insert(data, cell_ref.output()[offset]);
+                has_prev_bit.insert(cell_ref.output()[offset]);
+            }
+        }
+    }
+
+    // chains are grouped by the cells containing each of their stages
+    let mut groups: Vec<Vec<Vec<Net>>> = vec![];
+    let mut group_indices: HashMap<Vec<Net>, usize> = HashMap::new();
+    for cell_ref in design.iter_cells() {
+        if !matches!(&*cell_ref.get(), Cell::Dff(_)) {
+            continue;
+        }
+        for net in cell_ref.output().iter() {
+            if has_prev_bit.contains(&net) || !next_bits.contains_key(&net) {
+                continue;
+            }
+            let mut chain = vec![net];
+            while let Some(&next_net) = next_bits.get(chain.last().unwrap()) {
+                chain.push(next_net);
+            }
+            let key = Vec::from_iter(chain.iter().map(|net| flip_flop_bits[net].0.output()[0]));
+            let index = *group_indices.entry(key).or_insert_with(|| {
+                groups.push(vec![]);
+                groups.len() - 1
+            });
+            groups[index].push(chain);
+        }
+    }
+
+    let mut shift_registers = vec![];
+    let mut owner: HashMap<Net, usize> = HashMap::new();
+    for chains in groups {
+        let mut stages = vec![];
+        let mut outputs = vec![];
+        for stage_index in 0..chains[0].len() {
+            let (cell_ref, _) = flip_flop_bits[&chains[0][stage_index]];
+            let Cell::Dff(flip_flop) = &*cell_ref.get() else { unreachable!() };
+            let offsets = Vec::from_iter(chains.iter().map(|chain| flip_flop_bits[&chain[stage_index]].1));
+            stages.push(select_bits(flip_flop, &offsets));
+            outputs.push(Value::from_iter(chains.iter().map(|chain| chain[stage_index])));
+        }
+        for net in outputs.iter().flat_map(Value::iter) {
+            owner.insert(net, shift_registers.len());
+        }
+        shift_registers.push(ShiftRegister { names: vec![], stages, outputs });
+    }
+    let names = collect_names(design, &owner, shift_registers.len());
+    for (shift_register, names) in shift_registers.iter_mut().zip(names) {
+        shift_register.names = names;
+    }
+    shift_registers
+}
+
+/// A counter recognized by [`counters`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Counter {
+    /// Names of the counter in the source code.
+    pub names: Vec<String>,
+    /// The flip-flop holding the count. Its data is the output of the `adc` cell adding `step` to the count.
+    pub flip_flop: FlipFlop,
+    /// The count, i.e. the output of the flip-flop.
+    pub output: Value,
+    /// The amount added to the count whenever the flip-flop is enabled and not reset, modulo the counter width.
+    /// Down counters have a step that is negative in two's complement.
+    pub step: Const,
+}
+
+impl Counter {
+    pub fn width(&self) -> usize {
+        self.output.len()
+    }
+}
+
+/// Finds the counters in `design`.
+///
+/// A counter is a group of flip-flop bits in a `dff` cell whose data are the low bits of the sum of an `adc` cell
+/// adding a constant to the outputs of the same bits. The bits of the count above the counter do not affect it,
+/// so only the low bits of a wider `adc` cell may form a counter, but the count must start at bit 0 of the sum.
+pub fn counters(design: &Design) -> Vec<Counter> {
+    let mut counters = vec![];
+    let mut owner: HashMap<Net, usize> = HashMap::new();
+    for cell_ref in design.iter_cells() {
+        let Cell::Adc(arg1, arg2, ci) = &*cell_ref.get() else { continue };
+        let Some(ci) = ci.as_const() else { continue };
+        let sum = cell_ref.output();
+        for (count, addend) in [(arg1, arg2), (arg2, arg1)] {
+            let mut flip_flop_cell_ref = None;
+            let mut offsets = vec![];
+            for (index, net) in count.iter().enumerate() {
+                let Ok((net_cell_ref, offset)) = design.find_cell(net) else { break };
+                let Cell::Dff(flip_flop) = &*net_cell_ref.get() else { break };
+                if *flip_flop_cell_ref.get_or_insert(net_cell_ref) != net_cell_ref
+                    || flip_flop.data[offset] != sum[index]
+                    || offsets.contains(&offset)
+                {
+                    break;
+                }
+                offsets.push(offset);
+            }
+            let Some(flip_flop_cell_ref) = flip_flop_cell_ref.filter(|_| !offsets.is_empty()) else { continue };
+            let Some(addend) = addend.slice(..offsets.len()).as_const() else { continue };
+            let step = addend.adc(Const::zero(offsets.len()), ci).slice(..offsets.len());
+            if step.has_undef() || step.iter().all(|trit| trit == Trit::Zero) {
+                continue;
+            }
+            let Cell::Dff(flip_flop) = &*flip_flop_cell_ref.get() else { unreachable!() };
+            let output = Value::from_iter(offsets.iter().map(|&offset| flip_flop_cell_ref.output()[offset]));
+            for net in output.iter() {
+                owner.insert(net, counters.len());
+            }
+            counters.push(Counter { names: vec![], flip_flop: select_bits(flip_flop, &offsets), output, step });
+            break;
+        }
+    }
+    let names = collect_names(design, &owner, counters.len());
+    for (counter, names) in counters.iter_mut().zip(names) {
+        counter.names = names;
+    }
+    counters
+}
+
+fn write_controls(f: &mut std::fmt::Formatter<'_>, flip_flop: &FlipFlop) -> std::fmt::Result {
+    if flip_flop.has_enable() {
+        write!(f, ", enable")?;
+    }
+    if flip_flop.has_reset() {
+        write!(f, ", reset to {}", flip_flop.reset_value)?;
+    }
+    if flip_flop.has_clear() {
+        write!(f, ", clear to {}", flip_flop.clear_value)?;
+    }
+    Ok(())
+}
+
+impl Display for ShiftRegister {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.names.first() {
+            Some(name) => write!(f, "shift register {name:?}")?,
+            None => write!(f, "shift register")?,
+        }
+        write!(f, " ({} stages, {} bits", self.stages.len(), self.width())?;
+        write_controls(f, &self.stages[0])?;
+        writeln!(f, ")")
+    }
+}
+
+impl Display for Counter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.names.first() {
+            Some(name) => write!(f, "counter {name:?}")?,
+            None => write!(f, "counter")?,
+        }
+        write!(f, " ({} bits, step {}", self.width(), self.step)?;
+        write_controls(f, &self.flip_flop)?;
+        writeln!(f, ")")
+    }
+}
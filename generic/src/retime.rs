@@ -0,0 +1,337 @@
+//! Register retiming.
+//!
+//! The `retime` pass moves individual bits of `dff` cells across single-bit combinational cells (`aig`, `and`,
+//! `or`, `xor`, and `mux`) to balance the logic levels, as computed by [`LevelAnalysis`], before and after
+//! each register:
+//!
+//! - registers are moved forward, from the inputs of a cell to its output, if the logic driving the registers
+//!   is shallower than the logic driven by the cell;
+//! - a register is moved backward, from the output of a cell to its inputs, if the logic driving the cell is
+//!   deeper than the logic driven by the register.
+//!
+//! Registers are only moved together if they have the same clock, enable, reset, and clear, and their initial,
+//! reset, and clear values are recomputed for the new position. A register is not moved backward if the cell
+//! cannot produce its values. Registers moved forward must not have any users besides the cell and `debug` cells.
+//!
+//! The new registers take the metadata of the registers they replace, so that they can still be found by
+//! the names in the source code. `name` cells referring to a register prevent it from being moved forward.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use prjunnamed_netlist::{Cell, CellRef, Const, ControlNet, Design, FlipFlop, Net, RewriteRuleset, Trit, Value};
+
+use crate::LevelAnalysis;
+
+const MAX_ROUNDS: usize = 64;
+
+type Controls = (ControlNet, ControlNet, ControlNet, ControlNet, bool);
+
+fn controls(flip_flop: &FlipFlop) -> Controls {
+    (flip_flop.clock, flip_flop.clear, flip_flop.reset, flip_flop.enable, flip_flop.reset_over_enable)
+}
+
+/// Returns the distinct non-constant inputs of a cell that registers can be moved across.
+fn retimable_inputs(cell: &Cell) -> Option<Vec<Net>> {
+    let mut nets = match cell {
+        Cell::Aig(arg1, arg2) => vec![arg1.net(), arg2.net()],
+        Cell::And(arg1, arg2) | Cell::Or(arg1, arg2) | Cell::Xor(arg1, arg2) if arg1.len() == 1 => {
+            vec![arg1[0], arg2[0]]
+        }
+        Cell::Mux(arg1, arg2, arg3) if arg2.len() == 1 => vec![*arg1, arg2[0], arg3[0]],
+        _ => return None,
+    };
+    if nets.contains(&Net::UNDEF) {
+        return None;
+    }
+    let mut inputs = vec![];
+    for net in nets.drain(..) {
+        if !net.is_const() && !inputs.contains(&net) {
+            inputs.push(net);
+        }
+    }
+    if inputs.is_empty() { None } else { Some(inputs) }
+}
+
+fn evaluate(cell: &Cell, inputs: &[Net], values: &[bool]) -> bool {
+    let value = |net: Net| match net.as_const() {
+        Some(trit) => trit == Trit::One,
+        None => values[inputs.iter().position(|&input| input == net).unwrap()],
+    };
+    let control = |control_net: ControlNet| value(control_net.net()) ^ control_net.is_negative();
+    match cell {
+        Cell::Aig(arg1, arg2) => control(*arg1) && control(*arg2),
+        Cell::And(arg1, arg2) => value(arg1[0]) && value(arg2[0]),
+        Cell::Or(arg1, arg2) => value(arg1[0]) || value(arg2[0]),
+        Cell::Xor(arg1, arg2) => value(arg1[0]) ^ value(arg2[0]),
+        Cell::Mux(arg1, arg2, arg3) => {
+            if value(*arg1) {
+                value(arg2[0])
+            } else {
+                value(arg3[0])
+            }
+        }
+        _ => unreachable!(),
+    }
+}
+
+fn assignments(count: usize) -> impl Iterator<Item = Vec<bool>> {
+    (0..1usize << count).map(move |assignment| Vec::from_iter((0..count).map(|index| assignment & (1 << index) != 0)))
+}
+
+/// Computes the output of `cell` for the given input values. The output is undefined only if the undefined inputs
+/// can be chosen to produce either value.
+fn image(cell: &Cell, inputs: &[Net], trits: &[Trit]) -> Trit {
+    let mut result = None;
+    for values in assignments(inputs.len()) {
+        if trits.iter().zip(&values).any(|(&trit, &value)| trit != Trit::Undef && trit != Trit::from(value)) {
+            continue;
+        }
+        let output = Trit::from(evaluate(cell, inputs, &values));
+        result = match result {
+            None => Some(output),
+            Some(trit) if trit == output => Some(trit),
+            Some(_) => Some(Trit::Undef),
+        };
+    }
+    result.unwrap()
+}
+
+/// Computes input values for which `cell` outputs `trit`, if there are any.
+fn preimage(cell: &Cell, inputs: &[Net], trit: Trit) -> Option<Vec<Trit>> {
+    if trit == Trit::Undef {
+        return Some(vec![Trit::Undef; inputs.len()]);
+    }
+    assignments(inputs.len())
+        .find(|values| Trit::from(evaluate(cell, inputs, values)) == trit)
+        .map(|values| Vec::from_iter(values.into_iter().map(Trit::from)))
+}
+
+fn substitute(cell: &Cell, inputs: &[Net], nets: &[Net]) -> Cell {
+    let mut cell = cell.clone();
+    cell.visit_mut(|net| {
+        if let Some(index) = inputs.iter().position(|input| input == net) {
+            *net = nets[index];
+        }
+    });
+    cell
+}
+
+struct Timing {
+    /// Number of logic levels between the registers or inputs and a net.
+    levels: LevelAnalysis,
+    /// Number of logic levels between a net and the registers or outputs.
+    depths: HashMap<Net, u32>,
+}
+
+impl Timing {
+    fn new(design: &Design) -> Self {
+        let cells = Vec::from_iter(design.iter_cells_topo());
+        let levels = LevelAnalysis::new();
+        for cell_ref in &cells {
+            levels.cell_added(design, &cell_ref.get(), &cell_ref.output());
+        }
+        let mut depths: HashMap<Net, u32> = HashMap::new();
+        for cell_ref in cells.iter().rev() {
+            let cell = cell_ref.get();
+            if cell.has_state(design) {
+                continue;
+            }
+            let cost = if let Cell::Not(..) = &*cell { 0 } else { 1 };
+            let depth = cell_ref.output().iter().map(|net| depths.get(&net).copied().unwrap_or(0)).max().unwrap_or(0);
+            cell_ref.visit(|net| {
+                let entry = depths.entry(net).or_default();
+                *entry = (*entry).max(depth + cost);
+            });
+        }
+        Timing { levels, depths }
+    }
+
+    fn depth(&self, net: Net) -> u32 {
+        self.depths.get(&net).copied().unwrap_or(0)
+    }
+}
+
+struct Retimer<'a> {
+    design: &'a Design,
+    timing: Timing,
+    users: HashMap<Net, Vec<CellRef<'a>>>,
+    claimed_cells: BTreeSet<CellRef<'a>>,
+    claimed_bits: BTreeSet<(CellRef<'a>, usize)>,
+}
+
+impl<'a> Retimer<'a> {
+    fn new(design: &'a Design) -> Self {
+        let mut users: HashMap<Net, Vec<CellRef>> = HashMap::new();
+        for cell_ref in design.iter_cells() {
+            cell_ref.visit(|net| users.entry(net).or_default().push(cell_ref));
+        }
+        Retimer {
+            design,
+            timing: Timing::new(design),
+            users,
+            claimed_cells: BTreeSet::new(),
+            claimed_bits: BTreeSet::new(),
+        }
+    }
+
+    fn users(&self, net: Net) -> impl Iterator<Item = CellRef<'a>> + '_ {
+        self.users.get(&net).into_iter().flatten().copied().filter(|user| !matches!(&*user.get(), Cell::Debug(..)))
+    }
+
+    fn is_claimed(&self, cell_ref: CellRef<'a>, bits: &[(CellRef<'a>, usize)]) -> bool {
+        self.claimed_cells.contains(&cell_ref) || bits.iter().any(|bit| self.claimed_bits.contains(bit))
+    }
+
+    fn claim(&mut self, cell_ref: CellRef<'a>, bits: &[(CellRef<'a>, usize)]) {
+        self.claimed_cells.insert(cell_ref);
+        self.claimed_bits.extend(bits.iter().copied());
+    }
+
+    /// Moves the registers driving the inputs of `cell_ref` to its output.
+    fn move_forward(&mut self, cell_ref: CellRef<'a>, inputs: &[Net]) -> Option<Vec<(CellRef<'a>, usize)>> {
+        let mut bits = vec![];
+        let mut flip_flops = vec![];
+        for &net in inputs {
+            let Ok((dff, offset)) = self.design.find_cell(net) else { return None };
+            let Cell::Dff(flip_flop) = &*dff.get() else { return None };
+            if self.users(net).any(|user| user != cell_ref) {
+                return None;
+            }
+            if let Some(first) = flip_flops.first()
+                && controls(first) != controls(flip_flop)
+            {
+                return None;
+            }
+            bits.push((dff, offset));
+            flip_flops.push(flip_flop.slice(offset..offset + 1));
+        }
+        let output = cell_ref.output()[0];
+        let arrival = flip_flops.iter().map(|flip_flop| self.timing.levels.get(flip_flop.data[0])).max().unwrap();
+        if arrival >= self.timing.depth(output) || self.is_claimed(cell_ref, &bits) {
+            return None;
+        }
+        self.claim(cell_ref, &bits);
+
+        let cell = cell_ref.get();
+        let values = |value: fn(&FlipFlop) -> &Const| {
+            let trits = Vec::from_iter(flip_flops.iter().map(|flip_flop| value(flip_flop)[0]));
+            Const::from(image(&cell, inputs, &trits))
+        };
+        let data = Vec::from_iter(flip_flops.iter().map(|flip_flop| flip_flop.data[0]));
+        let data = {
+            let _guard = self.design.use_metadata_from(&[cell_ref]);
+            self.design.add_cell(substitute(&cell, inputs, &data))
+        };
+        let dffs = Vec::from_iter(bits.iter().map(|&(dff, _offset)| dff));
+        let _guard = self.design.use_metadata_from(&dffs);
+        let output_value = self.design.add_dff(FlipFlop {
+            data,
+            clear_value: values(|flip_flop| &flip_flop.clear_value),
+            reset_value: values(|flip_flop| &flip_flop.reset_value),
+            init_value: values(|flip_flop| &flip_flop.init_value),
+            ..flip_flops[0].clone()
+        });
+        self.design.replace_value(output, output_value);
+        cell_ref.unalive();
+        Some(bits)
+    }
+
+    /// Moves the register driven by the output of `cell_ref` to its inputs.
+    fn move_backward(&mut self, cell_ref: CellRef<'a>, inputs: &[Net]) -> Option<Vec<(CellRef<'a>, usize)>> {
+        let output = cell_ref.output()[0];
+        let mut users = self.users(output);
+        let (Some(dff), None) = (users.next(), users.next()) else { return None };
+        drop(users);
+        let Cell::Dff(flip_flop) = &*dff.get() else { return None };
+        // the register must sample the output exactly once, and only as data
+        let mut offsets = flip_flop.data.iter().enumerate().filter(|&(_, net)| net == output);
+        let (Some((offset, _)), None) = (offsets.next(), offsets.next()) else { return None };
+        let controls = [flip_flop.clock, flip_flop.clear, flip_flop.reset, flip_flop.enable];
+        if controls.iter().any(|control| control.net() == output) {
+            return None;
+        }
+        let flip_flop = flip_flop.slice(offset..offset + 1);
+        let register = dff.output()[offset];
+        let arrival = inputs.iter().map(|&net| self.timing.levels.get(net)).max().unwrap();
+        if arrival <= self.timing.depth(register) || self.is_claimed(cell_ref, &[(dff, offset)]) {
+            return None;
+        }
+
+        let cell = cell_ref.get();
+        let clear_values = preimage(&cell, inputs, flip_flop.clear_value[0])?;
+        let reset_values = preimage(&cell, inputs, flip_flop.reset_value[0])?;
+        let init_values = preimage(&cell, inputs, flip_flop.init_value[0])?;
+        self.claim(cell_ref, &[(dff, offset)]);
+
+        let registers = {
+            let _guard = self.design.use_metadata_from(&[dff]);
+            Vec::from_iter(inputs.iter().enumerate().map(|(index, &net)| {
+                self.design.add_dff(FlipFlop {
+                    data: net.into(),
+                    clear_value: clear_values[index].into(),
+                    reset_value: reset_values[index].into(),
+                    init_value: init_values[index].into(),
+                    ..flip_flop.clone()
+                })[0]
+            }))
+        };
+        let _guard = self.design.use_metadata_from(&[cell_ref]);
+        let output_value = self.design.add_cell(substitute(&cell, inputs, &registers));
+        self.design.replace_value(register, output_value);
+        cell_ref.unalive();
+        Some(vec![(dff, offset)])
+    }
+
+    fn run(mut self) -> bool {
+        let mut removed_bits: BTreeMap<usize, (CellRef, BTreeSet<usize>)> = BTreeMap::new();
+        for cell_ref in self.design.iter_cells() {
+            let Some(inputs) = retimable_inputs(&cell_ref.get()) else { continue };
+            let bits = match self.move_forward(cell_ref, &inputs) {
+                Some(bits) => bits,
+                None => match self.move_backward(cell_ref, &inputs) {
+                    Some(bits) => bits,
+                    None => continue,
+                },
+            };
+            for (dff, offset) in bits {
+                removed_bits.entry(dff.debug_index()).or_insert_with(|| (dff, BTreeSet::new())).1.insert(offset);
+            }
+        }
+
+        // replace the registers that had some of their bits moved with narrower ones
+        let did_change = !removed_bits.is_empty();
+        for (dff, offsets) in removed_bits.into_values() {
+            let Cell::Dff(flip_flop) = &*dff.get() else { unreachable!() };
+            let kept = Vec::from_iter((0..flip_flop.data.len()).filter(|offset| !offsets.contains(offset)));
+            if !kept.is_empty() {
+                let constant = |value: &Const| Const::from_iter(kept.iter().map(|&offset| value[offset]));
+                let _guard = self.design.use_metadata_from(&[dff]);
+                let output_value = self.design.add_dff(FlipFlop {
+                    data: Value::from_iter(kept.iter().map(|&offset| flip_flop.data[offset])),
+                    clear_value: constant(&flip_flop.clear_value),
+                    reset_value: constant(&flip_flop.reset_value),
+                    init_value: constant(&flip_flop.init_value),
+                    ..flip_flop.clone()
+                });
+                let output = dff.output();
+                self.design.replace_value(Value::from_iter(kept.iter().map(|&offset| output[offset])), output_value);
+            }
+            dff.unalive();
+        }
+        did_change
+    }
+}
+
+/// Moves registers across combinational logic to balance the logic levels between them.
+pub fn retime(design: &mut Design) {
+    for round in 1..=MAX_ROUNDS {
+        if cfg!(feature = "trace") {
+            eprintln!(">retime #{round}");
+        }
+        if !Retimer::new(design).run() {
+            break;
+        }
+        design.apply();
+    }
+    design.compact();
+}
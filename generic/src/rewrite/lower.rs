@@ -1,4 +1,9 @@
-use prjunnamed_netlist::{Cell, MetaItemRef, Net, RewriteResult, RewriteRuleset, Rewriter, Value};
+use prjunnamed_netlist::{
+    lower_clz, lower_ctz, lower_popcnt, Cell, MetaItemRef, MulArchitecture, Net, RewriteResult, RewriteRuleset,
+    Rewriter, Value,
+};
+
+use crate::multiplier::lower_mul;
 
 pub struct LowerMux;
 
@@ -71,7 +76,16 @@ impl RewriteRuleset for LowerLt {
     }
 }
 
-pub struct LowerMul;
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LowerMul {
+    architecture: MulArchitecture,
+}
+
+impl LowerMul {
+    pub fn new(architecture: MulArchitecture) -> Self {
+        LowerMul { architecture }
+    }
+}
 
 impl RewriteRuleset for LowerMul {
     fn rewrite<'a>(
@@ -84,15 +98,27 @@ impl RewriteRuleset for LowerMul {
         let &Cell::Mul(ref a, ref b) = cell else {
             return RewriteResult::None;
         };
-        let mut value = Value::zero(a.len());
-        for (index, bit) in b.iter().enumerate() {
-            value = rewriter.add_cell(Cell::Adc(
-                value,
-                Value::zero(index).concat(rewriter.add_cell(Cell::Mux(bit, a.clone(), Value::zero(a.len())))),
-                Net::ZERO,
-            ));
+        lower_mul(&|cell| rewriter.add_cell(cell), self.architecture, a, b).into()
+    }
+}
+
+pub struct LowerCount;
+
+impl RewriteRuleset for LowerCount {
+    fn rewrite<'a>(
+        &self,
+        cell: &Cell,
+        _meta: MetaItemRef<'a>,
+        _output: Option<&Value>,
+        rewriter: &Rewriter<'a>,
+    ) -> RewriteResult<'a> {
+        let add_cell = |cell| rewriter.add_cell(cell);
+        match cell {
+            Cell::Popcnt(a) => lower_popcnt(&add_cell, a).into(),
+            Cell::Clz(a) => lower_clz(&add_cell, a).into(),
+            Cell::Ctz(a) => lower_ctz(&add_cell, a).into(),
+            _ => RewriteResult::None,
         }
-        value.slice(..a.len()).into()
     }
 }
 
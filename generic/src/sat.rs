@@ -0,0 +1,207 @@
+//! A small DPLL SAT solver for proving the equivalence of logic cones.
+//!
+//! The solver uses two watched literals per clause for unit propagation and backtracks chronologically. It is
+//! intended for the small, mostly propagation-driven problems produced by [`crate::fraig`], and gives up once
+//! it encounters a given number of conflicts.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lit(u32);
+
+impl Lit {
+    pub fn new(var: u32, negative: bool) -> Self {
+        Lit(var * 2 + negative as u32)
+    }
+
+    pub fn var(self) -> u32 {
+        self.0 / 2
+    }
+
+    pub fn is_negative(self) -> bool {
+        self.0 % 2 == 1
+    }
+}
+
+impl std::ops::Not for Lit {
+    type Output = Lit;
+
+    fn not(self) -> Self::Output {
+        Lit(self.0 ^ 1)
+    }
+}
+
+impl std::ops::BitXor<bool> for Lit {
+    type Output = Lit;
+
+    fn bitxor(self, invert: bool) -> Self::Output {
+        Lit(self.0 ^ invert as u32)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SatResult {
+    Sat,
+    Unsat,
+    Unknown,
+}
+
+struct Decision {
+    trail_len: usize,
+    lit: Lit,
+    flipped: bool,
+}
+
+#[derive(Default)]
+pub struct Solver {
+    clauses: Vec<Vec<Lit>>,
+    /// Indices of the clauses watching each literal, indexed by literal.
+    watches: Vec<Vec<usize>>,
+    values: Vec<Option<bool>>,
+    trail: Vec<Lit>,
+    propagated: usize,
+    decisions: Vec<Decision>,
+    /// Set if an empty clause or conflicting unit clauses were added.
+    is_unsat: bool,
+    units: Vec<Lit>,
+}
+
+impl Solver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_var(&mut self) -> Lit {
+        let var = self.values.len() as u32;
+        self.values.push(None);
+        self.watches.extend([vec![], vec![]]);
+        Lit::new(var, false)
+    }
+
+    pub fn add_clause(&mut self, lits: &[Lit]) {
+        let mut clause = Vec::from(lits);
+        clause.sort_by_key(|lit| lit.0);
+        clause.dedup();
+        if clause.windows(2).any(|pair| pair[0] == !pair[1]) {
+            return;
+        }
+        match clause.len() {
+            0 => self.is_unsat = true,
+            1 => self.units.push(clause[0]),
+            _ => {
+                let index = self.clauses.len();
+                self.watches[(!clause[0]).0 as usize].push(index);
+                self.watches[(!clause[1]).0 as usize].push(index);
+                self.clauses.push(clause);
+            }
+        }
+    }
+
+    pub fn value(&self, lit: Lit) -> Option<bool> {
+        self.values[lit.var() as usize].map(|value| value ^ lit.is_negative())
+    }
+
+    fn assign(&mut self, lit: Lit) {
+        self.values[lit.var() as usize] = Some(!lit.is_negative());
+        self.trail.push(lit);
+    }
+
+    fn backtrack(&mut self, trail_len: usize) {
+        for lit in self.trail.drain(trail_len..) {
+            self.values[lit.var() as usize] = None;
+        }
+        self.propagated = trail_len;
+    }
+
+    /// Returns `false` if propagation results in a conflict.
+    fn propagate(&mut self) -> bool {
+        while self.propagated < self.trail.len() {
+            // `lit` became true, so the clauses watching `!lit` must find another literal to watch
+            let lit = self.trail[self.propagated];
+            self.propagated += 1;
+            let mut watchers = std::mem::take(&mut self.watches[lit.0 as usize]);
+            let mut index = 0;
+            let mut is_conflict = false;
+            while index < watchers.len() {
+                let clause_index = watchers[index];
+                let clause = &mut self.clauses[clause_index];
+                if clause[0] == !lit {
+                    clause.swap(0, 1);
+                }
+                let other = clause[0];
+                if self.values[other.var() as usize].map(|value| value ^ other.is_negative()) == Some(true) {
+                    index += 1;
+                    continue;
+                }
+                let replacement = (2..clause.len()).find(|&position| {
+                    let candidate = clause[position];
+                    self.values[candidate.var() as usize].map(|value| value ^ candidate.is_negative()) != Some(false)
+                });
+                if let Some(position) = replacement {
+                    clause.swap(1, position);
+                    self.watches[(!clause[1]).0 as usize].push(clause_index);
+                    watchers.swap_remove(index);
+                    continue;
+                }
+                index += 1;
+                match self.value(other) {
+                    None => self.assign(other),
+                    Some(false) => {
+                        is_conflict = true;
+                        break;
+                    }
+                    Some(true) => unreachable!(),
+                }
+            }
+            self.watches[lit.0 as usize].extend(watchers);
+            if is_conflict {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Determines whether the clauses are satisfiable, giving up after `conflict_limit` conflicts. If the result
+    /// is [`SatResult::Sat`], [`Self::value`] returns the satisfying assignment.
+    pub fn solve(&mut self, conflict_limit: u64) -> SatResult {
+        if self.is_unsat {
+            return SatResult::Unsat;
+        }
+        for lit in std::mem::take(&mut self.units) {
+            match self.value(lit) {
+                None => self.assign(lit),
+                Some(true) => (),
+                Some(false) => return SatResult::Unsat,
+            }
+        }
+        let mut conflicts = 0;
+        let mut next_var = 0;
+        loop {
+            if self.propagate() {
+                while next_var < self.values.len() && self.values[next_var].is_some() {
+                    next_var += 1;
+                }
+                if next_var == self.values.len() {
+                    return SatResult::Sat;
+                }
+                let lit = Lit::new(next_var as u32, true);
+                self.decisions.push(Decision { trail_len: self.trail.len(), lit, flipped: false });
+                self.assign(lit);
+            } else {
+                conflicts += 1;
+                if conflicts > conflict_limit {
+                    return SatResult::Unknown;
+                }
+                loop {
+                    let Some(decision) = self.decisions.pop() else { return SatResult::Unsat };
+                    self.backtrack(decision.trail_len);
+                    next_var = 0;
+                    if !decision.flipped {
+                        let lit = !decision.lit;
+                        self.decisions.push(Decision { trail_len: self.trail.len(), lit, flipped: true });
+                        self.assign(lit);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,356 @@
+//! Resource sharing of mutually exclusive operators.
+//!
+//! The `share` pass finds pairs of arithmetic operators (`adc`, `mul`, `ult`, `slt`, and shifts) whose results
+//! are never used at the same time, and replaces them with a single operator whose operands are multiplexed.
+//! Two operators are mutually exclusive if they only drive the two arms of the same `mux` cell, or if they
+//! only drive the updates of `assign` cells enabled by different outputs of the same `match` cell, since
+//! the outputs of a `match` cell are one-hot.
+//!
+//! Sharing two operators requires a multiplexer for each operand bit that differs between them, so the pass
+//! estimates the area of both alternatives, and only shares the operators if that reduces the area.
+
+use std::collections::{HashMap, HashSet};
+
+use prjunnamed_netlist::{AssignCell, Cell, CellRef, Design, Net, Value};
+
+const MAX_ROUNDS: usize = 16;
+
+/// Approximate area of a 1-bit 2:1 multiplexer, in AIG nodes after lowering.
+const MUX_AREA: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Kind {
+    Adc,
+    Mul,
+    ULt,
+    SLt,
+    Shl(u32),
+    UShr(u32),
+    SShr(u32),
+    XShr(u32),
+}
+
+impl Kind {
+    fn from_cell(cell: &Cell) -> Option<(Kind, Vec<Value>)> {
+        Some(match cell {
+            Cell::Adc(arg1, arg2, arg3) => (Kind::Adc, vec![arg1.clone(), arg2.clone(), Value::from(*arg3)]),
+            Cell::Mul(arg1, arg2) => (Kind::Mul, vec![arg1.clone(), arg2.clone()]),
+            Cell::ULt(arg1, arg2) => (Kind::ULt, vec![arg1.clone(), arg2.clone()]),
+            Cell::SLt(arg1, arg2) => (Kind::SLt, vec![arg1.clone(), arg2.clone()]),
+            Cell::Shl(arg1, arg2, stride) => (Kind::Shl(*stride), vec![arg1.clone(), arg2.clone()]),
+            Cell::UShr(arg1, arg2, stride) => (Kind::UShr(*stride), vec![arg1.clone(), arg2.clone()]),
+            Cell::SShr(arg1, arg2, stride) => (Kind::SShr(*stride), vec![arg1.clone(), arg2.clone()]),
+            Cell::XShr(arg1, arg2, stride) => (Kind::XShr(*stride), vec![arg1.clone(), arg2.clone()]),
+            _ => return None,
+        })
+    }
+
+    fn cell(self, mut operands: Vec<Value>) -> Cell {
+        let arg2 = operands.swap_remove(1);
+        let arg1 = operands.swap_remove(0);
+        match self {
+            Kind::Adc => Cell::Adc(arg1, arg2, operands[0][0]),
+            Kind::Mul => Cell::Mul(arg1, arg2),
+            Kind::ULt => Cell::ULt(arg1, arg2),
+            Kind::SLt => Cell::SLt(arg1, arg2),
+            Kind::Shl(stride) => Cell::Shl(arg1, arg2, stride),
+            Kind::UShr(stride) => Cell::UShr(arg1, arg2, stride),
+            Kind::SShr(stride) => Cell::SShr(arg1, arg2, stride),
+            Kind::XShr(stride) => Cell::XShr(arg1, arg2, stride),
+        }
+    }
+
+    fn is_commutative(self) -> bool {
+        matches!(self, Kind::Adc | Kind::Mul)
+    }
+
+    /// Approximate area of the operator, in AIG nodes after lowering.
+    fn area(self, operands: &[Value]) -> usize {
+        let width = operands[0].len();
+        match self {
+            // a full adder is two XOR gates and a majority gate
+            Kind::Adc => 9 * width,
+            // each partial product bit below the output width needs an AND gate and a full adder
+            Kind::Mul => 10 * width * (width + 1) / 2,
+            // a comparator is the carry chain of a subtractor
+            Kind::ULt | Kind::SLt => 3 * width,
+            // a logarithmic shifter has a row of multiplexers for each bit of the shift amount
+            Kind::Shl(_) | Kind::UShr(_) | Kind::SShr(_) | Kind::XShr(_) => MUX_AREA * width * operands[1].len(),
+        }
+    }
+}
+
+/// Returns the number of operand bits that differ between two operators, each of which needs a multiplexer.
+fn mux_width(operands1: &[Value], operands2: &[Value]) -> usize {
+    operands1
+        .iter()
+        .zip(operands2)
+        .map(|(value1, value2)| value1.iter().zip(value2).filter(|(a, b)| a != b).count())
+        .sum()
+}
+
+/// Extends the operands of two operators of the same kind to the same widths, and orders the operands of
+/// commutative operators to minimize the number of multiplexers. Returns `None` if the operators have
+/// incompatible widths.
+fn align(kind: Kind, operands1: &[Value], operands2: &[Value]) -> Option<(Vec<Value>, Vec<Value>)> {
+    let (mut operands1, mut operands2) = (operands1.to_vec(), operands2.to_vec());
+    match kind {
+        Kind::Adc | Kind::Mul => (),
+        Kind::ULt | Kind::SLt => {
+            let width = operands1[0].len().max(operands2[0].len());
+            for operand in operands1.iter_mut().chain(&mut operands2) {
+                *operand = if kind == Kind::SLt { operand.sext(width) } else { operand.zext(width) };
+            }
+        }
+        Kind::Shl(_) | Kind::UShr(_) | Kind::SShr(_) | Kind::XShr(_) => {
+            let width = operands1[1].len().max(operands2[1].len());
+            operands1[1] = operands1[1].zext(width);
+            operands2[1] = operands2[1].zext(width);
+        }
+    }
+    if operands1[0].len() != operands2[0].len() {
+        return None;
+    }
+    if kind.is_commutative() {
+        let mut swapped = operands2.clone();
+        swapped.swap(0, 1);
+        if mux_width(&operands1, &swapped) < mux_width(&operands1, &operands2) {
+            operands2 = swapped;
+        }
+    }
+    Some((operands1, operands2))
+}
+
+struct Operator<'a> {
+    cell_ref: CellRef<'a>,
+    kind: Kind,
+    operands: Vec<Value>,
+}
+
+/// An operator that is only used by `assign` cells enabled by the outputs of a `match` cell.
+struct AssignedOperator<'a> {
+    operator: Operator<'a>,
+    consumers: Vec<CellRef<'a>>,
+    enables: Vec<Net>,
+}
+
+struct Sharing<'a> {
+    design: &'a Design,
+    /// Cells using each net, other than `debug` cells.
+    users: HashMap<Net, Vec<CellRef<'a>>>,
+    /// First output nets of the operators that were already shared in this round.
+    shared: HashSet<Net>,
+}
+
+impl<'a> Sharing<'a> {
+    fn new(design: &'a Design) -> Self {
+        let mut users: HashMap<Net, Vec<CellRef<'a>>> = HashMap::new();
+        for cell_ref in design.iter_cells() {
+            if matches!(&*cell_ref.get(), Cell::Debug(..)) {
+                continue;
+            }
+            cell_ref.visit(|net| {
+                let net_users = users.entry(net).or_default();
+                if net_users.last() != Some(&cell_ref) {
+                    net_users.push(cell_ref);
+                }
+            });
+        }
+        Sharing { design, users, shared: HashSet::new() }
+    }
+
+    /// Returns the cells using any of the outputs of `cell_ref`.
+    fn consumers(&self, cell_ref: CellRef<'a>) -> Vec<CellRef<'a>> {
+        let mut consumers = vec![];
+        for net in cell_ref.output().iter() {
+            for &user in self.users.get(&net).into_iter().flatten() {
+                if !consumers.contains(&user) {
+                    consumers.push(user);
+                }
+            }
+        }
+        consumers
+    }
+
+    /// Returns the operator whose output `value` is a prefix of, if it has not been shared yet.
+    fn operator(&self, value: &Value) -> Option<Operator<'a>> {
+        let (cell_ref, 0) = self.design.find_cell(value.iter().next()?).ok()? else { return None };
+        let output = cell_ref.output();
+        if value.len() > output.len() || *value != output.slice(..value.len()) || self.shared.contains(&output[0]) {
+            return None;
+        }
+        let (kind, operands) = Kind::from_cell(&cell_ref.get())?;
+        Some(Operator { cell_ref, kind, operands })
+    }
+
+    /// Checks whether any of `nets` combinationally depends on the outputs of `cell_refs`.
+    fn depends_on(&self, nets: impl IntoIterator<Item = Net>, cell_refs: &[CellRef<'a>]) -> bool {
+        let mut queue = Vec::from_iter(nets);
+        let mut visited = HashSet::new();
+        while let Some(net) = queue.pop() {
+            let Ok((cell_ref, _offset)) = self.design.find_cell(net) else { continue };
+            if cell_refs.contains(&cell_ref) {
+                return true;
+            }
+            if !visited.insert(cell_ref.output()[0]) || cell_ref.get().has_state(self.design) {
+                continue;
+            }
+            cell_ref.visit(|net| queue.push(net));
+        }
+        false
+    }
+
+    /// Replaces `operator1` and `operator2` with a shared operator that computes the result of `operator1` if
+    /// `select` is asserted, and the result of `operator2` otherwise. The sharing is only done if the added
+    /// multiplexers are smaller than the removed operator and `extra_area`. Returns the output of the shared
+    /// operator.
+    fn share(
+        &mut self,
+        select: Net,
+        operator1: &Operator<'a>,
+        operator2: &Operator<'a>,
+        extra_area: usize,
+    ) -> Option<Value> {
+        if operator1.kind != operator2.kind || operator1.cell_ref == operator2.cell_ref {
+            return None;
+        }
+        let (operands1, operands2) = align(operator1.kind, &operator1.operands, &operator2.operands)?;
+        let mux_area = MUX_AREA * mux_width(&operands1, &operands2);
+        if mux_area >= operator1.kind.area(&operands1) + extra_area {
+            return None;
+        }
+        let inputs = operands1.iter().chain(&operands2).flat_map(|value| value.iter()).chain([select]);
+        if self.depends_on(inputs, &[operator1.cell_ref, operator2.cell_ref]) {
+            return None;
+        }
+        if cfg!(feature = "trace") {
+            eprintln!(
+                ">share {} {}",
+                self.design.display_net(operator1.cell_ref.output()[0]),
+                self.design.display_net(operator2.cell_ref.output()[0])
+            );
+        }
+        let _guard = self.design.use_metadata_from(&[operator1.cell_ref, operator2.cell_ref]);
+        let operands = Vec::from_iter(operands1.into_iter().zip(operands2).map(|(operand1, operand2)| {
+            if operand1 == operand2 { operand1 } else { self.design.add_mux(select, operand1, operand2) }
+        }));
+        self.shared.insert(operator1.cell_ref.output()[0]);
+        self.shared.insert(operator2.cell_ref.output()[0]);
+        Some(self.design.add_cell(operator1.kind.cell(operands)))
+    }
+
+    /// Shares the operators driving both arms of a `mux` cell.
+    fn share_mux(&mut self, cell_ref: CellRef<'a>) -> bool {
+        let Cell::Mux(select, arg1, arg2) = &*cell_ref.get() else { return false };
+        let (Some(operator1), Some(operator2)) = (self.operator(arg1), self.operator(arg2)) else { return false };
+        for operator in [&operator1, &operator2] {
+            if self.consumers(operator.cell_ref) != [cell_ref] {
+                return false;
+            }
+        }
+        // the `mux` cell itself is removed as well
+        let Some(output) = self.share(*select, &operator1, &operator2, MUX_AREA * arg1.len()) else { return false };
+        self.design.replace_value(cell_ref.output(), output.slice(..arg1.len()));
+        true
+    }
+
+    /// Returns the `match` cell enabling the `assign` cells that use the output of `operator`, if the operator
+    /// is only used by such cells.
+    fn assigned_operator(&self, operator: Operator<'a>) -> Option<(CellRef<'a>, AssignedOperator<'a>)> {
+        let consumers = self.consumers(operator.cell_ref);
+        let mut match_cell_ref = None;
+        let mut enables = vec![];
+        for consumer in &consumers {
+            let Cell::Assign(AssignCell { value, enable, update, .. }) = &*consumer.get() else { return None };
+            let output = operator.cell_ref.output();
+            if self.operator(update).map(|other| other.cell_ref) != Some(operator.cell_ref)
+                || value.iter().chain([*enable]).any(|net| output.iter().any(|output_net| output_net == net))
+            {
+                return None;
+            }
+            let (enable_cell_ref, _offset) = self.design.find_cell(*enable).ok()?;
+            if !matches!(&*enable_cell_ref.get(), Cell::Match(..))
+                || match_cell_ref.is_some_and(|match_cell_ref| match_cell_ref != enable_cell_ref)
+            {
+                return None;
+            }
+            match_cell_ref = Some(enable_cell_ref);
+            if !enables.contains(enable) {
+                enables.push(*enable);
+            }
+        }
+        Some((match_cell_ref?, AssignedOperator { operator, consumers, enables }))
+    }
+
+    /// Shares operators that are only used by `assign` cells enabled by different outputs of a `match` cell.
+    fn share_assigns(&mut self) -> bool {
+        let mut groups: HashMap<(Net, Kind), Vec<AssignedOperator<'a>>> = HashMap::new();
+        let mut order = vec![];
+        for cell_ref in self.design.iter_cells() {
+            let Some(operator) = self.operator(&cell_ref.output()) else { continue };
+            let kind = operator.kind;
+            let Some((match_cell_ref, assigned)) = self.assigned_operator(operator) else { continue };
+            let key = (match_cell_ref.output()[0], kind);
+            if !groups.contains_key(&key) {
+                order.push(key);
+            }
+            groups.entry(key).or_default().push(assigned);
+        }
+        let mut did_share = false;
+        for key in order {
+            let group = &groups[&key];
+            let mut used = vec![false; group.len()];
+            for index1 in 0..group.len() {
+                for index2 in index1 + 1..group.len() {
+                    if used[index1] || used[index2] {
+                        continue;
+                    }
+                    let (assigned1, assigned2) = (&group[index1], &group[index2]);
+                    if assigned1.enables.iter().any(|enable| assigned2.enables.contains(enable)) {
+                        continue;
+                    }
+                    // the operands of the operator with a single enable are selected by that enable
+                    let (select, operator1, operator2) = match (&assigned1.enables[..], &assigned2.enables[..]) {
+                        (&[enable], _) => (enable, &assigned1.operator, &assigned2.operator),
+                        (_, &[enable]) => (enable, &assigned2.operator, &assigned1.operator),
+                        _ => continue,
+                    };
+                    let Some(output) = self.share(select, operator1, operator2, 0) else { continue };
+                    for consumer in assigned1.consumers.iter().chain(&assigned2.consumers) {
+                        let Cell::Assign(assign_cell) = &*consumer.get() else { unreachable!() };
+                        consumer.replace(Cell::Assign(AssignCell {
+                            update: output.slice(..assign_cell.update.len()),
+                            ..assign_cell.clone()
+                        }));
+                    }
+                    used[index1] = true;
+                    used[index2] = true;
+                    did_share = true;
+                }
+            }
+        }
+        did_share
+    }
+
+    fn run(mut self) -> bool {
+        let mut did_share = false;
+        for cell_ref in self.design.iter_cells() {
+            did_share |= self.share_mux(cell_ref);
+        }
+        did_share |= self.share_assigns();
+        did_share
+    }
+}
+
+/// Shares arithmetic operators whose results are mutually exclusive.
+pub fn share(design: &mut Design) {
+    for round in 1..=MAX_ROUNDS {
+        if cfg!(feature = "trace") {
+            eprintln!(">share #{round}");
+        }
+        if !Sharing::new(design).run() {
+            break;
+        }
+        design.compact();
+    }
+}
@@ -101,6 +101,21 @@ pub fn simplify(design: &mut Design) -> bool {
         [PXShr   [PAny@a] [PAny]     [PZero]]   => a;
         [PXShr@y [PZero]  [PAny]     [PAny]]    => Value::zero(y.len());
 
+        [PPopcnt   [PConst@a]]          => a.popcnt();
+        [PPopcnt@y [PHasX]]             => Value::undef(y.len());
+        [PPopcnt   [PAny@a]]            if a.len() == 1 => a;
+        [PPopcnt@y [PAny@a]]            if let Some(b) = popcnt_drop_zeros(design, a, y.len()) => b;
+
+        [PClz      [PConst@a]]          => a.clz();
+        [PClz@y    [PHasX]]             => Value::undef(y.len());
+        [PClz      [PAny@a]]            if a.len() == 1 => design.add_not(a);
+        [PClz@y    [PAny@a]]            if a.msb() == Net::ONE => Value::zero(y.len());
+
+        [PCtz      [PConst@a]]          => a.ctz();
+        [PCtz@y    [PHasX]]             => Value::undef(y.len());
+        [PCtz      [PAny@a]]            if a.len() == 1 => design.add_not(a);
+        [PCtz@y    [PAny@a]]            if a.lsb() == Net::ONE => Value::zero(y.len());
+
         [PMul    [PConst@a] [PConst@b]] => a.mul(b);
         [PMul@y  [PAny]     [PHasX]]    => Value::undef(y.len());
         [PMul@y  [PHasX]    [PAny]]     => Value::undef(y.len());
@@ -329,6 +344,15 @@ fn make_adc(design: &Design, a: impl Into<Value>, b: impl Into<Value>, ci: impl
     }
 }
 
+/// Removes the constant zero bits from the input of a `popcnt` cell, since they do not contribute to the count.
+fn popcnt_drop_zeros(design: &Design, a: Value, y_len: usize) -> Option<Value> {
+    let b = Value::from_iter(a.iter().filter(|&net| net != Net::ZERO));
+    if b.len() == a.len() {
+        return None;
+    }
+    Some(design.add_popcnt(b).zext(y_len))
+}
+
 fn fold_controls(design: &Design, cell_ref: CellRef) {
     let uninvert = |net: Net| -> Option<Net> {
         if let Ok((cell_ref, offset)) = design.find_cell(net) {
@@ -19,17 +19,11 @@ impl<'a> TreeRebalance<'a> {
     fn new(design: &Design, levels: &'a LevelAnalysis) -> Self {
         let mut inner_aig = HashSet::new();
         let mut inner_xor = HashSet::new();
-        let mut use_count = HashMap::<Net, u32>::new();
-        for cell in design.iter_cells() {
-            cell.visit(|net| {
-                *use_count.entry(net).or_default() += 1;
-            });
-        }
         for cell in design.iter_cells() {
             if let Cell::Aig(net1, net2) = *cell.get() {
                 for net in [net1, net2] {
                     if let ControlNet::Pos(net) = net
-                        && use_count[&net] == 1
+                        && design.use_count(net) == 1
                     {
                         inner_aig.insert(net);
                     }
@@ -38,7 +32,7 @@ impl<'a> TreeRebalance<'a> {
             if let Cell::Xor(ref val1, ref val2) = *cell.get() {
                 for val in [val1, val2] {
                     for net in val {
-                        if use_count[&net] == 1 {
+                        if design.use_count(net) == 1 {
                             inner_xor.insert(net);
                         }
                     }
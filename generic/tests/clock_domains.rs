@@ -0,0 +1,179 @@
+use std::str::FromStr;
+
+use prjunnamed_generic::{CrossingKind, clock_domains};
+use prjunnamed_netlist::{Cell, ControlNet, Design, Net};
+
+fn find_input(design: &Design, name: &str) -> Net {
+    design
+        .iter_cells()
+        .find_map(|cell_ref| match &*cell_ref.get() {
+            Cell::Input(input_name, _) if input_name == name => Some(cell_ref.output().unwrap_net()),
+            _ => None,
+        })
+        .unwrap()
+}
+
+#[test]
+fn test_single_domain() {
+    let design = Design::from_str(concat!(
+        "%0:4 = input \"d\"\n",
+        "%4:1 = input \"clk\"\n",
+        "%5:4 = dff %0:4 clk=%4\n",
+        "%9:4 = not %5:4\n",
+        "%13:4 = dff %9:4 clk=%4\n",
+        "%17:0 = output \"q\" %13:4\n",
+    ))
+    .unwrap();
+    let report = clock_domains(&design);
+    assert_eq!(report.domains.len(), 1);
+    assert_eq!(report.domains[0].names, vec!["clk".to_string()]);
+    assert_eq!(report.domains[0].clock, ControlNet::Pos(find_input(&design, "clk")));
+    assert_eq!(report.domains[0].outputs.len(), 8);
+    assert!(report.crossings.is_empty());
+}
+
+#[test]
+fn test_synchronizer() {
+    let design = Design::from_str(concat!(
+        "%0:1 = input \"d\"\n",
+        "%1:1 = input \"clk_a\"\n",
+        "%2:1 = input \"clk_b\"\n",
+        "%3:1 = dff %0 clk=%1\n",
+        "%4:1 = dff %3 clk=%2\n",
+        "%5:1 = dff %4 clk=%2\n",
+        "%6:0 = output \"q\" %5\n",
+        "%7:0 = name \"meta\" %4\n",
+    ))
+    .unwrap();
+    let report = clock_domains(&design);
+    assert_eq!(report.domains.len(), 2);
+    assert_eq!(report.crossings.len(), 1);
+    let crossing = &report.crossings[0];
+    assert_eq!(crossing.kind, CrossingKind::Synchronized);
+    assert_eq!(crossing.names, vec!["meta".to_string()]);
+    assert_eq!(crossing.source, ControlNet::Pos(find_input(&design, "clk_a")));
+    assert_eq!(crossing.destination, ControlNet::Pos(find_input(&design, "clk_b")));
+    assert_eq!(crossing.width(), 1);
+    assert_eq!(crossing.sources, crossing.inputs);
+    assert_eq!(report.unsynchronized().count(), 0);
+}
+
+#[test]
+fn test_unsynchronized_bit() {
+    // the sampled bit passes through combinational logic, which may glitch
+    let design = Design::from_str(concat!(
+        "%0:2 = input \"d\"\n",
+        "%2:1 = input \"clk_a\"\n",
+        "%3:1 = input \"clk_b\"\n",
+        "%4:2 = dff %0:2 clk=%2\n",
+        "%6:1 = and %4+0 %4+1\n",
+        "%7:1 = dff %6 clk=%3\n",
+        "%8:1 = dff %7 clk=%3\n",
+        "%9:0 = output \"q\" %8\n",
+    ))
+    .unwrap();
+    let report = clock_domains(&design);
+    assert_eq!(report.crossings.len(), 1);
+    assert_eq!(report.crossings[0].kind, CrossingKind::UnsynchronizedBit);
+    assert_eq!(report.crossings[0].sources.len(), 2);
+
+    // the output of the first flip-flop is used by something other than the second flip-flop
+    let design = Design::from_str(concat!(
+        "%0:1 = input \"d\"\n",
+        "%1:1 = input \"clk_a\"\n",
+        "%2:1 = input \"clk_b\"\n",
+        "%3:1 = dff %0 clk=%1\n",
+        "%4:1 = dff %3 clk=%2\n",
+        "%5:1 = dff %4 clk=%2\n",
+        "%6:0 = output \"q\" %5\n",
+        "%7:0 = output \"meta\" %4\n",
+    ))
+    .unwrap();
+    let report = clock_domains(&design);
+    assert_eq!(report.crossings.len(), 1);
+    assert_eq!(report.crossings[0].kind, CrossingKind::UnsynchronizedBit);
+}
+
+#[test]
+fn test_unsynchronized_bus() {
+    let design = Design::from_str(concat!(
+        "%0:4 = input \"d\"\n",
+        "%4:1 = input \"clk_a\"\n",
+        "%5:1 = input \"clk_b\"\n",
+        "%6:4 = dff %0:4 clk=%4\n",
+        "%10:4 = dff %6:4 clk=%5\n",
+        "%14:4 = dff %10:4 clk=%5\n",
+        "%18:0 = output \"q\" %14:4\n",
+        "%19:0 = name \"bus\" %10:4\n",
+    ))
+    .unwrap();
+    let report = clock_domains(&design);
+    assert_eq!(report.crossings.len(), 1);
+    let crossing = &report.crossings[0];
+    assert_eq!(crossing.kind, CrossingKind::UnsynchronizedBus);
+    assert_eq!(crossing.width(), 4);
+    assert_eq!(crossing.sources.len(), 4);
+    assert_eq!(report.unsynchronized().count(), 1);
+    assert_eq!(
+        format!("{report}"),
+        concat!(
+            "clock domain \"clk_a\" (rising edge): 4 bits\n",
+            "clock domain \"clk_b\" (rising edge): 8 bits\n",
+            "unsynchronized bus crossing from \"clk_a\" (rising edge) to \"clk_b\" (rising edge): 4 bits into \"bus\"\n",
+        )
+    );
+}
+
+#[test]
+fn test_polarity() {
+    let design = Design::from_str(concat!(
+        "%0:1 = input \"d\"\n",
+        "%1:1 = input \"clk\"\n",
+        "%2:1 = dff %0 clk=%1\n",
+        "%3:1 = dff %2 clk=!%1\n",
+        "%4:1 = dff %3 clk=%1\n",
+        "%5:0 = output \"q\" %4\n",
+    ))
+    .unwrap();
+    let report = clock_domains(&design);
+    assert_eq!(report.domains.len(), 2);
+    let clk = find_input(&design, "clk");
+    assert!(report.domain(ControlNet::Pos(clk)).is_some());
+    assert!(report.domain(ControlNet::Neg(clk)).is_some());
+    assert_eq!(report.crossings.len(), 2);
+    assert_eq!(report.crossings[0].destination, ControlNet::Neg(clk));
+    assert_eq!(report.crossings[1].destination, ControlNet::Pos(clk));
+}
+
+#[test]
+fn test_memory() {
+    let design = Design::from_str(concat!(
+        "%0:2 = input \"wa\"\n",
+        "%2:4 = input \"wd\"\n",
+        "%6:2 = input \"ra\"\n",
+        "%8:1 = input \"clk_a\"\n",
+        "%9:1 = input \"clk_b\"\n",
+        "%10:2 = dff %6:2 clk=%9\n",
+        "%12:2 = dff %6:2 clk=%8\n",
+        "%14:_ = memory depth=#4 width=#4 {\n",
+        "  write addr=%0:2 data=%2:4 mask=1111 clk=%8\n",
+        "  %14:4 = read addr=%10:2\n",
+        "  %18:4 = read addr=%12:2 clk=%9 [undef]\n",
+        "}\n",
+        "%22:4 = dff %14:4 clk=%9\n",
+        "%26:0 = output \"q1\" %22:4\n",
+        "%27:0 = output \"q2\" %18:4\n",
+    ))
+    .unwrap();
+    let report = clock_domains(&design);
+    assert_eq!(report.domains.len(), 2);
+    assert_eq!(report.domain(ControlNet::Pos(find_input(&design, "clk_a"))).unwrap().write_ports, 1);
+    assert_eq!(report.domain(ControlNet::Pos(find_input(&design, "clk_b"))).unwrap().outputs.len(), 10);
+    // the asynchronous read port sees the contents written in the other domain, while the synchronous read port
+    // samples an address from the other domain
+    assert_eq!(report.crossings.len(), 2);
+    assert_eq!(report.crossings[0].inputs.len(), 2);
+    assert_eq!(report.crossings[0].kind, CrossingKind::UnsynchronizedBus);
+    assert_eq!(report.crossings[1].inputs.len(), 4);
+    assert_eq!(report.crossings[1].sources.len(), 4);
+}
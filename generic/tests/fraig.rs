@@ -0,0 +1,157 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use prjunnamed_generic::{Fraig, fraig};
+use prjunnamed_netlist::{Cell, Design, Net, Value};
+
+fn outputs(design: &Design) -> BTreeMap<String, Value> {
+    BTreeMap::from_iter(design.iter_cells().filter_map(|cell_ref| match &*cell_ref.get() {
+        Cell::Output(name, value) => Some((name.clone(), value.clone())),
+        _ => None,
+    }))
+}
+
+#[test]
+fn test_fraig_equivalent() {
+    // a & b, and !(!a | !b)
+    let mut design = Design::from_str(concat!(
+        "%0:2 = input \"a\"\n",
+        "%2:1 = and %0+0 %0+1\n",
+        "%3:2 = not %0:2\n",
+        "%5:1 = or %3+0 %3+1\n",
+        "%6:1 = not %5\n",
+        "%7:0 = output \"x\" %2\n",
+        "%8:0 = output \"y\" %6\n",
+    ))
+    .unwrap();
+    fraig(&mut design);
+    let outputs = outputs(&design);
+    assert_eq!(outputs["x"], outputs["y"]);
+    assert_eq!(design.iter_cells().filter(|cell_ref| matches!(&*cell_ref.get(), Cell::Or(..))).count(), 0);
+}
+
+#[test]
+fn test_fraig_complement() {
+    // a ^ b, and (a & b) | (!a & !b)
+    let mut design = Design::from_str(concat!(
+        "%0:2 = input \"a\"\n",
+        "%2:1 = xor %0+0 %0+1\n",
+        "%3:1 = aig %0+0 %0+1\n",
+        "%4:1 = aig !%0+0 !%0+1\n",
+        "%5:1 = or %3 %4\n",
+        "%6:0 = output \"x\" %2\n",
+        "%7:0 = output \"y\" %5\n",
+    ))
+    .unwrap();
+    fraig(&mut design);
+    let outputs = outputs(&design);
+    let (driver, _) = design.find_cell(outputs["y"][0]).unwrap();
+    assert_eq!(*driver.get(), Cell::Not(outputs["x"].clone()));
+}
+
+#[test]
+fn test_fraig_constant() {
+    let mut design = Design::from_str(concat!(
+        "%0:2 = input \"a\"\n",
+        "%2:1 = not %0+0\n",
+        "%3:1 = and %0+0 %0+1\n",
+        "%4:1 = and %3 %2\n",
+        "%5:1 = or %3 %2\n",
+        "%6:1 = or %5 %0+0\n",
+        "%7:0 = output \"x\" %4\n",
+        "%8:0 = output \"y\" %6\n",
+    ))
+    .unwrap();
+    fraig(&mut design);
+    let outputs = outputs(&design);
+    assert_eq!(outputs["x"], Value::from(Net::ZERO));
+    assert_eq!(outputs["y"], Value::from(Net::ONE));
+}
+
+#[test]
+fn test_fraig_rare_difference() {
+    // the conjunction of 16 inputs is almost never true for random inputs, but it is not constant
+    let mut design = Design::new();
+    let inputs = design.add_input("a", 16);
+    let mut conjunction = inputs[0];
+    for net in &inputs[1..] {
+        conjunction = design.add_and(conjunction, net).unwrap_net();
+    }
+    design.add_output("y", conjunction);
+    design.apply();
+    fraig(&mut design);
+    assert_ne!(outputs(&design)["y"], Value::from(Net::ZERO));
+}
+
+#[test]
+fn test_fraig_conflict_limit() {
+    // (a ^ b) ^ c, and a ^ (b ^ c); proving their equivalence requires search
+    let source = concat!(
+        "%0:3 = input \"a\"\n",
+        "%3:1 = xor %0+0 %0+1\n",
+        "%4:1 = xor %3 %0+2\n",
+        "%5:1 = xor %0+1 %0+2\n",
+        "%6:1 = xor %0+0 %5\n",
+        "%7:0 = output \"x\" %4\n",
+        "%8:0 = output \"y\" %6\n",
+    );
+    let mut design = Design::from_str(source).unwrap();
+    assert_eq!(Fraig::new().with_conflict_limit(0).run(&mut design), 0);
+    let mut design = Design::from_str(source).unwrap();
+    assert_eq!(Fraig::new().run(&mut design), 1);
+    let outputs = outputs(&design);
+    assert_eq!(outputs["x"], outputs["y"]);
+}
+
+#[test]
+fn test_fraig_undef_independent() {
+    // (a0 ^ X) ^ (a1 ^ X) is not a0 ^ a1, since the two undefined constants may be chosen independently
+    let mut design = Design::from_str(concat!(
+        "%0:2 = input \"a\"\n",
+        "%2:1 = xor %0+0 X\n",
+        "%3:1 = xor %0+1 X\n",
+        "%4:1 = xor %2 %3\n",
+        "%5:1 = xor %0+0 %0+1\n",
+        "%6:0 = output \"x\" %4\n",
+        "%7:0 = output \"y\" %5\n",
+    ))
+    .unwrap();
+    assert_eq!(Fraig::new().run(&mut design), 0);
+    let outputs = outputs(&design);
+    assert_ne!(outputs["x"], outputs["y"]);
+}
+
+#[test]
+fn test_fraig_undef_masked() {
+    // (a & X) | a is a, whatever the undefined constant is
+    let mut design = Design::from_str(concat!(
+        "%0:1 = input \"a\"\n",
+        "%1:1 = and %0 X\n",
+        "%2:1 = or %1 %0\n",
+        "%3:0 = output \"y\" %2\n",
+    ))
+    .unwrap();
+    assert_eq!(Fraig::new().run(&mut design), 1);
+    let input = design.iter_cells().find_map(|cell_ref| match &*cell_ref.get() {
+        Cell::Input(name, _) if name == "a" => Some(cell_ref.output()),
+        _ => None,
+    }).unwrap();
+    assert_eq!(outputs(&design)["y"], input);
+}
+
+#[test]
+fn test_fraig_proof_limit() {
+    let source = concat!(
+        "%0:2 = input \"a\"\n",
+        "%2:1 = and %0+0 %0+1\n",
+        "%3:2 = not %0:2\n",
+        "%5:1 = or %3+0 %3+1\n",
+        "%6:1 = not %5\n",
+        "%7:0 = output \"x\" %2\n",
+        "%8:0 = output \"y\" %6\n",
+    );
+    let mut design = Design::from_str(source).unwrap();
+    assert_eq!(Fraig::new().with_proof_limit(0).run(&mut design), 0);
+    let mut design = Design::from_str(source).unwrap();
+    assert_eq!(Fraig::new().with_proof_limit(1).run(&mut design), 1);
+}
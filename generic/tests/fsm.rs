@@ -0,0 +1,105 @@
+use std::collections::BTreeSet;
+use std::str::FromStr;
+
+use prjunnamed_generic::fsm;
+use prjunnamed_netlist::{Cell, Const, Design, EnumEncoding};
+
+fn count_cells(design: &Design, predicate: impl Fn(&Cell) -> bool) -> usize {
+    design.iter_cells().filter(|cell_ref| predicate(&cell_ref.get())).count()
+}
+
+fn dff_widths(design: &Design) -> Vec<usize> {
+    Vec::from_iter(design.iter_cells().filter_map(|cell_ref| match &*cell_ref.get() {
+        Cell::Dff(flip_flop) => Some(flip_flop.data.len()),
+        _ => None,
+    }))
+}
+
+// IDLE -(go)-> RUN -> DONE -> IDLE
+const HANDSHAKE: &str = concat!(
+    "%0:1 = input \"clk\"\n",
+    "%1:1 = input \"rst\"\n",
+    "%2:1 = input \"go\"\n",
+    "%3:2 = dff %13:2 clk=%0 rst=%1,00 !0\n",
+    "%5:3 = match %3:2 { 00 01 10 }\n",
+    "%8:1 = and %5+0 %2\n",
+    "%9:2 = assign en=%8 %3:2 01\n",
+    "%11:2 = assign en=%5+1 %9:2 10\n",
+    "%13:2 = assign en=%5+2 %11:2 00\n",
+    "%15:0 = output \"busy\" %5+1\n",
+);
+
+#[test]
+fn test_fsm_one_hot() {
+    let mut design = Design::from_str(&format!(
+        "!0 = enum encoding=\"one-hot\" {{ \"IDLE\"=00 \"RUN\"=01 \"DONE\"=10 }}\n{HANDSHAKE}"
+    ))
+    .unwrap();
+    let fsms = fsm(&mut design);
+    assert_eq!(fsms.len(), 1);
+    let fsm = &fsms[0];
+    assert_eq!(fsm.encoding, EnumEncoding::OneHot);
+    assert_eq!(Vec::from_iter(fsm.states.iter().map(|state| (state.name.as_deref(), state.old_code.clone()))), vec![
+        (Some("IDLE"), Const::lit("00")),
+        (Some("RUN"), Const::lit("01")),
+        (Some("DONE"), Const::lit("10"))
+    ]);
+    assert_eq!(Vec::from_iter(fsm.states.iter().map(|state| state.new_code.clone())), vec![
+        Const::lit("001"),
+        Const::lit("010"),
+        Const::lit("100")
+    ]);
+    assert_eq!(fsm.transitions, BTreeSet::from([(0, 0), (0, 1), (1, 2), (2, 0)]));
+    assert_eq!(dff_widths(&design), vec![3]);
+    for cell_ref in design.iter_cells() {
+        if let Cell::Match(match_cell) = &*cell_ref.get() {
+            assert_eq!(match_cell.patterns, vec![vec![Const::lit("XX1")], vec![Const::lit("X1X")], vec![Const::lit(
+                "1XX"
+            )]]);
+        }
+    }
+}
+
+#[test]
+fn test_fsm_default_binary() {
+    let mut design = Design::from_str(concat!(
+        "%0:1 = input \"clk\"\n",
+        "%1:1 = input \"go\"\n",
+        "%2:4 = dff %12:4 clk=%0 init=0001\n",
+        "%6:1 = eq %2:4 0100\n",
+        "%7:1 = eq %2:4 1000\n",
+        "%8:4 = assign en=%1 %2:4 0100\n",
+        "%12:4 = assign en=%6 %8:4 1000\n",
+        "%16:0 = output \"done\" %7\n",
+    ))
+    .unwrap();
+    let fsms = fsm(&mut design);
+    assert_eq!(fsms.len(), 1);
+    assert_eq!(fsms[0].encoding, EnumEncoding::Binary);
+    assert_eq!(fsms[0].states.len(), 3);
+    assert_eq!(dff_widths(&design), vec![2]);
+    assert_eq!(count_cells(&design, |cell| matches!(cell, Cell::Eq(..))), 0);
+}
+
+#[test]
+fn test_fsm_name_decoded() {
+    let mut design = Design::from_str(&format!("!0 = enum {{ }}\n{HANDSHAKE}%16:0 = name \"state\" %3:2\n")).unwrap();
+    let fsms = fsm(&mut design);
+    assert_eq!(fsms.len(), 1);
+    assert_eq!(fsms[0].names, vec!["state".to_owned()]);
+    assert_eq!(fsms[0].encoding, EnumEncoding::Binary);
+    // the name refers to the state in the original encoding
+    for cell_ref in design.iter_cells() {
+        if let Cell::Name(_, value) = &*cell_ref.get() {
+            let (driver, _) = design.find_cell(value[0]).unwrap();
+            assert!(matches!(&*driver.get(), Cell::Assign(..)));
+        }
+    }
+}
+
+#[test]
+fn test_fsm_state_exposed() {
+    let mut design = Design::from_str(&format!("!0 = enum {{ }}\n{HANDSHAKE}%16:0 = output \"state\" %3:2\n")).unwrap();
+    assert!(fsm(&mut design).is_empty());
+    assert_eq!(dff_widths(&design), vec![2]);
+}
@@ -0,0 +1,40 @@
+use std::str::FromStr;
+
+use prjunnamed_generic::LevelAnalysis;
+use prjunnamed_netlist::{Design, RewriteRuleset};
+
+#[test]
+fn test_pending_level_delta() {
+    let design = Design::from_str(concat!(
+        "%0:1 = input \"a\"\n",
+        "%1:1 = input \"b\"\n",
+        "%2:1 = input \"c\"\n",
+        "%3:1 = input \"d\"\n",
+        "%4:1 = and %0 %1\n",
+        "%5:1 = and %4 %2\n",
+        "%6:1 = and %5 %3\n",
+        "%7:0 = output \"z\" %6\n",
+    ))
+    .unwrap();
+    let levels = LevelAnalysis::new();
+    for cell_ref in design.iter_cells_topo() {
+        levels.cell_added(&design, &cell_ref.get(), &cell_ref.output());
+    }
+    let cells = Vec::from_iter(design.iter_cells().map(|cell_ref| cell_ref.output()));
+    assert_eq!(levels.get(cells[6][0]), 3);
+
+    // `(a & b) & (c & d)` is one level shallower
+    let checkpoint = design.checkpoint();
+    let p = design.add_and(&cells[2], &cells[3]);
+    let q = design.add_and(&cells[4], p);
+    design.replace_value(&cells[6], &q);
+    assert_eq!(levels.pending_level_delta(&design), -1);
+    design.rollback(checkpoint);
+
+    // `(a & b) & (c & (a & c))` is one level deeper than `(a & b) & c`
+    let p = design.add_and(&cells[0], &cells[2]);
+    let q = design.add_and(&cells[2], p);
+    let r = design.add_and(&cells[4], q);
+    design.replace_value(&cells[5], &r);
+    assert_eq!(levels.pending_level_delta(&design), 1);
+}
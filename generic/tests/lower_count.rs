@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use prjunnamed_generic::{canonicalize, LowerCount};
+use prjunnamed_netlist::{assert_isomorphic, Cell, Design, Net, Trit};
+
+fn simulate(design: &Design, input: u64) -> u64 {
+    let mut values: HashMap<Net, bool> = HashMap::new();
+    let value = |values: &HashMap<Net, bool>, net: Net| match net.as_const() {
+        Some(trit) => trit == Trit::One,
+        None => values[&net],
+    };
+    let mut result = 0;
+    for cell_ref in design.iter_cells_topo() {
+        let output = cell_ref.output();
+        let bits: Vec<bool> = match &*cell_ref.get() {
+            Cell::Input(_, width) => Vec::from_iter((0..*width).map(|index| (input >> index) & 1 != 0)),
+            Cell::Output(_, output_value) => {
+                for (index, net) in output_value.iter().enumerate() {
+                    result |= (value(&values, net) as u64) << index;
+                }
+                continue;
+            }
+            Cell::Not(arg) => Vec::from_iter(arg.iter().map(|net| !value(&values, net))),
+            Cell::And(arg1, arg2) => {
+                Vec::from_iter(arg1.iter().zip(arg2).map(|(a, b)| value(&values, a) & value(&values, b)))
+            }
+            Cell::Mux(sel, arg1, arg2) => {
+                let arg = if value(&values, *sel) { arg1 } else { arg2 };
+                Vec::from_iter(arg.iter().map(|net| value(&values, net)))
+            }
+            Cell::Adc(arg1, arg2, carry_in) => {
+                let mut carry = value(&values, *carry_in);
+                let mut bits = vec![];
+                for (a, b) in arg1.iter().zip(arg2) {
+                    let (a, b) = (value(&values, a), value(&values, b));
+                    bits.push(a ^ b ^ carry);
+                    carry = (a & b) | (carry & (a ^ b));
+                }
+                bits.push(carry);
+                bits
+            }
+            cell => panic!("unexpected cell {cell:?}"),
+        };
+        for (net, bit) in output.iter().zip(bits) {
+            values.insert(net, bit);
+        }
+    }
+    result
+}
+
+#[test]
+fn test_lower_count() {
+    for width in 1..=10 {
+        for (kind, cell, count) in [
+            ("popcnt", Cell::Popcnt as fn(_) -> _, (|a: u64, _| a.count_ones() as u64) as fn(u64, usize) -> u64),
+            ("clz", Cell::Clz, |a, width| (a.leading_zeros() as usize + width - 64) as u64),
+            ("ctz", Cell::Ctz, |a, width| (a.trailing_zeros() as usize).min(width) as u64),
+        ] {
+            let mut design = Design::new();
+            let a = design.add_input("a", width);
+            let y = design.add_cell(cell(a));
+            design.add_output("y", y);
+            design.apply();
+            design.rewrite(&[&LowerCount]);
+            assert!(
+                !design
+                    .iter_cells()
+                    .any(|cell_ref| matches!(&*cell_ref.get(), Cell::Popcnt(..) | Cell::Clz(..) | Cell::Ctz(..)))
+            );
+            for a in 0..1 << width {
+                assert_eq!(simulate(&design, a), count(a, width), "{kind} {width}: {a:0width$b}");
+            }
+        }
+    }
+}
+
+#[test]
+fn test_simplify_count() {
+    let mut design = Design::from_str(concat!(
+        "%0:4 = input \"a\"\n",
+        "%4:3 = popcnt 1011\n",
+        "%5:3 = clz 0X11\n",
+        "%6:3 = ctz [%0:3 1]\n",
+        "%7:3 = popcnt [%0+0 0 %0+1 0]\n",
+        "%8:1 = clz %0+3\n",
+        "%9:0 = output \"y1\" %4:3\n",
+        "%10:0 = output \"y2\" %5:3\n",
+        "%11:0 = output \"y3\" %6:3\n",
+        "%12:0 = output \"y4\" %7:3\n",
+        "%13:0 = output \"y5\" %8:1\n",
+    ))
+    .unwrap();
+    canonicalize(&mut design);
+    let mut gold = Design::from_str(concat!(
+        "%0:4 = input \"a\"\n",
+        "%4:2 = popcnt [%0+0 %0+1]\n",
+        "%5:1 = not %0+3\n",
+        "%9:0 = output \"y1\" 011\n",
+        "%10:0 = output \"y2\" XXX\n",
+        "%11:0 = output \"y3\" 000\n",
+        "%12:0 = output \"y4\" [0 %4:2]\n",
+        "%13:0 = output \"y5\" %5:1\n",
+    ))
+    .unwrap();
+    assert_isomorphic!(design, gold);
+}
@@ -1,7 +1,12 @@
+use std::collections::{BTreeMap, HashMap};
 use std::str::FromStr;
+use std::sync::Arc;
 
-use prjunnamed_generic::{LowerMul, Normalize};
-use prjunnamed_netlist::{assert_isomorphic, Design};
+use prjunnamed_generic::{lower_arith, LowerMul, Normalize};
+use prjunnamed_netlist::{
+    assert_isomorphic, Cell, Const, Design, MulArchitecture, Net, Target, TargetCell, TargetImportError,
+    TargetPrototype, Trit,
+};
 
 #[test]
 fn test_lower_mul() {
@@ -12,7 +17,7 @@ fn test_lower_mul() {
         "%4:0 = output \"c\" %2:5\n",
     ))
     .unwrap();
-    design.rewrite(&[&LowerMul, &Normalize]);
+    design.rewrite(&[&LowerMul::default(), &Normalize]);
     let mut gold = Design::from_str(concat!(
         "%0:5 = input \"a\"\n",
         "%1:5 = input \"b\"\n",
@@ -31,3 +36,162 @@ fn test_lower_mul() {
     .unwrap();
     assert_isomorphic!(design, gold);
 }
+
+fn simulate(design: &Design, inputs: &[(&str, u64)]) -> u64 {
+    let mut values: HashMap<Net, bool> = HashMap::new();
+    let value = |values: &HashMap<Net, bool>, net: Net| match net.as_const() {
+        Some(trit) => trit == Trit::One,
+        None => values[&net],
+    };
+    let mut result = 0;
+    for cell_ref in design.iter_cells_topo() {
+        let output = cell_ref.output();
+        let bits: Vec<bool> = match &*cell_ref.get() {
+            Cell::Input(name, width) => {
+                let input = inputs.iter().find(|(input_name, _)| input_name == name).unwrap().1;
+                Vec::from_iter((0..*width).map(|index| (input >> index) & 1 != 0))
+            }
+            Cell::Output(_, output_value) => {
+                for (index, net) in output_value.iter().enumerate() {
+                    result |= (value(&values, net) as u64) << index;
+                }
+                continue;
+            }
+            Cell::Buf(arg) => Vec::from_iter(arg.iter().map(|net| value(&values, net))),
+            Cell::Not(arg) => Vec::from_iter(arg.iter().map(|net| !value(&values, net))),
+            Cell::And(arg1, arg2) => {
+                Vec::from_iter(arg1.iter().zip(arg2).map(|(a, b)| value(&values, a) & value(&values, b)))
+            }
+            Cell::Or(arg1, arg2) => {
+                Vec::from_iter(arg1.iter().zip(arg2).map(|(a, b)| value(&values, a) | value(&values, b)))
+            }
+            Cell::Xor(arg1, arg2) => {
+                Vec::from_iter(arg1.iter().zip(arg2).map(|(a, b)| value(&values, a) ^ value(&values, b)))
+            }
+            Cell::Mux(sel, arg1, arg2) => {
+                let arg = if value(&values, *sel) { arg1 } else { arg2 };
+                Vec::from_iter(arg.iter().map(|net| value(&values, net)))
+            }
+            Cell::Adc(arg1, arg2, carry_in) => {
+                let mut carry = value(&values, *carry_in);
+                let mut bits = vec![];
+                for (a, b) in arg1.iter().zip(arg2) {
+                    let (a, b) = (value(&values, a), value(&values, b));
+                    bits.push(a ^ b ^ carry);
+                    carry = (a & b) | (carry & (a ^ b));
+                }
+                bits.push(carry);
+                bits
+            }
+            cell => panic!("unexpected cell {cell:?}"),
+        };
+        for (net, bit) in output.iter().zip(bits) {
+            values.insert(net, bit);
+        }
+    }
+    result
+}
+
+#[test]
+fn test_lower_mul_architectures() {
+    for architecture in
+        [MulArchitecture::Array, MulArchitecture::BoothArray, MulArchitecture::Wallace, MulArchitecture::BoothWallace]
+    {
+        for width in 1..=6 {
+            let mut design = Design::new();
+            let a = design.add_input("a", width);
+            let b = design.add_input("b", width);
+            let y = design.add_mul(a, b);
+            design.add_output("y", y);
+            design.apply();
+            design.rewrite(&[&LowerMul::new(architecture)]);
+            assert!(!design.iter_cells().any(|cell_ref| matches!(&*cell_ref.get(), Cell::Mul(..))));
+            let mask = (1u64 << width) - 1;
+            for a in 0..1 << width {
+                for b in 0..1 << width {
+                    assert_eq!(
+                        simulate(&design, &[("a", a), ("b", b)]),
+                        (a * b) & mask,
+                        "{architecture:?} {width}: {a} * {b}"
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_lower_mul_constant() {
+    for architecture in [MulArchitecture::Array, MulArchitecture::Wallace] {
+        for constant in 0..1u64 << 6 {
+            let mut design = Design::new();
+            let a = design.add_input("a", 6);
+            let y = design.add_mul(a, Const::from_uint(constant as u128, 6));
+            design.add_output("y", y);
+            design.apply();
+            design.rewrite(&[&LowerMul::new(architecture)]);
+            for a in 0..1 << 6 {
+                assert_eq!(simulate(&design, &[("a", a)]), (a * constant) & 0x3f, "{architecture:?}: {a} * {constant}");
+            }
+        }
+    }
+    // 0b111100 is 0b1000000 - 0b100, and the first term is out of range, so only the second term remains
+    let mut design = Design::new();
+    let a = design.add_input("a", 6);
+    let y = design.add_mul(a, Const::lit("111100"));
+    design.add_output("y", y);
+    design.apply();
+    design.rewrite(&[&LowerMul::default()]);
+    assert_eq!(design.iter_cells().filter(|cell_ref| matches!(&*cell_ref.get(), Cell::Adc(..))).count(), 1);
+}
+
+#[derive(Debug)]
+struct BoothTarget;
+
+impl Target for BoothTarget {
+    fn name(&self) -> &str {
+        "booth"
+    }
+
+    fn options(&self) -> BTreeMap<String, String> {
+        BTreeMap::new()
+    }
+
+    fn prototype(&self, _name: &str) -> Option<&TargetPrototype> {
+        None
+    }
+
+    fn validate(&self, _design: &Design, _cell: &TargetCell) {}
+
+    fn import(&self, _design: &mut Design) -> Result<(), TargetImportError> {
+        Ok(())
+    }
+
+    fn export(&self, _design: &mut Design) {}
+
+    fn synthesize(&self, _design: &mut Design) -> Result<(), ()> {
+        Ok(())
+    }
+
+    fn mul_architecture(&self) -> MulArchitecture {
+        MulArchitecture::BoothArray
+    }
+}
+
+#[test]
+fn test_lower_arith_mul_target_architecture() {
+    // a radix-4 Booth multiplier sums the partial products of a 4-bit operand with 2 adders, not 4
+    let mut design = Design::with_target(Some(Arc::new(BoothTarget)));
+    let a = design.add_input("a", 4);
+    let b = design.add_input("b", 4);
+    let y = design.add_mul(a, b);
+    design.add_output("y", y);
+    design.apply();
+    lower_arith(&mut design);
+    assert_eq!(design.iter_cells().filter(|cell_ref| matches!(&*cell_ref.get(), Cell::Adc(..))).count(), 2);
+    for a in 0..16 {
+        for b in 0..16 {
+            assert_eq!(simulate(&design, &[("a", a), ("b", b)]), (a * b) & 15, "{a} * {b}");
+        }
+    }
+}
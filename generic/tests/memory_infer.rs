@@ -0,0 +1,145 @@
+use std::str::FromStr;
+
+use prjunnamed_generic::memory_infer;
+use prjunnamed_netlist::{Cell, Design, Memory, Value};
+
+fn count_cells(design: &Design, predicate: impl Fn(&Cell) -> bool) -> usize {
+    design.iter_cells().filter(|cell_ref| predicate(&cell_ref.get())).count()
+}
+
+fn find_memory(design: &Design) -> Memory {
+    design
+        .iter_cells()
+        .find_map(|cell_ref| match &*cell_ref.get() {
+            Cell::Memory(memory) => Some(memory.clone()),
+            _ => None,
+        })
+        .unwrap()
+}
+
+fn find_input(design: &Design, name: &str) -> Value {
+    design
+        .iter_cells()
+        .find_map(|cell_ref| match &*cell_ref.get() {
+            Cell::Input(input_name, _) if input_name == name => Some(cell_ref.output()),
+            _ => None,
+        })
+        .unwrap()
+}
+
+const WRITE_EQ: &str = concat!(
+    "%0:4 = input \"wdata\"\n",
+    "%4:2 = input \"waddr\"\n",
+    "%6:1 = input \"we\"\n",
+    "%7:2 = input \"raddr\"\n",
+    "%9:1 = input \"clk\"\n",
+    "%10:1 = eq %4:2 00\n",
+    "%11:1 = eq %4:2 01\n",
+    "%12:1 = eq %4:2 10\n",
+    "%13:1 = eq %4:2 11\n",
+    "%14:1 = and %6 %10\n",
+    "%15:1 = and %6 %11\n",
+    "%16:1 = and %6 %12\n",
+    "%17:1 = and %6 %13\n",
+    "%18:4 = dff %0:4 clk=%9 en=%14 init=0001\n",
+    "%22:4 = dff %0:4 clk=%9 en=%15 init=0010\n",
+    "%26:4 = dff %0:4 clk=%9 en=%16 init=0100\n",
+    "%30:4 = dff %0:4 clk=%9 en=%17 init=1000\n",
+);
+
+#[test]
+fn test_memory_infer_xshr() {
+    let mut design = Design::from_str(&format!(
+        "{WRITE_EQ}{}{}",
+        "%34:16 = xshr [%30:4 %26:4 %22:4 %18:4] %7:2 #4\n", "%50:0 = output \"rdata\" %34:4\n",
+    ))
+    .unwrap();
+    assert!(memory_infer(&mut design));
+    assert_eq!(count_cells(&design, |cell| matches!(cell, Cell::Dff(..))), 0);
+    assert_eq!(count_cells(&design, |cell| matches!(cell, Cell::XShr(..))), 0);
+    let memory = find_memory(&design);
+    assert_eq!((memory.depth, memory.width), (4, 4));
+    assert_eq!(memory.init_value, prjunnamed_netlist::Const::lit("1000010000100001"));
+    assert_eq!(memory.write_ports.len(), 1);
+    assert_eq!(memory.write_ports[0].addr, find_input(&design, "waddr"));
+    assert_eq!(memory.write_ports[0].data, find_input(&design, "wdata"));
+    assert_eq!(memory.write_ports[0].mask, find_input(&design, "we").repeat(4));
+    assert_eq!(memory.read_ports.len(), 1);
+    assert_eq!(memory.read_ports[0].addr, find_input(&design, "raddr"));
+    assert!(memory.read_ports[0].flip_flop.is_none());
+}
+
+#[test]
+fn test_memory_infer_xshr_bit_address() {
+    // the read address is multiplied by the row width
+    let mut design = Design::from_str(&format!(
+        "{WRITE_EQ}{}{}",
+        "%34:16 = xshr [%30:4 %26:4 %22:4 %18:4] [%7:2 00] #1\n", "%50:0 = output \"rdata\" %34:4\n",
+    ))
+    .unwrap();
+    assert!(memory_infer(&mut design));
+    assert_eq!(find_memory(&design).read_ports[0].addr, find_input(&design, "raddr"));
+}
+
+#[test]
+fn test_memory_infer_mux_tree() {
+    let mut design = Design::from_str(&format!(
+        "{WRITE_EQ}{}{}{}{}{}",
+        "%34:4 = mux %7+0 %22:4 %18:4\n",
+        "%38:4 = mux %7+0 %30:4 %26:4\n",
+        "%42:4 = mux %7+1 %38:4 %34:4\n",
+        "%46:4 = mux %7+1 %30:4 %18:4\n",
+        "%50:0 = output \"rdata\" [%46:4 %42:4]\n",
+    ))
+    .unwrap();
+    // the second read (of rows 0 and 3) is not a complete tree
+    assert!(!memory_infer(&mut design));
+    assert_eq!(count_cells(&design, |cell| matches!(cell, Cell::Memory(..))), 0);
+
+    let mut design = Design::from_str(&format!(
+        "{WRITE_EQ}{}{}{}{}",
+        "%34:4 = mux %7+0 %22:4 %18:4\n",
+        "%38:4 = mux %7+0 %30:4 %26:4\n",
+        "%42:4 = mux %7+1 %38:4 %34:4\n",
+        "%50:0 = output \"rdata\" %42:4\n",
+    ))
+    .unwrap();
+    assert!(memory_infer(&mut design));
+    assert_eq!(count_cells(&design, |cell| matches!(cell, Cell::Dff(..) | Cell::Mux(..))), 0);
+    assert_eq!(find_memory(&design).read_ports[0].addr, find_input(&design, "raddr"));
+}
+
+#[test]
+fn test_memory_infer_match() {
+    let mut design = Design::from_str(concat!(
+        "%0:4 = input \"wdata\"\n",
+        "%4:1 = input \"waddr\"\n",
+        "%5:1 = input \"we\"\n",
+        "%6:1 = input \"raddr\"\n",
+        "%7:1 = input \"clk\"\n",
+        "%8:2 = match en=%5 %4 { 0 1 }\n",
+        "%10:4 = dff %0:4 clk=%7 en=%8+0\n",
+        "%14:4 = dff %0:4 clk=%7 en=%8+1\n",
+        "%18:4 = mux %6 %14:4 %10:4\n",
+        "%22:0 = output \"rdata\" %18:4\n",
+    ))
+    .unwrap();
+    assert!(memory_infer(&mut design));
+    let memory = find_memory(&design);
+    assert_eq!((memory.depth, memory.width), (2, 4));
+    assert_eq!(memory.write_ports[0].mask, find_input(&design, "we").repeat(4));
+}
+
+#[test]
+fn test_memory_infer_other_use() {
+    // a row is also used directly, so the flip-flops must stay
+    let mut design = Design::from_str(&format!(
+        "{WRITE_EQ}{}{}{}",
+        "%34:16 = xshr [%30:4 %26:4 %22:4 %18:4] %7:2 #4\n",
+        "%50:0 = output \"rdata\" %34:4\n",
+        "%51:0 = output \"row0\" %18:4\n",
+    ))
+    .unwrap();
+    assert!(!memory_infer(&mut design));
+    assert_eq!(count_cells(&design, |cell| matches!(cell, Cell::Dff(..))), 4);
+}
@@ -0,0 +1,146 @@
+use std::str::FromStr;
+
+use prjunnamed_generic::{counters, shift_registers};
+use prjunnamed_netlist::{Cell, Const, Design, Value};
+
+fn find_input(design: &Design, name: &str) -> Value {
+    design
+        .iter_cells()
+        .find_map(|cell_ref| match &*cell_ref.get() {
+            Cell::Input(input_name, _) if input_name == name => Some(cell_ref.output()),
+            _ => None,
+        })
+        .unwrap()
+}
+
+fn find_output(design: &Design, name: &str) -> Value {
+    design
+        .iter_cells()
+        .find_map(|cell_ref| match &*cell_ref.get() {
+            Cell::Output(output_name, value) if output_name == name => Some(value.clone()),
+            _ => None,
+        })
+        .unwrap()
+}
+
+#[test]
+fn test_shift_register_word() {
+    let design = Design::from_str(concat!(
+        "%0:4 = input \"d\"\n",
+        "%4:1 = input \"clk\"\n",
+        "%5:1 = input \"en\"\n",
+        "%6:4 = dff %0:4 clk=%4 en=%5\n",
+        "%10:4 = dff %6:4 clk=%4 en=%5\n",
+        "%14:4 = dff %10:4 clk=%4 en=%5 init=1010\n",
+        "%18:0 = output \"q\" %14:4\n",
+        "%19:0 = name \"sr\" [%14:4 %10:4 %6:4]\n",
+    ))
+    .unwrap();
+    let shift_registers = shift_registers(&design);
+    assert_eq!(shift_registers.len(), 1);
+    let shift_register = &shift_registers[0];
+    assert_eq!(shift_register.names, vec!["sr".to_string()]);
+    assert_eq!((shift_register.stages.len(), shift_register.width()), (3, 4));
+    assert_eq!(shift_register.input(), &find_input(&design, "d"));
+    assert_eq!(shift_register.output(), &find_output(&design, "q"));
+    assert_eq!(shift_register.stages[2].init_value, Const::lit("1010"));
+    assert_eq!(shift_register.stages[1].data, shift_register.outputs[0]);
+}
+
+#[test]
+fn test_shift_register_single_cell() {
+    // `sr <= {sr[2:0], d}`
+    let design = Design::from_str(concat!(
+        "%0:1 = input \"d\"\n",
+        "%1:1 = input \"clk\"\n",
+        "%2:4 = dff [%2+2 %2+1 %2+0 %0] clk=%1\n",
+        "%6:0 = output \"q\" %2+3\n",
+    ))
+    .unwrap();
+    let shift_registers = shift_registers(&design);
+    assert_eq!(shift_registers.len(), 1);
+    assert_eq!((shift_registers[0].stages.len(), shift_registers[0].width()), (4, 1));
+    assert_eq!(shift_registers[0].input(), &find_input(&design, "d"));
+    assert_eq!(shift_registers[0].output(), &find_output(&design, "q"));
+}
+
+#[test]
+fn test_shift_register_tap() {
+    // the output of the second stage is also used elsewhere, and the fourth stage has different controls
+    let design = Design::from_str(concat!(
+        "%0:2 = input \"d\"\n",
+        "%2:1 = input \"clk\"\n",
+        "%3:1 = input \"rst\"\n",
+        "%4:2 = dff %0:2 clk=%2\n",
+        "%6:2 = dff %4:2 clk=%2\n",
+        "%8:2 = dff %6:2 clk=%2\n",
+        "%10:2 = dff %8:2 clk=%2 rst=%3,00\n",
+        "%12:0 = output \"tap\" %6:2\n",
+        "%13:0 = output \"q\" %10:2\n",
+    ))
+    .unwrap();
+    let shift_registers = shift_registers(&design);
+    assert_eq!(shift_registers.len(), 1);
+    assert_eq!(shift_registers[0].stages.len(), 2);
+    assert_eq!(shift_registers[0].output(), &find_output(&design, "tap"));
+}
+
+#[test]
+fn test_shift_register_ring() {
+    let design = Design::from_str(concat!(
+        "%0:1 = input \"clk\"\n",
+        "%1:3 = dff [%1+1 %1+0 %1+2] clk=%0 init=001\n",
+        "%4:0 = output \"q\" %1:3\n",
+    ))
+    .unwrap();
+    assert!(shift_registers(&design).is_empty());
+}
+
+#[test]
+fn test_counter() {
+    let design = Design::from_str(concat!(
+        "%0:1 = input \"clk\"\n",
+        "%1:1 = input \"rst\"\n",
+        "%2:1 = input \"en\"\n",
+        "%3:4 = dff %7:4 clk=%0 rst=%1,0000 en=%2\n",
+        "%7:5 = adc %3:4 0001 0\n",
+        "%12:0 = output \"q\" %3:4\n",
+        "%13:0 = name \"count\" %3:4\n",
+    ))
+    .unwrap();
+    let counters = counters(&design);
+    assert_eq!(counters.len(), 1);
+    assert_eq!(counters[0].names, vec!["count".to_string()]);
+    assert_eq!(counters[0].output, find_output(&design, "q"));
+    assert_eq!(counters[0].step, Const::lit("0001"));
+    assert_eq!(counters[0].flip_flop.reset, find_input(&design, "rst")[0].into());
+    assert_eq!(counters[0].flip_flop.enable, find_input(&design, "en")[0].into());
+}
+
+#[test]
+fn test_counter_down() {
+    let design = Design::from_str(concat!(
+        "%0:1 = input \"clk\"\n",
+        "%1:3 = dff %4:3 clk=%0\n",
+        "%4:4 = adc 111 %1:3 0\n",
+        "%8:0 = output \"q\" %1:3\n",
+    ))
+    .unwrap();
+    let counters = counters(&design);
+    assert_eq!(counters.len(), 1);
+    assert_eq!(counters[0].step, Const::lit("111"));
+}
+
+#[test]
+fn test_counter_not_constant() {
+    // an accumulator is not a counter
+    let design = Design::from_str(concat!(
+        "%0:1 = input \"clk\"\n",
+        "%1:3 = input \"a\"\n",
+        "%4:3 = dff %7:3 clk=%0\n",
+        "%7:4 = adc %4:3 %1:3 0\n",
+        "%11:0 = output \"q\" %4:3\n",
+    ))
+    .unwrap();
+    assert!(counters(&design).is_empty());
+}
@@ -0,0 +1,139 @@
+use std::str::FromStr;
+
+use prjunnamed_generic::retime;
+use prjunnamed_netlist::{Cell, Const, Design, FlipFlop};
+
+fn flip_flops(design: &Design) -> Vec<FlipFlop> {
+    Vec::from_iter(design.iter_cells().filter_map(|cell_ref| match &*cell_ref.get() {
+        Cell::Dff(flip_flop) => Some(flip_flop.clone()),
+        _ => None,
+    }))
+}
+
+#[test]
+fn test_retime_forward() {
+    // the registers are driven by inputs, and drive 4 levels of logic
+    let mut design = Design::from_str(concat!(
+        "%0:1 = input \"clk\"\n",
+        "%1:2 = input \"a\"\n",
+        "%3:3 = input \"b\"\n",
+        "%6:2 = dff %1:2 clk=%0 init=01\n",
+        "%8:1 = and %6+0 %6+1\n",
+        "%9:1 = xor %8 %3+0\n",
+        "%10:1 = xor %9 %3+1\n",
+        "%11:1 = xor %10 %3+2\n",
+        "%12:0 = output \"y\" %11\n",
+    ))
+    .unwrap();
+    retime(&mut design);
+    let flip_flops = flip_flops(&design);
+    assert_eq!(flip_flops.len(), 1);
+    assert_eq!(flip_flops[0].init_value, Const::lit("0"));
+    let (driver, _) = design.find_cell(flip_flops[0].data[0]).unwrap();
+    assert!(matches!(&*driver.get(), Cell::And(..)));
+}
+
+#[test]
+fn test_retime_backward() {
+    // the register is driven by 4 levels of logic, and drives an output
+    let mut design = Design::from_str(concat!(
+        "%0:1 = input \"clk\"\n",
+        "%1:1 = input \"rst\"\n",
+        "%2:5 = input \"a\"\n",
+        "%7:1 = and %2+0 %2+1\n",
+        "%8:1 = and %7 %2+2\n",
+        "%9:1 = and %8 %2+3\n",
+        "%10:1 = and %9 %2+4\n",
+        "%11:1 = dff %10 clk=%0 rst=%1,1 init=0\n",
+        "%12:0 = output \"y\" %11\n",
+        "%13:0 = name \"q\" %11\n",
+    ))
+    .unwrap();
+    retime(&mut design);
+    // 2 levels of logic before and after the registers
+    let flip_flops = flip_flops(&design);
+    assert_eq!(flip_flops.len(), 3);
+    for flip_flop in &flip_flops {
+        assert_eq!(flip_flop.init_value, Const::lit("0"));
+        assert_eq!(flip_flop.reset_value, Const::lit("1"));
+    }
+    for cell_ref in design.iter_cells() {
+        if let Cell::Name(_, value) = &*cell_ref.get() {
+            let (driver, _) = design.find_cell(value[0]).unwrap();
+            assert!(matches!(&*driver.get(), Cell::And(..)));
+        }
+    }
+}
+
+#[test]
+fn test_retime_refuse() {
+    // the registers have different resets, and cannot be moved forward together
+    let mut design = Design::from_str(concat!(
+        "%0:1 = input \"clk\"\n",
+        "%1:2 = input \"rst\"\n",
+        "%3:2 = input \"a\"\n",
+        "%5:3 = input \"b\"\n",
+        "%8:1 = dff %3+0 clk=%0 rst=%1+0,0\n",
+        "%9:1 = dff %3+1 clk=%0 rst=%1+1,0\n",
+        "%10:1 = and %8 %9\n",
+        "%11:1 = xor %10 %5+0\n",
+        "%12:1 = xor %11 %5+1\n",
+        "%13:1 = xor %12 %5+2\n",
+        "%14:0 = output \"y\" %13\n",
+    ))
+    .unwrap();
+    retime(&mut design);
+    assert_eq!(flip_flops(&design).len(), 2);
+    // the register cannot be moved backward, since the cell never outputs its initial value
+    let mut design = Design::from_str(concat!(
+        "%0:1 = input \"clk\"\n",
+        "%1:3 = input \"a\"\n",
+        "%4:1 = and %1+0 %1+1\n",
+        "%5:1 = and %4 %1+2\n",
+        "%6:1 = and %5 0\n",
+        "%7:1 = dff %6 clk=%0 init=1\n",
+        "%8:0 = output \"y\" %7\n",
+    ))
+    .unwrap();
+    retime(&mut design);
+    let flip_flops = flip_flops(&design);
+    assert_eq!(flip_flops.len(), 1);
+    assert_eq!(flip_flops[0].init_value, Const::lit("1"));
+}
+
+#[test]
+fn test_retime_backward_not_data() {
+    // the logic drives the enable of the register rather than its data
+    let mut design = Design::from_str(concat!(
+        "%0:1 = input \"clk\"\n",
+        "%1:5 = input \"a\"\n",
+        "%6:1 = and %1+1 %1+2\n",
+        "%7:1 = and %6 %1+3\n",
+        "%8:1 = and %7 %1+4\n",
+        "%9:1 = and %8 %1+0\n",
+        "%10:1 = dff %1+0 clk=%0 en=%9\n",
+        "%11:0 = output \"y\" %10\n",
+    ))
+    .unwrap();
+    retime(&mut design);
+    let enable_flip_flops = flip_flops(&design);
+    assert_eq!(enable_flip_flops.len(), 1);
+    let (driver, _) = design.find_cell(enable_flip_flops[0].enable.net()).unwrap();
+    assert!(matches!(&*driver.get(), Cell::And(..)));
+    // the logic drives two bits of the register
+    let mut design = Design::from_str(concat!(
+        "%0:1 = input \"clk\"\n",
+        "%1:5 = input \"a\"\n",
+        "%6:1 = and %1+1 %1+2\n",
+        "%7:1 = and %6 %1+3\n",
+        "%8:1 = and %7 %1+4\n",
+        "%9:1 = and %8 %1+0\n",
+        "%10:2 = dff [%9 %9] clk=%0\n",
+        "%12:0 = output \"y\" %10:2\n",
+    ))
+    .unwrap();
+    retime(&mut design);
+    let flip_flops = flip_flops(&design);
+    assert_eq!(flip_flops.len(), 1);
+    assert_eq!(flip_flops[0].data.len(), 2);
+}
@@ -0,0 +1,116 @@
+use std::str::FromStr;
+
+use prjunnamed_generic::share;
+use prjunnamed_netlist::{Cell, Design};
+
+fn count_cells(design: &Design, predicate: impl Fn(&Cell) -> bool) -> usize {
+    design.iter_cells().filter(|cell_ref| predicate(&cell_ref.get())).count()
+}
+
+#[test]
+fn test_share_mux_adc() {
+    // s ? a + b : c + d
+    let mut design = Design::from_str(concat!(
+        "%0:8 = input \"a\"\n",
+        "%8:8 = input \"b\"\n",
+        "%16:8 = input \"c\"\n",
+        "%24:8 = input \"d\"\n",
+        "%32:1 = input \"s\"\n",
+        "%33:9 = adc %0:8 %8:8 0\n",
+        "%42:9 = adc %16:8 %24:8 0\n",
+        "%51:8 = mux %32 %33:8 %42:8\n",
+        "%59:0 = output \"y\" %51:8\n",
+    ))
+    .unwrap();
+    share(&mut design);
+    assert_eq!(count_cells(&design, |cell| matches!(cell, Cell::Adc(..))), 1);
+    assert_eq!(count_cells(&design, |cell| matches!(cell, Cell::Mux(..))), 2);
+    let output = design
+        .iter_cells()
+        .find_map(|cell_ref| match &*cell_ref.get() {
+            Cell::Output(_, value) => Some(value.clone()),
+            _ => None,
+        })
+        .unwrap();
+    let (driver, _) = design.find_cell(output[0]).unwrap();
+    assert!(matches!(&*driver.get(), Cell::Adc(..)));
+}
+
+#[test]
+fn test_share_mux_common_operand() {
+    // s ? a * b : c * a; the multiplication is commutative, so only `b` and `c` need a multiplexer
+    let mut design = Design::from_str(concat!(
+        "%0:8 = input \"a\"\n",
+        "%8:8 = input \"b\"\n",
+        "%16:8 = input \"c\"\n",
+        "%24:1 = input \"s\"\n",
+        "%25:8 = mul %0:8 %8:8\n",
+        "%33:8 = mul %16:8 %0:8\n",
+        "%41:8 = mux %24 %25:8 %33:8\n",
+        "%49:0 = output \"y\" %41:8\n",
+    ))
+    .unwrap();
+    share(&mut design);
+    assert_eq!(count_cells(&design, |cell| matches!(cell, Cell::Mul(..))), 1);
+    assert_eq!(count_cells(&design, |cell| matches!(cell, Cell::Mux(..))), 1);
+}
+
+#[test]
+fn test_share_unprofitable() {
+    // s ? a < b : c < d; multiplexing all of the operands is larger than a second comparator
+    let source = concat!(
+        "%0:8 = input \"a\"\n",
+        "%8:8 = input \"b\"\n",
+        "%16:8 = input \"c\"\n",
+        "%24:8 = input \"d\"\n",
+        "%32:1 = input \"s\"\n",
+        "%33:1 = ult %0:8 %8:8\n",
+        "%34:1 = ult %16:8 %24:8\n",
+        "%35:1 = mux %32 %33 %34\n",
+        "%36:0 = output \"y\" %35\n",
+    );
+    let mut design = Design::from_str(source).unwrap();
+    share(&mut design);
+    assert_eq!(count_cells(&design, |cell| matches!(cell, Cell::ULt(..))), 2);
+}
+
+#[test]
+fn test_share_match() {
+    // case (s) 2'b01: y = a * b; 2'b10: y = a * c; default: y = 0; endcase
+    let mut design = Design::from_str(concat!(
+        "%0:8 = input \"a\"\n",
+        "%8:8 = input \"b\"\n",
+        "%16:8 = input \"c\"\n",
+        "%24:2 = input \"s\"\n",
+        "%26:8 = mul %0:8 %8:8\n",
+        "%34:8 = mul %0:8 %16:8\n",
+        "%42:2 = match %24:2 { 01 10 }\n",
+        "%44:8 = assign en=%42+0 00000000 %26:8\n",
+        "%52:8 = assign en=%42+1 %44:8 %34:8\n",
+        "%60:0 = output \"y\" %52:8\n",
+    ))
+    .unwrap();
+    share(&mut design);
+    assert_eq!(count_cells(&design, |cell| matches!(cell, Cell::Mul(..))), 1);
+    assert_eq!(count_cells(&design, |cell| matches!(cell, Cell::Assign(..))), 2);
+}
+
+#[test]
+fn test_share_match_loop() {
+    // the second multiplication uses the result of the first assignment, so sharing them would form a loop
+    let mut design = Design::from_str(concat!(
+        "%0:8 = input \"a\"\n",
+        "%8:8 = input \"b\"\n",
+        "%16:8 = input \"c\"\n",
+        "%24:2 = input \"s\"\n",
+        "%26:8 = mul %0:8 %8:8\n",
+        "%34:8 = mul %44:8 %16:8\n",
+        "%42:2 = match %24:2 { 01 10 }\n",
+        "%44:8 = assign en=%42+0 00000000 %26:8\n",
+        "%52:8 = assign en=%42+1 %44:8 %34:8\n",
+        "%60:0 = output \"y\" %52:8\n",
+    ))
+    .unwrap();
+    share(&mut design);
+    assert_eq!(count_cells(&design, |cell| matches!(cell, Cell::Mul(..))), 2);
+}
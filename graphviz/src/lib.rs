@@ -100,21 +100,25 @@ impl<'a> Node<'a> {
 struct Context<'a> {
     /// Name that will be used to refer to high-fanout cells
     best_name: BTreeMap<CellRef<'a>, String>,
-    fanout: BTreeMap<CellRef<'a>, BTreeSet<CellRef<'a>>>,
     nodes: Vec<Node<'a>>,
 }
 
 impl<'a> Context<'a> {
     fn add_node(&mut self, node: Node<'a>) {
-        for input in &node.inputs {
-            self.fanout.entry(input.from_cell).or_default().insert(node.cell);
-        }
-
         self.nodes.push(node);
     }
 
     fn high_fanout(&self, cell: CellRef<'_>) -> Option<usize> {
-        let fanout = self.fanout.get(&cell).map(BTreeSet::len).unwrap_or(0);
+        // `name` and `debug` cells are not drawn, and do not count towards the fanout
+        let mut users = BTreeSet::new();
+        for net in cell.output().iter() {
+            for (user, _) in cell.design().uses(net) {
+                if !matches!(&*user.get(), Cell::Name(..) | Cell::Debug(..)) {
+                    users.insert(user.debug_index());
+                }
+            }
+        }
+        let fanout = users.len();
         let threshold = if self.best_name.contains_key(&cell) { 5 } else { 10 };
 
         if fanout >= threshold { Some(fanout) } else { None }
@@ -239,7 +243,7 @@ pub fn describe<'a>(writer: &mut impl io::Write, design: &'a Design) -> io::Resu
         }
     }
 
-    let mut ctx = Context { best_name, fanout: BTreeMap::new(), nodes: vec![] };
+    let mut ctx = Context { best_name, nodes: vec![] };
 
     for cell in design.iter_cells_topo() {
         let mut node = match &*cell.get() {
@@ -259,6 +263,9 @@ pub fn describe<'a>(writer: &mut impl io::Write, design: &'a Design) -> io::Resu
             Cell::UShr(a, b, c) => Node::from_name(cell, "ushr").value(a).value(b).arg(c),
             Cell::SShr(a, b, c) => Node::from_name(cell, "sshr").value(a).value(b).arg(c),
             Cell::XShr(a, b, c) => Node::from_name(cell, "xshr").value(a).value(b).arg(c),
+            Cell::Popcnt(a) => Node::from_name(cell, "popcnt").value(a),
+            Cell::Clz(a) => Node::from_name(cell, "clz").value(a),
+            Cell::Ctz(a) => Node::from_name(cell, "ctz").value(a),
             Cell::Mul(a, b) => Node::from_name(cell, "mul").value(a).value(b),
             Cell::UDiv(a, b) => Node::from_name(cell, "udiv").value(a).value(b),
             Cell::UMod(a, b) => Node::from_name(cell, "umod").value(a).value(b),
@@ -326,7 +333,9 @@ pub fn describe<'a>(writer: &mut impl io::Write, design: &'a Design) -> io::Resu
                     if !port.mask.is_ones() {
                         node = node.prefix_value(". mask=", &port.mask);
                     }
-                    node = node.control(". clk", port.clock, None);
+                    if let Some(clock) = port.clock {
+                        node = node.control(". clk", clock, None);
+                    }
                 }
 
                 for port in &memory.read_ports {
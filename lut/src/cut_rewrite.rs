@@ -0,0 +1,364 @@
+use std::collections::{BTreeSet, HashMap};
+
+use prjunnamed_netlist::{Cell, ControlNet, Design, Net, Trit};
+
+use crate::Lut;
+use crate::npn::{LibraryGraph, LibraryNode, NpnTransform, library_graph, npn_canonical};
+
+/// The number of cuts kept for each node.
+const CUT_LIMIT: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Node {
+    Aig(ControlNet, ControlNet),
+    Xor(Net, Net),
+    Not(Net),
+}
+
+impl Node {
+    fn from_cell(cell: &Cell) -> Option<Node> {
+        match cell {
+            Cell::Aig(arg1, arg2) => Some(Node::Aig(*arg1, *arg2)),
+            Cell::Xor(arg1, arg2) if arg1.len() == 1 => Some(Node::Xor(arg1[0], arg2[0])),
+            Cell::Not(arg) if arg.len() == 1 => Some(Node::Not(arg[0])),
+            _ => None,
+        }
+    }
+
+    fn inputs(self) -> Vec<Net> {
+        match self {
+            Node::Aig(arg1, arg2) => vec![arg1.net(), arg2.net()],
+            Node::Xor(arg1, arg2) => vec![arg1, arg2],
+            Node::Not(arg) => vec![arg],
+        }
+    }
+
+    fn cell(self) -> Cell {
+        match self {
+            Node::Aig(arg1, arg2) => Cell::Aig(arg1, arg2),
+            Node::Xor(arg1, arg2) => Cell::Xor(arg1.into(), arg2.into()),
+            Node::Not(arg) => Cell::Not(arg.into()),
+        }
+    }
+
+    /// Inverters are not counted towards the area or the level of a graph.
+    fn cost(self) -> u32 {
+        match self {
+            Node::Not(_) => 0,
+            _ => 1,
+        }
+    }
+
+    /// Normalizes the node for structural hashing.
+    fn key(self) -> Node {
+        match self {
+            Node::Aig(arg1, arg2) => Node::Aig(arg1.min(arg2), arg1.max(arg2)),
+            Node::Xor(arg1, arg2) => Node::Xor(arg1.min(arg2), arg1.max(arg2)),
+            Node::Not(_) => self,
+        }
+    }
+}
+
+type Cut = Vec<Net>;
+
+fn merge_cuts(cut1: &Cut, cut2: &Cut) -> Option<Cut> {
+    let mut cut = BTreeSet::from_iter(cut1.iter().copied());
+    cut.extend(cut2.iter().copied());
+    if cut.len() > 4 { None } else { Some(Vec::from_iter(cut)) }
+}
+
+/// A net with a polarity, used while instantiating a library graph.
+#[derive(Debug, Clone, Copy)]
+struct Literal {
+    net: Net,
+    invert: bool,
+}
+
+struct Rewriter<'a> {
+    design: &'a Design,
+    nodes: HashMap<Net, Node>,
+    levels: HashMap<Net, u32>,
+    use_count: HashMap<Net, u32>,
+    cuts: HashMap<Net, Vec<Cut>>,
+    strash: HashMap<Node, Net>,
+    replaced: HashMap<Net, Net>,
+    classes: HashMap<u16, (u16, NpnTransform)>,
+}
+
+/// A planned replacement of a node.
+struct Replacement {
+    cut: Cut,
+    canonical: u16,
+    transform: NpnTransform,
+    gain: u32,
+}
+
+impl<'a> Rewriter<'a> {
+    fn new(design: &'a Design) -> Self {
+        let mut use_count: HashMap<Net, u32> = HashMap::new();
+        for cell_ref in design.iter_cells() {
+            if matches!(&*cell_ref.get(), Cell::Debug(..)) {
+                continue;
+            }
+            cell_ref.visit(|net| *use_count.entry(net).or_default() += 1);
+        }
+        Rewriter {
+            design,
+            nodes: HashMap::new(),
+            levels: HashMap::new(),
+            use_count,
+            cuts: HashMap::new(),
+            strash: HashMap::new(),
+            replaced: HashMap::new(),
+            classes: HashMap::new(),
+        }
+    }
+
+    fn resolve(&self, net: Net) -> Net {
+        self.replaced.get(&net).copied().unwrap_or(net)
+    }
+
+    fn level(&self, net: Net) -> u32 {
+        self.levels.get(&net).copied().unwrap_or(0)
+    }
+
+    fn use_count(&self, net: Net) -> u32 {
+        self.use_count.get(&net).copied().unwrap_or(0)
+    }
+
+    fn cuts(&self, net: Net) -> Vec<Cut> {
+        let mut cuts = self.cuts.get(&net).cloned().unwrap_or_default();
+        if !net.is_const() {
+            cuts.push(vec![net]);
+        }
+        cuts
+    }
+
+    /// Adds a node to the network, with the replacements made so far applied to its inputs.
+    fn add_node(&mut self, net: Net, node: Node) {
+        let node = match node {
+            Node::Aig(arg1, arg2) => Node::Aig(
+                ControlNet::from_net_invert(self.resolve(arg1.net()), arg1.is_negative()),
+                ControlNet::from_net_invert(self.resolve(arg2.net()), arg2.is_negative()),
+            ),
+            Node::Xor(arg1, arg2) => Node::Xor(self.resolve(arg1), self.resolve(arg2)),
+            Node::Not(arg) => Node::Not(self.resolve(arg)),
+        };
+        let inputs = node.inputs();
+        let level = inputs.iter().map(|&input| self.level(input)).max().unwrap() + node.cost();
+        let mut cuts: Vec<Cut> = vec![];
+        if let [input] = inputs[..] {
+            cuts = self.cuts(input);
+        } else {
+            for cut1 in self.cuts(inputs[0]) {
+                for cut2 in self.cuts(inputs[1]) {
+                    if let Some(cut) = merge_cuts(&cut1, &cut2)
+                        && !cuts.contains(&cut)
+                    {
+                        cuts.push(cut);
+                    }
+                }
+            }
+        }
+        cuts.retain(|cut| !cut.contains(&net));
+        cuts.sort_by_key(|cut| cut.len());
+        cuts.truncate(CUT_LIMIT);
+        self.nodes.insert(net, node);
+        self.levels.insert(net, level);
+        self.cuts.insert(net, cuts);
+        self.strash.entry(node.key()).or_insert(net);
+    }
+
+    /// Computes the truth table of `net` over the leaves of `cut`.
+    fn truth_table(&self, net: Net, cut: &Cut) -> Option<u16> {
+        let mut lut = Lut::from_cell(self.nodes[&net].cell())?;
+        // expand the inputs that are not leaves until only the leaves remain
+        loop {
+            let Some(index) = lut.inputs().iter().position(|input| !cut.contains(&input)) else { break };
+            let input = lut.inputs()[index];
+            lut = lut.merge(index, &Lut::from_cell(self.nodes.get(&input)?.cell())?);
+        }
+        let leaves = Vec::from_iter((0..4).map(|index| cut.get(index).copied()));
+        let lut = lut.expand_with_fixed(&leaves)?;
+        let mut table = 0;
+        for (index, trit) in lut.table().iter().enumerate() {
+            match trit {
+                Trit::One => table |= 1 << index,
+                Trit::Zero => (),
+                Trit::Undef => return None,
+            }
+        }
+        Some(table)
+    }
+
+    /// Collects the nodes that are only used by the cone of `net` above `cut`, which become unused if `net` is
+    /// replaced. Returns the number of these nodes that count towards the area.
+    fn dereference(&self, net: Net, cut: &Cut, use_count: &mut HashMap<Net, u32>, removed: &mut Vec<Net>) -> u32 {
+        let node = self.nodes[&net];
+        removed.push(net);
+        let mut area = node.cost();
+        for input in node.inputs() {
+            if cut.contains(&input) {
+                continue;
+            }
+            let count = use_count.entry(input).or_insert_with(|| self.use_count(input));
+            *count -= 1;
+            if *count == 0 {
+                area += self.dereference(input, cut, use_count, removed);
+            }
+        }
+        area
+    }
+
+    fn leaf_literal(&self, cut: &Cut, transform: &NpnTransform, input: usize) -> Literal {
+        let net = cut.get(transform.permutation[input]).copied().unwrap_or(Net::ZERO);
+        Literal { net, invert: transform.negate[input] }
+    }
+
+    /// Counts the nodes of `graph` that do not exist in the network yet, and computes the level of its output.
+    fn evaluate_graph(&self, graph: &LibraryGraph, cut: &Cut, transform: &NpnTransform, removed: &[Net]) -> (u32, u32) {
+        // `None` represents a node that would have to be added
+        let mut literals: Vec<(Option<Literal>, u32)> = vec![];
+        for input in 0..4 {
+            let literal = self.leaf_literal(cut, transform, input);
+            literals.push((Some(literal), self.level(literal.net)));
+        }
+        let mut added = 0;
+        for node in graph.nodes {
+            let (arg1, arg2, is_xor) = match *node {
+                LibraryNode::And(arg1, arg2) => (arg1, arg2, false),
+                LibraryNode::Xor(arg1, arg2) => (arg1, arg2, true),
+            };
+            let operand = |literal: u8| {
+                let (value, level) = literals[literal as usize / 2];
+                (value.map(|value| Literal { invert: value.invert ^ (literal & 1 != 0), ..value }), level)
+            };
+            let ((value1, level1), (value2, level2)) = (operand(arg1), operand(arg2));
+            let existing = match (value1, value2) {
+                (Some(value1), Some(value2)) => {
+                    let (key, invert) = if is_xor {
+                        (Node::Xor(value1.net, value2.net).key(), value1.invert ^ value2.invert)
+                    } else {
+                        let control = |value: Literal| ControlNet::from_net_invert(value.net, value.invert);
+                        (Node::Aig(control(value1), control(value2)).key(), false)
+                    };
+                    self.strash.get(&key).filter(|net| !removed.contains(net)).map(|&net| Literal { net, invert })
+                }
+                _ => None,
+            };
+            if existing.is_none() {
+                added += 1;
+            }
+            let level = match existing {
+                Some(literal) => self.level(literal.net),
+                None => level1.max(level2) + 1,
+            };
+            literals.push((existing, level));
+        }
+        (added, literals[graph.output as usize / 2].1)
+    }
+
+    fn instantiate(&mut self, graph: &LibraryGraph, cut: &Cut, transform: &NpnTransform) -> Net {
+        let mut literals = Vec::from_iter((0..4).map(|input| self.leaf_literal(cut, transform, input)));
+        for node in graph.nodes {
+            let literal = |literals: &[Literal], literal: u8| {
+                let value = literals[literal as usize / 2];
+                Literal { invert: value.invert ^ (literal & 1 != 0), ..value }
+            };
+            let (node, invert) = match *node {
+                LibraryNode::And(arg1, arg2) => {
+                    let control = |value: Literal| ControlNet::from_net_invert(value.net, value.invert);
+                    (Node::Aig(control(literal(&literals, arg1)), control(literal(&literals, arg2))), false)
+                }
+                LibraryNode::Xor(arg1, arg2) => {
+                    let (value1, value2) = (literal(&literals, arg1), literal(&literals, arg2));
+                    (Node::Xor(value1.net, value2.net), value1.invert ^ value2.invert)
+                }
+            };
+            let net = match self.strash.get(&node.key()) {
+                Some(&net) => net,
+                None => {
+                    let net = self.design.add_cell(node.cell()).unwrap_net();
+                    self.add_node(net, node);
+                    net
+                }
+            };
+            for input in node.inputs() {
+                *self.use_count.entry(input).or_default() += 1;
+            }
+            literals.push(Literal { net, invert });
+        }
+        let output = literals[graph.output as usize / 2];
+        if output.invert ^ (graph.output & 1 != 0) ^ transform.negate_output {
+            let net = self.design.add_not1(output.net);
+            self.add_node(net, Node::Not(output.net));
+            *self.use_count.entry(output.net).or_default() += 1;
+            net
+        } else {
+            output.net
+        }
+    }
+
+    fn find_replacement(&mut self, net: Net) -> Option<Replacement> {
+        let mut best: Option<Replacement> = None;
+        for cut in self.cuts[&net].clone() {
+            let Some(table) = self.truth_table(net, &cut) else { continue };
+            let (canonical, transform) = *self.classes.entry(table).or_insert_with(|| npn_canonical(table));
+            let mut removed = vec![];
+            let area = self.dereference(net, &cut, &mut HashMap::new(), &mut removed);
+            let gain = match library_graph(canonical) {
+                None => area,
+                Some(graph) => {
+                    let (added, level) = self.evaluate_graph(&graph, &cut, &transform, &removed);
+                    if level > self.level(net) || added >= area {
+                        continue;
+                    }
+                    area - added
+                }
+            };
+            if best.as_ref().is_none_or(|best| gain > best.gain) {
+                best = Some(Replacement { cut, canonical, transform, gain });
+            }
+        }
+        best
+    }
+
+    fn replace(&mut self, net: Net, replacement: Replacement) {
+        let mut removed = vec![];
+        let mut use_count = HashMap::new();
+        self.dereference(net, &replacement.cut, &mut use_count, &mut removed);
+        self.use_count.extend(use_count);
+        for removed_net in &removed {
+            let node = self.nodes.remove(removed_net).unwrap();
+            if self.strash.get(&node.key()) == Some(removed_net) {
+                self.strash.remove(&node.key());
+            }
+        }
+        let new_net = match library_graph(replacement.canonical) {
+            None => Net::from(replacement.transform.negate_output),
+            Some(graph) => self.instantiate(&graph, &replacement.cut, &replacement.transform),
+        };
+        *self.use_count.entry(new_net).or_default() += self.use_count(net);
+        self.replaced.insert(net, new_net);
+        self.design.replace_net(net, new_net);
+    }
+}
+
+/// Rewrites the graphs of `aig` and single-bit `xor` cells, replacing the logic cones of 4-input cuts with smaller
+/// graphs from a precomputed library of the NPN classes of 4-input functions. A cone is only replaced if that
+/// reduces the number of `aig` and `xor` cells without increasing the logic level of its output.
+pub fn cut_rewrite(design: &mut Design) {
+    let mut rewriter = Rewriter::new(design);
+    for cell_ref in design.iter_cells_topo() {
+        let Some(node) = Node::from_cell(&cell_ref.get()) else { continue };
+        let net = cell_ref.output()[0];
+        rewriter.add_node(net, node);
+        if node.cost() == 0 || rewriter.use_count(net) == 0 {
+            continue;
+        }
+        if let Some(replacement) = rewriter.find_replacement(net) {
+            rewriter.replace(net, replacement);
+        }
+    }
+    design.compact();
+}
@@ -0,0 +1,454 @@
+//! Don't-care optimization of mapped LUTs.
+//!
+//! The truth table of each LUT is recomputed within a window of the mapped logic: the inputs of the LUT, the other
+//! inputs of the LUTs it drives, and the LUTs computing those from a few more nets. Every combination of values of
+//! these nets is simulated. A row of the truth table is a don't-care if it is never selected (a satisfiability
+//! don't-care, such as two enables of a `match` being active at once), or if flipping the output of the LUT never
+//! changes the outputs of the LUTs it drives (an observability don't-care, such as a `mux` input while it is not
+//! selected). The rows are then set to `X`, which lets [`Lut::new`] remove the inputs that no longer matter.
+//!
+//! Registers that provably hold a one-hot value (such as the state registers of encoded FSMs) further restrict the
+//! combinations that are simulated. A register is one-hot if its initial, clear, and reset values are one-hot, and
+//! its data input is one-hot whenever its output is.
+//!
+//! The values of the nets in a window are assumed to be defined, which is sound because an `X` may be refined to
+//! any defined value. The `X` rows of the original truth tables are not treated as don't-cares, since the windows
+//! of the LUTs processed earlier are computed with those rows set to zero.
+
+use std::collections::{HashMap, HashSet};
+
+use prjunnamed_netlist::{Cell, Const, Design, Net, Trit, Value};
+
+use crate::{Lut, LutMapping, lower::is_lut_mappable};
+
+/// The maximum number of nets whose values are enumerated within a window.
+const MAX_LEAVES: usize = 10;
+
+/// The maximum number of LUTs driven by a LUT for its observability to be considered.
+const MAX_FANOUT: usize = 8;
+
+/// The maximum number of nets visited while checking that a net does not depend on another one.
+const MAX_SEARCH: usize = 256;
+
+#[derive(Debug, Clone, Copy)]
+enum Operand {
+    Const(bool),
+    Slot(usize),
+}
+
+/// A LUT evaluated by a [`Program`].
+#[derive(Debug, Clone)]
+struct Step {
+    node: usize,
+    inputs: Vec<Operand>,
+    output: usize,
+}
+
+/// Evaluates LUTs on the values of the leaves of a window, which occupy the first slots.
+#[derive(Debug, Clone)]
+struct Program {
+    slots: HashMap<Net, usize>,
+    steps: Vec<Step>,
+}
+
+impl Program {
+    fn new(leaves: &[Net]) -> Program {
+        let slots = HashMap::from_iter(leaves.iter().enumerate().map(|(index, &net)| (net, index)));
+        Program { slots, steps: vec![] }
+    }
+
+    fn slot_count(&self) -> usize {
+        self.slots.len()
+    }
+
+    fn operand(&self, net: Net) -> Operand {
+        if net == Net::ZERO {
+            Operand::Const(false)
+        } else if net == Net::ONE {
+            Operand::Const(true)
+        } else {
+            Operand::Slot(self.slots[&net])
+        }
+    }
+
+    fn add(&mut self, network: &Network, node: usize) -> usize {
+        let inputs = Vec::from_iter(network.inputs[node].iter().map(|&net| self.operand(net)));
+        let output = self.slots.len();
+        self.slots.insert(network.outputs[node], output);
+        self.steps.push(Step { node, inputs, output });
+        output
+    }
+
+    fn index(step: &Step, values: &[bool]) -> usize {
+        let mut index = 0;
+        for (bit, operand) in step.inputs.iter().enumerate() {
+            let value = match *operand {
+                Operand::Const(value) => value,
+                Operand::Slot(slot) => values[slot],
+            };
+            index |= (value as usize) << bit;
+        }
+        index
+    }
+
+    fn run(&self, network: &Network, steps: std::ops::Range<usize>, values: &mut [bool]) {
+        for step in &self.steps[steps] {
+            values[step.output] = network.tables[step.node][Self::index(step, values)];
+        }
+    }
+}
+
+/// The mapped logic, with the truth tables of the LUTs as vectors of booleans.
+struct Network<'a> {
+    design: &'a Design,
+    outputs: Vec<Net>,
+    inputs: Vec<Vec<Net>>,
+    tables: Vec<Vec<bool>>,
+    index: HashMap<Net, usize>,
+    fanouts: HashMap<Net, Vec<usize>>,
+    /// Nets used by cells that are not replaced by the LUTs.
+    root_uses: HashSet<Net>,
+    /// Maps each bit of a one-hot register to the first bit of the register and its width.
+    one_hot: HashMap<Net, (Net, usize)>,
+}
+
+impl<'a> Network<'a> {
+    fn new(design: &'a Design, mapping: &LutMapping) -> Self {
+        let mut network = Network {
+            design,
+            outputs: vec![],
+            inputs: vec![],
+            tables: vec![],
+            index: HashMap::new(),
+            fanouts: HashMap::new(),
+            root_uses: HashSet::new(),
+            one_hot: HashMap::new(),
+        };
+        for cover in &mapping.covers {
+            let node = network.outputs.len();
+            network.index.insert(cover.output, node);
+            network.outputs.push(cover.output);
+            network.inputs.push(Vec::from_iter(cover.lut.inputs().iter()));
+            network.tables.push(Vec::from_iter(cover.lut.table().iter().map(|trit| trit == Trit::One)));
+            for net in cover.lut.inputs() {
+                network.fanouts.entry(net).or_default().push(node);
+            }
+        }
+        for cell_ref in design.iter_cells() {
+            if matches!(&*cell_ref.get(), Cell::Debug(..)) || is_lut_mappable(&cell_ref.get()) {
+                continue;
+            }
+            cell_ref.visit(|net| {
+                network.root_uses.insert(net);
+            });
+        }
+        network
+    }
+
+    /// Returns `true` if `net` may depend combinationally on the output of `node`.
+    fn may_depend_on(&self, net: Net, node: usize) -> bool {
+        let target = self.outputs[node];
+        let mut queue = vec![net];
+        let mut visited = HashSet::new();
+        while let Some(net) = queue.pop() {
+            if net == target {
+                return true;
+            }
+            if net.is_const() || !visited.insert(net) {
+                continue;
+            }
+            if visited.len() > MAX_SEARCH {
+                return true;
+            }
+            if let Some(&index) = self.index.get(&net) {
+                // the covers are in topological order
+                if index > node {
+                    queue.extend(&self.inputs[index]);
+                }
+            } else if let Ok((cell_ref, _)) = self.design.find_cell(net)
+                && !matches!(&*cell_ref.get(), Cell::Dff(..))
+            {
+                cell_ref.visit(|net| queue.push(net));
+            }
+        }
+        false
+    }
+
+    /// Replaces leaves computed by LUTs with the inputs of those LUTs, as long as there are at most `limit` leaves.
+    /// Returns the replaced LUTs in topological order.
+    fn expand(&self, leaves: &mut Vec<Net>, limit: usize, exclude: &[usize]) -> Vec<usize> {
+        let mut nodes = vec![];
+        loop {
+            let mut candidates = Vec::from_iter(
+                leaves.iter().filter_map(|net| self.index.get(net)).filter(|node| !exclude.contains(node)),
+            );
+            candidates.sort_by(|a, b| b.cmp(a));
+            let mut expanded = false;
+            for &node in candidates {
+                let mut new_leaves = Vec::from_iter(leaves.iter().copied().filter(|&net| net != self.outputs[node]));
+                for &net in &self.inputs[node] {
+                    if !(net == Net::ZERO || net == Net::ONE || new_leaves.contains(&net)) {
+                        new_leaves.push(net);
+                    }
+                }
+                if new_leaves.len() <= limit {
+                    *leaves = new_leaves;
+                    nodes.push(node);
+                    expanded = true;
+                    break;
+                }
+            }
+            if !expanded {
+                break;
+            }
+        }
+        nodes.sort();
+        nodes
+    }
+
+    /// Returns a function that checks whether an assignment of values to `leaves` (with the value of leaf `index`
+    /// in bit `index`) is possible given the one-hot registers.
+    fn constraints(&self, leaves: &[Net]) -> impl Fn(usize) -> bool + use<> {
+        let mut groups: HashMap<Net, (usize, usize, usize)> = HashMap::new();
+        for (index, net) in leaves.iter().enumerate() {
+            if let Some(&(register, width)) = self.one_hot.get(net) {
+                let group = groups.entry(register).or_insert((0, 0, width));
+                group.0 |= 1 << index;
+                group.1 += 1;
+            }
+        }
+        let groups = Vec::from_iter(groups.into_values());
+        move |assignment| {
+            groups.iter().all(|&(mask, count, width)| match (assignment & mask).count_ones() {
+                0 => count < width,
+                1 => true,
+                _ => false,
+            })
+        }
+    }
+
+    /// Proves that the register with `output` and `data` always holds a one-hot value, assuming that the other
+    /// candidate registers do.
+    fn is_one_hot(&self, output: &Value, data: &Value) -> bool {
+        let mut leaves = vec![];
+        for net in data {
+            if !(net == Net::ZERO || net == Net::ONE || output.iter().any(|bit| bit == net) || leaves.contains(&net)) {
+                leaves.push(net);
+            }
+        }
+        let nodes = self.expand(&mut leaves, MAX_LEAVES, &[]);
+        leaves.retain(|&net| !output.iter().any(|bit| bit == net));
+        if leaves.len() > MAX_LEAVES {
+            return false;
+        }
+        let leaves = Vec::from_iter(output.iter().chain(leaves));
+        let possible = self.constraints(&leaves[output.len()..]);
+        let mut program = Program::new(&leaves);
+        for &node in &nodes {
+            program.add(self, node);
+        }
+        if data.iter().any(|net| net != Net::ZERO && net != Net::ONE && !program.slots.contains_key(&net)) {
+            return false;
+        }
+        let data = Vec::from_iter(data.iter().map(|net| program.operand(net)));
+        let mut values = vec![false; program.slot_count()];
+        for state in 0..output.len() {
+            for assignment in 0..1 << (leaves.len() - output.len()) {
+                if !possible(assignment) {
+                    continue;
+                }
+                for (index, value) in values[..leaves.len()].iter_mut().enumerate() {
+                    *value = if index < output.len() {
+                        index == state
+                    } else {
+                        (assignment >> (index - output.len())) & 1 != 0
+                    };
+                }
+                program.run(self, 0..nodes.len(), &mut values);
+                let count = data
+                    .iter()
+                    .filter(|operand| match **operand {
+                        Operand::Const(value) => value,
+                        Operand::Slot(slot) => values[slot],
+                    })
+                    .count();
+                if count != 1 {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    /// Finds the registers that always hold a one-hot value.
+    fn find_one_hot(&mut self) {
+        let is_one_hot_const = |value: &Const| {
+            value.iter().all(|trit| trit != Trit::Undef) && value.iter().filter(|&trit| trit == Trit::One).count() == 1
+        };
+        let mut candidates = vec![];
+        for cell_ref in self.design.iter_cells() {
+            let Cell::Dff(flip_flop) = &*cell_ref.get() else { continue };
+            if flip_flop.output_len() < 2
+                || !is_one_hot_const(&flip_flop.init_value)
+                || (flip_flop.has_clear() && !is_one_hot_const(&flip_flop.clear_value))
+                || (flip_flop.has_reset() && !is_one_hot_const(&flip_flop.reset_value))
+            {
+                continue;
+            }
+            candidates.push((cell_ref.output(), flip_flop.data.clone()));
+        }
+        // each register is assumed to be one-hot, and the assumption is withdrawn until the proof succeeds for all
+        // of the remaining ones
+        let mut changed = true;
+        for (output, _) in &candidates {
+            for net in output {
+                self.one_hot.insert(net, (output[0], output.len()));
+            }
+        }
+        while changed {
+            changed = false;
+            candidates.retain(|(output, data)| {
+                if self.is_one_hot(output, data) {
+                    true
+                } else {
+                    for net in output {
+                        self.one_hot.remove(&net);
+                    }
+                    changed = true;
+                    false
+                }
+            });
+        }
+    }
+
+    /// Recomputes the truth table of `node` with its don't-cares removed. Returns `true` if any inputs are removed.
+    fn optimize(&mut self, node: usize) -> bool {
+        let output = self.outputs[node];
+        let inputs = self.inputs[node].clone();
+        let mut fanout = self.fanouts.get(&output).cloned().unwrap_or_default();
+        fanout.sort();
+        fanout.dedup();
+        let observable = self.root_uses.contains(&output)
+            || fanout.len() > MAX_FANOUT
+            || fanout
+                .iter()
+                .any(|&other| self.inputs[other].iter().any(|&net| net != output && self.may_depend_on(net, node)));
+        let collect = |nodes: &[usize]| {
+            let mut leaves = vec![];
+            for &net in inputs.iter().chain(nodes.iter().flat_map(|&other| &self.inputs[other])) {
+                if !(net == output || net == Net::ZERO || net == Net::ONE || leaves.contains(&net)) {
+                    leaves.push(net);
+                }
+            }
+            leaves
+        };
+        let mut leaves = collect(if observable { &[] } else { &fanout });
+        if leaves.len() > MAX_LEAVES {
+            fanout.clear();
+            leaves = collect(&[]);
+            if leaves.len() > MAX_LEAVES {
+                return false;
+            }
+        } else if observable {
+            fanout.clear();
+        }
+        let mut exclude = fanout.clone();
+        exclude.push(node);
+        let inner = self.expand(&mut leaves, MAX_LEAVES, &exclude);
+
+        let possible = self.constraints(&leaves);
+        let mut program = Program::new(&leaves);
+        for &other in &inner {
+            program.add(self, other);
+        }
+        let slot = program.add(self, node);
+        for &other in &fanout {
+            program.add(self, other);
+        }
+        let roots = Vec::from_iter(program.steps[inner.len() + 1..].iter().map(|step| step.output));
+
+        let mut care = vec![false; 1 << inputs.len()];
+        let mut values = vec![false; program.slot_count()];
+        for assignment in 0..1 << leaves.len() {
+            if !possible(assignment) {
+                continue;
+            }
+            for (index, value) in values[..leaves.len()].iter_mut().enumerate() {
+                *value = (assignment >> index) & 1 != 0;
+            }
+            program.run(self, 0..inner.len(), &mut values);
+            let row = Program::index(&program.steps[inner.len()], &values);
+            if care[row] {
+                continue;
+            }
+            if roots.is_empty() {
+                care[row] = true;
+                continue;
+            }
+            values[slot] = self.tables[node][row];
+            program.run(self, inner.len() + 1..program.steps.len(), &mut values);
+            let observed = Vec::from_iter(roots.iter().map(|&root| values[root]));
+            values[slot] = !values[slot];
+            program.run(self, inner.len() + 1..program.steps.len(), &mut values);
+            if roots.iter().zip(observed).any(|(&root, value)| values[root] != value) {
+                care[row] = true;
+            }
+        }
+        if care.iter().all(|&care| care) {
+            return false;
+        }
+
+        let table = Const::from_iter(self.tables[node].iter().zip(&care).map(|(&value, &care)| match (value, care) {
+            (_, false) => Trit::Undef,
+            (false, true) => Trit::Zero,
+            (true, true) => Trit::One,
+        }));
+        let lut = Lut::new(Value::from(&inputs[..]), table);
+        if lut.inputs().len() == inputs.len() {
+            return false;
+        }
+        for net in &inputs {
+            if !lut.inputs().iter().any(|input| input == *net)
+                && let Some(users) = self.fanouts.get_mut(net)
+            {
+                users.retain(|&user| user != node);
+            }
+        }
+        self.inputs[node] = Vec::from_iter(lut.inputs().iter());
+        self.tables[node] = Vec::from_iter(lut.table().iter().map(|trit| trit == Trit::One));
+        true
+    }
+}
+
+/// Simplifies the LUTs of `mapping` using the combinations of their inputs that never occur or never affect the
+/// outputs of the mapped logic, and removes the LUTs that are no longer used. Returns the number of LUTs that
+/// lost any inputs.
+pub fn optimize_dont_cares(design: &Design, mapping: &mut LutMapping) -> usize {
+    let mut network = Network::new(design, mapping);
+    network.find_one_hot();
+    let mut optimized = 0;
+    for node in 0..network.outputs.len() {
+        if network.optimize(node) {
+            optimized += 1;
+        }
+    }
+
+    let mut used = vec![false; network.outputs.len()];
+    let mut used_nets = network.root_uses.clone();
+    for node in (0..network.outputs.len()).rev() {
+        if used_nets.contains(&network.outputs[node]) {
+            used[node] = true;
+            used_nets.extend(&network.inputs[node]);
+        }
+    }
+    let covers = std::mem::take(&mut mapping.covers);
+    for (node, mut cover) in covers.into_iter().enumerate() {
+        if !used[node] {
+            continue;
+        }
+        let table = Const::from_iter(network.tables[node].iter().map(|&value| Trit::from(value)));
+        cover.lut = Lut::new_fixed(Value::from(&network.inputs[node][..]), table);
+        mapping.covers.push(cover);
+    }
+    optimized
+}
@@ -5,6 +5,17 @@ use std::{
 
 use prjunnamed_netlist::{Cell, Const, ControlNet, Net, Trit, Value};
 
+mod cut_rewrite;
+mod dont_care;
+mod lower;
+mod mapper;
+mod npn;
+
+pub use cut_rewrite::cut_rewrite;
+pub use dont_care::optimize_dont_cares;
+pub use lower::{lower_luts, CarryStage, LutTarget};
+pub use mapper::{DelayModel, LutCover, LutMapper, LutMapping};
+
 #[derive(Debug, Clone)]
 enum SwizzleInput {
     Zero,
@@ -110,8 +121,8 @@ impl Lut {
                 arg2.net(),
                 Const::lit(match (arg1, arg2) {
                     (ControlNet::Neg(_), ControlNet::Neg(_)) => "0001",
-                    (ControlNet::Neg(_), ControlNet::Pos(_)) => "0010",
-                    (ControlNet::Pos(_), ControlNet::Neg(_)) => "0100",
+                    (ControlNet::Neg(_), ControlNet::Pos(_)) => "0100",
+                    (ControlNet::Pos(_), ControlNet::Neg(_)) => "0010",
                     (ControlNet::Pos(_), ControlNet::Pos(_)) => "1000",
                 }),
             ),
@@ -0,0 +1,144 @@
+use std::collections::{BTreeMap, HashMap};
+
+use prjunnamed_netlist::{Cell, Const, Design, MetaItemRef, Net, Value};
+
+use crate::{DelayModel, Lut, LutMapper, optimize_dont_cares};
+
+/// One bit of an `Adc` cell.
+#[derive(Debug, Clone)]
+pub struct CarryStage<'a> {
+    /// The function computing the sum output. Initially this is `arg1 ^ arg2 ^ ci`; if the target absorbs
+    /// a LUT into the stage (see [`LutTarget::absorb_into_carry`]), it is the function of that LUT instead.
+    pub lut: Lut,
+    pub arg1: Net,
+    pub arg2: Net,
+    pub ci: Net,
+    /// The merged metadata of the `Adc` cell and of every LUT absorbed into the stage.
+    pub meta: MetaItemRef<'a>,
+}
+
+/// Describes how a target implements the LUTs and carry chains produced by [`lower_luts`].
+pub trait LutTarget {
+    /// The number of inputs of the target LUTs.
+    fn lut_size(&self) -> usize;
+
+    fn delay_model(&self) -> &dyn DelayModel;
+
+    /// Adds a cell implementing `lut` to the design and returns its output. The LUT has at most
+    /// [`Self::lut_size`] inputs, some of which may be constant.
+    fn materialize_lut(&self, design: &Design, lut: &Lut, meta: MetaItemRef) -> Net;
+
+    /// Attempts to implement a LUT computing `lut` within a carry chain stage whose sum output is one of its inputs.
+    /// `lut` is the merged function of the LUT and the stage.
+    ///
+    /// Returns the new function of the stage, or `None` if the stage cannot implement `lut`. The default
+    /// implementation never absorbs LUTs.
+    fn absorb_into_carry(&self, stage: &CarryStage, lut: &Lut) -> Option<Lut> {
+        let _ = (stage, lut);
+        None
+    }
+
+    /// Adds cells implementing a carry chain stage to the design and returns the sum and carry outputs.
+    ///
+    /// The default implementation uses two LUTs, which is appropriate for targets without a dedicated carry chain.
+    fn materialize_carry(&self, design: &Design, stage: &CarryStage) -> (Net, Net) {
+        let sum = self.materialize_lut(design, &stage.lut, stage.meta);
+        let carry = Lut::lut3(stage.arg1, stage.arg2, stage.ci, Const::lit("11101000"));
+        let co = self.materialize_lut(design, &carry, stage.meta);
+        (sum, co)
+    }
+}
+
+pub(crate) fn is_lut_mappable(cell: &Cell) -> bool {
+    matches!(
+        cell,
+        Cell::Buf(..) | Cell::Not(..) | Cell::And(..) | Cell::Or(..) | Cell::Xor(..) | Cell::Mux(..) | Cell::Aig(..)
+    )
+}
+
+struct Stage<'a> {
+    stage: CarryStage<'a>,
+    output: Net,
+    co: Net,
+}
+
+/// Replaces the bitwise logic cells (`Buf`, `Not`, `And`, `Or`, `Xor`, `Mux`, `Aig`) and the `Adc` cells
+/// of a design with the LUTs and carry chains of `target`.
+pub fn lower_luts(design: &mut Design, target: &dyn LutTarget) {
+    let mut mapping = LutMapper::new(target.lut_size(), target.delay_model()).map(design);
+    optimize_dont_cares(design, &mut mapping);
+
+    // the logic cells are replaced by the covers, so their uses are not counted
+    let mut cover_use_count: HashMap<Net, usize> = HashMap::new();
+    for cover in &mapping.covers {
+        for net in cover.lut.inputs() {
+            *cover_use_count.entry(net).or_default() += 1;
+        }
+    }
+    let use_count = |net: Net| {
+        let cell_use_count = design
+            .uses(net)
+            .iter()
+            .filter(|(cell_ref, _)| !matches!(&*cell_ref.get(), Cell::Debug(..)) && !is_lut_mappable(&cell_ref.get()))
+            .count();
+        cell_use_count + cover_use_count.get(&net).copied().unwrap_or(0)
+    };
+
+    let mut stages: BTreeMap<Net, Stage> = BTreeMap::new();
+    let mut lowered_cells = vec![];
+    for cell_ref in design.iter_cells() {
+        if is_lut_mappable(&cell_ref.get()) {
+            lowered_cells.push(cell_ref);
+        }
+        let Cell::Adc(arg1, arg2, ci) = &*cell_ref.get() else { continue };
+        lowered_cells.push(cell_ref);
+        let output = cell_ref.output();
+        if arg1.is_empty() {
+            design.replace_value(output, *ci);
+            continue;
+        }
+        let mut carry = Value::from(ci);
+        carry.extend(design.add_void(output.len() - 2));
+        carry.extend([output.msb()]);
+        for index in 0..arg1.len() {
+            let stage = CarryStage {
+                lut: Lut::lut3(arg1[index], arg2[index], carry[index], Const::lit("10010110")),
+                arg1: arg1[index],
+                arg2: arg2[index],
+                ci: carry[index],
+                meta: cell_ref.metadata(),
+            };
+            stages.insert(output[index], Stage { stage, output: output[index], co: carry[index + 1] });
+        }
+    }
+
+    'covers: for cover in mapping.covers {
+        // if the only user of a sum output is a LUT, the target may be able to compute that LUT within the stage.
+        for (input_index, net) in cover.lut.inputs().iter().enumerate() {
+            let Some(stage) = stages.get_mut(&net).filter(|stage| stage.output == net) else { continue };
+            if use_count(net) != 1 {
+                continue;
+            }
+            let merged_lut = cover.lut.merge(input_index, &stage.stage.lut);
+            if let Some(merged_lut) = target.absorb_into_carry(&stage.stage, &merged_lut) {
+                stage.stage.lut = merged_lut;
+                stage.stage.meta = stage.stage.meta.merge(cover.meta);
+                stage.output = cover.output;
+                continue 'covers;
+            }
+        }
+        let output = target.materialize_lut(design, &cover.lut, cover.meta);
+        design.replace_value(cover.output, output);
+    }
+
+    for stage in stages.into_values() {
+        let (sum, co) = target.materialize_carry(design, &stage.stage);
+        design.replace_value(stage.output, sum);
+        design.replace_value(stage.co, co);
+    }
+
+    for cell_ref in lowered_cells {
+        cell_ref.unalive();
+    }
+    design.compact();
+}
@@ -0,0 +1,539 @@
+// based on https://people.eecs.berkeley.edu/~alanmi/publications/2007/tech07_fast.pdf (priority cuts), with
+// delay-optimal mapping followed by area flow and exact area recovery under the delay-optimal required times.
+
+use std::collections::HashMap;
+
+use prjunnamed_netlist::{Cell, Design, MetaItemRef, Net, Value};
+
+use crate::Lut;
+
+const LUT_AREA: f32 = 1.0;
+
+/// Estimates the delay of the building blocks of a LUT-based FPGA.
+///
+/// The delays are in arbitrary units (picoseconds are a good choice) and only have to be consistent with each other.
+pub trait DelayModel {
+    /// Delay from any input of a LUT with `input_count` used inputs to its output.
+    fn lut_delay(&self, input_count: usize) -> u32;
+
+    /// Delay of one stage of a carry chain, from carry input to carry output.
+    fn carry_delay(&self) -> u32;
+
+    /// Delay of routing a net with `fanout` sinks to one of them.
+    fn route_delay(&self, fanout: usize) -> u32;
+}
+
+/// A LUT computing one net of the mapped logic.
+#[derive(Debug, Clone)]
+pub struct LutCover<'a> {
+    /// The net that the LUT replaces.
+    pub output: Net,
+    /// The function of the LUT. Its inputs are either nets driven by cells that are not a part of the mapped logic,
+    /// or outputs of other covers.
+    pub lut: Lut,
+    /// The merged metadata of every cell absorbed into the LUT.
+    pub meta: MetaItemRef<'a>,
+}
+
+#[derive(Debug, Clone)]
+pub struct LutMapping<'a> {
+    /// The covers, in topological order.
+    pub covers: Vec<LutCover<'a>>,
+    /// The estimated delay of the critical path.
+    pub delay: u32,
+}
+
+/// Maps the bitwise logic cells (`Buf`, `Not`, `And`, `Or`, `Xor`, `Mux`, `Aig`) of a design to LUTs.
+///
+/// The mapper minimizes the delay of the critical path first and the number of LUTs second. `Adc` cells are
+/// not mapped (they are expected to be implemented using a carry chain), but are taken into account when
+/// computing the delay.
+pub struct LutMapper<'m> {
+    lut_size: usize,
+    cut_limit: usize,
+    area_rounds: usize,
+    delay_model: &'m dyn DelayModel,
+}
+
+impl<'m> LutMapper<'m> {
+    pub fn new(lut_size: usize, delay_model: &'m dyn DelayModel) -> Self {
+        assert!(lut_size >= 3, "LUTs with less than 3 inputs cannot implement a mux");
+        LutMapper { lut_size, cut_limit: 8, area_rounds: 2, delay_model }
+    }
+
+    /// Sets the number of cuts kept for every net. Higher values give better results at the cost of runtime.
+    pub fn cut_limit(mut self, cut_limit: usize) -> Self {
+        assert!(cut_limit >= 1);
+        self.cut_limit = cut_limit;
+        self
+    }
+
+    /// Sets the number of exact area recovery rounds performed after the area flow round.
+    pub fn area_rounds(mut self, area_rounds: usize) -> Self {
+        self.area_rounds = area_rounds;
+        self
+    }
+
+    pub fn map<'a>(&self, design: &'a Design) -> LutMapping<'a> {
+        let mut network = Network::new(self, design);
+        network.select(Mode::Delay);
+        network.compute_cover();
+        let delay = network.critical_delay();
+        network.compute_required(delay);
+        network.select(Mode::AreaFlow);
+        network.compute_cover();
+        network.compute_required(delay);
+        for _ in 0..self.area_rounds {
+            network.select(Mode::ExactArea);
+            network.compute_cover();
+            network.compute_required(delay);
+        }
+        network.into_mapping()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Delay,
+    AreaFlow,
+    ExactArea,
+}
+
+#[derive(Debug, Clone)]
+struct Cut {
+    leaves: Vec<Net>,
+    arrival: u32,
+    area_flow: f32,
+}
+
+#[derive(Debug)]
+struct Node<'a> {
+    net: Net,
+    lut: Lut,
+    meta: MetaItemRef<'a>,
+    cuts: Vec<Cut>,
+    best: usize,
+    arrival: u32,
+    delay_arrival: u32,
+    area_flow: f32,
+    required: u32,
+    refs: u32,
+}
+
+#[derive(Debug)]
+enum Item {
+    Node(usize),
+    Carry { arg1: Value, arg2: Value, ci: Net, output: Value },
+}
+
+struct Network<'a, 'm> {
+    mapper: &'m LutMapper<'m>,
+    nodes: Vec<Node<'a>>,
+    node_index: HashMap<Net, usize>,
+    items: Vec<Item>,
+    fanout: HashMap<Net, usize>,
+    // uses of nets by cells other than the mapped logic (including `Adc` cells)
+    root_uses: HashMap<Net, u32>,
+    // nets used by cells other than the mapped logic and `Adc` cells; these are the ends of timing paths
+    endpoints: Vec<Net>,
+    carry_arrival: HashMap<Net, u32>,
+    carry_required: HashMap<Net, u32>,
+}
+
+fn merge_leaves(lhs: &[Net], rhs: &[Net]) -> Vec<Net> {
+    let mut result = Vec::with_capacity(lhs.len() + rhs.len());
+    let (mut lhs, mut rhs) = (lhs.iter().peekable(), rhs.iter().peekable());
+    loop {
+        match (lhs.peek(), rhs.peek()) {
+            (Some(&&l), Some(&&r)) if l == r => {
+                result.push(l);
+                lhs.next();
+                rhs.next();
+            }
+            (Some(&&l), Some(&&r)) if l < r => {
+                result.push(l);
+                lhs.next();
+            }
+            (_, Some(&&r)) => {
+                result.push(r);
+                rhs.next();
+            }
+            (Some(&&l), None) => {
+                result.push(l);
+                lhs.next();
+            }
+            (None, None) => break,
+        }
+    }
+    result
+}
+
+fn is_subset(subset: &[Net], superset: &[Net]) -> bool {
+    subset.iter().all(|net| superset.binary_search(net).is_ok())
+}
+
+impl<'a, 'm> Network<'a, 'm> {
+    fn new(mapper: &'m LutMapper<'m>, design: &'a Design) -> Self {
+        let mut network = Network {
+            mapper,
+            nodes: vec![],
+            node_index: HashMap::new(),
+            items: vec![],
+            fanout: HashMap::new(),
+            root_uses: HashMap::new(),
+            endpoints: vec![],
+            carry_arrival: HashMap::new(),
+            carry_required: HashMap::new(),
+        };
+        for cell_ref in design.iter_cells_topo() {
+            match &*cell_ref.get() {
+                Cell::Buf(..)
+                | Cell::Not(..)
+                | Cell::And(..)
+                | Cell::Or(..)
+                | Cell::Xor(..)
+                | Cell::Mux(..)
+                | Cell::Aig(..) => {
+                    let output = cell_ref.output();
+                    for index in 0..output.len() {
+                        let lut = if matches!(&*cell_ref.get(), Cell::Aig(..)) {
+                            Lut::from_cell(cell_ref.get()).unwrap()
+                        } else {
+                            Lut::from_cell(cell_ref.get().slice(index..index + 1).unwrap()).unwrap()
+                        };
+                        network.node_index.insert(output[index], network.nodes.len());
+                        network.items.push(Item::Node(network.nodes.len()));
+                        network.nodes.push(Node {
+                            net: output[index],
+                            lut,
+                            meta: cell_ref.metadata(),
+                            cuts: vec![],
+                            best: 0,
+                            arrival: 0,
+                            delay_arrival: 0,
+                            area_flow: 0.0,
+                            required: u32::MAX,
+                            refs: 0,
+                        });
+                    }
+                }
+                Cell::Adc(arg1, arg2, ci) => {
+                    network.items.push(Item::Carry {
+                        arg1: arg1.clone(),
+                        arg2: arg2.clone(),
+                        ci: *ci,
+                        output: cell_ref.output(),
+                    });
+                }
+                _ => (),
+            }
+        }
+        let mut endpoints = vec![];
+        for cell_ref in design.iter_cells() {
+            let cell = cell_ref.get();
+            if matches!(&*cell, Cell::Debug(..)) {
+                continue;
+            }
+            let is_mapped = matches!(
+                &*cell,
+                Cell::Buf(..)
+                    | Cell::Not(..)
+                    | Cell::And(..)
+                    | Cell::Or(..)
+                    | Cell::Xor(..)
+                    | Cell::Mux(..)
+                    | Cell::Aig(..)
+            );
+            let is_carry = matches!(&*cell, Cell::Adc(..));
+            cell.visit(|net| {
+                *network.fanout.entry(net).or_default() += 1;
+                if !is_mapped {
+                    *network.root_uses.entry(net).or_default() += 1;
+                    if !is_carry {
+                        endpoints.push(net);
+                    }
+                }
+            });
+        }
+        endpoints.sort();
+        endpoints.dedup();
+        network.endpoints = endpoints;
+        network
+    }
+
+    fn route_delay(&self, net: Net) -> u32 {
+        self.mapper.delay_model.route_delay(self.fanout.get(&net).copied().unwrap_or(1))
+    }
+
+    fn arrival(&self, net: Net) -> u32 {
+        match self.node_index.get(&net) {
+            Some(&index) => self.nodes[index].arrival,
+            None => self.carry_arrival.get(&net).copied().unwrap_or(0),
+        }
+    }
+
+    fn area_flow(&self, net: Net) -> f32 {
+        match self.node_index.get(&net) {
+            Some(&index) => self.nodes[index].area_flow,
+            None => 0.0,
+        }
+    }
+
+    fn evaluate_cut(&self, leaves: Vec<Net>) -> Cut {
+        let mut arrival = 0;
+        let mut area_flow = LUT_AREA;
+        for &leaf in &leaves {
+            arrival = arrival.max(self.arrival(leaf) + self.route_delay(leaf));
+            area_flow += self.area_flow(leaf);
+        }
+        arrival += self.mapper.delay_model.lut_delay(leaves.len());
+        Cut { leaves, arrival, area_flow }
+    }
+
+    fn enumerate_cuts(&self, index: usize) -> Vec<Cut> {
+        let mut cuts: Vec<Vec<Net>> = vec![vec![]];
+        for input in self.nodes[index].lut.inputs() {
+            let mut input_cuts = vec![vec![input]];
+            if let Some(&input_index) = self.node_index.get(&input) {
+                input_cuts.extend(self.nodes[input_index].cuts.iter().map(|cut| cut.leaves.clone()));
+            }
+            let mut merged_cuts = vec![];
+            for cut in &cuts {
+                for input_cut in &input_cuts {
+                    let merged_cut = merge_leaves(cut, input_cut);
+                    if merged_cut.len() <= self.mapper.lut_size && !merged_cuts.contains(&merged_cut) {
+                        merged_cuts.push(merged_cut);
+                    }
+                }
+            }
+            cuts = merged_cuts;
+        }
+        cuts.sort_by_key(|cut| cut.len());
+        let mut dominant_cuts: Vec<Vec<Net>> = vec![];
+        for cut in cuts {
+            if !dominant_cuts.iter().any(|dominant_cut| is_subset(dominant_cut, &cut)) {
+                dominant_cuts.push(cut);
+            }
+        }
+        let mut cuts = Vec::from_iter(dominant_cuts.into_iter().map(|leaves| self.evaluate_cut(leaves)));
+        cuts.sort_by(|a, b| {
+            (a.arrival, a.leaves.len()).cmp(&(b.arrival, b.leaves.len())).then(a.area_flow.total_cmp(&b.area_flow))
+        });
+        cuts.truncate(self.mapper.cut_limit);
+        cuts
+    }
+
+    fn area_ref(&mut self, leaves: &[Net]) -> f32 {
+        let mut area = LUT_AREA;
+        for leaf in leaves {
+            if let Some(&index) = self.node_index.get(leaf) {
+                self.nodes[index].refs += 1;
+                if self.nodes[index].refs == 1 {
+                    let node = &self.nodes[index];
+                    area += self.area_ref(&node.cuts[node.best].leaves.clone());
+                }
+            }
+        }
+        area
+    }
+
+    fn area_deref(&mut self, leaves: &[Net]) -> f32 {
+        let mut area = LUT_AREA;
+        for leaf in leaves {
+            if let Some(&index) = self.node_index.get(leaf) {
+                self.nodes[index].refs -= 1;
+                if self.nodes[index].refs == 0 {
+                    let node = &self.nodes[index];
+                    area += self.area_deref(&node.cuts[node.best].leaves.clone());
+                }
+            }
+        }
+        area
+    }
+
+    fn select_node(&mut self, index: usize, mode: Mode) {
+        if mode == Mode::Delay {
+            self.nodes[index].cuts = self.enumerate_cuts(index);
+        } else {
+            let cuts = std::mem::take(&mut self.nodes[index].cuts);
+            self.nodes[index].cuts = Vec::from_iter(cuts.into_iter().map(|cut| self.evaluate_cut(cut.leaves)));
+        }
+        let node = &self.nodes[index];
+        let limit = if node.required != u32::MAX { node.required } else { node.delay_arrival };
+        let fastest = (0..node.cuts.len()).min_by_key(|&cut_index| node.cuts[cut_index].arrival).unwrap();
+        let feasible = Vec::from_iter((0..node.cuts.len()).filter(|&cut_index| node.cuts[cut_index].arrival <= limit));
+        let best = match mode {
+            Mode::Delay => 0,
+            _ if feasible.is_empty() => fastest,
+            Mode::ExactArea if node.refs > 0 => {
+                let best_leaves = node.cuts[node.best].leaves.clone();
+                self.area_deref(&best_leaves);
+                let mut best = (f32::INFINITY, u32::MAX, fastest);
+                for cut_index in feasible {
+                    let leaves = self.nodes[index].cuts[cut_index].leaves.clone();
+                    let area = self.area_ref(&leaves);
+                    self.area_deref(&leaves);
+                    let arrival = self.nodes[index].cuts[cut_index].arrival;
+                    if (area, arrival) < (best.0, best.1) {
+                        best = (area, arrival, cut_index);
+                    }
+                }
+                let leaves = self.nodes[index].cuts[best.2].leaves.clone();
+                self.area_ref(&leaves);
+                best.2
+            }
+            Mode::AreaFlow | Mode::ExactArea => {
+                let key = |&cut_index: &usize| (node.cuts[cut_index].area_flow, node.cuts[cut_index].arrival);
+                feasible.into_iter().min_by(|a, b| key(a).partial_cmp(&key(b)).unwrap()).unwrap()
+            }
+        };
+        let node = &mut self.nodes[index];
+        node.best = best;
+        node.arrival = node.cuts[best].arrival;
+        if mode == Mode::Delay {
+            node.delay_arrival = node.arrival;
+        }
+        let fanout = self.fanout.get(&node.net).copied().unwrap_or(1).max(1);
+        node.area_flow = node.cuts[best].area_flow / fanout as f32;
+    }
+
+    fn propagate_carry_arrival(&mut self, arg1: &Value, arg2: &Value, ci: Net, output: &Value) {
+        let delay_model = self.mapper.delay_model;
+        let mut carry = self.arrival(ci) + self.route_delay(ci);
+        for index in 0..arg1.len() {
+            let operands = carry
+                .max(self.arrival(arg1[index]) + self.route_delay(arg1[index]))
+                .max(self.arrival(arg2[index]) + self.route_delay(arg2[index]));
+            self.carry_arrival.insert(output[index], operands + delay_model.lut_delay(3));
+            carry = operands + delay_model.carry_delay();
+        }
+        self.carry_arrival.insert(output.msb(), carry);
+    }
+
+    fn select(&mut self, mode: Mode) {
+        for item_index in 0..self.items.len() {
+            match &self.items[item_index] {
+                &Item::Node(index) => self.select_node(index, mode),
+                Item::Carry { arg1, arg2, ci, output } => {
+                    let (arg1, arg2, ci, output) = (arg1.clone(), arg2.clone(), *ci, output.clone());
+                    self.propagate_carry_arrival(&arg1, &arg2, ci, &output);
+                }
+            }
+        }
+    }
+
+    fn compute_cover(&mut self) {
+        for node in &mut self.nodes {
+            node.refs = self.root_uses.get(&node.net).copied().unwrap_or(0);
+        }
+        for item in self.items.iter().rev() {
+            let &Item::Node(index) = item else { continue };
+            if self.nodes[index].refs == 0 {
+                continue;
+            }
+            let node = &self.nodes[index];
+            for leaf in node.cuts[node.best].leaves.clone() {
+                if let Some(&leaf_index) = self.node_index.get(&leaf) {
+                    self.nodes[leaf_index].refs += 1;
+                }
+            }
+        }
+    }
+
+    fn critical_delay(&self) -> u32 {
+        self.endpoints.iter().map(|&net| self.arrival(net)).max().unwrap_or(0)
+    }
+
+    fn require(&mut self, net: Net, required: u32) {
+        match self.node_index.get(&net) {
+            Some(&index) => {
+                let node = &mut self.nodes[index];
+                node.required = node.required.min(required);
+            }
+            None => {
+                let entry = self.carry_required.entry(net).or_insert(u32::MAX);
+                *entry = (*entry).min(required);
+            }
+        }
+    }
+
+    fn required(&self, net: Net) -> u32 {
+        match self.node_index.get(&net) {
+            Some(&index) => self.nodes[index].required,
+            None => self.carry_required.get(&net).copied().unwrap_or(u32::MAX),
+        }
+    }
+
+    fn compute_required(&mut self, delay: u32) {
+        let delay_model = self.mapper.delay_model;
+        for node in &mut self.nodes {
+            node.required = u32::MAX;
+        }
+        self.carry_required.clear();
+        for net in self.endpoints.clone() {
+            self.require(net, delay);
+        }
+        for item_index in (0..self.items.len()).rev() {
+            match &self.items[item_index] {
+                &Item::Node(index) => {
+                    let node = &self.nodes[index];
+                    if node.refs == 0 || node.required == u32::MAX {
+                        continue;
+                    }
+                    let cut = node.cuts[node.best].clone();
+                    let required = node.required.saturating_sub(delay_model.lut_delay(cut.leaves.len()));
+                    for leaf in cut.leaves {
+                        self.require(leaf, required.saturating_sub(self.route_delay(leaf)));
+                    }
+                }
+                Item::Carry { arg1, arg2, ci, output } => {
+                    let (arg1, arg2, ci, output) = (arg1.clone(), arg2.clone(), *ci, output.clone());
+                    let mut carry = self.required(output.msb());
+                    for index in (0..arg1.len()).rev() {
+                        let operands = carry
+                            .saturating_sub(delay_model.carry_delay())
+                            .min(self.required(output[index]).saturating_sub(delay_model.lut_delay(3)));
+                        for net in [arg1[index], arg2[index]] {
+                            self.require(net, operands.saturating_sub(self.route_delay(net)));
+                        }
+                        carry = operands;
+                    }
+                    self.require(ci, carry.saturating_sub(self.route_delay(ci)));
+                }
+            }
+        }
+    }
+
+    fn cone_function(&self, index: usize, leaves: &[Net]) -> (Lut, MetaItemRef<'a>) {
+        let node = &self.nodes[index];
+        let mut lut = node.lut.clone();
+        let mut meta = node.meta;
+        for input in node.lut.inputs() {
+            if leaves.binary_search(&input).is_ok() {
+                continue;
+            }
+            // `Lut::merge` simplifies the merged function, which drops the inputs it no longer depends on and
+            // deduplicates the inputs shared with the merged cone; such an input has nothing left to merge.
+            let Some(input_index) = lut.inputs().iter().position(|net| net == input) else { continue };
+            let (input_lut, input_meta) = self.cone_function(self.node_index[&input], leaves);
+            lut = lut.merge(input_index, &input_lut);
+            meta = meta.merge(input_meta);
+        }
+        (lut, meta)
+    }
+
+    fn into_mapping(self) -> LutMapping<'a> {
+        let mut covers = vec![];
+        for item in &self.items {
+            let &Item::Node(index) = item else { continue };
+            let node = &self.nodes[index];
+            if node.refs == 0 {
+                continue;
+            }
+            let (lut, meta) = self.cone_function(index, &node.cuts[node.best].leaves);
+            assert!(lut.inputs().len() <= self.mapper.lut_size);
+            covers.push(LutCover { output: node.net, lut, meta });
+        }
+        LutMapping { covers, delay: self.critical_delay() }
+    }
+}
@@ -0,0 +1,342 @@
+//! NPN classification of 4-input functions, and a library of small AND/XOR graphs implementing each class.
+//!
+//! Two functions are NPN-equivalent if one can be obtained from the other by negating inputs, permuting inputs,
+//! and negating the output. There are 222 NPN classes of 4-input functions; each class is represented by its
+//! member with the smallest truth table.
+
+use std::sync::OnceLock;
+
+use LibraryNode::{And, Xor};
+
+/// Truth tables of the 4 inputs.
+const INPUTS: [u16; 4] = [0xaaaa, 0xcccc, 0xf0f0, 0xff00];
+
+/// Maps a function onto the representative of its NPN class.
+///
+/// The representative is `canonical(x) = function(y) ^ negate_output`, where `y[permutation[i]] = x[i] ^ negate[i]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NpnTransform {
+    pub permutation: [usize; 4],
+    pub negate: [bool; 4],
+    pub negate_output: bool,
+}
+
+impl NpnTransform {
+    fn apply(&self, function: u16) -> u16 {
+        let mut result = 0;
+        for minterm in 0..16 {
+            let mut index = 0;
+            for input in 0..4 {
+                let value = (minterm >> input) & 1 != 0;
+                if value ^ self.negate[input] {
+                    index |= 1 << self.permutation[input];
+                }
+            }
+            if ((function >> index) & 1 != 0) ^ self.negate_output {
+                result |= 1 << minterm;
+            }
+        }
+        result
+    }
+}
+
+fn permutations() -> &'static [[usize; 4]] {
+    static PERMUTATIONS: OnceLock<Vec<[usize; 4]>> = OnceLock::new();
+    PERMUTATIONS.get_or_init(|| {
+        let mut result = vec![];
+        for index in 0..256 {
+            let permutation = [index & 3, (index >> 2) & 3, (index >> 4) & 3, (index >> 6) & 3];
+            if (0..4).all(|input| permutation.contains(&input)) {
+                result.push(permutation);
+            }
+        }
+        result
+    })
+}
+
+/// Returns the representative of the NPN class of a function, and the transform producing it.
+pub fn npn_canonical(function: u16) -> (u16, NpnTransform) {
+    let mut best = None;
+    for &permutation in permutations() {
+        for negate_mask in 0..16 {
+            let negate = [0, 1, 2, 3].map(|input| negate_mask & (1 << input) != 0);
+            for negate_output in [false, true] {
+                let transform = NpnTransform { permutation, negate, negate_output };
+                let canonical = transform.apply(function);
+                if best.is_none_or(|(best, _)| canonical < best) {
+                    best = Some((canonical, transform));
+                }
+            }
+        }
+    }
+    best.unwrap()
+}
+
+/// A node of a library graph. The operands are literals: `2 * index + negated`, where indices 0 to 3 refer to
+/// the inputs, and index 4 onwards to the preceding nodes. `Xor` operands are never negated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LibraryNode {
+    And(u8, u8),
+    Xor(u8, u8),
+}
+
+/// A graph implementing the representative of an NPN class.
+#[derive(Debug, Clone, Copy)]
+pub struct LibraryGraph {
+    pub nodes: &'static [LibraryNode],
+    /// The literal of the output.
+    pub output: u8,
+}
+
+impl LibraryGraph {
+    fn evaluate(&self) -> u16 {
+        let mut values = Vec::from(INPUTS);
+        let literal = |values: &[u16], literal: u8| {
+            let value = values[literal as usize / 2];
+            if literal & 1 != 0 { !value } else { value }
+        };
+        for node in self.nodes {
+            values.push(match *node {
+                And(arg1, arg2) => literal(&values, arg1) & literal(&values, arg2),
+                Xor(arg1, arg2) => literal(&values, arg1) ^ literal(&values, arg2),
+            });
+        }
+        literal(&values, self.output)
+    }
+}
+
+/// Returns the library graph implementing the representative of an NPN class, or `None` for the constant class.
+pub fn library_graph(canonical: u16) -> Option<LibraryGraph> {
+    let index = LIBRARY.binary_search_by_key(&canonical, |&(function, _, _)| function).ok()?;
+    let (_, output, nodes) = LIBRARY[index];
+    let graph = LibraryGraph { nodes, output };
+    debug_assert_eq!(graph.evaluate(), canonical);
+    Some(graph)
+}
+
+// The smallest graphs for each class found by enumerating AND/XOR formulas with free inverters in the order of
+// increasing size, with identical subformulas shared. Sorted by the truth table of the class representative.
+#[rustfmt::skip]
+static LIBRARY: &[(u16, u8, &[LibraryNode])] = &[
+    (0x0001, 12, &[And(5, 7), And(3, 8), And(1, 10)]),
+    (0x0003, 10, &[And(5, 7), And(3, 8)]),
+    (0x0006, 12, &[Xor(0, 2), And(7, 8), And(5, 10)]),
+    (0x0007, 12, &[And(0, 2), And(7, 9), And(5, 10)]),
+    (0x000f, 8, &[And(5, 7)]),
+    (0x0016, 16, &[And(0, 2), And(4, 9), Xor(2, 10), Xor(0, 12), And(7, 14)]),
+    (0x0017, 16, &[Xor(0, 2), Xor(0, 4), And(8, 10), Xor(0, 12), And(7, 15)]),
+    (0x0018, 14, &[Xor(0, 2), Xor(0, 4), And(9, 10), And(7, 12)]),
+    (0x0019, 14, &[And(0, 4), And(2, 9), Xor(0, 10), And(7, 13)]),
+    (0x001b, 14, &[Xor(2, 4), And(0, 8), Xor(2, 10), And(7, 13)]),
+    (0x001e, 12, &[And(1, 3), Xor(4, 8), And(7, 11)]),
+    (0x001f, 12, &[And(1, 3), And(4, 9), And(7, 11)]),
+    (0x003c, 10, &[Xor(2, 4), And(7, 8)]),
+    (0x003d, 14, &[And(1, 3), And(5, 9), Xor(2, 10), And(7, 13)]),
+    (0x003f, 10, &[And(2, 4), And(7, 9)]),
+    (0x0069, 12, &[Xor(2, 4), Xor(0, 8), And(7, 11)]),
+    (0x006b, 16, &[And(0, 3), And(5, 9), Xor(2, 10), Xor(0, 12), And(7, 14)]),
+    (0x006f, 12, &[Xor(0, 2), And(4, 9), And(7, 11)]),
+    (0x007e, 14, &[Xor(0, 2), Xor(0, 4), And(9, 11), And(7, 13)]),
+    (0x007f, 12, &[And(2, 4), And(0, 8), And(7, 11)]),
+    (0x00ff, 7, &[]),
+    (0x0116, 20, &[And(0, 2), And(1, 3), And(4, 6), And(11, 13), Xor(6, 14), Xor(4, 16), And(9, 18)]),
+    (0x0117, 21, &[And(3, 5), Xor(0, 8), And(2, 4), And(7, 13), Xor(0, 14), And(11, 17), Xor(0, 18)]),
+    (0x0118, 18, &[Xor(0, 2), And(0, 4), And(6, 11), Xor(4, 12), Xor(0, 14), And(9, 16)]),
+    (0x0119, 19, &[And(5, 7), And(0, 9), And(4, 6), And(3, 13), And(11, 15), Xor(0, 16)]),
+    (0x011a, 18, &[And(1, 2), And(0, 4), And(6, 11), Xor(4, 12), And(9, 14), Xor(0, 16)]),
+    (0x011b, 18, &[And(1, 2), Xor(0, 4), Xor(0, 6), And(10, 12), And(9, 15), Xor(0, 16)]),
+    (0x011e, 16, &[And(1, 3), And(4, 6), And(9, 11), Xor(6, 12), Xor(4, 14)]),
+    (0x011f, 17, &[Xor(4, 6), And(1, 3), Xor(4, 10), And(8, 13), Xor(4, 14)]),
+    (0x012c, 16, &[Xor(4, 6), Xor(2, 8), And(0, 3), Xor(4, 12), And(10, 15)]),
+    (0x012d, 16, &[And(0, 3), Xor(4, 8), And(1, 3), And(6, 13), And(11, 15)]),
+    (0x012f, 16, &[And(0, 3), And(4, 9), And(1, 3), And(6, 13), And(11, 15)]),
+    (0x013c, 17, &[Xor(2, 4), And(1, 3), And(6, 11), And(9, 13), Xor(6, 14)]),
+    (0x013d, 17, &[Xor(2, 4), And(1, 3), Xor(6, 10), And(9, 13), Xor(6, 14)]),
+    (0x013e, 16, &[And(2, 4), And(3, 5), And(1, 10), Xor(6, 12), And(9, 15)]),
+    (0x013f, 16, &[And(2, 4), And(3, 5), And(1, 10), And(6, 13), And(9, 15)]),
+    (0x0168, 16, &[Xor(2, 4), Xor(0, 8), And(1, 3), Xor(6, 12), And(11, 15)]),
+    (0x0169, 16, &[Xor(2, 4), Xor(0, 8), And(1, 3), And(6, 13), And(11, 15)]),
+    (0x016a, 19, &[And(0, 6), Xor(2, 4), Xor(2, 6), And(11, 12), And(9, 15), Xor(0, 16)]),
+    (0x016b, 19, &[And(2, 4), Xor(0, 8), And(3, 5), Xor(6, 12), And(11, 15), Xor(6, 16)]),
+    (0x016e, 16, &[Xor(0, 2), And(4, 9), And(1, 3), Xor(6, 12), And(11, 15)]),
+    (0x016f, 16, &[Xor(0, 2), And(4, 9), And(1, 3), And(6, 13), And(11, 15)]),
+    (0x017e, 19, &[Xor(0, 2), And(0, 6), And(4, 11), Xor(0, 12), And(9, 15), Xor(6, 16)]),
+    (0x017f, 19, &[Xor(0, 6), Xor(0, 2), Xor(0, 4), And(11, 13), And(8, 15), Xor(0, 16)]),
+    (0x0180, 16, &[Xor(0, 2), Xor(0, 4), Xor(0, 6), And(11, 12), And(9, 14)]),
+    (0x0181, 16, &[Xor(0, 2), And(0, 6), And(4, 11), Xor(0, 12), And(9, 15)]),
+    (0x0182, 16, &[And(1, 2), Xor(0, 6), Xor(2, 4), And(10, 13), And(9, 14)]),
+    (0x0183, 16, &[And(1, 2), And(0, 6), Xor(2, 4), And(11, 13), And(9, 14)]),
+    (0x0186, 16, &[And(0, 2), Xor(4, 8), And(1, 3), Xor(6, 12), And(11, 15)]),
+    (0x0187, 16, &[And(0, 2), Xor(4, 8), And(1, 3), And(6, 13), And(11, 15)]),
+    (0x0189, 16, &[And(1, 4), And(0, 6), And(2, 11), And(9, 13), Xor(0, 14)]),
+    (0x018b, 17, &[And(0, 6), Xor(0, 4), And(3, 11), And(9, 13), Xor(0, 14)]),
+    (0x018f, 16, &[And(0, 2), And(4, 9), And(1, 3), And(6, 13), And(11, 15)]),
+    (0x0196, 19, &[Xor(2, 4), Xor(0, 8), And(1, 3), And(6, 13), And(11, 15), Xor(6, 16)]),
+    (0x0197, 19, &[Xor(2, 4), Xor(0, 8), And(1, 3), Xor(6, 12), And(11, 15), Xor(6, 16)]),
+    (0x0198, 14, &[Xor(0, 2), And(1, 5), Xor(6, 10), And(9, 13)]),
+    (0x0199, 14, &[Xor(0, 2), And(1, 5), And(6, 11), And(9, 13)]),
+    (0x019a, 17, &[And(0, 6), Xor(4, 6), And(3, 10), And(9, 13), Xor(0, 14)]),
+    (0x019b, 19, &[And(3, 4), Xor(0, 8), Xor(2, 6), Xor(0, 12), And(11, 14), Xor(6, 16)]),
+    (0x019e, 18, &[And(3, 5), And(1, 8), Xor(0, 2), And(4, 12), And(7, 15), Xor(10, 16)]),
+    (0x019f, 19, &[And(5, 7), Xor(0, 2), And(1, 5), And(6, 13), And(11, 15), And(9, 17)]),
+    (0x01a8, 14, &[Xor(0, 6), And(3, 5), Xor(0, 10), And(8, 12)]),
+    (0x01a9, 15, &[And(0, 6), And(3, 5), And(9, 11), Xor(0, 12)]),
+    (0x01aa, 14, &[And(3, 5), And(1, 9), And(6, 11), Xor(0, 12)]),
+    (0x01ab, 15, &[And(3, 5), Xor(6, 8), And(1, 11), Xor(6, 12)]),
+    (0x01ac, 19, &[And(0, 6), Xor(2, 6), Xor(0, 10), And(5, 12), And(9, 15), Xor(0, 16)]),
+    (0x01ad, 18, &[And(1, 4), Xor(0, 6), And(3, 5), And(10, 13), And(9, 15), Xor(0, 16)]),
+    (0x01ae, 14, &[And(1, 4), And(1, 3), Xor(6, 10), And(9, 13)]),
+    (0x01af, 14, &[And(1, 4), And(1, 3), And(6, 11), And(9, 13)]),
+    (0x01bc, 18, &[And(3, 5), Xor(6, 8), Xor(2, 4), And(1, 13), Xor(6, 14), And(11, 17)]),
+    (0x01bd, 18, &[And(3, 5), And(6, 9), Xor(0, 2), Xor(0, 4), And(12, 14), And(11, 17)]),
+    (0x01be, 17, &[And(2, 6), And(4, 9), Xor(2, 10), And(1, 13), Xor(6, 14)]),
+    (0x01bf, 17, &[Xor(2, 6), Xor(2, 4), And(1, 11), And(8, 13), Xor(2, 14)]),
+    (0x01e8, 18, &[And(1, 3), Xor(6, 8), And(0, 2), And(5, 13), Xor(6, 14), And(11, 17)]),
+    (0x01e9, 21, &[And(0, 6), And(1, 7), And(2, 11), And(4, 13), Xor(2, 14), And(9, 16), Xor(0, 18)]),
+    (0x01ea, 16, &[Xor(2, 4), And(1, 8), And(1, 3), Xor(6, 12), And(11, 15)]),
+    (0x01eb, 16, &[Xor(2, 4), And(1, 8), And(1, 3), And(6, 13), And(11, 15)]),
+    (0x01ee, 15, &[And(4, 6), And(3, 9), And(1, 10), Xor(6, 12)]),
+    (0x01ef, 15, &[And(1, 3), Xor(4, 6), And(9, 10), Xor(4, 12)]),
+    (0x01fe, 13, &[And(3, 5), And(1, 8), Xor(6, 10)]),
+    (0x033c, 14, &[And(2, 4), And(6, 9), Xor(4, 10), Xor(2, 12)]),
+    (0x033d, 19, &[Xor(4, 6), And(0, 3), And(5, 11), Xor(2, 12), And(9, 15), Xor(2, 16)]),
+    (0x033f, 15, &[Xor(2, 4), Xor(2, 6), And(8, 10), Xor(2, 12)]),
+    (0x0356, 12, &[And(1, 7), And(3, 5), Xor(8, 10)]),
+    (0x0357, 13, &[And(1, 7), And(3, 5), And(9, 11)]),
+    (0x0358, 16, &[And(1, 7), Xor(2, 6), And(5, 11), And(9, 13), Xor(4, 14)]),
+    (0x0359, 14, &[And(1, 7), Xor(2, 6), And(5, 10), Xor(8, 12)]),
+    (0x035a, 14, &[And(1, 7), And(2, 6), And(5, 11), Xor(8, 12)]),
+    (0x035b, 15, &[And(3, 5), Xor(0, 4), And(7, 10), And(9, 13)]),
+    (0x035e, 17, &[And(0, 7), And(2, 5), And(9, 11), Xor(6, 12), Xor(4, 14)]),
+    (0x035f, 14, &[And(0, 4), And(3, 5), And(6, 11), And(9, 13)]),
+    (0x0368, 18, &[Xor(4, 6), And(3, 9), Xor(0, 4), And(7, 12), And(11, 15), Xor(2, 16)]),
+    (0x0369, 17, &[And(0, 7), And(2, 6), And(4, 11), Xor(8, 12), Xor(2, 14)]),
+    (0x036a, 16, &[And(0, 7), Xor(2, 4), Xor(2, 6), And(11, 12), Xor(8, 14)]),
+    (0x036b, 17, &[And(3, 5), And(2, 4), Xor(0, 10), And(7, 12), And(9, 15)]),
+    (0x036c, 17, &[And(4, 6), And(0, 4), Xor(2, 10), And(9, 13), Xor(6, 14)]),
+    (0x036d, 18, &[And(0, 7), Xor(4, 6), And(2, 11), And(9, 13), Xor(4, 14), Xor(2, 16)]),
+    (0x036e, 17, &[Xor(2, 6), And(5, 8), Xor(0, 2), And(7, 12), And(11, 15)]),
+    (0x036f, 17, &[Xor(4, 6), And(0, 4), Xor(2, 10), And(8, 12), Xor(4, 14)]),
+    (0x037c, 16, &[And(0, 7), And(2, 9), And(4, 11), Xor(6, 12), Xor(2, 14)]),
+    (0x037d, 19, &[And(1, 7), And(2, 6), And(4, 11), Xor(2, 12), And(9, 15), Xor(6, 16)]),
+    (0x037e, 17, &[Xor(2, 4), And(0, 7), Xor(2, 10), And(9, 13), Xor(6, 14)]),
+    (0x03c0, 12, &[Xor(2, 4), Xor(2, 6), And(9, 10)]),
+    (0x03c1, 16, &[Xor(2, 4), And(1, 3), And(7, 11), Xor(2, 12), And(9, 15)]),
+    (0x03c3, 13, &[And(2, 6), And(4, 9), Xor(2, 10)]),
+    (0x03c5, 16, &[And(3, 5), And(0, 5), Xor(2, 10), And(7, 12), Xor(8, 14)]),
+    (0x03c6, 17, &[And(0, 5), And(3, 4), And(6, 11), And(9, 13), Xor(2, 14)]),
+    (0x03c7, 17, &[And(2, 6), And(1, 2), And(5, 11), And(9, 13), Xor(2, 14)]),
+    (0x03cf, 13, &[Xor(4, 6), And(2, 8), Xor(4, 10)]),
+    (0x03d4, 16, &[And(3, 5), Xor(2, 4), And(0, 10), And(7, 13), Xor(8, 14)]),
+    (0x03d5, 17, &[And(1, 7), Xor(2, 4), Xor(2, 6), And(11, 12), And(9, 15)]),
+    (0x03d6, 16, &[And(3, 5), And(2, 4), And(0, 11), And(7, 13), Xor(8, 14)]),
+    (0x03d7, 17, &[And(1, 7), And(2, 6), And(4, 11), Xor(2, 12), And(9, 14)]),
+    (0x03d8, 19, &[And(1, 7), And(2, 9), And(0, 7), And(4, 13), And(11, 15), Xor(6, 16)]),
+    (0x03d9, 16, &[And(0, 7), Xor(2, 6), And(4, 10), And(9, 13), Xor(2, 14)]),
+    (0x03db, 19, &[And(1, 7), Xor(2, 8), Xor(4, 6), Xor(2, 12), And(11, 14), Xor(6, 16)]),
+    (0x03dc, 15, &[And(0, 7), And(4, 9), And(3, 11), Xor(6, 12)]),
+    (0x03dd, 17, &[Xor(0, 4), And(6, 9), Xor(0, 10), And(3, 12), Xor(6, 14)]),
+    (0x03de, 15, &[And(0, 7), Xor(4, 8), And(3, 11), Xor(6, 12)]),
+    (0x03fc, 11, &[And(3, 5), Xor(6, 8)]),
+    (0x0660, 12, &[Xor(0, 2), Xor(4, 6), And(8, 10)]),
+    (0x0661, 20, &[Xor(4, 6), And(1, 5), And(3, 11), Xor(0, 12), And(9, 15), Xor(2, 16), Xor(0, 18)]),
+    (0x0662, 16, &[Xor(0, 2), And(0, 5), And(7, 11), Xor(4, 12), And(8, 15)]),
+    (0x0663, 16, &[And(4, 6), And(5, 7), And(1, 11), Xor(2, 12), And(9, 15)]),
+    (0x0666, 12, &[Xor(0, 2), And(4, 6), And(8, 11)]),
+    (0x0667, 18, &[And(4, 6), And(5, 7), And(1, 10), And(3, 13), Xor(0, 14), And(9, 17)]),
+    (0x0669, 16, &[Xor(0, 2), And(4, 6), And(9, 11), Xor(6, 12), Xor(4, 14)]),
+    (0x066b, 21, &[Xor(2, 6), Xor(0, 8), And(1, 5), And(7, 13), Xor(4, 14), And(10, 17), Xor(4, 18)]),
+    (0x066f, 17, &[Xor(4, 6), Xor(2, 4), Xor(0, 10), And(8, 13), Xor(4, 14)]),
+    (0x0672, 18, &[And(0, 2), And(3, 6), Xor(0, 10), And(5, 13), Xor(6, 14), And(9, 17)]),
+    (0x0673, 17, &[And(3, 7), Xor(0, 2), Xor(4, 6), And(10, 12), And(9, 15)]),
+    (0x0676, 16, &[And(4, 6), And(1, 4), And(3, 11), Xor(0, 12), And(9, 15)]),
+    (0x0678, 19, &[And(0, 2), Xor(4, 8), Xor(0, 2), And(6, 13), And(11, 15), Xor(6, 16)]),
+    (0x0679, 18, &[Xor(0, 2), And(0, 7), And(4, 11), And(9, 13), Xor(6, 14), Xor(4, 16)]),
+    (0x067a, 19, &[Xor(4, 6), And(2, 8), Xor(0, 6), And(4, 13), And(11, 15), Xor(0, 16)]),
+    (0x067b, 19, &[Xor(2, 4), Xor(0, 8), And(0, 6), Xor(2, 12), And(10, 14), Xor(6, 16)]),
+    (0x067e, 19, &[Xor(2, 6), Xor(0, 8), And(0, 2), Xor(4, 12), And(11, 15), Xor(6, 16)]),
+    (0x0690, 14, &[Xor(4, 6), Xor(2, 4), Xor(0, 10), And(8, 12)]),
+    (0x0691, 18, &[Xor(2, 6), Xor(0, 8), And(1, 5), And(7, 13), Xor(4, 14), And(11, 17)]),
+    (0x0693, 19, &[And(0, 4), And(2, 4), Xor(0, 10), And(6, 13), Xor(8, 14), Xor(2, 16)]),
+    (0x0696, 15, &[Xor(0, 2), And(4, 6), And(9, 11), Xor(4, 12)]),
+    (0x0697, 19, &[Xor(0, 2), And(0, 5), And(7, 11), Xor(4, 12), And(9, 15), Xor(4, 16)]),
+    (0x069f, 15, &[Xor(0, 2), Xor(4, 6), And(9, 10), Xor(4, 12)]),
+    (0x06b0, 18, &[Xor(4, 6), And(0, 3), And(4, 11), Xor(2, 12), Xor(0, 14), And(8, 16)]),
+    (0x06b1, 18, &[Xor(0, 4), And(2, 8), And(1, 3), And(5, 13), Xor(6, 14), And(11, 17)]),
+    (0x06b2, 18, &[And(4, 7), Xor(0, 2), And(1, 7), Xor(4, 12), And(10, 15), Xor(8, 16)]),
+    (0x06b3, 19, &[And(0, 6), Xor(2, 8), And(1, 7), And(4, 13), And(10, 15), Xor(6, 16)]),
+    (0x06b4, 19, &[And(1, 2), And(0, 3), And(5, 11), And(6, 13), And(9, 15), Xor(4, 16)]),
+    (0x06b5, 18, &[And(0, 4), Xor(4, 6), And(2, 10), And(9, 13), Xor(6, 14), Xor(0, 16)]),
+    (0x06b6, 19, &[And(4, 6), And(0, 4), And(3, 11), Xor(0, 12), And(9, 14), Xor(4, 16)]),
+    (0x06b7, 19, &[And(0, 6), Xor(2, 8), And(1, 7), Xor(4, 12), And(10, 15), Xor(6, 16)]),
+    (0x06b9, 17, &[Xor(0, 2), And(1, 7), And(4, 11), And(8, 13), Xor(6, 14)]),
+    (0x06bd, 17, &[Xor(0, 2), And(1, 7), Xor(4, 10), And(8, 13), Xor(6, 14)]),
+    (0x06f0, 14, &[Xor(0, 2), And(5, 9), And(6, 11), Xor(4, 12)]),
+    (0x06f1, 17, &[And(0, 7), And(2, 9), Xor(0, 10), And(5, 12), Xor(6, 14)]),
+    (0x06f2, 18, &[Xor(0, 2), And(5, 9), And(0, 5), And(7, 13), And(11, 15), Xor(4, 16)]),
+    (0x06f6, 15, &[Xor(2, 6), Xor(0, 8), And(5, 11), Xor(6, 12)]),
+    (0x06f9, 13, &[Xor(0, 2), And(5, 8), Xor(6, 10)]),
+    (0x0776, 18, &[And(0, 2), And(1, 3), And(5, 11), And(7, 13), Xor(4, 14), And(9, 17)]),
+    (0x0778, 16, &[And(4, 6), And(2, 9), And(0, 10), Xor(6, 12), Xor(4, 14)]),
+    (0x0779, 21, &[And(0, 2), Xor(6, 8), Xor(0, 2), And(5, 13), Xor(6, 14), And(11, 17), Xor(4, 18)]),
+    (0x077a, 19, &[Xor(2, 4), And(0, 9), And(0, 2), Xor(6, 12), And(11, 15), Xor(4, 16)]),
+    (0x077e, 18, &[And(4, 6), Xor(0, 2), And(5, 7), Xor(0, 12), And(11, 14), And(9, 17)]),
+    (0x07b0, 14, &[Xor(4, 6), Xor(0, 4), And(2, 10), And(8, 13)]),
+    (0x07b1, 18, &[Xor(0, 6), And(2, 9), And(1, 5), And(7, 13), Xor(4, 14), And(11, 17)]),
+    (0x07b4, 17, &[And(1, 2), And(2, 5), And(6, 11), And(9, 13), Xor(4, 14)]),
+    (0x07b5, 19, &[And(3, 4), And(1, 9), And(2, 5), And(6, 13), And(11, 15), Xor(4, 16)]),
+    (0x07b6, 18, &[And(1, 2), And(7, 9), Xor(2, 6), And(0, 13), And(5, 15), Xor(10, 16)]),
+    (0x07bc, 17, &[And(1, 2), And(2, 5), Xor(6, 10), And(9, 13), Xor(4, 14)]),
+    (0x07e0, 16, &[Xor(4, 6), Xor(0, 2), Xor(0, 4), And(11, 12), And(8, 15)]),
+    (0x07e1, 19, &[And(1, 3), And(2, 5), And(0, 10), And(6, 13), And(9, 15), Xor(4, 16)]),
+    (0x07e2, 19, &[Xor(0, 4), And(3, 8), And(0, 5), And(6, 13), And(11, 15), Xor(4, 16)]),
+    (0x07e3, 19, &[Xor(2, 6), And(0, 9), And(2, 6), Xor(4, 12), And(11, 14), Xor(2, 16)]),
+    (0x07e6, 18, &[And(0, 2), And(5, 9), And(1, 3), Xor(4, 12), And(7, 14), Xor(10, 16)]),
+    (0x07e9, 16, &[And(0, 2), And(5, 9), And(1, 3), And(7, 13), Xor(10, 14)]),
+    (0x07f0, 14, &[And(2, 5), And(0, 8), And(6, 11), Xor(4, 12)]),
+    (0x07f1, 17, &[Xor(0, 2), Xor(0, 6), And(9, 11), And(5, 13), Xor(6, 14)]),
+    (0x07f2, 15, &[Xor(2, 6), And(0, 9), And(5, 11), Xor(6, 12)]),
+    (0x07f8, 13, &[And(0, 2), And(5, 9), Xor(6, 10)]),
+    (0x0ff0, 8, &[Xor(4, 6)]),
+    (0x1668, 20, &[Xor(4, 6), Xor(0, 2), Xor(0, 4), And(11, 13), And(9, 15), Xor(2, 16), Xor(0, 18)]),
+    (0x1669, 19, &[And(2, 4), And(0, 8), And(6, 11), Xor(4, 12), Xor(2, 14), Xor(0, 16)]),
+    (0x166a, 20, &[Xor(4, 6), And(1, 2), And(4, 11), Xor(2, 12), And(9, 14), Xor(2, 16), Xor(0, 18)]),
+    (0x166b, 18, &[Xor(4, 6), Xor(2, 4), And(0, 11), And(9, 13), Xor(2, 14), Xor(0, 16)]),
+    (0x166e, 18, &[Xor(4, 6), And(0, 2), Xor(4, 10), And(9, 12), Xor(2, 14), Xor(0, 16)]),
+    (0x167e, 20, &[And(3, 5), Xor(0, 6), And(2, 4), Xor(0, 12), And(10, 14), And(9, 17), Xor(0, 18)]),
+    (0x1681, 20, &[And(5, 6), Xor(2, 8), Xor(0, 10), And(1, 3), Xor(6, 14), Xor(4, 16), And(13, 18)]),
+    (0x1683, 19, &[And(1, 6), Xor(0, 6), And(2, 11), And(4, 13), Xor(8, 14), Xor(2, 16)]),
+    (0x1686, 17, &[Xor(0, 2), Xor(0, 6), And(4, 11), And(9, 13), Xor(4, 14)]),
+    (0x1687, 19, &[Xor(0, 2), And(0, 5), And(6, 11), Xor(0, 12), And(9, 14), Xor(4, 16)]),
+    (0x1689, 18, &[And(1, 5), Xor(2, 6), Xor(0, 2), And(4, 12), And(10, 15), Xor(8, 16)]),
+    (0x168b, 21, &[Xor(2, 6), And(1, 9), Xor(4, 6), And(2, 12), And(11, 15), Xor(4, 16), Xor(0, 18)]),
+    (0x168e, 20, &[Xor(0, 2), And(7, 9), And(0, 2), And(4, 13), Xor(2, 14), And(11, 16), Xor(0, 18)]),
+    (0x1696, 16, &[And(2, 6), And(0, 8), And(4, 11), Xor(2, 12), Xor(0, 14)]),
+    (0x1697, 19, &[Xor(2, 4), Xor(0, 2), Xor(0, 6), And(11, 13), And(9, 15), Xor(0, 16)]),
+    (0x1698, 21, &[And(0, 2), And(4, 9), And(1, 3), And(7, 13), And(11, 15), Xor(2, 16), Xor(0, 18)]),
+    (0x1699, 17, &[And(0, 2), And(4, 9), And(6, 11), Xor(2, 12), Xor(0, 14)]),
+    (0x169a, 19, &[And(2, 7), And(0, 2), And(4, 11), And(9, 13), Xor(2, 14), Xor(0, 16)]),
+    (0x169b, 21, &[And(0, 2), And(4, 9), And(0, 3), And(7, 13), And(11, 15), Xor(2, 16), Xor(0, 18)]),
+    (0x169e, 16, &[Xor(4, 6), And(0, 9), And(2, 11), Xor(4, 12), Xor(0, 14)]),
+    (0x16a9, 19, &[And(1, 6), And(2, 9), And(4, 11), Xor(6, 12), Xor(2, 14), Xor(0, 16)]),
+    (0x16ac, 20, &[And(3, 4), Xor(4, 6), And(1, 11), And(9, 13), Xor(6, 14), Xor(2, 16), Xor(0, 18)]),
+    (0x16ad, 19, &[And(1, 2), And(2, 5), Xor(6, 10), And(9, 12), Xor(4, 14), Xor(0, 16)]),
+    (0x16bc, 16, &[And(2, 4), Xor(6, 8), And(0, 10), Xor(4, 12), Xor(2, 14)]),
+    (0x16e9, 17, &[And(0, 2), And(4, 9), Xor(6, 10), Xor(2, 12), Xor(0, 14)]),
+    (0x177e, 19, &[And(0, 2), Xor(4, 6), Xor(2, 4), Xor(0, 12), And(11, 15), Xor(8, 16)]),
+    (0x178e, 17, &[Xor(0, 2), Xor(4, 6), Xor(0, 10), And(9, 13), Xor(4, 14)]),
+    (0x1796, 17, &[Xor(2, 4), Xor(0, 2), And(6, 11), And(9, 13), Xor(0, 14)]),
+    (0x1798, 19, &[And(0, 2), Xor(2, 6), Xor(0, 10), And(4, 13), And(9, 15), Xor(6, 16)]),
+    (0x179a, 20, &[And(1, 6), Xor(2, 8), Xor(4, 6), Xor(2, 12), And(11, 14), Xor(6, 16), Xor(0, 18)]),
+    (0x17ac, 18, &[Xor(0, 2), And(1, 6), Xor(4, 10), And(8, 13), Xor(6, 14), Xor(0, 16)]),
+    (0x17e8, 16, &[Xor(0, 2), Xor(0, 4), And(8, 10), Xor(6, 12), Xor(0, 14)]),
+    (0x18e7, 15, &[Xor(0, 2), Xor(0, 4), And(9, 10), Xor(6, 12)]),
+    (0x19e1, 19, &[And(4, 7), And(5, 6), And(0, 11), And(2, 13), Xor(8, 14), Xor(0, 16)]),
+    (0x19e3, 19, &[And(0, 4), And(2, 9), And(1, 5), And(7, 13), Xor(10, 14), Xor(0, 16)]),
+    (0x19e6, 14, &[And(0, 4), And(2, 9), Xor(6, 10), Xor(0, 12)]),
+    (0x1bd8, 17, &[Xor(0, 6), Xor(2, 4), And(9, 11), Xor(2, 12), Xor(0, 14)]),
+    (0x1be4, 14, &[Xor(2, 4), And(0, 8), Xor(6, 10), Xor(2, 12)]),
+    (0x1ee1, 12, &[And(1, 3), Xor(6, 8), Xor(4, 10)]),
+    (0x3cc3, 11, &[Xor(4, 6), Xor(2, 8)]),
+    (0x6996, 12, &[Xor(4, 6), Xor(2, 8), Xor(0, 10)]),
+];
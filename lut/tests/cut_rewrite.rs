@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+
+use prjunnamed_lut::cut_rewrite;
+use prjunnamed_netlist::{Cell, ControlNet, Design, Net, Trit, Value};
+
+fn count_nodes(design: &Design) -> usize {
+    design.iter_cells().filter(|cell_ref| matches!(&*cell_ref.get(), Cell::Aig(..) | Cell::Xor(..))).count()
+}
+
+// evaluates the outputs of a design built from `aig`, `xor`, and `not` cells
+fn evaluate(design: &Design, inputs: &HashMap<String, Vec<bool>>) -> HashMap<String, Vec<bool>> {
+    let mut values: HashMap<Net, bool> = HashMap::new();
+    let value = |values: &HashMap<Net, bool>, net: Net| match net.as_const() {
+        Some(trit) => trit == Trit::One,
+        None => values[&net],
+    };
+    let mut outputs = HashMap::new();
+    for cell_ref in design.iter_cells_topo() {
+        let output = cell_ref.output();
+        match &*cell_ref.get() {
+            Cell::Input(name, _) => {
+                for (net, &bit) in output.iter().zip(&inputs[name]) {
+                    values.insert(net, bit);
+                }
+            }
+            Cell::Aig(arg1, arg2) => {
+                let control = |net: ControlNet| value(&values, net.net()) ^ net.is_negative();
+                values.insert(output[0], control(*arg1) && control(*arg2));
+            }
+            Cell::Xor(arg1, arg2) => {
+                for (index, net) in output.iter().enumerate() {
+                    values.insert(net, value(&values, arg1[index]) ^ value(&values, arg2[index]));
+                }
+            }
+            Cell::Not(arg) => {
+                for (index, net) in output.iter().enumerate() {
+                    values.insert(net, !value(&values, arg[index]));
+                }
+            }
+            Cell::Output(name, value_) => {
+                outputs.insert(name.clone(), Vec::from_iter(value_.iter().map(|net| value(&values, net))));
+            }
+            cell => panic!("unexpected cell {cell:?}"),
+        }
+    }
+    outputs
+}
+
+fn assert_equivalent(design1: &Design, design2: &Design, input_width: usize) {
+    for pattern in 0..1usize << input_width {
+        let inputs = HashMap::from([(
+            "i".to_owned(),
+            Vec::from_iter((0..input_width).map(|index| (pattern >> index) & 1 != 0)),
+        )]);
+        assert_eq!(evaluate(design1, &inputs), evaluate(design2, &inputs), "mismatch for input {pattern:b}");
+    }
+}
+
+fn or(design: &Design, net1: Net, net2: Net) -> Net {
+    let net = design.add_aig(ControlNet::Neg(net1), ControlNet::Neg(net2));
+    design.add_not1(net)
+}
+
+#[test]
+fn test_factor() {
+    // (a & b) | (a & c) is a & (b | c)
+    let mut design = Design::new();
+    let i = design.add_input("i", 3);
+    let x = design.add_aig(i[0], i[1]);
+    let y = design.add_aig(i[0], i[2]);
+    let z = or(&design, x, y);
+    design.add_output("z", z);
+    design.apply();
+    let reference = design.clone();
+    cut_rewrite(&mut design);
+    assert_eq!(count_nodes(&design), 2);
+    assert_equivalent(&reference, &design, 3);
+}
+
+#[test]
+fn test_xor() {
+    // (a & !b) | (!a & b) is a ^ b
+    let mut design = Design::new();
+    let i = design.add_input("i", 2);
+    let x = design.add_aig(ControlNet::Pos(i[0]), ControlNet::Neg(i[1]));
+    let y = design.add_aig(ControlNet::Neg(i[0]), ControlNet::Pos(i[1]));
+    let z = or(&design, x, y);
+    design.add_output("z", z);
+    design.apply();
+    let reference = design.clone();
+    cut_rewrite(&mut design);
+    assert_eq!(count_nodes(&design), 1);
+    assert!(design.iter_cells().any(|cell_ref| matches!(&*cell_ref.get(), Cell::Xor(..))));
+    assert_equivalent(&reference, &design, 2);
+}
+
+#[test]
+fn test_shared() {
+    // the same as `test_factor`, but `a & b` is also used elsewhere, so factoring does not save any nodes
+    let mut design = Design::new();
+    let i = design.add_input("i", 3);
+    let x = design.add_aig(i[0], i[1]);
+    let y = design.add_aig(i[0], i[2]);
+    let z = or(&design, x, y);
+    design.add_output("x", x);
+    design.add_output("z", z);
+    design.apply();
+    cut_rewrite(&mut design);
+    assert_eq!(count_nodes(&design), 3);
+}
+
+#[test]
+fn test_random() {
+    let mut state = 0x9e3779b97f4a7c15u64;
+    let mut random = move |limit: usize| {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state % limit as u64) as usize
+    };
+    for _ in 0..20 {
+        let mut design = Design::new();
+        let mut nets = Vec::from_iter(&design.add_input("i", 6));
+        for _ in 0..30 {
+            let net1 = nets[random(nets.len())];
+            let net2 = nets[random(nets.len())];
+            let net = match random(3) {
+                0 => design.add_xor1(net1, net2),
+                _ => design.add_aig(
+                    ControlNet::from_net_invert(net1, random(2) == 0),
+                    ControlNet::from_net_invert(net2, random(2) == 0),
+                ),
+            };
+            nets.push(net);
+        }
+        design.add_output("o", Value::from_iter(nets[nets.len() - 4..].iter().copied()));
+        design.apply();
+        let reference = design.clone();
+        let node_count = count_nodes(&design);
+        cut_rewrite(&mut design);
+        assert!(count_nodes(&design) <= node_count);
+        assert_equivalent(&reference, &design, 6);
+    }
+}
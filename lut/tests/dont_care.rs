@@ -0,0 +1,97 @@
+use prjunnamed_lut::{Lut, LutCover, LutMapping, optimize_dont_cares};
+use prjunnamed_netlist::{Cell, Const, ControlNet, Design, FlipFlop, Net, Value};
+
+fn mapping(design: &Design, covers: Vec<(Value, Lut)>) -> LutMapping<'_> {
+    let covers = Vec::from_iter(covers.into_iter().map(|(output, lut)| {
+        let (cell_ref, _) = design.find_cell(output[0]).unwrap();
+        LutCover { output: output[0], lut, meta: cell_ref.metadata() }
+    }));
+    LutMapping { covers, delay: 0 }
+}
+
+fn input(design: &Design, name: &str) -> Net {
+    design.add_cell(Cell::Input(name.into(), 1))[0]
+}
+
+#[test]
+fn test_dont_care_exclusive() {
+    // `p` and `q` are never both set, so `z` does not depend on `d`
+    let mut design = Design::new();
+    let (a, b, c, d) = (input(&design, "a"), input(&design, "b"), input(&design, "c"), input(&design, "d"));
+    let p = design.add_cell(Cell::And(a.into(), b.into()));
+    let q = design.add_cell(Cell::Aig(ControlNet::Neg(a), ControlNet::Pos(c)));
+    let both = design.add_cell(Cell::And(p.clone(), q.clone()));
+    let either = design.add_cell(Cell::Or(p.clone(), q.clone()));
+    let z = design.add_cell(Cell::Mux(both[0], d.into(), either));
+    design.add_cell(Cell::Output("z".into(), z.clone()));
+    design.apply();
+    let mut mapping = mapping(&design, vec![
+        (p.clone(), Lut::lut2(a, b, Const::lit("1000"))),
+        (q.clone(), Lut::lut2(a, c, Const::lit("0100"))),
+        (z.clone(), Lut::lut3(p[0], q[0], d, Const::lit("11100110"))),
+    ]);
+    assert_eq!(optimize_dont_cares(&design, &mut mapping), 1);
+    assert_eq!(mapping.covers.len(), 3);
+    assert_eq!(mapping.covers[2].lut.inputs(), &Value::from_iter([p[0], q[0]]));
+    assert_eq!(mapping.covers[2].lut.table(), &Const::lit("0110"));
+}
+
+#[test]
+fn test_dont_care_unobserved() {
+    // `y` is only observed while `s` is set, so it does not depend on `s`
+    let mut design = Design::new();
+    let (s, a, b, c) = (input(&design, "s"), input(&design, "a"), input(&design, "b"), input(&design, "c"));
+    let t = design.add_cell(Cell::And(s.into(), a.into()));
+    let y = design.add_cell(Cell::Or(t, b.into()));
+    let z = design.add_cell(Cell::Mux(s, y.clone(), c.into()));
+    design.add_cell(Cell::Output("z".into(), z.clone()));
+    design.apply();
+    let mut mapping = mapping(&design, vec![
+        (y.clone(), Lut::lut3(s, a, b, Const::lit("11111000"))),
+        (z.clone(), Lut::lut3(s, y[0], c, Const::lit("11011000"))),
+    ]);
+    assert_eq!(optimize_dont_cares(&design, &mut mapping), 1);
+    assert_eq!(mapping.covers[0].lut.inputs(), &Value::from_iter([a, b]));
+    assert_eq!(mapping.covers[0].lut.table(), &Const::lit("1110"));
+    assert_eq!(mapping.covers[1].lut.inputs(), &Value::from_iter([s, y[0], c]));
+}
+
+fn rotating_register(init: &str) -> (Design, Vec<(Value, Lut)>) {
+    // a register rotating its value, and the parity of its value
+    let mut design = Design::new();
+    let clock = input(&design, "clk");
+    let state = design.add_void(3);
+    let register = design.add_cell(Cell::Dff(
+        FlipFlop::new(Value::from_iter([state[2], state[0], state[1]]), clock).with_init(Const::lit(init)),
+    ));
+    design.replace_value(&state, &register);
+    design.apply();
+    let p = design.add_cell(Cell::Xor(register.slice(0..1), register.slice(1..2)));
+    let o = design.add_cell(Cell::Xor(p.clone(), register.slice(2..3)));
+    design.add_cell(Cell::Output("o".into(), o.clone()));
+    design.apply();
+    let covers = vec![
+        (p.clone(), Lut::lut2(register[0], register[1], Const::lit("0110"))),
+        (o.clone(), Lut::lut2(p[0], register[2], Const::lit("0110"))),
+    ];
+    (design, covers)
+}
+
+#[test]
+fn test_dont_care_one_hot() {
+    // the register is one-hot, so its parity is always one
+    let (design, covers) = rotating_register("001");
+    let mut mapping = mapping(&design, covers);
+    assert_eq!(optimize_dont_cares(&design, &mut mapping), 1);
+    assert_eq!(mapping.covers.len(), 1);
+    assert_eq!(mapping.covers[0].lut.inputs(), &Value::new());
+    assert_eq!(mapping.covers[0].lut.table(), &Const::lit("1"));
+}
+
+#[test]
+fn test_dont_care_not_one_hot() {
+    let (design, covers) = rotating_register("011");
+    let mut mapping = mapping(&design, covers);
+    assert_eq!(optimize_dont_cares(&design, &mut mapping), 0);
+    assert_eq!(mapping.covers.len(), 2);
+}
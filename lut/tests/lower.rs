@@ -0,0 +1,138 @@
+use std::cell::RefCell;
+
+use prjunnamed_lut::{CarryStage, DelayModel, Lut, LutTarget, lower_luts};
+use prjunnamed_netlist::{Cell, Design, MetaItemRef, Net};
+
+struct UnitDelay;
+
+impl DelayModel for UnitDelay {
+    fn lut_delay(&self, _input_count: usize) -> u32 {
+        1
+    }
+
+    fn carry_delay(&self) -> u32 {
+        0
+    }
+
+    fn route_delay(&self, _fanout: usize) -> u32 {
+        0
+    }
+}
+
+// stands in for a real target by turning every LUT into a design input
+#[derive(Default)]
+struct Recorder {
+    luts: RefCell<Vec<Lut>>,
+    carries: RefCell<Vec<Lut>>,
+}
+
+impl Recorder {
+    fn materialize(&self, design: &Design, lut: &Lut, lut_size: usize) -> Net {
+        assert!(lut.inputs().len() <= lut_size);
+        let mut luts = self.luts.borrow_mut();
+        luts.push(lut.clone());
+        design.add_input(format!("lut{}", luts.len() - 1), 1)[0]
+    }
+}
+
+// a target without a carry chain
+struct LutTarget6(Recorder);
+
+impl LutTarget for LutTarget6 {
+    fn lut_size(&self) -> usize {
+        6
+    }
+
+    fn delay_model(&self) -> &dyn DelayModel {
+        &UnitDelay
+    }
+
+    fn materialize_lut(&self, design: &Design, lut: &Lut, _meta: MetaItemRef) -> Net {
+        self.0.materialize(design, lut, 6)
+    }
+}
+
+// a target with a carry chain where the sum LUT has one spare input
+struct CarryTarget4(Recorder);
+
+impl LutTarget for CarryTarget4 {
+    fn lut_size(&self) -> usize {
+        4
+    }
+
+    fn delay_model(&self) -> &dyn DelayModel {
+        &UnitDelay
+    }
+
+    fn materialize_lut(&self, design: &Design, lut: &Lut, _meta: MetaItemRef) -> Net {
+        self.0.materialize(design, lut, 4)
+    }
+
+    fn absorb_into_carry(&self, stage: &CarryStage, lut: &Lut) -> Option<Lut> {
+        lut.expand_with_fixed(&[Some(stage.arg1), Some(stage.arg2), Some(stage.ci), None])
+    }
+
+    fn materialize_carry(&self, design: &Design, stage: &CarryStage) -> (Net, Net) {
+        let mut carries = self.0.carries.borrow_mut();
+        carries.push(stage.lut.clone());
+        let output = design.add_input(format!("carry{}", carries.len() - 1), 2);
+        (output[0], output[1])
+    }
+}
+
+fn count_cells(design: &Design, predicate: impl Fn(&Cell) -> bool) -> usize {
+    design.iter_cells().filter(|cell_ref| predicate(&cell_ref.get())).count()
+}
+
+#[test]
+fn test_lut6() {
+    let mut design = Design::new();
+    let inputs = design.add_input("i", 6);
+    let a = design.add_xor(inputs.slice(0..3), inputs.slice(3..6));
+    let b = design.add_and(a[0], a[1]).unwrap_net();
+    let c = design.add_mux1(b, a[2], inputs[0]);
+    design.add_output("o", c);
+    design.apply();
+    let target = LutTarget6(Recorder::default());
+    lower_luts(&mut design, &target);
+    // the entire cone has 6 inputs and fits into a single LUT
+    assert_eq!(target.0.luts.borrow().len(), 1);
+    assert_eq!(target.0.luts.borrow()[0].inputs().len(), 6);
+    assert_eq!(count_cells(&design, |cell| matches!(cell, Cell::Xor(..) | Cell::And(..) | Cell::Mux(..))), 0);
+}
+
+#[test]
+fn test_adc_without_carry() {
+    let mut design = Design::new();
+    let a = design.add_input("a", 2);
+    let b = design.add_input("b", 2);
+    let y = design.add_adc(a, b, Net::ZERO);
+    design.add_output("y", y);
+    design.apply();
+    let target = LutTarget6(Recorder::default());
+    lower_luts(&mut design, &target);
+    // a sum and a carry LUT for each of the 2 bits
+    assert_eq!(target.0.luts.borrow().len(), 4);
+    assert_eq!(count_cells(&design, |cell| matches!(cell, Cell::Adc(..))), 0);
+}
+
+#[test]
+fn test_adc_absorb() {
+    let mut design = Design::new();
+    let a = design.add_input("a", 2);
+    let b = design.add_input("b", 2);
+    let c = design.add_input("c", 2);
+    let y = design.add_adc(a, b, Net::ZERO);
+    let z = design.add_and(y.slice(0..2), &c);
+    design.add_output("y", y.msb());
+    design.add_output("z", z);
+    design.apply();
+    let target = CarryTarget4(Recorder::default());
+    lower_luts(&mut design, &target);
+    // the AND gates use the spare input of the carry chain stages
+    assert_eq!(target.0.luts.borrow().len(), 0);
+    assert_eq!(target.0.carries.borrow().len(), 2);
+    for (index, lut) in target.0.carries.borrow().iter().enumerate() {
+        assert_eq!(lut.inputs()[3], c[index]);
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+
+use prjunnamed_lut::{DelayModel, LutMapper, LutMapping};
+use prjunnamed_netlist::{Cell, ControlNet, Design, Net, Trit, Value};
+
+struct UnitDelay;
+
+impl DelayModel for UnitDelay {
+    fn lut_delay(&self, _input_count: usize) -> u32 {
+        1
+    }
+
+    fn carry_delay(&self) -> u32 {
+        0
+    }
+
+    fn route_delay(&self, _fanout: usize) -> u32 {
+        0
+    }
+}
+
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn pick(&mut self, nets: &[Net]) -> Net {
+        nets[self.next() as usize % nets.len()]
+    }
+}
+
+fn random_design(seed: u64, inputs: usize, cells: usize) -> Design {
+    let mut rng = Rng(seed);
+    let mut design = Design::new();
+    let mut nets = Vec::from_iter(design.add_input("i", inputs).iter());
+    for _ in 0..cells {
+        let (a, b, c) = (rng.pick(&nets), rng.pick(&nets), rng.pick(&nets));
+        let net = match rng.next() % 6 {
+            0 => design.add_and(a, b).unwrap_net(),
+            1 => design.add_or(a, b).unwrap_net(),
+            2 => design.add_xor(a, b).unwrap_net(),
+            3 => design.add_mux1(a, b, c),
+            4 => design.add_not(a).unwrap_net(),
+            _ => design.add_aig(ControlNet::Neg(a), ControlNet::Pos(b)),
+        };
+        nets.push(net);
+    }
+    design.add_output("o", Value::from(&nets[nets.len() - 8..]));
+    design.apply();
+    design
+}
+
+fn eval_lut(lut: &prjunnamed_lut::Lut, value: impl Fn(Net) -> bool) -> bool {
+    let mut index = 0;
+    for (input_index, net) in lut.inputs().iter().enumerate() {
+        if value(net) {
+            index |= 1 << input_index;
+        }
+    }
+    lut.table()[index] == Trit::One
+}
+
+fn assert_equivalent(design: &Design, mapping: &LutMapping, inputs: usize) {
+    let mut cells = vec![];
+    for cell_ref in design.iter_cells_topo() {
+        match &*cell_ref.get() {
+            Cell::Input(..) | Cell::Output(..) => (),
+            cell => cells.push((cell_ref.output()[0], prjunnamed_lut::Lut::from_cell(cell).unwrap())),
+        }
+    }
+    let input = design.iter_cells().find(|cell_ref| matches!(&*cell_ref.get(), Cell::Input(..))).unwrap().output();
+    for pattern in 0..(1u64 << inputs) {
+        let mut values = HashMap::from([(Net::ZERO, false), (Net::ONE, true)]);
+        for (index, net) in input.iter().enumerate() {
+            values.insert(net, (pattern >> index) & 1 != 0);
+        }
+        for (output, lut) in &cells {
+            values.insert(*output, eval_lut(lut, |net| values[&net]));
+        }
+        // leaves are either outside of the mapped logic, or outputs of preceding covers
+        let mut mapped_values = HashMap::new();
+        for cover in &mapping.covers {
+            let value = eval_lut(&cover.lut, |net| *mapped_values.get(&net).unwrap_or(&values[&net]));
+            mapped_values.insert(cover.output, value);
+        }
+        for (net, value) in mapped_values {
+            assert_eq!(value, values[&net], "mismatch for {net} with inputs {pattern:b}");
+        }
+    }
+}
+
+#[test]
+fn test_and_tree_depth() {
+    let design = Design::new();
+    let mut nets = Vec::from_iter(design.add_input("i", 16).iter());
+    while nets.len() > 1 {
+        nets = Vec::from_iter(nets.chunks(2).map(|pair| design.add_and(pair[0], pair[1]).unwrap_net()));
+    }
+    design.add_output("o", nets[0]);
+    let mut design = design;
+    design.apply();
+    let mapping = LutMapper::new(4, &UnitDelay).map(&design);
+    // the tree of 2-input gates is 4 levels deep, but 4-input LUTs cover it in 2 levels
+    assert_eq!(mapping.delay, 2);
+    assert_eq!(mapping.covers.len(), 5);
+    for cover in &mapping.covers {
+        assert!(cover.lut.inputs().len() <= 4);
+    }
+}
+
+#[test]
+fn test_area_recovery() {
+    let design = Design::new();
+    let inputs = design.add_input("i", 3);
+    let and = design.add_and(inputs[0], inputs[1]).unwrap_net();
+    let xor = design.add_xor(and, inputs[2]).unwrap_net();
+    let or = design.add_or(and, inputs[2]).unwrap_net();
+    design.add_output("o", Value::from_iter([xor, or]));
+    let mut design = design;
+    design.apply();
+    let mapping = LutMapper::new(4, &UnitDelay).map(&design);
+    // the AND gate is duplicated into both LUTs instead of taking a LUT of its own
+    assert_eq!(mapping.delay, 1);
+    assert_eq!(mapping.covers.len(), 2);
+    assert_equivalent(&design, &mapping, 3);
+}
+
+#[test]
+fn test_random_equivalence() {
+    for seed in 1..20 {
+        let design = random_design(seed, 8, 60);
+        for lut_size in [3, 4, 6] {
+            let mapping = LutMapper::new(lut_size, &UnitDelay).map(&design);
+            for cover in &mapping.covers {
+                assert!(cover.lut.inputs().len() <= lut_size);
+            }
+            assert_equivalent(&design, &mapping, 8);
+        }
+    }
+}
@@ -51,6 +51,10 @@ use prjunnamed_netlist::{
     MemoryWritePort, Net, Trit, Value,
 };
 
+mod merge_read_dff;
+
+pub use merge_read_dff::merge_read_dffs;
+
 /// Describes a "swizzle" transformation, used to align memory geometry to target requirements.
 ///
 /// This structure is constructed by the target, with some help from common code.
@@ -203,7 +207,7 @@ pub trait MemoryExt {
 
     /// Returns true iff the memory is legal for fallback lowering.
     ///
-    /// A memory is legal for fallback lowering iff all write ports share the same clock.
+    /// A memory is legal for fallback lowering iff all write ports share the same clock, or are all asynchronous.
     fn can_lower_fallback(&self) -> bool;
 
     /// Performs fallback lowering of the memory.
@@ -212,6 +216,10 @@ pub trait MemoryExt {
     /// write address decoding logic and read muxes.  It is thus *very* inefficient unless the memory depth
     /// is *very* small.
     ///
+    /// If the write ports are asynchronous, a latch is created for every bit of the memory instead: a mux that
+    /// selects the write data while any write port is writing to the bit, and otherwise feeds its own output back.
+    /// The netlist has no latch cell, so this is a combinational loop, and the initial value of the memory is lost.
+    ///
     /// If the memory has no write ports, it is lowered to just a tree of muxes for every read port, with
     /// constants at the leafs.  On FPGA targets, this will be lowered to a fairly simple tree of LUTs,
     /// which can be reasonably efficient for memories of small depth.
@@ -433,16 +441,17 @@ impl MemoryExt for Memory {
                 !transparent_write_ports[port_index],
                 "a single write port cannot have both a read-before-write and a transparent relation to two read ports"
             );
-            port.addr = design.add_dff(FlipFlop::new(std::mem::take(&mut port.addr), port.clock));
-            port.data = design.add_dff(FlipFlop::new(std::mem::take(&mut port.data), port.clock));
+            let port_clock = port.clock.expect("read-before-write relations require a synchronous write port");
+            port.addr = design.add_dff(FlipFlop::new(std::mem::take(&mut port.addr), port_clock));
+            port.data = design.add_dff(FlipFlop::new(std::mem::take(&mut port.data), port_clock));
             port.mask = design.add_dedup_dff(
-                FlipFlop::new(std::mem::take(&mut port.mask), port.clock).with_init(if init_undef {
+                FlipFlop::new(std::mem::take(&mut port.mask), port_clock).with_init(if init_undef {
                     Const::undef(port.data.len())
                 } else {
                     Const::zero(port.data.len())
                 }),
             );
-            port.clock = !port.clock;
+            port.clock = Some(!port_clock);
             for read_port in &mut self.read_ports {
                 let read_ff = read_port.flip_flop.as_mut().unwrap();
                 read_ff.relations[port_index] = MemoryPortRelation::Undefined;
@@ -944,16 +953,34 @@ impl MemoryExt for Memory {
                 Value::from(memory.init_value)
             } else {
                 let q = design.add_void(memory.width);
-                let mut data = q.clone();
-                for port in memory.write_ports {
-                    assert_eq!(port.data.len(), data.len());
-                    // multiplex our data onto the chain
-                    data = design.add_bitwise_mux(port.mask, port.data, data);
+                match write_clock.unwrap() {
+                    Some(clock) => {
+                        let mut data = q.clone();
+                        for port in memory.write_ports {
+                            assert_eq!(port.data.len(), data.len());
+                            // multiplex our data onto the chain
+                            data = design.add_bitwise_mux(port.mask, port.data, data);
+                        }
+                        design.replace_value(
+                            &q,
+                            design.add_dff(FlipFlop::new(data, clock).with_init(memory.init_value)),
+                        );
+                    }
+                    None => {
+                        // the later ports take priority, as above; the data of the first port is only selected
+                        // if it is the one writing
+                        let mut ports = memory.write_ports.into_iter();
+                        let first_port = ports.next().unwrap();
+                        let (mut data, mut enable) = (first_port.data, first_port.mask);
+                        for port in ports {
+                            assert_eq!(port.data.len(), data.len());
+                            data = design.add_bitwise_mux(&port.mask, port.data, data);
+                            enable = design.add_or(enable, port.mask);
+                        }
+                        // hold the value of each bit with a latch
+                        design.replace_value(&q, design.add_bitwise_mux(enable, data, &q));
+                    }
                 }
-                design.replace_value(
-                    &q,
-                    design.add_dff(FlipFlop::new(data, write_clock.unwrap()).with_init(memory.init_value)),
-                );
                 q
             };
             // all read ports are asynchronous, and there is only a single row in the memory.
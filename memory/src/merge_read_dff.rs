@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+
+use prjunnamed_netlist::{
+    Cell, CellRef, Const, ControlNet, Design, Memory, MemoryPortRelation, MemoryReadFlipFlop, Net, Value,
+};
+
+/// The flip-flop bit sampling one bit of a read port.
+struct SampledBit<'a> {
+    flip_flop: CellRef<'a>,
+    offset: usize,
+    /// The write ports whose data bypasses the memory when they write the row being read.
+    transparent: Vec<usize>,
+}
+
+struct Merger<'a> {
+    design: &'a Design,
+    users: HashMap<Net, Vec<CellRef<'a>>>,
+}
+
+impl<'a> Merger<'a> {
+    fn new(design: &'a Design) -> Self {
+        let mut users: HashMap<Net, Vec<CellRef<'a>>> = HashMap::new();
+        for cell_ref in design.iter_cells() {
+            if matches!(&*cell_ref.get(), Cell::Debug(..)) {
+                continue;
+            }
+            cell_ref.visit(|net| users.entry(net).or_default().push(cell_ref));
+        }
+        Merger { design, users }
+    }
+
+    /// Returns the only user of `net`, if it uses `net` only once.
+    fn only_user(&self, net: Net) -> Option<CellRef<'a>> {
+        match self.users.get(&net).map(Vec::as_slice) {
+            Some(&[cell_ref]) => Some(cell_ref),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if `net` is set exactly when the read port `read_port_index` reads the row written by
+    /// the write port `write_port_index` and the write is enabled for bit `bit_index`.
+    fn is_address_match(
+        &self,
+        memory: &Memory,
+        read_port_index: usize,
+        write_port_index: usize,
+        bit_index: usize,
+        net: Net,
+    ) -> bool {
+        let read_port = &memory.read_ports[read_port_index];
+        let write_port = &memory.write_ports[write_port_index];
+        let mask = write_port.mask[bit_index];
+        let is_addr_eq = |net: Net| {
+            let Ok((cell_ref, _)) = self.design.find_cell(net) else { return false };
+            let Cell::Eq(arg1, arg2) = &*cell_ref.get() else { return false };
+            let width = arg1.len();
+            if read_port.addr.len() > width || write_port.addr.len() > width {
+                return false;
+            }
+            let (read_addr, write_addr) = (read_port.addr.zext(width), write_port.addr.zext(width));
+            (*arg1 == read_addr && *arg2 == write_addr) || (*arg1 == write_addr && *arg2 == read_addr)
+        };
+        if mask == Net::ONE && is_addr_eq(net) {
+            return true;
+        }
+        let Ok((cell_ref, offset)) = self.design.find_cell(net) else { return false };
+        match &*cell_ref.get() {
+            Cell::And(arg1, arg2) => {
+                (arg1[offset] == mask && is_addr_eq(arg2[offset])) || (arg2[offset] == mask && is_addr_eq(arg1[offset]))
+            }
+            Cell::Aig(ControlNet::Pos(arg1), ControlNet::Pos(arg2)) => {
+                (*arg1 == mask && is_addr_eq(*arg2)) || (*arg2 == mask && is_addr_eq(*arg1))
+            }
+            Cell::Mux(select, arg1, arg2) => arg1[offset] == mask && arg2[offset] == Net::ZERO && is_addr_eq(*select),
+            _ => false,
+        }
+    }
+
+    /// Follows bit `bit_index` of the read data `net` through the transparency logic to the flip-flop sampling it.
+    fn sampled_bit(
+        &self,
+        memory: &Memory,
+        port_index: usize,
+        bit_index: usize,
+        mut net: Net,
+    ) -> Option<SampledBit<'a>> {
+        let read_port = &memory.read_ports[port_index];
+        let mut transparent = vec![];
+        loop {
+            let cell_ref = self.only_user(net)?;
+            match &*cell_ref.get() {
+                Cell::Dff(flip_flop) => {
+                    let mut offsets = flip_flop.data.iter().enumerate().filter(|&(_, data)| data == net);
+                    let (Some((offset, _)), None) = (offsets.next(), offsets.next()) else { return None };
+                    return Some(SampledBit { flip_flop: cell_ref, offset, transparent });
+                }
+                Cell::Mux(select, arg1, arg2) => {
+                    let mut offsets = arg2.iter().enumerate().filter(|&(_, arg)| arg == net);
+                    let (Some((offset, _)), None) = (offsets.next(), offsets.next()) else { return None };
+                    let write_port_index = memory.write_ports.iter().enumerate().position(|(index, write_port)| {
+                        write_port.data.len() == read_port.data_len
+                            && write_port.data[bit_index] == arg1[offset]
+                            && !transparent.contains(&index)
+                            && self.is_address_match(memory, port_index, index, bit_index, *select)
+                    })?;
+                    transparent.push(write_port_index);
+                    net = cell_ref.output()[offset];
+                }
+                _ => return None,
+            }
+        }
+    }
+
+    /// If the asynchronous read port `port_index` (with read data `data`) is only used by flip-flops with the same
+    /// controls, returns the equivalent synchronous read port flip-flop, and the outputs of those flip-flops.
+    fn read_flip_flop(&self, memory: &Memory, port_index: usize, data: &Value) -> Option<(MemoryReadFlipFlop, Value)> {
+        let mut sampled_bits = vec![];
+        for (bit_index, net) in data.iter().enumerate() {
+            sampled_bits.push(self.sampled_bit(memory, port_index, bit_index, net)?);
+        }
+        let first = sampled_bits.first()?;
+        let Cell::Dff(controls) = &*first.flip_flop.get() else { unreachable!() };
+        if !controls.has_clock() {
+            return None;
+        }
+        let (mut clear_value, mut reset_value, mut init_value) = (Const::new(), Const::new(), Const::new());
+        let mut output = Value::new();
+        for sampled_bit in &sampled_bits {
+            let Cell::Dff(flip_flop) = &*sampled_bit.flip_flop.get() else { unreachable!() };
+            if flip_flop.clock != controls.clock
+                || flip_flop.clear != controls.clear
+                || flip_flop.reset != controls.reset
+                || flip_flop.enable != controls.enable
+                || (flip_flop.has_reset()
+                    && flip_flop.has_enable()
+                    && flip_flop.reset_over_enable != controls.reset_over_enable)
+            {
+                return None;
+            }
+            clear_value.push(flip_flop.clear_value[sampled_bit.offset]);
+            reset_value.push(flip_flop.reset_value[sampled_bit.offset]);
+            init_value.push(flip_flop.init_value[sampled_bit.offset]);
+            output.push(sampled_bit.flip_flop.output()[sampled_bit.offset]);
+        }
+
+        let mut relations = vec![];
+        for (write_port_index, write_port) in memory.write_ports.iter().enumerate() {
+            let transparent =
+                sampled_bits.iter().filter(|sampled_bit| sampled_bit.transparent.contains(&write_port_index)).count();
+            relations.push(if write_port.clock != Some(controls.clock) {
+                if transparent != 0 {
+                    return None;
+                }
+                MemoryPortRelation::Undefined
+            } else if transparent == 0 {
+                // the flip-flop samples the value of the row before the write
+                MemoryPortRelation::ReadBeforeWrite
+            } else if transparent == sampled_bits.len() {
+                MemoryPortRelation::Transparent
+            } else {
+                return None;
+            });
+        }
+
+        let flip_flop = MemoryReadFlipFlop {
+            clock: controls.clock,
+            clear: controls.clear,
+            reset: controls.reset,
+            enable: controls.enable,
+            reset_over_enable: controls.reset_over_enable,
+            clear_value,
+            reset_value,
+            init_value,
+            relations,
+        };
+        Some((flip_flop, output))
+    }
+}
+
+/// Merges flip-flops sampling the data of asynchronous memory read ports into the read ports, making them
+/// synchronous. This is the reverse of [`MemoryExt::unmap_read_dff`].
+///
+/// A read port is merged if every bit of its data is sampled by one flip-flop bit, and all of these flip-flops
+/// have the same controls. The data may pass through a `mux` cell selecting the data of a write port if the write
+/// is enabled and its address is equal to the read address, in which case the port is transparent relative to
+/// that write port. Otherwise, the read port reads the value before the write for the write ports with the same
+/// clock as the flip-flops.
+///
+/// [`MemoryExt::unmap_read_dff`]: crate::MemoryExt::unmap_read_dff
+pub fn merge_read_dffs(design: &mut Design) {
+    let merger = Merger::new(design);
+    for cell_ref in design.iter_cells() {
+        let Cell::Memory(memory) = &*cell_ref.get() else { continue };
+        let output = cell_ref.output();
+        let mut new_memory = memory.clone();
+        let mut replacements = vec![];
+        let mut merged_ranges = vec![];
+        for port_index in 0..memory.read_ports.len() {
+            let range = memory.read_port_output_slice(port_index);
+            let flip_flop = if memory.read_ports[port_index].flip_flop.is_none() {
+                merger.read_flip_flop(memory, port_index, &output.slice(range.clone()))
+            } else {
+                None
+            };
+            if let Some((flip_flop, flip_flop_output)) = flip_flop {
+                new_memory.read_ports[port_index].flip_flop = Some(flip_flop);
+                replacements.push((flip_flop_output, range.clone()));
+                merged_ranges.push(range);
+            } else {
+                replacements.push((output.slice(range.clone()), range));
+            }
+        }
+        if merged_ranges.is_empty() {
+            continue;
+        }
+        cell_ref.unalive();
+        let new_output = design.add_memory(new_memory);
+        for (value, range) in replacements {
+            design.replace_value(value, new_output.slice(range));
+        }
+        // the flip-flops and the transparency logic are no longer used
+        for range in merged_ranges {
+            design.replace_value(output.slice(range.clone()), Value::undef(range.len()));
+        }
+    }
+    drop(merger);
+    design.compact();
+}
@@ -90,3 +90,96 @@ fn test_simple() {
     .unwrap();
     assert_isomorphic!(design, gold);
 }
+
+#[test]
+fn test_async_write() {
+    let mut design = Design::from_str(concat!(
+        "%0:2 = input \"wd\"\n",
+        "%10:1 = input \"wa\"\n",
+        "%20:1 = input \"we\"\n",
+        "%30:1 = input \"ra\"\n",
+        "%100:_ = memory depth=#2 width=#2 {\n",
+        "    write addr=%10 data=%0:2 mask=%20*2\n",
+        "    %100:2 = read addr=%30\n",
+        "}\n",
+        "%110:0 = output \"rd\" %100:2\n",
+    ))
+    .unwrap();
+    for cell_ref in design.iter_cells() {
+        let Cell::Memory(memory) = &*cell_ref.get() else { continue };
+        assert!(memory.can_lower_fallback());
+    }
+    lower_fallback(&mut design);
+    let mut gold = Design::from_str(concat!(
+        "%0:2 = input \"wd\"\n",
+        "%10:1 = input \"wa\"\n",
+        "%20:1 = input \"we\"\n",
+        "%30:1 = input \"ra\"\n",
+        "%100:1 = eq %10 0\n",
+        "%101:1 = eq %10 1\n",
+        "%110:1 = mux %100 %20 0\n",
+        "%111:1 = mux %101 %20 0\n",
+        "%120:2 = mux %110 %0:2 %120:2\n",
+        "%121:2 = mux %111 %0:2 %121:2\n",
+        "%130:2 = mux %30 %121:2 %120:2\n",
+        "%140:0 = output \"rd\" %130:2\n",
+    ))
+    .unwrap();
+    assert_isomorphic!(design, gold);
+}
+
+#[test]
+fn test_async_write_ports() {
+    let mut design = Design::from_str(concat!(
+        "%0:1 = input \"w0d\"\n",
+        "%1:1 = input \"w0e\"\n",
+        "%2:1 = input \"w1d\"\n",
+        "%3:1 = input \"w1e\"\n",
+        "%100:_ = memory depth=#1 width=#1 {\n",
+        "    write addr=0 data=%0 mask=%1\n",
+        "    write addr=0 data=%2 mask=%3\n",
+        "    %100:1 = read addr=0\n",
+        "}\n",
+        "%110:0 = output \"rd\" %100\n",
+    ))
+    .unwrap();
+    lower_fallback(&mut design);
+    // the second port takes priority when both are writing
+    let mut gold = Design::from_str(concat!(
+        "%0:1 = input \"w0d\"\n",
+        "%1:1 = input \"w0e\"\n",
+        "%2:1 = input \"w1d\"\n",
+        "%3:1 = input \"w1e\"\n",
+        "%4:1 = eq 0 0\n",
+        "%5:1 = mux %4 %1 0\n",
+        "%6:1 = mux %4 %3 0\n",
+        "%7:1 = mux %6 %2 %0\n",
+        "%8:1 = or %5 %6\n",
+        "%9:1 = mux %8 %7 %9\n",
+        "%10:0 = output \"rd\" %9\n",
+    ))
+    .unwrap();
+    assert_isomorphic!(design, gold);
+}
+
+#[test]
+fn test_mixed_write_clocks() {
+    let design = Design::from_str(concat!(
+        "%0:2 = input \"wd\"\n",
+        "%10:1 = input \"wa\"\n",
+        "%20:1 = input \"we\"\n",
+        "%30:1 = input \"ra\"\n",
+        "%40:1 = input \"clk\"\n",
+        "%100:_ = memory depth=#2 width=#2 {\n",
+        "    write addr=%10 data=%0:2 mask=%20*2\n",
+        "    write addr=%10 data=%0:2 mask=%20*2 clk=%40\n",
+        "    %100:2 = read addr=%30\n",
+        "}\n",
+        "%110:0 = output \"rd\" %100:2\n",
+    ))
+    .unwrap();
+    for cell_ref in design.iter_cells() {
+        let Cell::Memory(memory) = &*cell_ref.get() else { continue };
+        assert!(!memory.can_lower_fallback());
+    }
+}
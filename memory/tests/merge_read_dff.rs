@@ -0,0 +1,147 @@
+use std::str::FromStr;
+
+use prjunnamed_memory::merge_read_dffs;
+use prjunnamed_netlist::{assert_isomorphic, Design};
+
+#[test]
+fn test_simple() {
+    let mut design = Design::from_str(concat!(
+        "%0:4 = input \"wd\"\n",
+        "%10:4 = input \"wa\"\n",
+        "%20:1 = input \"we\"\n",
+        "%21:1 = input \"re\"\n",
+        "%30:4 = input \"ra\"\n",
+        "%40:1 = input \"wclk\"\n",
+        "%41:1 = input \"rclk\"\n",
+        "%42:1 = input \"rclr\"\n",
+        "%43:1 = input \"rrst\"\n",
+        "%50:_ = memory depth=#16 width=#4 {\n",
+        "    init 0101\n",
+        "    write addr=%10:4 data=%0:4 mask=%20*4 clk=%40\n",
+        "    %50:4 = read addr=%30:4\n",
+        "}\n",
+        "%60:4 = dff %50:4 clk=%41 clr=%42,1111 rst=%43,0000 en=%21 rst/en init=1010\n",
+        "%70:0 = output \"rd\" %60:4\n",
+    ))
+    .unwrap();
+    merge_read_dffs(&mut design);
+    let mut gold = Design::from_str(concat!(
+        "%0:4 = input \"wd\"\n",
+        "%10:4 = input \"wa\"\n",
+        "%20:1 = input \"we\"\n",
+        "%21:1 = input \"re\"\n",
+        "%30:4 = input \"ra\"\n",
+        "%40:1 = input \"wclk\"\n",
+        "%41:1 = input \"rclk\"\n",
+        "%42:1 = input \"rclr\"\n",
+        "%43:1 = input \"rrst\"\n",
+        "%50:_ = memory depth=#16 width=#4 {\n",
+        "    init 0101\n",
+        "    write addr=%10:4 data=%0:4 mask=%20*4 clk=%40 \n",
+        "    %50:4 = read addr=%30:4 clk=%41 clr=%42,1111 rst=%43,0000 en=%21 rst/en init=1010 [undef]\n",
+        "}\n",
+        "%60:0 = output \"rd\" %50:4\n",
+    ))
+    .unwrap();
+    assert_isomorphic!(design, gold);
+}
+
+#[test]
+fn test_read_before_write() {
+    let mut design = Design::from_str(concat!(
+        "%0:4 = input \"wd\"\n",
+        "%10:4 = input \"wa\"\n",
+        "%20:1 = input \"we\"\n",
+        "%30:4 = input \"ra\"\n",
+        "%40:1 = input \"clk\"\n",
+        "%50:_ = memory depth=#16 width=#4 {\n",
+        "    write addr=%10:4 data=%0:4 mask=%20*4 clk=%40\n",
+        "    %50:4 = read addr=%30:4\n",
+        "}\n",
+        "%60:4 = dff %50:4 clk=%40\n",
+        "%70:0 = output \"rd\" %60:4\n",
+    ))
+    .unwrap();
+    merge_read_dffs(&mut design);
+    let mut gold = Design::from_str(concat!(
+        "%0:4 = input \"wd\"\n",
+        "%10:4 = input \"wa\"\n",
+        "%20:1 = input \"we\"\n",
+        "%30:4 = input \"ra\"\n",
+        "%40:1 = input \"clk\"\n",
+        "%50:_ = memory depth=#16 width=#4 {\n",
+        "    write addr=%10:4 data=%0:4 mask=%20*4 clk=%40\n",
+        "    %50:4 = read addr=%30:4 clk=%40 [rdfirst]\n",
+        "}\n",
+        "%60:0 = output \"rd\" %50:4\n",
+    ))
+    .unwrap();
+    assert_isomorphic!(design, gold);
+}
+
+#[test]
+fn test_transparent() {
+    let mut design = Design::from_str(concat!(
+        "%0:4 = input \"wd\"\n",
+        "%10:4 = input \"wa\"\n",
+        "%20:1 = input \"we\"\n",
+        "%21:1 = input \"re\"\n",
+        "%30:4 = input \"ra\"\n",
+        "%40:1 = input \"clk\"\n",
+        "%42:1 = input \"rclr\"\n",
+        "%43:1 = input \"rrst\"\n",
+        "%50:_ = memory depth=#16 width=#4 {\n",
+        "    init 0101\n",
+        "    write addr=%10:4 data=%0:4 mask=%20*4 clk=%40\n",
+        "    %50:4 = read addr=%30:4\n",
+        "}\n",
+        "%60:1 = eq %30:4 %10:4\n",
+        "%61:1 = mux %60 %20 0\n",
+        "%70:4 = mux %61 %0:4 %50:4\n",
+        "%100:4 = dff %70:4 clk=%40 clr=%42,1111 rst=%43,0000 en=%21 rst/en init=1010\n",
+        "%110:0 = output \"rd\" %100:4\n",
+    ))
+    .unwrap();
+    merge_read_dffs(&mut design);
+    let mut gold = Design::from_str(concat!(
+        "%0:4 = input \"wd\"\n",
+        "%10:4 = input \"wa\"\n",
+        "%20:1 = input \"we\"\n",
+        "%21:1 = input \"re\"\n",
+        "%30:4 = input \"ra\"\n",
+        "%40:1 = input \"clk\"\n",
+        "%42:1 = input \"rclr\"\n",
+        "%43:1 = input \"rrst\"\n",
+        "%50:_ = memory depth=#16 width=#4 {\n",
+        "    init 0101\n",
+        "    write addr=%10:4 data=%0:4 mask=%20*4 clk=%40 \n",
+        "    %50:4 = read addr=%30:4 clk=%40 clr=%42,1111 rst=%43,0000 en=%21 rst/en init=1010 [trans]\n",
+        "}\n",
+        "%60:0 = output \"rd\" %50:4\n",
+    ))
+    .unwrap();
+    assert_isomorphic!(design, gold);
+}
+
+#[test]
+fn test_async_data_used() {
+    // the asynchronous read data is also used directly, so the flip-flop cannot be merged
+    let source = concat!(
+        "%0:4 = input \"wd\"\n",
+        "%10:4 = input \"wa\"\n",
+        "%20:1 = input \"we\"\n",
+        "%30:4 = input \"ra\"\n",
+        "%40:1 = input \"clk\"\n",
+        "%50:_ = memory depth=#16 width=#4 {\n",
+        "    write addr=%10:4 data=%0:4 mask=%20*4 clk=%40\n",
+        "    %50:4 = read addr=%30:4\n",
+        "}\n",
+        "%60:4 = dff %50:4 clk=%40\n",
+        "%70:0 = output \"rd\" %60:4\n",
+        "%71:0 = output \"ard\" %50:4\n",
+    );
+    let mut design = Design::from_str(source).unwrap();
+    merge_read_dffs(&mut design);
+    let mut gold = Design::from_str(source).unwrap();
+    assert_isomorphic!(design, gold);
+}
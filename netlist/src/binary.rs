@@ -0,0 +1,767 @@
+//! Binary serialization of designs.
+//!
+//! The binary format is a compact and fast alternative to the text format, meant for large designs and for caching
+//! designs between the stages of a synthesis pipeline. It stores the cells of a design the way they are represented
+//! in memory, along with the metadata string and item tables, the IOs, and the target, so that a design is read back
+//! with the same cell and metadata indices it was written with. Designs can be written to and read from any stream
+//! without holding the serialized form in memory.
+//!
+//! The format is versioned, but is not meant to be used for long-term storage: inputs written by a different version
+//! of the format are rejected, and are expected to be regenerated.
+//!
+//! An input starts with a magic number and a version number, followed by the target name and options, the IOs,
+//! the metadata string and item tables, and the cells. Integers are encoded as LEB128 (signed integers are zigzag
+//! encoded first), sequences and strings are prefixed with their length, and constants are packed four trits to
+//! a byte. Nets are encoded as their index plus one, so that the undefined net takes a single byte. Only the first
+//! index of a cell with more than one output bit is written.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::Display;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::sync::Arc;
+
+use crate::cell::CellRepr;
+use crate::design::AnnotatedCell;
+use crate::metadata::{MetaItemIndex, MetadataStore};
+use crate::{
+    AssignCell, Cell, Const, ControlNet, Design, FlipFlop, Instance, IoBuffer, IoNet, IoValue, MatchCell, Memory,
+    MemoryPortRelation, MemoryReadFlipFlop, MemoryReadPort, MemoryWritePort, Net, ParamValue, Target, TargetCell, Trit,
+    Value, create_target,
+};
+
+const MAGIC: [u8; 8] = *b"UIRBIN\r\n";
+
+const VERSION: u32 = 1;
+
+#[derive(Debug)]
+pub enum BinaryError {
+    /// Reading the input failed.
+    Io(io::Error),
+    /// The input does not start with the magic number of the binary format.
+    NotBinary,
+    /// The input was written by an incompatible version of the binary format.
+    UnsupportedVersion(u32),
+    /// The input is malformed at the given byte offset.
+    Malformed(u64),
+    /// The target recorded in the input could not be created.
+    Target(Box<dyn Error>),
+}
+
+impl From<io::Error> for BinaryError {
+    fn from(error: io::Error) -> Self {
+        BinaryError::Io(error)
+    }
+}
+
+impl Display for BinaryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BinaryError::Io(error) => write!(f, "failed to read binary design: {error}"),
+            BinaryError::NotBinary => write!(f, "input is not a binary design"),
+            BinaryError::UnsupportedVersion(version) => {
+                write!(f, "binary design has unsupported version {version} (expected {VERSION})")
+            }
+            BinaryError::Malformed(offset) => write!(f, "binary design is malformed at offset {offset}"),
+            BinaryError::Target(error) => write!(f, "failed to create target of binary design: {error}"),
+        }
+    }
+}
+
+impl Error for BinaryError {}
+
+pub(crate) struct Encoder<W: Write> {
+    writer: BufWriter<W>,
+}
+
+impl<W: Write> Encoder<W> {
+    pub(crate) fn byte(&mut self, value: u8) -> io::Result<()> {
+        self.writer.write_all(&[value])
+    }
+
+    pub(crate) fn uint(&mut self, mut value: u64) -> io::Result<()> {
+        while value >= 0x80 {
+            self.byte(value as u8 | 0x80)?;
+            value >>= 7;
+        }
+        self.byte(value as u8)
+    }
+
+    pub(crate) fn sint(&mut self, value: i64) -> io::Result<()> {
+        self.uint(((value << 1) ^ (value >> 63)) as u64)
+    }
+
+    pub(crate) fn usize(&mut self, value: usize) -> io::Result<()> {
+        self.uint(value as u64)
+    }
+
+    pub(crate) fn bool(&mut self, value: bool) -> io::Result<()> {
+        self.byte(value as u8)
+    }
+
+    pub(crate) fn string(&mut self, value: &str) -> io::Result<()> {
+        self.usize(value.len())?;
+        self.writer.write_all(value.as_bytes())
+    }
+
+    pub(crate) fn seq<T>(
+        &mut self,
+        items: impl ExactSizeIterator<Item = T>,
+        mut f: impl FnMut(&mut Self, T) -> io::Result<()>,
+    ) -> io::Result<()> {
+        self.usize(items.len())?;
+        for item in items {
+            f(self, item)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn const_value(&mut self, value: &Const) -> io::Result<()> {
+        self.usize(value.len())?;
+        let mut byte = 0;
+        for (index, trit) in value.iter().enumerate() {
+            let code = match trit {
+                Trit::Zero => 0,
+                Trit::One => 1,
+                Trit::Undef => 2,
+            };
+            byte |= code << (index % 4 * 2);
+            if index % 4 == 3 || index == value.len() - 1 {
+                self.byte(byte)?;
+                byte = 0;
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn param(&mut self, value: &ParamValue) -> io::Result<()> {
+        match value {
+            ParamValue::Const(value) => {
+                self.byte(0)?;
+                self.const_value(value)
+            }
+            ParamValue::Int(value) => {
+                self.byte(1)?;
+                self.sint(*value)
+            }
+            ParamValue::Float(value) => {
+                self.byte(2)?;
+                self.writer.write_all(&value.to_le_bytes())
+            }
+            ParamValue::String(value) => {
+                self.byte(3)?;
+                self.string(value)
+            }
+        }
+    }
+
+    fn net(&mut self, net: Net) -> io::Result<()> {
+        self.uint(net.index.wrapping_add(1).into())
+    }
+
+    fn control_net(&mut self, control_net: ControlNet) -> io::Result<()> {
+        self.uint((u64::from(control_net.net().index.wrapping_add(1)) << 1) | control_net.is_negative() as u64)
+    }
+
+    fn option_control_net(&mut self, control_net: Option<ControlNet>) -> io::Result<()> {
+        match control_net {
+            None => self.bool(false),
+            Some(control_net) => {
+                self.bool(true)?;
+                self.control_net(control_net)
+            }
+        }
+    }
+
+    fn value(&mut self, value: &Value) -> io::Result<()> {
+        self.seq(value.iter(), Self::net)
+    }
+
+    fn io_value(&mut self, value: &IoValue) -> io::Result<()> {
+        self.seq(value.iter(), |encoder, io_net| encoder.uint(io_net.index.wrapping_add(1).into()))
+    }
+
+    fn cell(&mut self, cell: &Cell) -> io::Result<()> {
+        self.byte(match cell {
+            Cell::Buf(..) => 16,
+            Cell::Not(..) => 17,
+            Cell::And(..) => 18,
+            Cell::Or(..) => 19,
+            Cell::Xor(..) => 20,
+            Cell::Mux(..) => 21,
+            Cell::Adc(..) => 22,
+            Cell::Aig(..) => 23,
+            Cell::Eq(..) => 24,
+            Cell::ULt(..) => 25,
+            Cell::SLt(..) => 26,
+            Cell::Shl(..) => 27,
+            Cell::UShr(..) => 28,
+            Cell::SShr(..) => 29,
+            Cell::XShr(..) => 30,
+            Cell::Popcnt(..) => 31,
+            Cell::Clz(..) => 32,
+            Cell::Ctz(..) => 33,
+            Cell::Mul(..) => 34,
+            Cell::UDiv(..) => 35,
+            Cell::UMod(..) => 36,
+            Cell::SDivTrunc(..) => 37,
+            Cell::SDivFloor(..) => 38,
+            Cell::SModTrunc(..) => 39,
+            Cell::SModFloor(..) => 40,
+            Cell::Match(..) => 41,
+            Cell::Assign(..) => 42,
+            Cell::Dff(..) => 43,
+            Cell::Memory(..) => 44,
+            Cell::IoBuf(..) => 45,
+            Cell::Target(..) => 46,
+            Cell::Other(..) => 47,
+            Cell::Input(..) => 48,
+            Cell::Output(..) => 49,
+            Cell::Name(..) => 50,
+            Cell::Debug(..) => 51,
+        })?;
+        match cell {
+            Cell::Buf(arg) | Cell::Not(arg) | Cell::Popcnt(arg) | Cell::Clz(arg) | Cell::Ctz(arg) => self.value(arg),
+            Cell::And(arg1, arg2)
+            | Cell::Or(arg1, arg2)
+            | Cell::Xor(arg1, arg2)
+            | Cell::Eq(arg1, arg2)
+            | Cell::ULt(arg1, arg2)
+            | Cell::SLt(arg1, arg2)
+            | Cell::Mul(arg1, arg2)
+            | Cell::UDiv(arg1, arg2)
+            | Cell::UMod(arg1, arg2)
+            | Cell::SDivTrunc(arg1, arg2)
+            | Cell::SDivFloor(arg1, arg2)
+            | Cell::SModTrunc(arg1, arg2)
+            | Cell::SModFloor(arg1, arg2) => {
+                self.value(arg1)?;
+                self.value(arg2)
+            }
+            Cell::Mux(arg1, arg2, arg3) => {
+                self.net(*arg1)?;
+                self.value(arg2)?;
+                self.value(arg3)
+            }
+            Cell::Adc(arg1, arg2, arg3) => {
+                self.value(arg1)?;
+                self.value(arg2)?;
+                self.net(*arg3)
+            }
+            Cell::Aig(arg1, arg2) => {
+                self.control_net(*arg1)?;
+                self.control_net(*arg2)
+            }
+            Cell::Shl(arg1, arg2, stride)
+            | Cell::UShr(arg1, arg2, stride)
+            | Cell::SShr(arg1, arg2, stride)
+            | Cell::XShr(arg1, arg2, stride) => {
+                self.value(arg1)?;
+                self.value(arg2)?;
+                self.uint((*stride).into())
+            }
+            Cell::Match(match_cell) => {
+                self.value(&match_cell.value)?;
+                self.net(match_cell.enable)?;
+                self.seq(match_cell.patterns.iter(), |encoder, alternates| {
+                    encoder.seq(alternates.iter(), Self::const_value)
+                })
+            }
+            Cell::Assign(assign_cell) => {
+                self.value(&assign_cell.value)?;
+                self.net(assign_cell.enable)?;
+                self.value(&assign_cell.update)?;
+                self.usize(assign_cell.offset)
+            }
+            Cell::Dff(flip_flop) => {
+                self.value(&flip_flop.data)?;
+                self.control_net(flip_flop.clock)?;
+                self.control_net(flip_flop.clear)?;
+                self.control_net(flip_flop.reset)?;
+                self.control_net(flip_flop.enable)?;
+                self.bool(flip_flop.reset_over_enable)?;
+                self.const_value(&flip_flop.clear_value)?;
+                self.const_value(&flip_flop.reset_value)?;
+                self.const_value(&flip_flop.init_value)
+            }
+            Cell::Memory(memory) => {
+                self.usize(memory.depth)?;
+                self.usize(memory.width)?;
+                self.const_value(&memory.init_value)?;
+                self.seq(memory.write_ports.iter(), |encoder, port| {
+                    encoder.value(&port.addr)?;
+                    encoder.value(&port.data)?;
+                    encoder.value(&port.mask)?;
+                    encoder.option_control_net(port.clock)
+                })?;
+                self.seq(memory.read_ports.iter(), |encoder, port| {
+                    encoder.value(&port.addr)?;
+                    encoder.usize(port.data_len)?;
+                    let Some(flip_flop) = &port.flip_flop else { return encoder.bool(false) };
+                    encoder.bool(true)?;
+                    encoder.control_net(flip_flop.clock)?;
+                    encoder.control_net(flip_flop.clear)?;
+                    encoder.control_net(flip_flop.reset)?;
+                    encoder.control_net(flip_flop.enable)?;
+                    encoder.bool(flip_flop.reset_over_enable)?;
+                    encoder.const_value(&flip_flop.clear_value)?;
+                    encoder.const_value(&flip_flop.reset_value)?;
+                    encoder.const_value(&flip_flop.init_value)?;
+                    encoder.seq(flip_flop.relations.iter(), |encoder, relation| {
+                        encoder.byte(match relation {
+                            MemoryPortRelation::Undefined => 0,
+                            MemoryPortRelation::ReadBeforeWrite => 1,
+                            MemoryPortRelation::Transparent => 2,
+                        })
+                    })
+                })
+            }
+            Cell::IoBuf(io_buffer) => {
+                self.io_value(&io_buffer.io)?;
+                self.value(&io_buffer.output)?;
+                self.control_net(io_buffer.enable)
+            }
+            Cell::Target(target_cell) => {
+                self.string(&target_cell.kind)?;
+                self.seq(target_cell.params.iter(), Self::param)?;
+                self.value(&target_cell.inputs)?;
+                self.usize(target_cell.output_len)?;
+                self.io_value(&target_cell.ios)
+            }
+            Cell::Other(instance) => {
+                self.string(&instance.kind)?;
+                self.seq(instance.params.iter(), |encoder, (name, value)| {
+                    encoder.string(name)?;
+                    encoder.param(value)
+                })?;
+                self.seq(instance.inputs.iter(), |encoder, (name, value)| {
+                    encoder.string(name)?;
+                    encoder.value(value)
+                })?;
+                self.seq(instance.outputs.iter(), |encoder, (name, range)| {
+                    encoder.string(name)?;
+                    encoder.usize(range.start)?;
+                    encoder.usize(range.end)
+                })?;
+                self.seq(instance.ios.iter(), |encoder, (name, value)| {
+                    encoder.string(name)?;
+                    encoder.io_value(value)
+                })
+            }
+            Cell::Input(name, width) => {
+                self.string(name)?;
+                self.usize(*width)
+            }
+            Cell::Output(name, value) | Cell::Name(name, value) | Cell::Debug(name, value) => {
+                self.string(name)?;
+                self.value(value)
+            }
+        }
+    }
+
+    fn tagged_nets(&mut self, tag: u8, nets: &[Net]) -> io::Result<()> {
+        self.byte(tag)?;
+        nets.iter().try_for_each(|&net| self.net(net))
+    }
+
+    fn annotated_cell(&mut self, cell: &AnnotatedCell) -> io::Result<()> {
+        match cell.repr {
+            CellRepr::Void => return self.byte(0),
+            CellRepr::Skip(_) => return Ok(()),
+            CellRepr::Buf(arg) => self.tagged_nets(1, &[arg])?,
+            CellRepr::Not(arg) => self.tagged_nets(2, &[arg])?,
+            CellRepr::And(arg1, arg2) => self.tagged_nets(3, &[arg1, arg2])?,
+            CellRepr::Or(arg1, arg2) => self.tagged_nets(4, &[arg1, arg2])?,
+            CellRepr::Xor(arg1, arg2) => self.tagged_nets(5, &[arg1, arg2])?,
+            CellRepr::Mux(arg1, arg2, arg3) => self.tagged_nets(6, &[arg1, arg2, arg3])?,
+            CellRepr::Adc(arg1, arg2, arg3) => self.tagged_nets(7, &[arg1, arg2, arg3])?,
+            CellRepr::Aig(arg1, invert1, arg2, invert2) => {
+                self.byte(8)?;
+                self.control_net(ControlNet::from_net_invert(arg1, invert1))?;
+                self.control_net(ControlNet::from_net_invert(arg2, invert2))?;
+            }
+            CellRepr::Boxed(ref boxed_cell) => self.cell(boxed_cell)?,
+        }
+        self.usize(cell.meta.0)
+    }
+}
+
+pub(crate) struct Decoder<R: Read> {
+    reader: BufReader<R>,
+    /// Offset of the next byte to be read, for error reporting.
+    offset: u64,
+    cell_count: usize,
+    io_count: u32,
+    item_count: usize,
+}
+
+impl<R: Read> Decoder<R> {
+    pub(crate) fn malformed(&self) -> BinaryError {
+        BinaryError::Malformed(self.offset)
+    }
+
+    fn bytes(&mut self, bytes: &mut [u8]) -> Result<(), BinaryError> {
+        self.reader.read_exact(bytes)?;
+        self.offset += bytes.len() as u64;
+        Ok(())
+    }
+
+    pub(crate) fn byte(&mut self) -> Result<u8, BinaryError> {
+        let mut byte = [0];
+        self.bytes(&mut byte)?;
+        Ok(byte[0])
+    }
+
+    pub(crate) fn uint(&mut self) -> Result<u64, BinaryError> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            if shift == 63 && byte > 1 {
+                break;
+            }
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(self.malformed())
+    }
+
+    pub(crate) fn sint(&mut self) -> Result<i64, BinaryError> {
+        let value = self.uint()?;
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32, BinaryError> {
+        let value = self.uint()?;
+        value.try_into().map_err(|_| self.malformed())
+    }
+
+    pub(crate) fn usize(&mut self) -> Result<usize, BinaryError> {
+        let value = self.uint()?;
+        value.try_into().map_err(|_| self.malformed())
+    }
+
+    /// Reads an index into a table with `count` entries.
+    pub(crate) fn index(&mut self, count: usize) -> Result<usize, BinaryError> {
+        let index = self.usize()?;
+        if index < count { Ok(index) } else { Err(self.malformed()) }
+    }
+
+    pub(crate) fn bool(&mut self) -> Result<bool, BinaryError> {
+        match self.byte()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(self.malformed()),
+        }
+    }
+
+    pub(crate) fn string(&mut self) -> Result<String, BinaryError> {
+        let len = self.usize()?;
+        // the length is not trusted to preallocate the string, in case the input is malformed
+        let mut bytes = Vec::new();
+        (&mut self.reader).take(len as u64).read_to_end(&mut bytes)?;
+        if bytes.len() != len {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        let string = String::from_utf8(bytes).map_err(|_| self.malformed())?;
+        self.offset += len as u64;
+        Ok(string)
+    }
+
+    pub(crate) fn seq<T>(
+        &mut self,
+        mut f: impl FnMut(&mut Self) -> Result<T, BinaryError>,
+    ) -> Result<Vec<T>, BinaryError> {
+        let len = self.usize()?;
+        let mut items = Vec::new();
+        for _ in 0..len {
+            items.push(f(self)?);
+        }
+        Ok(items)
+    }
+
+    pub(crate) fn const_value(&mut self) -> Result<Const, BinaryError> {
+        let len = self.usize()?;
+        let mut trits = Vec::new();
+        let mut byte = 0;
+        for index in 0..len {
+            if index % 4 == 0 {
+                byte = self.byte()?;
+            }
+            trits.push(match (byte >> (index % 4 * 2)) & 3 {
+                0 => Trit::Zero,
+                1 => Trit::One,
+                2 => Trit::Undef,
+                _ => return Err(self.malformed()),
+            });
+        }
+        Ok(Const::from(trits))
+    }
+
+    pub(crate) fn param(&mut self) -> Result<ParamValue, BinaryError> {
+        match self.byte()? {
+            0 => Ok(ParamValue::Const(self.const_value()?)),
+            1 => Ok(ParamValue::Int(self.sint()?)),
+            2 => {
+                let mut bytes = [0; 8];
+                self.bytes(&mut bytes)?;
+                Ok(ParamValue::Float(u64::from_le_bytes(bytes)))
+            }
+            3 => Ok(ParamValue::String(self.string()?)),
+            _ => Err(self.malformed()),
+        }
+    }
+
+    fn net(&mut self) -> Result<Net, BinaryError> {
+        let net = Net { index: self.u32()?.wrapping_sub(1) };
+        match net.as_cell_index() {
+            Ok(index) if index >= self.cell_count => Err(self.malformed()),
+            _ => Ok(net),
+        }
+    }
+
+    fn control_net(&mut self) -> Result<ControlNet, BinaryError> {
+        let value = self.uint()?;
+        let index = u32::try_from(value >> 1).map_err(|_| self.malformed())?.wrapping_sub(1);
+        let net = Net { index };
+        match net.as_cell_index() {
+            Ok(index) if index >= self.cell_count => Err(self.malformed()),
+            _ => Ok(ControlNet::from_net_invert(net, value & 1 != 0)),
+        }
+    }
+
+    fn option_control_net(&mut self) -> Result<Option<ControlNet>, BinaryError> {
+        if self.bool()? { Ok(Some(self.control_net()?)) } else { Ok(None) }
+    }
+
+    fn value(&mut self) -> Result<Value, BinaryError> {
+        Ok(Value::from_iter(self.seq(Self::net)?))
+    }
+
+    fn io_value(&mut self) -> Result<IoValue, BinaryError> {
+        let io_nets = self.seq(|decoder| {
+            let io_net = IoNet { index: decoder.u32()?.wrapping_sub(1) };
+            if io_net.is_floating() || io_net.index < decoder.io_count { Ok(io_net) } else { Err(decoder.malformed()) }
+        })?;
+        Ok(IoValue::from_iter(io_nets))
+    }
+
+    fn cell(&mut self, tag: u8) -> Result<Cell, BinaryError> {
+        Ok(match tag {
+            16 => Cell::Buf(self.value()?),
+            17 => Cell::Not(self.value()?),
+            18 => Cell::And(self.value()?, self.value()?),
+            19 => Cell::Or(self.value()?, self.value()?),
+            20 => Cell::Xor(self.value()?, self.value()?),
+            21 => Cell::Mux(self.net()?, self.value()?, self.value()?),
+            22 => Cell::Adc(self.value()?, self.value()?, self.net()?),
+            23 => Cell::Aig(self.control_net()?, self.control_net()?),
+            24 => Cell::Eq(self.value()?, self.value()?),
+            25 => Cell::ULt(self.value()?, self.value()?),
+            26 => Cell::SLt(self.value()?, self.value()?),
+            27 => Cell::Shl(self.value()?, self.value()?, self.u32()?),
+            28 => Cell::UShr(self.value()?, self.value()?, self.u32()?),
+            29 => Cell::SShr(self.value()?, self.value()?, self.u32()?),
+            30 => Cell::XShr(self.value()?, self.value()?, self.u32()?),
+            31 => Cell::Popcnt(self.value()?),
+            32 => Cell::Clz(self.value()?),
+            33 => Cell::Ctz(self.value()?),
+            34 => Cell::Mul(self.value()?, self.value()?),
+            35 => Cell::UDiv(self.value()?, self.value()?),
+            36 => Cell::UMod(self.value()?, self.value()?),
+            37 => Cell::SDivTrunc(self.value()?, self.value()?),
+            38 => Cell::SDivFloor(self.value()?, self.value()?),
+            39 => Cell::SModTrunc(self.value()?, self.value()?),
+            40 => Cell::SModFloor(self.value()?, self.value()?),
+            41 => Cell::Match(MatchCell {
+                value: self.value()?,
+                enable: self.net()?,
+                patterns: self.seq(|decoder| decoder.seq(Self::const_value))?,
+            }),
+            42 => Cell::Assign(AssignCell {
+                value: self.value()?,
+                enable: self.net()?,
+                update: self.value()?,
+                offset: self.usize()?,
+            }),
+            43 => Cell::Dff(FlipFlop {
+                data: self.value()?,
+                clock: self.control_net()?,
+                clear: self.control_net()?,
+                reset: self.control_net()?,
+                enable: self.control_net()?,
+                reset_over_enable: self.bool()?,
+                clear_value: self.const_value()?,
+                reset_value: self.const_value()?,
+                init_value: self.const_value()?,
+            }),
+            44 => Cell::Memory(Memory {
+                depth: self.usize()?,
+                width: self.usize()?,
+                init_value: self.const_value()?,
+                write_ports: self.seq(|decoder| {
+                    Ok(MemoryWritePort {
+                        addr: decoder.value()?,
+                        data: decoder.value()?,
+                        mask: decoder.value()?,
+                        clock: decoder.option_control_net()?,
+                    })
+                })?,
+                read_ports: self.seq(|decoder| {
+                    Ok(MemoryReadPort {
+                        addr: decoder.value()?,
+                        data_len: decoder.usize()?,
+                        flip_flop: if decoder.bool()? {
+                            Some(MemoryReadFlipFlop {
+                                clock: decoder.control_net()?,
+                                clear: decoder.control_net()?,
+                                reset: decoder.control_net()?,
+                                enable: decoder.control_net()?,
+                                reset_over_enable: decoder.bool()?,
+                                clear_value: decoder.const_value()?,
+                                reset_value: decoder.const_value()?,
+                                init_value: decoder.const_value()?,
+                                relations: decoder.seq(|decoder| match decoder.byte()? {
+                                    0 => Ok(MemoryPortRelation::Undefined),
+                                    1 => Ok(MemoryPortRelation::ReadBeforeWrite),
+                                    2 => Ok(MemoryPortRelation::Transparent),
+                                    _ => Err(decoder.malformed()),
+                                })?,
+                            })
+                        } else {
+                            None
+                        },
+                    })
+                })?,
+            }),
+            45 => Cell::IoBuf(IoBuffer { io: self.io_value()?, output: self.value()?, enable: self.control_net()? }),
+            46 => Cell::Target(TargetCell {
+                kind: self.string()?,
+                params: self.seq(Self::param)?,
+                inputs: self.value()?,
+                output_len: self.usize()?,
+                ios: self.io_value()?,
+            }),
+            47 => Cell::Other(Instance {
+                kind: self.string()?,
+                params: BTreeMap::from_iter(self.seq(|decoder| Ok((decoder.string()?, decoder.param()?)))?),
+                inputs: BTreeMap::from_iter(self.seq(|decoder| Ok((decoder.string()?, decoder.value()?)))?),
+                outputs: BTreeMap::from_iter(
+                    self.seq(|decoder| Ok((decoder.string()?, decoder.usize()?..decoder.usize()?)))?,
+                ),
+                ios: BTreeMap::from_iter(self.seq(|decoder| Ok((decoder.string()?, decoder.io_value()?)))?),
+            }),
+            48 => Cell::Input(self.string()?, self.usize()?),
+            49 => Cell::Output(self.string()?, self.value()?),
+            50 => Cell::Name(self.string()?, self.value()?),
+            51 => Cell::Debug(self.string()?, self.value()?),
+            _ => return Err(self.malformed()),
+        })
+    }
+
+    /// Reads a cell, and returns it followed by the skip cells for the rest of its outputs.
+    fn annotated_cells(&mut self, index: usize) -> Result<Vec<AnnotatedCell>, BinaryError> {
+        let repr = match self.byte()? {
+            0 => return Ok(vec![CellRepr::Void.into()]),
+            1 => CellRepr::Buf(self.net()?),
+            2 => CellRepr::Not(self.net()?),
+            3 => CellRepr::And(self.net()?, self.net()?),
+            4 => CellRepr::Or(self.net()?, self.net()?),
+            5 => CellRepr::Xor(self.net()?, self.net()?),
+            6 => CellRepr::Mux(self.net()?, self.net()?, self.net()?),
+            7 => CellRepr::Adc(self.net()?, self.net()?, self.net()?),
+            8 => Cell::Aig(self.control_net()?, self.control_net()?).into(),
+            tag => self.cell(tag)?.into(),
+        };
+        let meta = MetaItemIndex(self.index(self.item_count)?);
+        let output_len = repr.output_len();
+        let mut cells = vec![AnnotatedCell { repr, meta }];
+        let skip = CellRepr::Skip(index.try_into().map_err(|_| self.malformed())?);
+        cells.extend((1..output_len).map(|_| skip.clone().into()));
+        Ok(cells)
+    }
+}
+
+/// Writes `design` to `writer` in the binary format.
+///
+/// The writer is buffered internally. The design must not have any changes that have not been applied yet.
+pub fn write_binary(design: &Design, writer: impl Write) -> io::Result<()> {
+    assert!(!design.is_changed(), "cannot serialize a design with changes that have not been applied");
+    let mut encoder = Encoder { writer: BufWriter::new(writer) };
+    encoder.writer.write_all(&MAGIC)?;
+    encoder.writer.write_all(&VERSION.to_le_bytes())?;
+    match design.target() {
+        None => encoder.bool(false)?,
+        Some(target) => {
+            encoder.bool(true)?;
+            encoder.string(target.name())?;
+            encoder.seq(target.options().iter(), |encoder, (name, value)| {
+                encoder.string(name)?;
+                encoder.string(value)
+            })?;
+        }
+    }
+    encoder.uint(design.next_io().into())?;
+    encoder.seq(design.io_ranges().iter(), |encoder, (name, range)| {
+        encoder.string(name)?;
+        encoder.uint(range.start.into())?;
+        encoder.uint(range.end.into())
+    })?;
+    design.metadata().write_binary(&mut encoder)?;
+    let cells = design.annotated_cells();
+    encoder.usize(cells.len())?;
+    for cell in cells {
+        encoder.annotated_cell(cell)?;
+    }
+    encoder.writer.flush()
+}
+
+/// Reads a design from `reader` in the binary format.
+///
+/// If the design was written with a target, the target is created from the registry with the recorded options, like
+/// for a `target` statement in the text format; otherwise, the design uses `target`. The reader is buffered internally.
+///
+/// The cells are not validated beyond what is necessary to construct a well-formed design; the input is expected to
+/// have been written by [`write_binary`].
+pub fn read_binary(target: Option<Arc<dyn Target>>, reader: impl Read) -> Result<Design, BinaryError> {
+    let mut decoder = Decoder { reader: BufReader::new(reader), offset: 0, cell_count: 0, io_count: 0, item_count: 0 };
+    let mut magic = [0; 8];
+    decoder.bytes(&mut magic)?;
+    if magic != MAGIC {
+        return Err(BinaryError::NotBinary);
+    }
+    let mut version = [0; 4];
+    decoder.bytes(&mut version)?;
+    let version = u32::from_le_bytes(version);
+    if version != VERSION {
+        return Err(BinaryError::UnsupportedVersion(version));
+    }
+    let target = if decoder.bool()? {
+        let name = decoder.string()?;
+        let options = BTreeMap::from_iter(decoder.seq(|decoder| Ok((decoder.string()?, decoder.string()?)))?);
+        Some(create_target(&name, options).map_err(BinaryError::Target)?)
+    } else {
+        target
+    };
+    let next_io = decoder.u32()?;
+    decoder.io_count = next_io;
+    let mut ios = BTreeMap::new();
+    for _ in 0..decoder.usize()? {
+        let (name, start, end) = (decoder.string()?, decoder.u32()?, decoder.u32()?);
+        if start > end || end > next_io || ios.insert(name, start..end).is_some() {
+            return Err(decoder.malformed());
+        }
+    }
+    let metadata = MetadataStore::read_binary(&mut decoder)?;
+    decoder.item_count = metadata.len().1;
+    decoder.cell_count = decoder.usize()?;
+    let mut cells = Vec::new();
+    while cells.len() < decoder.cell_count {
+        cells.extend(decoder.annotated_cells(cells.len())?);
+    }
+    if cells.len() != decoder.cell_count {
+        return Err(decoder.malformed());
+    }
+    Ok(Design::from_parts(target, ios, next_io, cells, metadata))
+}
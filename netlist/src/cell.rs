@@ -123,7 +123,22 @@ pub enum Cell {
     /// See also [general notes above][Cell::Shl].
     XShr(Value, Value, u32),
 
-    // future possibilities: popcnt, count leading/trailing zeros, powers
+    /// Number of bits of `a` that are one.
+    ///
+    /// General notes for all counting cells:
+    /// - output is [`Cell::count_width`] of the input width, i.e. it is just wide enough to hold `a.len()`.
+    /// - if any bit of the input is `X`, all bits of the output are `X`.
+    Popcnt(Value),
+    /// Number of zero bits above the most significant one bit of `a`, or `a.len()` if `a` is zero.
+    ///
+    /// See also [general notes above][Cell::Popcnt].
+    Clz(Value),
+    /// Number of zero bits below the least significant one bit of `a`, or `a.len()` if `a` is zero.
+    ///
+    /// See also [general notes above][Cell::Popcnt].
+    Ctz(Value),
+
+    // future possibilities: powers
     Mul(Value, Value),
     UDiv(Value, Value),
     UMod(Value, Value),
@@ -204,6 +219,8 @@ impl Cell {
                 assert!(!arg1.is_empty());
             }
 
+            Cell::Popcnt(_) | Cell::Clz(_) | Cell::Ctz(_) => (),
+
             Cell::Shl(..) => (),
             Cell::UShr(..) => (),
             Cell::SShr(arg1, _, _) => assert!(!arg1.is_empty()),
@@ -254,7 +271,7 @@ impl Cell {
                         assert_eq!(flip_flop.relations.len(), memory.write_ports.len());
                         for (write_port_index, &relation) in flip_flop.relations.iter().enumerate() {
                             if relation != MemoryPortRelation::Undefined {
-                                assert_eq!(memory.write_ports[write_port_index].clock, flip_flop.clock);
+                                assert_eq!(memory.write_ports[write_port_index].clock, Some(flip_flop.clock));
                             }
                         }
                     }
@@ -419,6 +436,12 @@ impl CellRepr {
 }
 
 impl Cell {
+    /// Returns the width of the output of a counting cell ([`Cell::Popcnt`], [`Cell::Clz`], or [`Cell::Ctz`]) with
+    /// an input that is `input_len` bits wide.
+    pub fn count_width(input_len: usize) -> usize {
+        (usize::BITS - input_len.leading_zeros()) as usize
+    }
+
     pub fn output_len(&self) -> usize {
         match self {
             Cell::Buf(arg) | Cell::Not(arg) => arg.len(),
@@ -438,6 +461,8 @@ impl Cell {
                 arg1.len()
             }
 
+            Cell::Popcnt(arg) | Cell::Clz(arg) | Cell::Ctz(arg) => Cell::count_width(arg.len()),
+
             Cell::Mul(arg1, arg2)
             | Cell::UDiv(arg1, arg2)
             | Cell::UMod(arg1, arg2)
@@ -489,9 +514,14 @@ impl Cell {
     pub fn visit(&self, mut f: impl FnMut(Net)) {
         match self {
             Cell::Input(..) => (),
-            Cell::Buf(arg) | Cell::Not(arg) | Cell::Output(_, arg) | Cell::Name(_, arg) | Cell::Debug(_, arg) => {
-                arg.visit(&mut f)
-            }
+            Cell::Buf(arg)
+            | Cell::Not(arg)
+            | Cell::Popcnt(arg)
+            | Cell::Clz(arg)
+            | Cell::Ctz(arg)
+            | Cell::Output(_, arg)
+            | Cell::Name(_, arg)
+            | Cell::Debug(_, arg) => arg.visit(&mut f),
             Cell::And(arg1, arg2)
             | Cell::Or(arg1, arg2)
             | Cell::Xor(arg1, arg2)
@@ -534,9 +564,14 @@ impl Cell {
     pub fn visit_mut(&mut self, mut f: impl FnMut(&mut Net)) {
         match self {
             Cell::Input(..) => (),
-            Cell::Buf(arg) | Cell::Not(arg) | Cell::Output(_, arg) | Cell::Name(_, arg) | Cell::Debug(_, arg) => {
-                arg.visit_mut(&mut f)
-            }
+            Cell::Buf(arg)
+            | Cell::Not(arg)
+            | Cell::Popcnt(arg)
+            | Cell::Clz(arg)
+            | Cell::Ctz(arg)
+            | Cell::Output(_, arg)
+            | Cell::Name(_, arg)
+            | Cell::Debug(_, arg) => arg.visit_mut(&mut f),
             Cell::And(arg1, arg2)
             | Cell::Or(arg1, arg2)
             | Cell::Xor(arg1, arg2)
@@ -32,9 +32,7 @@ pub struct Memory {
     pub read_ports: Vec<MemoryReadPort>,
 }
 
-/// A synchronous memory write port.
-///
-/// Asynchronous memory write ports are not currently supported.
+/// A memory write port, either synchronous or asynchronous.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct MemoryWritePort {
     /// The write address, selecting which row(s) to write.  The address is always counted
@@ -53,7 +51,12 @@ pub struct MemoryWritePort {
     /// The write mask.  Must have the same width as `data`.  On every active clock edge,
     /// a `1` enables writing to the memory for the given data bit, `0` prevents writing.
     pub mask: Value,
-    pub clock: ControlNet,
+    /// The write clock, or `None` for asynchronous (level-sensitive) write ports.  If this is `None`,
+    /// every memory bit for which the mask is `1` continuously follows the corresponding data bit,
+    /// like a latch, and keeps its value once the mask becomes `0`.
+    ///
+    /// An asynchronous write is immediately visible on asynchronous read ports.
+    pub clock: Option<ControlNet>,
 }
 
 /// A memory read port, either synchronous or asynchronous.
@@ -102,7 +105,8 @@ pub struct MemoryReadFlipFlop {
     /// port with the same index in the `write_ports` vector of the containing memory.
     ///
     /// Only ports sharing the same `clock` net can have defined relations — this vector must contain
-    /// `MemoryPortRelation::Undefined` for every write port where `write_port.clock != read_port.clock`.
+    /// `MemoryPortRelation::Undefined` for every write port where `write_port.clock != Some(read_port.clock)`,
+    /// including every asynchronous write port.
     ///
     /// If a given memory bit is simultanously written by more than one write port while being
     /// read, the read data is undefined (as is the value written to memory), regardless of
@@ -169,14 +173,18 @@ impl MemoryWritePort {
         self.addr.visit(&mut f);
         self.data.visit(&mut f);
         self.mask.visit(&mut f);
-        self.clock.visit(&mut f);
+        if let Some(clock) = self.clock {
+            clock.visit(&mut f);
+        }
     }
 
     pub fn visit_mut(&mut self, mut f: impl FnMut(&mut Net)) {
         self.addr.visit_mut(&mut f);
         self.data.visit_mut(&mut f);
         self.mask.visit_mut(&mut f);
-        self.clock.visit_mut(&mut f);
+        if let Some(ref mut clock) = self.clock {
+            clock.visit_mut(&mut f);
+        }
     }
 }
 
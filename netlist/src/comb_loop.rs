@@ -0,0 +1,189 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Display;
+
+use crate::design::{TopoSortItem, get_deps, get_item_from_net, is_comb_edge, is_splittable};
+use crate::{Cell, CellRef, Design, MetaItem, Net};
+
+/// A combinational loop, i.e. a strongly connected component of the graph of combinational cells.
+///
+/// The graph is the one used by [`Design::topo_sort`]: inputs, IO buffers, flip-flops, and impure target cells
+/// break loops, and each bit of a bitwise cell is considered separately. In addition, a memory only depends on
+/// the inputs that reach its read data without a clock edge: the addresses of asynchronous read ports, and
+/// the inputs of asynchronous write ports.
+#[derive(Clone)]
+pub struct CombLoop<'a> {
+    /// The cells in the loop, in netlist order.
+    pub cells: Vec<CellRef<'a>>,
+}
+
+impl<'a> CombLoop<'a> {
+    /// Returns the names of the nets driven by the cells in the loop, from `name` and `debug` cells and from
+    /// identifier metadata.
+    pub fn names(&self) -> Vec<String> {
+        let Some(first) = self.cells.first() else { return vec![] };
+        let design = first.design();
+        let mut names = vec![];
+        let mut add_name = |name: String| {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        };
+        for cell_ref in design.iter_cells() {
+            let (Cell::Name(name, value) | Cell::Debug(name, value)) = &*cell_ref.get() else { continue };
+            let is_driven_by_loop = value.iter().any(|net| match design.find_cell(net) {
+                Ok((driver, _)) => self.cells.contains(&driver),
+                Err(_) => false,
+            });
+            if is_driven_by_loop {
+                add_name(name.clone());
+            }
+        }
+        for cell_ref in &self.cells {
+            for item in cell_ref.metadata().iter() {
+                if let MetaItem::Ident { name, .. } = item.get() {
+                    add_name(name.get().to_string());
+                }
+            }
+        }
+        names
+    }
+}
+
+impl Display for CombLoop<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "combinational loop")?;
+        let names = self.names();
+        if !names.is_empty() {
+            write!(f, " driving")?;
+            for name in &names {
+                write!(f, " {name:?}")?;
+            }
+        }
+        writeln!(f, ":")?;
+        for &cell_ref in &self.cells {
+            write!(f, "  {}", cell_ref.design().display_cell(cell_ref))?;
+            for item in cell_ref.metadata().iter() {
+                if let MetaItem::Source { file, start, .. } = item.get() {
+                    write!(f, " at {}:{}:{}", &*file.get(), start.line + 1, start.column + 1)?;
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+fn get_comb_deps(item: TopoSortItem) -> Vec<Net> {
+    let TopoSortItem::Cell(cell) = item else { return get_deps(item) };
+    let Cell::Memory(memory) = &*cell.get() else { return get_deps(item) };
+    let mut result = vec![];
+    for write_port in &memory.write_ports {
+        if write_port.clock.is_none() {
+            write_port.addr.visit(|net| result.push(net));
+            write_port.data.visit(|net| result.push(net));
+            write_port.mask.visit(|net| result.push(net));
+        }
+    }
+    for read_port in &memory.read_ports {
+        if read_port.flip_flop.is_none() {
+            read_port.addr.visit(|net| result.push(net));
+        }
+    }
+    result.retain(|net| is_comb_edge(cell.design(), *net));
+    result
+}
+
+impl Design {
+    /// Finds the combinational loops in the design.
+    ///
+    /// [`Design::topo_sort`] and [`Design::iter_cells_topo`] break combinational loops at an arbitrary point; if this
+    /// function returns no loops, the order they return is a topological order.
+    pub fn find_comb_loops(&self) -> Vec<CombLoop<'_>> {
+        struct StackEntry<'a> {
+            item: TopoSortItem<'a>,
+            deps: Vec<Net>,
+        }
+
+        // `CellRef` has interior mutability, so the items are identified by the cell index and the bit
+        fn key(item: TopoSortItem) -> (usize, usize) {
+            match item {
+                TopoSortItem::Cell(cell) => (cell.debug_index(), usize::MAX),
+                TopoSortItem::CellBit(cell, bit) => (cell.debug_index(), bit),
+            }
+        }
+
+        // Tarjan's strongly connected components algorithm
+        let mut indices = BTreeMap::new();
+        let mut low_links = BTreeMap::new();
+        let mut components: Vec<TopoSortItem> = vec![];
+        let mut on_components = BTreeSet::new();
+        let mut self_loops = BTreeSet::new();
+        let mut result = vec![];
+        for cell in self.iter_cells() {
+            let roots = if is_splittable(cell) {
+                Vec::from_iter((0..cell.output_len()).map(|bit| TopoSortItem::CellBit(cell, bit)))
+            } else {
+                vec![TopoSortItem::Cell(cell)]
+            };
+            for root in roots {
+                if indices.contains_key(&key(root)) {
+                    continue;
+                }
+                indices.insert(key(root), indices.len());
+                low_links.insert(key(root), indices.len() - 1);
+                components.push(root);
+                on_components.insert(key(root));
+                let mut stack = vec![StackEntry { item: root, deps: get_comb_deps(root) }];
+                while let Some(top) = stack.last_mut() {
+                    if let Some(net) = top.deps.pop() {
+                        let Some(item) = get_item_from_net(self, net) else { continue };
+                        if item == top.item {
+                            self_loops.insert(key(item));
+                        }
+                        if let Some(&index) = indices.get(&key(item)) {
+                            if on_components.contains(&key(item)) {
+                                let low_link = low_links.get_mut(&key(top.item)).unwrap();
+                                *low_link = (*low_link).min(index);
+                            }
+                            continue;
+                        }
+                        indices.insert(key(item), indices.len());
+                        low_links.insert(key(item), indices.len() - 1);
+                        components.push(item);
+                        on_components.insert(key(item));
+                        stack.push(StackEntry { item, deps: get_comb_deps(item) });
+                    } else {
+                        let item = top.item;
+                        stack.pop();
+                        let low_link = low_links[&key(item)];
+                        if let Some(parent) = stack.last() {
+                            let parent_low_link = low_links.get_mut(&key(parent.item)).unwrap();
+                            *parent_low_link = (*parent_low_link).min(low_link);
+                        }
+                        if low_link != indices[&key(item)] {
+                            continue;
+                        }
+                        let mut component = vec![];
+                        loop {
+                            let member = components.pop().unwrap();
+                            on_components.remove(&key(member));
+                            component.push(member);
+                            if member == item {
+                                break;
+                            }
+                        }
+                        if component.len() > 1 || self_loops.contains(&key(item)) {
+                            let mut cells = Vec::from_iter(component.into_iter().map(|member| match member {
+                                TopoSortItem::Cell(cell) | TopoSortItem::CellBit(cell, _) => cell,
+                            }));
+                            cells.sort();
+                            cells.dedup();
+                            result.push(CombLoop { cells });
+                        }
+                    }
+                }
+            }
+        }
+        result
+    }
+}
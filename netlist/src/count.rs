@@ -0,0 +1,80 @@
+//! Lowering of `popcnt`, `clz`, and `ctz` cells.
+//!
+//! The population count is computed by a tree of adders. The first level of the tree sums the bits of the input
+//! three at a time using full adders (with the third bit as the carry input), and each further level sums pairs of
+//! results from the previous level.
+//!
+//! The trailing zero count is computed by a priority tree: the input is split into a low half whose width is a power
+//! of two and a high half, the counts for both halves are computed recursively, and the count for the high half
+//! (offset by the width of the low half) is selected if the low half is all zeros. The leading zero count is the
+//! trailing zero count of the input with its bits reversed.
+
+use crate::{Cell, Net, Value};
+
+/// Returns the number of bits set in `value`, with a width of [`Cell::count_width`] of the width of `value`.
+pub fn lower_popcnt(add_cell: &dyn Fn(Cell) -> Value, value: &Value) -> Value {
+    // each partial sum is paired with the amount of input bits it counts, which determines its width
+    let chunks = (0..value.len()).step_by(3).map(|index| value.slice(index..value.len().min(index + 3)));
+    let mut sums = Vec::from_iter(chunks.map(|chunk| match chunk.len() {
+        1 => (chunk, 1),
+        2 => (add_cell(Cell::Adc(chunk.slice(..1), chunk.slice(1..), Net::ZERO)), 2),
+        _ => (add_cell(Cell::Adc(chunk.slice(..1), chunk.slice(1..2), chunk[2])), 3),
+    }));
+    while sums.len() > 1 {
+        for pair in std::mem::take(&mut sums).chunks(2) {
+            match pair {
+                [(sum1, count1), (sum2, count2)] => {
+                    let width = sum1.len().max(sum2.len());
+                    let sum = add_cell(Cell::Adc(sum1.zext(width), sum2.zext(width), Net::ZERO));
+                    sums.push((sum.slice(..Cell::count_width(count1 + count2)), count1 + count2));
+                }
+                [sum] => sums.push(sum.clone()),
+                _ => unreachable!(),
+            }
+        }
+    }
+    match sums.pop() {
+        Some((sum, _)) => sum.zext(Cell::count_width(value.len())),
+        None => Value::new(),
+    }
+}
+
+/// Returns whether `value` is all zeros, and the number of trailing zeros in `value`, with a width of
+/// [`Cell::count_width`] of the width of `value`.
+fn lower_ctz_tree(add_cell: &dyn Fn(Cell) -> Value, value: &Value) -> (Net, Value) {
+    if value.len() == 1 {
+        let zero = add_cell(Cell::Not(value.clone()));
+        return (zero[0], zero);
+    }
+    // the low half is the widest power of two strictly narrower than `value`
+    let lo_width: usize = 1 << (usize::BITS - 1 - (value.len() - 1).leading_zeros());
+    let lo_bits = lo_width.trailing_zeros() as usize;
+    let (lo_zero, lo_count) = lower_ctz_tree(add_cell, &value.slice(..lo_width));
+    let (hi_zero, hi_count) = lower_ctz_tree(add_cell, &value.slice(lo_width..));
+    let width = Cell::count_width(value.len());
+    // if the low half is not all zeros, its count is less than `lo_width`
+    let lo_count = lo_count.slice(..lo_bits).zext(width);
+    // otherwise, the count is `lo_width` plus the count for the high half
+    let hi_count = if value.len() == 2 * lo_width {
+        let hi_nonzero = add_cell(Cell::Not(hi_zero.into()));
+        hi_count.slice(..lo_bits).concat(hi_nonzero).concat(hi_zero)
+    } else {
+        hi_count.zext(lo_bits).concat(Net::ONE)
+    };
+    let zero = add_cell(Cell::And(lo_zero.into(), hi_zero.into()));
+    let count = add_cell(Cell::Mux(lo_zero, hi_count, lo_count));
+    (zero[0], count)
+}
+
+/// Returns the number of trailing zeros in `value`, with a width of [`Cell::count_width`] of the width of `value`.
+pub fn lower_ctz(add_cell: &dyn Fn(Cell) -> Value, value: &Value) -> Value {
+    if value.is_empty() {
+        return Value::new();
+    }
+    lower_ctz_tree(add_cell, value).1
+}
+
+/// Returns the number of leading zeros in `value`, with a width of [`Cell::count_width`] of the width of `value`.
+pub fn lower_clz(add_cell: &dyn Fn(Cell) -> Value, value: &Value) -> Value {
+    lower_ctz(add_cell, &Value::from_iter(value.iter().rev()))
+}
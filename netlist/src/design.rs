@@ -13,7 +13,8 @@ use crate::{
     Target, TargetCell, TargetCellPurity, TargetPrototype, Trit, Value,
 };
 use crate::metadata::{MetadataStore, MetaStringIndex, MetaItemIndex};
-use crate::smt::{SmtEngine, SmtBuilder};
+use crate::smt::{SmtEngine, SmtBuilder, is_state};
+use crate::snapshot::Snapshot;
 
 /// Sea of [`Cell`]s.
 #[derive(Debug, Clone)]
@@ -23,6 +24,53 @@ pub struct Design {
     changes: RefCell<ChangeQueue>,
     metadata: RefCell<MetadataStore>,
     target: Option<Arc<dyn Target>>,
+    /// Built on first use by [`Design::uses`], and then maintained by [`Design::apply`].
+    use_index: RefCell<Option<UseIndex>>,
+    /// Number of calls to [`Design::apply`]; used to detect invalid use of [`Checkpoint`]s.
+    generation: u64,
+}
+
+/// Snapshot of the changes of a [`Design`] that have not been applied yet, created by [`Design::checkpoint`].
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    generation: u64,
+    changes: ChangeQueue,
+    metadata_len: (usize, usize),
+}
+
+/// Uses of each net, as the index of the using cell and the offset of the use among the nets visited by
+/// [`CellRef::visit`]. Nets that are not cell outputs are not included.
+type UseIndex = HashMap<Net, Vec<(usize, usize)>>;
+
+fn add_uses(use_index: &mut UseIndex, index: usize, cell: &AnnotatedCell) {
+    if matches!(cell.repr, CellRepr::Skip(_) | CellRepr::Void) {
+        return;
+    }
+    let mut offset = 0;
+    cell.visit(|net| {
+        if net.is_cell() {
+            use_index.entry(net).or_default().push((index, offset));
+        }
+        offset += 1;
+    });
+}
+
+fn remove_uses(use_index: &mut UseIndex, index: usize, cell: &AnnotatedCell) {
+    if matches!(cell.repr, CellRepr::Skip(_) | CellRepr::Void) {
+        return;
+    }
+    let mut offset = 0;
+    cell.visit(|net| {
+        let this_use = (index, offset);
+        offset += 1;
+        let Some(uses) = use_index.get_mut(&net) else { return };
+        if let Some(position) = uses.iter().position(|&net_use| net_use == this_use) {
+            uses.swap_remove(position);
+        }
+        if uses.is_empty() {
+            use_index.remove(&net);
+        }
+    });
 }
 
 #[derive(Debug, Clone, Default)]
@@ -32,6 +80,8 @@ struct ChangeQueue {
     added_cells: Vec<AnnotatedCell>,
     cell_cache: HashMap<AnnotatedCell, Value>,
     cell_metadata: MetaItemIndex,
+    /// Metadata items that the active `WithMetadataGuard`s will restore when dropped.
+    restore_metadata: Vec<MetaItemIndex>,
     appended_metadata: BTreeMap<usize, Vec<MetaItemIndex>>,
     replaced_cells: BTreeMap<usize, AnnotatedCell>,
     unalived_cells: BTreeSet<usize>,
@@ -50,6 +100,8 @@ impl Design {
             changes: RefCell::new(ChangeQueue::default()),
             metadata: RefCell::new(MetadataStore::new()),
             target,
+            use_index: RefCell::new(None),
+            generation: 0,
         }
     }
 
@@ -124,6 +176,8 @@ impl Design {
     pub fn use_metadata(&self, item: MetaItemRef) -> WithMetadataGuard<'_> {
         let mut changes = self.changes.borrow_mut();
         let guard = WithMetadataGuard { design: self, restore: changes.cell_metadata };
+        let restore = changes.cell_metadata;
+        changes.restore_metadata.push(restore);
         changes.cell_metadata = item.index();
         guard
     }
@@ -323,6 +377,18 @@ impl Design {
             Ok(CellRepr::Skip(index)) => locate_cell(Net::from_cell_index(*index as usize)),
             result => result,
         };
+        // whether `net` is held by a flip-flop of a `dff` cell or of a synchronous memory read port
+        let is_state_net = |net: Net| {
+            let Ok(index) = net.as_cell_index() else { return false };
+            let (start, cell) = match locate_cell(net) {
+                Ok(CellRepr::Skip(start)) => (*start as usize, locate_cell(Net::from_cell_index(*start as usize))),
+                result => (index, result),
+            };
+            match cell {
+                Ok(CellRepr::Void) | Err(_) => false,
+                Ok(cell) => is_state(&cell.get(), index - start),
+            }
+        };
 
         let mut smt = SmtBuilder::new(self, engine);
         for (index, cell) in self.cells.iter().chain(changes.added_cells.iter()).enumerate() {
@@ -339,12 +405,8 @@ impl Design {
                 if matches!(cell, CellRepr::Void) {
                     smt.replace_void_net(net, new_net)?;
                     continue;
-                } else if matches!(&*cell.get(), Cell::Dff(_)) {
-                    if let Ok(new_cell) = get_cell(new_net) {
-                        if matches!(&*new_cell.get(), Cell::Dff(_)) {
-                            smt.replace_dff_net(net, new_net)?;
-                        }
-                    }
+                } else if is_state_net(net) && is_state_net(new_net) {
+                    smt.replace_dff_net(net, new_net)?;
                 }
             }
             smt.replace_net(net, new_net)?;
@@ -387,10 +449,26 @@ impl Design {
 
         let mut changes = std::mem::take(self.changes.get_mut());
         self.changes.get_mut().next_io = changes.next_io;
+        self.generation += 1;
 
         let mut did_change = !changes.added_ios.is_empty() || !changes.added_cells.is_empty();
+        let is_target_cell = |cell: &AnnotatedCell| {
+            !matches!(cell.repr, CellRepr::Skip(_) | CellRepr::Void) && matches!(&*cell.get(), Cell::Target(_))
+        };
+        let validate_design = !changes.added_ios.is_empty()
+            || changes.added_cells.iter().chain(changes.replaced_cells.values()).any(is_target_cell);
         self.ios.extend(changes.added_ios);
+        let first_added_index = self.cells.len();
         self.cells.extend(changes.added_cells);
+        let mut use_index = self.use_index.get_mut().take();
+        if let Some(use_index) = &mut use_index {
+            for index in first_added_index..self.cells.len() {
+                add_uses(use_index, index, &self.cells[index]);
+            }
+            for &index in changes.unalived_cells.iter().chain(changes.replaced_cells.keys()) {
+                remove_uses(use_index, index, &self.cells[index]);
+            }
+        }
         for cell_index in changes.unalived_cells {
             let output_len = self.cells[cell_index].output_len().max(1);
             for index in cell_index..cell_index + output_len {
@@ -401,6 +479,9 @@ impl Design {
         for (index, new_cell) in changes.replaced_cells {
             assert_eq!(self.cells[index].output_len(), new_cell.output_len());
             self.cells[index] = new_cell;
+            if let Some(use_index) = &mut use_index {
+                add_uses(use_index, index, &self.cells[index]);
+            }
             // CellRef::replace() ensures the new cell is different.
             did_change = true;
         }
@@ -411,7 +492,7 @@ impl Design {
         }
         changes.cell_cache.clear();
         if !changes.replaced_nets.is_empty() {
-            for cell in self.cells.iter_mut().filter(|cell| !matches!(cell.repr, CellRepr::Skip(_) | CellRepr::Void)) {
+            let mut replace_nets = |cell: &mut AnnotatedCell| {
                 cell.repr.visit_mut(|net| {
                     while let Some(new_net) = changes.replaced_nets.get(net) {
                         if *net != *new_net {
@@ -419,13 +500,260 @@ impl Design {
                             did_change = true;
                         }
                     }
-                });
+                })
+            };
+            match &mut use_index {
+                Some(use_index) => {
+                    // only the cells using the replaced nets need to be updated
+                    let mut users: BTreeSet<usize> = BTreeSet::new();
+                    for net in changes.replaced_nets.keys() {
+                        users.extend(use_index.get(net).into_iter().flatten().map(|&(index, _)| index));
+                    }
+                    for index in users {
+                        replace_nets(&mut self.cells[index]);
+                    }
+                    for &net in changes.replaced_nets.keys() {
+                        let Some(uses) = use_index.remove(&net) else { continue };
+                        let mut new_net = net;
+                        while let Some(&next_net) = changes.replaced_nets.get(&new_net) {
+                            new_net = next_net;
+                        }
+                        if new_net.is_cell() {
+                            use_index.entry(new_net).or_default().extend(uses);
+                        }
+                    }
+                }
+                None => {
+                    for cell in
+                        self.cells.iter_mut().filter(|cell| !matches!(cell.repr, CellRepr::Skip(_) | CellRepr::Void))
+                    {
+                        replace_nets(cell);
+                    }
+                }
             }
             changes.replaced_nets.clear();
         }
+        *self.use_index.get_mut() = use_index;
+        if validate_design && let Some(target) = self.target.clone() {
+            target.validate_design(self);
+        }
         did_change
     }
 
+    /// Records the changes that have not been applied yet, so that the changes made after this call can be
+    /// discarded by [`Design::rollback`].
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            generation: self.generation,
+            changes: self.changes.borrow().clone(),
+            metadata_len: self.metadata.borrow().len(),
+        }
+    }
+
+    /// Discards the changes made after `checkpoint` was created, including the metadata strings and items added
+    /// since then. References to the discarded cells and metadata must not be used afterwards.
+    ///
+    /// Panics if the changes have been applied since `checkpoint` was created, or if a [`WithMetadataGuard`] that
+    /// uses or restores a metadata item added since then is still active.
+    pub fn rollback(&self, checkpoint: Checkpoint) {
+        assert_eq!(checkpoint.generation, self.generation, "cannot roll back to a checkpoint before the last apply");
+        let mut changes = self.changes.borrow_mut();
+        assert!(
+            changes.restore_metadata.iter().chain([&changes.cell_metadata]).all(|index| index.0 < checkpoint.metadata_len.1),
+            "cannot roll back while metadata added after the checkpoint is in use"
+        );
+        // the metadata used for new cells is managed by `WithMetadataGuard`
+        let cell_metadata = changes.cell_metadata;
+        let restore_metadata = std::mem::take(&mut changes.restore_metadata);
+        *changes = checkpoint.changes;
+        changes.cell_metadata = cell_metadata;
+        changes.restore_metadata = restore_metadata;
+        self.metadata.borrow_mut().truncate(checkpoint.metadata_len);
+    }
+
+    /// Returns the cells added since the last [`Design::apply`] that have not been unalived, and their outputs.
+    pub fn pending_cells(&self) -> Vec<(Cell, Value)> {
+        let changes = self.changes.borrow();
+        let mut result = vec![];
+        for (offset, cell) in changes.added_cells.iter().enumerate() {
+            let index = self.cells.len() + offset;
+            if matches!(cell.repr, CellRepr::Skip(_) | CellRepr::Void) || changes.unalived_cells.contains(&index) {
+                continue;
+            }
+            result.push((cell.get().into_owned(), Value::from_cell_range(index, cell.output_len())));
+        }
+        result
+    }
+
+    /// Returns the nets replaced since the last [`Design::apply`], and the nets they will be replaced with.
+    pub fn pending_net_replacements(&self) -> Vec<(Net, Net)> {
+        let changes = self.changes.borrow();
+        Vec::from_iter(changes.replaced_nets.keys().map(|&net| (net, self.map_net_new(net))))
+    }
+
+    /// Returns the change in the number of cells that applying the pending changes and then compacting the design
+    /// would cause. Cells that are already unused are not taken into account.
+    pub fn pending_cell_delta(&self) -> isize {
+        let changes = self.changes.borrow();
+        let final_net = |mut net: Net| {
+            while let Some(&new_net) = changes.replaced_nets.get(&net) {
+                net = new_net;
+            }
+            net
+        };
+        // the index of the cell driving `net`, either an existing or an added one
+        let driver = |net: Net| -> Option<usize> {
+            let index = net.as_cell_index().ok()?;
+            let repr = match index.checked_sub(self.cells.len()) {
+                None => &self.cells[index].repr,
+                Some(offset) => &changes.added_cells[offset].repr,
+            };
+            match repr {
+                CellRepr::Void => None,
+                CellRepr::Skip(start) => Some(*start as usize),
+                _ => Some(index),
+            }
+        };
+        // the cell with the index `index` after the changes are applied, if it is not unalived
+        let final_cell = |index: usize| -> Option<Cow<Cell>> {
+            if changes.unalived_cells.contains(&index) {
+                return None;
+            }
+            if let Some(new_cell) = changes.replaced_cells.get(&index) {
+                return Some(new_cell.get());
+            }
+            let cell = match index.checked_sub(self.cells.len()) {
+                None => &self.cells[index],
+                Some(offset) => &changes.added_cells[offset],
+            };
+            if matches!(cell.repr, CellRepr::Skip(_) | CellRepr::Void) { None } else { Some(cell.get()) }
+        };
+        let output_len = |index: usize| match index.checked_sub(self.cells.len()) {
+            None => self.cells[index].output_len(),
+            Some(offset) => changes.added_cells[offset].output_len(),
+        };
+
+        let is_debug = |cell: &Cell| matches!(cell, Cell::Debug(..));
+        // uses of existing nets by existing cells, not counting `debug` cells
+        let existing_use_count = |net: Net| -> isize {
+            if !net.as_cell_index().is_ok_and(|index| index < self.cells.len()) {
+                return 0;
+            }
+            self.uses(net).iter().filter(|(cell_ref, _)| !is_debug(&cell_ref.get())).count() as isize
+        };
+
+        // uses removed from nets (before replacement) and added to nets (after replacement) by the changes
+        let mut removed_uses: HashMap<Net, isize> = HashMap::new();
+        let mut added_uses: HashMap<Net, isize> = HashMap::new();
+        let mut candidates = BTreeSet::new();
+        let mut added_count = 0;
+        let mut removed_count = 0;
+        for index in changes.unalived_cells.iter().chain(changes.replaced_cells.keys()).copied() {
+            let Some(cell) = self.cells.get(index) else { continue };
+            if matches!(cell.repr, CellRepr::Skip(_) | CellRepr::Void) {
+                continue;
+            }
+            if changes.unalived_cells.contains(&index) {
+                removed_count += 1;
+            }
+            if !is_debug(&cell.get()) {
+                cell.visit(|net| {
+                    *removed_uses.entry(net).or_default() += 1;
+                    candidates.extend(driver(net));
+                });
+            }
+        }
+        for index in
+            changes.replaced_cells.keys().copied().chain(self.cells.len()..self.cells.len() + changes.added_cells.len())
+        {
+            let Some(cell) = final_cell(index) else { continue };
+            if index >= self.cells.len() {
+                added_count += 1;
+                candidates.insert(index);
+            }
+            if !is_debug(&cell) {
+                cell.visit(|net| *added_uses.entry(final_net(net)).or_default() += 1);
+            }
+        }
+        let mut replaced_by: HashMap<Net, Vec<Net>> = HashMap::new();
+        for &net in changes.replaced_nets.keys() {
+            replaced_by.entry(final_net(net)).or_default().push(net);
+            candidates.extend(driver(net));
+        }
+
+        // the number of uses of each net after the changes are applied, not counting `debug` cells
+        let initial_use_count = |net: Net| -> isize {
+            if changes.replaced_nets.contains_key(&net) {
+                return 0;
+            }
+            let mut count = added_uses.get(&net).copied().unwrap_or(0);
+            for net in std::iter::once(net).chain(replaced_by.get(&net).into_iter().flatten().copied()) {
+                count += existing_use_count(net) - removed_uses.get(&net).copied().unwrap_or(0);
+            }
+            count
+        };
+        let mut use_counts: HashMap<Net, isize> = HashMap::new();
+
+        // remove the cells that become unused, and then the cells that are only used by them
+        let mut removed = BTreeSet::new();
+        while let Some(index) = candidates.pop_first() {
+            let Some(cell) = final_cell(index) else { continue };
+            if removed.contains(&index) || is_debug(&cell) || cell.has_effects(self) {
+                continue;
+            }
+            let output = Value::from_cell_range(index, output_len(index));
+            if output.iter().any(|net| *use_counts.entry(net).or_insert_with(|| initial_use_count(net)) > 0) {
+                continue;
+            }
+            if index < self.cells.len() {
+                if output.iter().all(|net| existing_use_count(net) == 0) {
+                    // the cell is already unused
+                    continue;
+                }
+                removed_count += 1;
+            } else {
+                added_count -= 1;
+            }
+            removed.insert(index);
+            cell.visit(|net| {
+                let net = final_net(net);
+                *use_counts.entry(net).or_insert_with(|| initial_use_count(net)) -= 1;
+                candidates.extend(driver(net));
+            });
+        }
+        added_count - removed_count
+    }
+
+    fn build_use_index(&self) -> UseIndex {
+        let mut use_index = UseIndex::new();
+        for (index, cell) in self.cells.iter().enumerate() {
+            add_uses(&mut use_index, index, cell);
+        }
+        use_index
+    }
+
+    /// Returns the cells using `net`, once for each use, in netlist order. Each cell is returned together with
+    /// the offset of the use among the nets visited by [`CellRef::visit`].
+    ///
+    /// The first call builds an index of the uses of every net, which is then kept up to date by [`Design::apply`]
+    /// and [`Design::compact`]. Changes that have not been applied yet are not taken into account.
+    pub fn uses(&self, net: impl Into<Net>) -> Vec<(CellRef<'_>, usize)> {
+        let net = net.into();
+        let mut use_index = self.use_index.borrow_mut();
+        let use_index = use_index.get_or_insert_with(|| self.build_use_index());
+        let mut uses = use_index.get(&net).cloned().unwrap_or_default();
+        uses.sort();
+        Vec::from_iter(uses.into_iter().map(|(index, offset)| (CellRef { design: self, index }, offset)))
+    }
+
+    /// Returns the number of uses of `net`. See [`Design::uses`].
+    pub fn use_count(&self, net: impl Into<Net>) -> usize {
+        let net = net.into();
+        let mut use_index = self.use_index.borrow_mut();
+        let use_index = use_index.get_or_insert_with(|| self.build_use_index());
+        use_index.get(&net).map(Vec::len).unwrap_or(0)
+    }
+
     pub fn target(&self) -> Option<Arc<dyn Target>> {
         self.target.as_ref().map(|target| target.clone())
     }
@@ -437,12 +765,50 @@ impl Design {
             .prototype(&target_cell.kind)
             .expect("target prototype not defined")
     }
+
+    /// Returns an immutable copy of the design that can be shared between threads.
+    ///
+    /// Like [`Design::iter_cells`], the snapshot does not include the changes that have not been applied yet.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot::new(self.ios.clone(), self.cells.clone(), self.metadata.borrow().clone(), self.target.clone())
+    }
+
+    /// Returns the cells of the design, including void and skip cells. Used for serialization.
+    pub(crate) fn annotated_cells(&self) -> &[AnnotatedCell] {
+        &self.cells
+    }
+
+    /// Returns the ranges of IO nets of the design by name. Used for serialization.
+    pub(crate) fn io_ranges(&self) -> &BTreeMap<String, Range<u32>> {
+        &self.ios
+    }
+
+    /// Returns the index of the first IO net that has not been allocated yet. Used for serialization.
+    pub(crate) fn next_io(&self) -> u32 {
+        self.changes.borrow().next_io
+    }
+
+    /// Creates a design from deserialized parts. The parts must be consistent with each other.
+    pub(crate) fn from_parts(
+        target: Option<Arc<dyn Target>>,
+        ios: BTreeMap<String, Range<u32>>,
+        next_io: u32,
+        cells: Vec<AnnotatedCell>,
+        metadata: MetadataStore,
+    ) -> Design {
+        let mut design = Design::with_target(target);
+        design.ios = ios;
+        design.changes.get_mut().next_io = next_io;
+        design.cells = cells;
+        design.metadata = RefCell::new(metadata);
+        design
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-struct AnnotatedCell {
-    repr: CellRepr,
-    meta: MetaItemIndex,
+pub(crate) struct AnnotatedCell {
+    pub(crate) repr: CellRepr,
+    pub(crate) meta: MetaItemIndex,
 }
 
 impl Deref for AnnotatedCell {
@@ -466,7 +832,11 @@ pub struct WithMetadataGuard<'a> {
 
 impl Drop for WithMetadataGuard<'_> {
     fn drop(&mut self) {
-        self.design.changes.borrow_mut().cell_metadata = self.restore;
+        let mut changes = self.design.changes.borrow_mut();
+        changes.cell_metadata = self.restore;
+        if let Some(position) = changes.restore_metadata.iter().rposition(|&index| index == self.restore) {
+            changes.restore_metadata.remove(position);
+        }
     }
 }
 
@@ -647,6 +1017,13 @@ impl Design {
         add_xshr(arg1: impl Into<Value>, arg2: impl Into<Value>, stride: u32) -> Value :
             XShr(arg1.into(), arg2.into(), stride);
 
+        add_popcnt(arg: impl Into<Value>) -> Value :
+            Popcnt(arg.into());
+        add_clz(arg: impl Into<Value>) -> Value :
+            Clz(arg.into());
+        add_ctz(arg: impl Into<Value>) -> Value :
+            Ctz(arg.into());
+
         add_mul(arg1: impl Into<Value>, arg2: impl Into<Value>) -> Value :
             Mul(arg1.into(), arg2.into());
         add_udiv(arg1: impl Into<Value>, arg2: impl Into<Value>) -> Value :
@@ -715,66 +1092,63 @@ pub enum TopoSortItem<'a> {
     CellBit(CellRef<'a>, usize),
 }
 
-impl Design {
-    pub fn topo_sort(&self) -> Vec<TopoSortItem<'_>> {
-        fn is_splittable(cell: CellRef) -> bool {
-            matches!(
-                &*cell.get(),
-                Cell::Buf(..) | Cell::Not(..) | Cell::And(..) | Cell::Or(..) | Cell::Xor(..) | Cell::Mux(..)
-            )
-        }
-
-        fn is_comb_edge(design: &Design, net: Net) -> bool {
-            if let Ok((cell, _)) = design.find_cell(net) {
-                match &*cell.get() {
-                    Cell::Input(..) | Cell::IoBuf(..) | Cell::Dff(..) | Cell::Other(..) => false,
-                    Cell::Target(target_cell) => design.target_prototype(target_cell).purity == TargetCellPurity::Pure,
-                    _ => true,
-                }
-            } else {
-                false
-            }
-        }
+pub(crate) fn is_splittable(cell: CellRef) -> bool {
+    matches!(&*cell.get(), Cell::Buf(..) | Cell::Not(..) | Cell::And(..) | Cell::Or(..) | Cell::Xor(..) | Cell::Mux(..))
+}
 
-        fn get_deps(item: TopoSortItem) -> Vec<Net> {
-            let mut result = vec![];
-            match item {
-                TopoSortItem::Cell(cell) => {
-                    cell.visit(|net| {
-                        result.push(net);
-                    });
-                }
-                TopoSortItem::CellBit(cell, bit) => match &*cell.get() {
-                    Cell::Buf(val) | Cell::Not(val) => {
-                        result.push(val[bit]);
-                    }
-                    Cell::And(val1, val2) | Cell::Or(val1, val2) | Cell::Xor(val1, val2) => {
-                        result.push(val1[bit]);
-                        result.push(val2[bit]);
-                    }
-                    Cell::Mux(net, val1, val2) => {
-                        result.push(*net);
-                        result.push(val1[bit]);
-                        result.push(val2[bit]);
-                    }
-                    _ => unreachable!(),
-                },
-            }
-            let cell = match item {
-                TopoSortItem::Cell(cell) => cell,
-                TopoSortItem::CellBit(cell, _) => cell,
-            };
-            result.retain(|net| is_comb_edge(cell.design(), *net));
-            result
+pub(crate) fn is_comb_edge(design: &Design, net: Net) -> bool {
+    if let Ok((cell, _)) = design.find_cell(net) {
+        match &*cell.get() {
+            Cell::Input(..) | Cell::IoBuf(..) | Cell::Dff(..) | Cell::Other(..) => false,
+            Cell::Target(target_cell) => design.target_prototype(target_cell).purity == TargetCellPurity::Pure,
+            _ => true,
         }
+    } else {
+        false
+    }
+}
 
-        fn get_item_from_net(design: &Design, net: Net) -> Option<TopoSortItem<'_>> {
-            let Ok((cell, bit)) = design.find_cell(net) else {
-                return None;
-            };
-            if is_splittable(cell) { Some(TopoSortItem::CellBit(cell, bit)) } else { Some(TopoSortItem::Cell(cell)) }
+pub(crate) fn get_deps(item: TopoSortItem) -> Vec<Net> {
+    let mut result = vec![];
+    match item {
+        TopoSortItem::Cell(cell) => {
+            cell.visit(|net| {
+                result.push(net);
+            });
         }
+        TopoSortItem::CellBit(cell, bit) => match &*cell.get() {
+            Cell::Buf(val) | Cell::Not(val) => {
+                result.push(val[bit]);
+            }
+            Cell::And(val1, val2) | Cell::Or(val1, val2) | Cell::Xor(val1, val2) => {
+                result.push(val1[bit]);
+                result.push(val2[bit]);
+            }
+            Cell::Mux(net, val1, val2) => {
+                result.push(*net);
+                result.push(val1[bit]);
+                result.push(val2[bit]);
+            }
+            _ => unreachable!(),
+        },
+    }
+    let cell = match item {
+        TopoSortItem::Cell(cell) => cell,
+        TopoSortItem::CellBit(cell, _) => cell,
+    };
+    result.retain(|net| is_comb_edge(cell.design(), *net));
+    result
+}
 
+pub(crate) fn get_item_from_net(design: &Design, net: Net) -> Option<TopoSortItem<'_>> {
+    let Ok((cell, bit)) = design.find_cell(net) else {
+        return None;
+    };
+    if is_splittable(cell) { Some(TopoSortItem::CellBit(cell, bit)) } else { Some(TopoSortItem::Cell(cell)) }
+}
+
+impl Design {
+    pub fn topo_sort(&self) -> Vec<TopoSortItem<'_>> {
         struct StackEntry<'a> {
             item: TopoSortItem<'a>,
             deps: Vec<Net>,
@@ -947,6 +1321,9 @@ impl Design {
             self.cells.push(AnnotatedCell { repr: CellRepr::Boxed(Box::new(Cell::Debug(name, value))), meta });
         }
 
+        if self.use_index.get_mut().is_some() {
+            *self.use_index.get_mut() = Some(self.build_use_index());
+        }
         did_change
     }
 
@@ -981,6 +1358,9 @@ impl Design {
                 Cell::UShr(arg, _, _) => wide("ushr", arg.len()),
                 Cell::SShr(arg, _, _) => wide("sshr", arg.len()),
                 Cell::XShr(arg, _, _) => wide("xshr", arg.len()),
+                Cell::Popcnt(arg) => wide("popcnt", arg.len()),
+                Cell::Clz(arg) => wide("clz", arg.len()),
+                Cell::Ctz(arg) => wide("ctz", arg.len()),
                 Cell::Mul(arg, _) => wide("mul", arg.len()),
                 Cell::UDiv(arg, _) => wide("udiv", arg.len()),
                 Cell::UMod(arg, _) => wide("umod", arg.len()),
@@ -1088,6 +1468,21 @@ impl Display for Design {
                     Design::write_string(f, &name.get())?;
                     write!(f, " {value}")?;
                 }
+                MetaItem::EnumEncoding { encoding, members } => {
+                    write!(f, "enum ")?;
+                    if let Some(encoding) = encoding {
+                        write!(f, "encoding=")?;
+                        Design::write_string(f, &encoding.to_string())?;
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{{")?;
+                    for (name, value) in members {
+                        write!(f, " ")?;
+                        Design::write_string(f, &name.get())?;
+                        write!(f, "={value}")?;
+                    }
+                    write!(f, " }}")?;
+                }
             }
             writeln!(f)?;
         }
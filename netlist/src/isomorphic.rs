@@ -135,9 +135,11 @@ pub fn isomorphic(lft: &Design, rgt: &Design) -> Result<(), NotIsomorphic> {
             visited.insert((net_l, net_r));
         }
         match (&*cell_l.get(), &*cell_r.get()) {
-            (Cell::Buf(val_l), Cell::Buf(val_r)) | (Cell::Not(val_l), Cell::Not(val_r)) => {
-                queue_vals(&mut queue, val_l, val_r)?
-            }
+            (Cell::Buf(val_l), Cell::Buf(val_r))
+            | (Cell::Not(val_l), Cell::Not(val_r))
+            | (Cell::Popcnt(val_l), Cell::Popcnt(val_r))
+            | (Cell::Clz(val_l), Cell::Clz(val_r))
+            | (Cell::Ctz(val_l), Cell::Ctz(val_r)) => queue_vals(&mut queue, val_l, val_r)?,
             (Cell::And(arg1_l, arg2_l), Cell::And(arg1_r, arg2_r))
             | (Cell::Or(arg1_l, arg2_l), Cell::Or(arg1_r, arg2_r))
             | (Cell::Xor(arg1_l, arg2_l), Cell::Xor(arg1_r, arg2_r))
@@ -226,9 +228,15 @@ pub fn isomorphic(lft: &Design, rgt: &Design) -> Result<(), NotIsomorphic> {
                     queue_vals(&mut queue, &write_port_l.addr, &write_port_r.addr)?;
                     queue_vals(&mut queue, &write_port_l.data, &write_port_r.data)?;
                     queue_vals(&mut queue, &write_port_l.mask, &write_port_r.mask)?;
-                    queue.insert((write_port_l.clock.net(), write_port_r.clock.net()));
-                    if write_port_l.clock.is_positive() != write_port_r.clock.is_positive() {
-                        return Err(NotIsomorphic::NetMismatch(net_l, net_r));
+                    match (write_port_l.clock, write_port_r.clock) {
+                        (None, None) => (),
+                        (Some(clock_l), Some(clock_r)) => {
+                            queue.insert((clock_l.net(), clock_r.net()));
+                            if clock_l.is_positive() != clock_r.is_positive() {
+                                return Err(NotIsomorphic::NetMismatch(net_l, net_r));
+                            }
+                        }
+                        _ => return Err(NotIsomorphic::NetMismatch(net_l, net_r)),
                     }
                 }
                 for (read_port_l, read_port_r) in memory_l.read_ports.iter().zip(memory_r.read_ports.iter()) {
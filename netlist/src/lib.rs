@@ -14,7 +14,12 @@ mod metadata;
 mod design;
 mod print;
 mod parse;
+mod binary;
 mod rewrite;
+mod comb_loop;
+mod count;
+mod snapshot;
+mod pass;
 mod target;
 
 mod isomorphic;
@@ -28,12 +33,19 @@ pub use cell::{
     Cell, MatchCell, AssignCell, FlipFlop, IoBuffer, Memory, MemoryWritePort, MemoryReadPort, MemoryReadFlipFlop,
     MemoryPortRelation, TargetCell, Instance,
 };
-pub use metadata::{MetaStringRef, MetaItem, MetaItemRef, SourcePosition};
-pub use design::{Design, CellRef, WithMetadataGuard};
+pub use metadata::{
+    MetaStringRef, MetaItem, MetaItemRef, SnapshotMetaItem, SnapshotMetaItemRef, SourcePosition, EnumEncoding,
+};
+pub use design::{Design, CellRef, Checkpoint, WithMetadataGuard};
+pub use comb_loop::CombLoop;
+pub use count::{lower_popcnt, lower_clz, lower_ctz};
+pub use snapshot::{Snapshot, SnapshotCellRef, SnapshotCellIter};
+pub use pass::{PassManager, PassRecord, PassError};
 pub use parse::{parse, ParseError};
+pub use binary::{read_binary, write_binary, BinaryError};
 pub use target::{
     Target, TargetParamKind, TargetParam, TargetInput, TargetOutput, TargetIo, TargetCellPurity, TargetPrototype,
-    TargetCellImportError, TargetImportError, register_target, create_target,
+    TargetCellImportError, TargetImportError, MulArchitecture, register_target, create_target,
 };
 pub use rewrite::{Rewriter, RewriteNetSource, RewriteRuleset, RewriteResult};
 
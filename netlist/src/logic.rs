@@ -6,7 +6,7 @@ use std::{
     str::FromStr,
 };
 
-use crate::Net;
+use crate::{Cell, Net};
 
 /// An extended binary value.
 ///
@@ -399,6 +399,23 @@ impl Const {
             res.slice(..self.len())
         }
     }
+
+    fn count(&self, count: impl FnOnce(&Self) -> usize) -> Const {
+        let width = Cell::count_width(self.len());
+        if self.has_undef() { Const::undef(width) } else { Const::from_uint(count(self) as u128, width) }
+    }
+
+    pub fn popcnt(&self) -> Const {
+        self.count(|value| value.iter().filter(|&trit| trit == Trit::One).count())
+    }
+
+    pub fn clz(&self) -> Const {
+        self.count(|value| value.iter().rev().take_while(|&trit| trit == Trit::Zero).count())
+    }
+
+    pub fn ctz(&self) -> Const {
+        self.count(|value| value.iter().take_while(|&trit| trit == Trit::Zero).count())
+    }
 }
 
 impl Debug for Const {
@@ -623,4 +640,31 @@ mod test {
             assert_eq!(Const::lit(a).mul(Const::lit(b)), Const::lit(y));
         }
     }
+
+    #[test]
+    fn test_popcnt() {
+        for (a, y) in
+            [("", ""), ("0", "0"), ("1", "1"), ("0000", "000"), ("1011", "011"), ("1111", "100"), ("1X11", "XXX")]
+        {
+            assert_eq!(Const::lit(a).popcnt(), Const::lit(y));
+        }
+    }
+
+    #[test]
+    fn test_clz() {
+        for (a, y) in
+            [("", ""), ("0", "1"), ("1", "0"), ("0000", "100"), ("0011", "010"), ("1000", "000"), ("0X11", "XXX")]
+        {
+            assert_eq!(Const::lit(a).clz(), Const::lit(y));
+        }
+    }
+
+    #[test]
+    fn test_ctz() {
+        for (a, y) in
+            [("", ""), ("0", "1"), ("1", "0"), ("0000", "100"), ("1100", "010"), ("0001", "000"), ("11X0", "XXX")]
+        {
+            assert_eq!(Const::lit(a).ctz(), Const::lit(y));
+        }
+    }
 }
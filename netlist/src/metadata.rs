@@ -1,19 +1,24 @@
 //! Metadata storage.
 //!
 //! This module is intentionally tightly coupled to `crate::design`, and intentionally avoids exporting any APIs that
-//! expose indices. The exported APIs are based on [`MetaStringRef`] and [`MetaItemRef`], which parallel [`CellRef`].
+//! expose indices. The exported APIs are based on [`MetaStringRef`] and [`MetaItemRef`], which parallel [`CellRef`],
+//! and on [`SnapshotMetaItemRef`], which parallels [`SnapshotCellRef`].
 //!
 //! [`CellRef`]: crate::CellRef
+//! [`SnapshotCellRef`]: crate::SnapshotCellRef
 use std::{
     borrow::Cow,
     cell::Ref,
     collections::BTreeSet,
     fmt::{Debug, Display},
     hash::Hash,
+    io::{self, Read, Write},
+    str::FromStr,
 };
 use indexmap::IndexSet;
 
-use crate::{Design, ParamValue};
+use crate::binary::{Decoder, Encoder};
+use crate::{BinaryError, Const, Design, ParamValue};
 
 /// Position within a source file.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -82,16 +87,34 @@ pub enum MetaItem<'a> {
         /// Value.
         value: ParamValue,
     },
+    /// Encoding of a value that holds one of several named states, such as the state of a state machine.
+    EnumEncoding {
+        /// Requested encoding.  If `None`, the encoding is chosen by the synthesis flow.
+        encoding: Option<EnumEncoding>,
+        /// Names and values of the enumeration members, as written in the source code.
+        /// Names must not be empty.  Values must not contain undefined bits, and must all have the same width.
+        members: Vec<(MetaStringRef<'a>, Const)>,
+    },
     // to be added:
     // - MemoryKind
-    // - EnumEncoding
+}
+
+/// Encoding of a set of states.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EnumEncoding {
+    /// States are numbered sequentially and use the minimal number of bits.
+    Binary,
+    /// Like [`EnumEncoding::Binary`], but the codes of sequentially numbered states differ in one bit.
+    Gray,
+    /// Each state has a dedicated bit.
+    OneHot,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
-pub(crate) struct MetaStringIndex(usize);
+pub(crate) struct MetaStringIndex(pub(crate) usize);
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
-pub(crate) struct MetaItemIndex(usize);
+pub(crate) struct MetaItemIndex(pub(crate) usize);
 
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
 enum MetaItemRepr {
@@ -121,6 +144,10 @@ enum MetaItemRepr {
         name: MetaStringIndex,
         value: ParamValue,
     },
+    EnumEncoding {
+        encoding: Option<EnumEncoding>,
+        members: Vec<(MetaStringIndex, Const)>,
+    },
 }
 
 #[derive(Clone, Debug)]
@@ -146,6 +173,29 @@ pub struct MetaItemIterator<'a> {
     offset: usize,
 }
 
+/// Metadata item of a [`Snapshot`].
+///
+/// This is the same as [`MetaItem`], except that strings and items are borrowed from the snapshot.
+///
+/// [`Snapshot`]: crate::Snapshot
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SnapshotMetaItem<'a> {
+    None,
+    Set(Vec<SnapshotMetaItemRef<'a>>),
+    Source { file: &'a str, start: SourcePosition, end: SourcePosition },
+    NamedScope { name: &'a str, source: SnapshotMetaItemRef<'a>, parent: SnapshotMetaItemRef<'a> },
+    IndexedScope { index: i32, source: SnapshotMetaItemRef<'a>, parent: SnapshotMetaItemRef<'a> },
+    Ident { name: &'a str, scope: SnapshotMetaItemRef<'a> },
+    Attr { name: &'a str, value: &'a ParamValue },
+    EnumEncoding { encoding: Option<EnumEncoding>, members: Vec<(&'a str, &'a Const)> },
+}
+
+#[derive(Clone, Copy)]
+pub struct SnapshotMetaItemRef<'a> {
+    store: &'a MetadataStore,
+    index: MetaItemIndex,
+}
+
 impl MetaItem<'_> {
     pub fn validate(&self) {
         match self {
@@ -198,6 +248,17 @@ impl MetaItem<'_> {
             MetaItem::Attr { name, value: _ } => {
                 assert!(!name.get().is_empty(), "MetaItem::Attr must have a name");
             }
+            MetaItem::EnumEncoding { encoding: _, members } => {
+                for (name, value) in members {
+                    assert!(!name.get().is_empty(), "MetaItem::EnumEncoding members must have a name");
+                    assert!(!value.has_undef(), "MetaItem::EnumEncoding member values must be fully defined");
+                    assert_eq!(
+                        value.len(),
+                        members[0].1.len(),
+                        "MetaItem::EnumEncoding member values must have the same width"
+                    );
+                }
+            }
         }
     }
 }
@@ -244,6 +305,10 @@ impl MetadataStore {
             }
             MetaItem::Ident { name, scope } => MetaItemRepr::Ident { scope: scope.index, name: name.index },
             MetaItem::Attr { name, value } => MetaItemRepr::Attr { name: name.index, value: value.clone() },
+            MetaItem::EnumEncoding { encoding, members } => MetaItemRepr::EnumEncoding {
+                encoding: *encoding,
+                members: members.iter().map(|(name, value)| (name.index, value.clone())).collect(),
+            },
         };
         MetaItemIndex(self.items.insert_full(repr).0)
     }
@@ -255,6 +320,129 @@ impl MetadataStore {
     pub(crate) fn iter_items<'a>(&self, design: &'a Design) -> impl Iterator<Item = MetaItemRef<'a>> + use<'a> {
         (0..self.items.len()).map(|index| MetaItemRef { design, index: MetaItemIndex(index) })
     }
+
+    pub(crate) fn ref_snapshot_item(&self, index: MetaItemIndex) -> SnapshotMetaItemRef<'_> {
+        SnapshotMetaItemRef { store: self, index }
+    }
+
+    /// Returns the number of strings and items in the store.
+    pub(crate) fn len(&self) -> (usize, usize) {
+        (self.strings.len(), self.items.len())
+    }
+
+    /// Removes the strings and items added after the store had the length `len`.
+    pub(crate) fn truncate(&mut self, len: (usize, usize)) {
+        self.strings.truncate(len.0);
+        self.items.truncate(len.1);
+    }
+
+    /// Writes the string and item tables in the binary format. The empty string and [`MetaItem::None`] are always
+    /// present at index 0 and are not written.
+    pub(crate) fn write_binary(&self, encoder: &mut Encoder<impl Write>) -> io::Result<()> {
+        encoder.seq(self.strings.iter().skip(1), |encoder, string| encoder.string(string))?;
+        encoder.seq(self.items.iter().skip(1), |encoder, item| match item {
+            MetaItemRepr::None => unreachable!("metadata item table contains more than one MetaItem::None"),
+            MetaItemRepr::Set(items) => {
+                encoder.byte(1)?;
+                encoder.seq(items.iter(), |encoder, item| encoder.usize(item.0))
+            }
+            MetaItemRepr::Source { file, start, end } => {
+                encoder.byte(2)?;
+                encoder.usize(file.0)?;
+                for position in [start, end] {
+                    encoder.uint(position.line.into())?;
+                    encoder.uint(position.column.into())?;
+                }
+                Ok(())
+            }
+            MetaItemRepr::NamedScope { name, source, parent } => {
+                encoder.byte(3)?;
+                encoder.usize(name.0)?;
+                encoder.usize(source.0)?;
+                encoder.usize(parent.0)
+            }
+            MetaItemRepr::IndexedScope { index, source, parent } => {
+                encoder.byte(4)?;
+                encoder.sint((*index).into())?;
+                encoder.usize(source.0)?;
+                encoder.usize(parent.0)
+            }
+            MetaItemRepr::Ident { name, scope } => {
+                encoder.byte(5)?;
+                encoder.usize(name.0)?;
+                encoder.usize(scope.0)
+            }
+            MetaItemRepr::Attr { name, value } => {
+                encoder.byte(6)?;
+                encoder.usize(name.0)?;
+                encoder.param(value)
+            }
+            MetaItemRepr::EnumEncoding { encoding, members } => {
+                encoder.byte(7)?;
+                encoder.byte(match encoding {
+                    None => 0,
+                    Some(EnumEncoding::Binary) => 1,
+                    Some(EnumEncoding::Gray) => 2,
+                    Some(EnumEncoding::OneHot) => 3,
+                })?;
+                encoder.seq(members.iter(), |encoder, (name, value)| {
+                    encoder.usize(name.0)?;
+                    encoder.const_value(value)
+                })
+            }
+        })
+    }
+
+    /// Reads the string and item tables written by [`MetadataStore::write_binary`].
+    pub(crate) fn read_binary(decoder: &mut Decoder<impl Read>) -> Result<Self, BinaryError> {
+        let mut store = MetadataStore::new();
+        for string in decoder.seq(|decoder| decoder.string())? {
+            if !store.strings.insert(string) {
+                return Err(decoder.malformed());
+            }
+        }
+        let string_count = store.strings.len();
+        for _ in 0..decoder.usize()? {
+            // items may only refer to the items before them
+            let item_count = store.items.len();
+            let string = |decoder: &mut Decoder<_>| decoder.index(string_count).map(MetaStringIndex);
+            let item = |decoder: &mut Decoder<_>| decoder.index(item_count).map(MetaItemIndex);
+            let position = |decoder: &mut Decoder<_>| -> Result<_, BinaryError> {
+                Ok(SourcePosition { line: decoder.u32()?, column: decoder.u32()? })
+            };
+            let repr = match decoder.byte()? {
+                1 => MetaItemRepr::Set(decoder.seq(item)?),
+                2 => {
+                    MetaItemRepr::Source { file: string(decoder)?, start: position(decoder)?, end: position(decoder)? }
+                }
+                3 => {
+                    MetaItemRepr::NamedScope { name: string(decoder)?, source: item(decoder)?, parent: item(decoder)? }
+                }
+                4 => MetaItemRepr::IndexedScope {
+                    index: decoder.sint()?.try_into().map_err(|_| decoder.malformed())?,
+                    source: item(decoder)?,
+                    parent: item(decoder)?,
+                },
+                5 => MetaItemRepr::Ident { name: string(decoder)?, scope: item(decoder)? },
+                6 => MetaItemRepr::Attr { name: string(decoder)?, value: decoder.param()? },
+                7 => MetaItemRepr::EnumEncoding {
+                    encoding: match decoder.byte()? {
+                        0 => None,
+                        1 => Some(EnumEncoding::Binary),
+                        2 => Some(EnumEncoding::Gray),
+                        3 => Some(EnumEncoding::OneHot),
+                        _ => return Err(decoder.malformed()),
+                    },
+                    members: decoder.seq(|decoder| Ok((string(decoder)?, decoder.const_value()?)))?,
+                },
+                _ => return Err(decoder.malformed()),
+            };
+            if !store.items.insert(repr) {
+                return Err(decoder.malformed());
+            }
+        }
+        Ok(store)
+    }
 }
 
 impl<'a> MetaStringRef<'a> {
@@ -315,6 +503,13 @@ impl<'a> MetaItemRef<'a> {
             MetaItemRepr::Attr { name, value } => {
                 MetaItem::Attr { name: MetaStringRef { index: *name, design }, value: value.clone() }
             }
+            MetaItemRepr::EnumEncoding { encoding, members } => MetaItem::EnumEncoding {
+                encoding: *encoding,
+                members: members
+                    .iter()
+                    .map(|(name, value)| (MetaStringRef { index: *name, design }, value.clone()))
+                    .collect(),
+            },
         }
     }
 
@@ -351,6 +546,63 @@ impl<'a> MetaItemRef<'a> {
     }
 }
 
+impl<'a> SnapshotMetaItemRef<'a> {
+    pub fn is_none(&self) -> bool {
+        self.index == MetaItemIndex::NONE
+    }
+
+    fn get_repr(&self) -> &'a MetaItemRepr {
+        self.store.items.get_index(self.index.0).expect("invalid metadata item reference")
+    }
+
+    fn get_string(&self, index: MetaStringIndex) -> &'a str {
+        self.store.strings.get_index(index.0).expect("invalid metadata string reference").as_str()
+    }
+
+    pub fn get(&self) -> SnapshotMetaItem<'a> {
+        let store = self.store;
+        match self.get_repr() {
+            MetaItemRepr::None => SnapshotMetaItem::None,
+            MetaItemRepr::Set(items) => {
+                SnapshotMetaItem::Set(items.iter().map(|&index| SnapshotMetaItemRef { store, index }).collect())
+            }
+            MetaItemRepr::Source { file, start, end } => {
+                SnapshotMetaItem::Source { file: self.get_string(*file), start: *start, end: *end }
+            }
+            MetaItemRepr::NamedScope { name, source, parent } => SnapshotMetaItem::NamedScope {
+                name: self.get_string(*name),
+                source: SnapshotMetaItemRef { store, index: *source },
+                parent: SnapshotMetaItemRef { store, index: *parent },
+            },
+            MetaItemRepr::IndexedScope { index, source, parent } => SnapshotMetaItem::IndexedScope {
+                index: *index,
+                source: SnapshotMetaItemRef { store, index: *source },
+                parent: SnapshotMetaItemRef { store, index: *parent },
+            },
+            MetaItemRepr::Ident { name, scope } => SnapshotMetaItem::Ident {
+                name: self.get_string(*name),
+                scope: SnapshotMetaItemRef { store, index: *scope },
+            },
+            MetaItemRepr::Attr { name, value } => SnapshotMetaItem::Attr { name: self.get_string(*name), value },
+            MetaItemRepr::EnumEncoding { encoding, members } => SnapshotMetaItem::EnumEncoding {
+                encoding: *encoding,
+                members: members.iter().map(|(name, value)| (self.get_string(*name), value)).collect(),
+            },
+        }
+    }
+
+    /// Iterates over the items in a [`SnapshotMetaItem::Set`], or over the item itself if it is not a set.
+    pub fn iter(&self) -> impl Iterator<Item = SnapshotMetaItemRef<'a>> + use<'a> {
+        let store = self.store;
+        let indices = match self.get_repr() {
+            MetaItemRepr::None => vec![],
+            MetaItemRepr::Set(items) => items.clone(),
+            _ => vec![self.index],
+        };
+        indices.into_iter().map(move |index| SnapshotMetaItemRef { store, index })
+    }
+}
+
 impl<'a> Iterator for MetaItemIterator<'a> {
     type Item = MetaItemRef<'a>;
 
@@ -371,6 +623,29 @@ impl<'a> Iterator for MetaItemIterator<'a> {
     }
 }
 
+impl Display for EnumEncoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EnumEncoding::Binary => write!(f, "binary"),
+            EnumEncoding::Gray => write!(f, "gray"),
+            EnumEncoding::OneHot => write!(f, "one-hot"),
+        }
+    }
+}
+
+impl FromStr for EnumEncoding {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "binary" => Ok(EnumEncoding::Binary),
+            "gray" => Ok(EnumEncoding::Gray),
+            "one-hot" => Ok(EnumEncoding::OneHot),
+            _ => Err(()),
+        }
+    }
+}
+
 impl Display for MetaItemIndex {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let index = self.0;
@@ -448,3 +723,17 @@ impl Hash for MetaItemRef<'_> {
         self.index.hash(state);
     }
 }
+
+impl Debug for SnapshotMetaItemRef<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.get())
+    }
+}
+
+impl PartialEq<SnapshotMetaItemRef<'_>> for SnapshotMetaItemRef<'_> {
+    fn eq(&self, other: &SnapshotMetaItemRef<'_>) -> bool {
+        std::ptr::eq(self.store, other.store) && self.index == other.index
+    }
+}
+
+impl Eq for SnapshotMetaItemRef<'_> {}
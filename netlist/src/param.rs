@@ -39,6 +39,12 @@ impl From<i64> for ParamValue {
     }
 }
 
+impl From<f64> for ParamValue {
+    fn from(value: f64) -> Self {
+        Self::Float(value.to_bits())
+    }
+}
+
 impl From<String> for ParamValue {
     fn from(value: String) -> Self {
         Self::String(value)
@@ -56,7 +62,7 @@ impl Display for ParamValue {
         match self {
             ParamValue::Const(value) => write!(f, "{value}"),
             ParamValue::Int(value) => write!(f, "#{value}"),
-            ParamValue::Float(_value) => unimplemented!("float parameter"),
+            ParamValue::Float(value) => write!(f, "#{:?}", f64::from_bits(*value)),
             ParamValue::String(value) => Design::write_string(f, &value),
         }
     }
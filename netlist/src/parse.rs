@@ -3,8 +3,8 @@ use std::{collections::BTreeMap, fmt::Display, ops::Range, str::FromStr, sync::A
 use yap::{one_of, types::WithContext, IntoTokens, TokenLocation, Tokens};
 
 use crate::{
-    create_target, AssignCell, Cell, Const, ControlNet, Design, FlipFlop, Instance, IoBuffer, IoNet, IoValue,
-    MatchCell, Memory, MemoryPortRelation, MemoryReadFlipFlop, MemoryReadPort, MemoryWritePort, MetaItem, Net,
+    create_target, AssignCell, Cell, Const, ControlNet, Design, EnumEncoding, FlipFlop, Instance, IoBuffer, IoNet,
+    IoValue, MatchCell, Memory, MemoryPortRelation, MemoryReadFlipFlop, MemoryReadPort, MemoryWritePort, MetaItem, Net,
     ParamValue, Target, TargetCell, Value,
 };
 use crate::metadata::{MetaStringIndex, MetaItemIndex, SourcePosition};
@@ -132,6 +132,16 @@ fn parse_integer<T: FromStr>(t: &mut WithContext<impl Tokens<Item = char>, Conte
     parse_decimal(t)
 }
 
+fn parse_float(t: &mut WithContext<impl Tokens<Item = char>, Context>) -> Option<f64> {
+    parse_symbol(t, '#')?;
+    let repr = t.take_while(|c| c.is_ascii_alphanumeric() || matches!(*c, '-' | '+' | '.')).collect::<String>();
+    // integers are written with the same prefix; floats always have a decimal point or an exponent
+    if !repr.contains(['.', 'e', 'E']) && !repr.contains("inf") && !repr.contains("NaN") {
+        return None;
+    }
+    repr.parse().ok()
+}
+
 fn parse_string_char(t: &mut WithContext<impl Tokens<Item = char>, Context>) -> Option<u8> {
     match t.next() {
         Some('"' | '\\') => None,
@@ -335,6 +345,7 @@ fn parse_value_concat(t: &mut WithContext<impl Tokens<Item = char>, Context>) ->
 fn parse_param_value(t: &mut WithContext<impl Tokens<Item = char>, Context>) -> Option<ParamValue> {
     one_of!(t;
         parse_const(t).map(ParamValue::Const),
+        parse_float(t).map(ParamValue::from),
         parse_integer(t).map(ParamValue::Int),
         parse_string(t).map(ParamValue::String)
     )
@@ -476,6 +487,37 @@ fn parse_metadata_attr(t: &mut WithContext<impl Tokens<Item = char>, Context>) -
     Some(ctx.design.add_metadata_item(&MetaItem::Attr { name, value }).index())
 }
 
+fn parse_metadata_enum(t: &mut WithContext<impl Tokens<Item = char>, Context>) -> Option<MetaItemIndex> {
+    parse_keyword_expect(t, "enum")?;
+    parse_blank(t);
+    let encoding = t.optional(|t| {
+        parse_keyword_eq_expect(t, "encoding")?;
+        let encoding = parse_string(t)?;
+        parse_blank(t);
+        Some(encoding)
+    });
+    let encoding = match encoding {
+        Some(encoding) => Some(EnumEncoding::from_str(&encoding).ok()?),
+        None => None,
+    };
+    let mut members = Vec::new();
+    parse_symbol(t, '{')?;
+    parse_blank(t);
+    while let Some(member) = t.optional(|t| {
+        let name = parse_metadata_string(t)?;
+        parse_symbol(t, '=')?;
+        let value = parse_const(t)?;
+        parse_blank(t);
+        Some((name, value))
+    }) {
+        members.push(member);
+    }
+    parse_symbol(t, '}')?;
+    let ctx = t.context_mut();
+    let members = members.into_iter().map(|(name, value)| (ctx.design.ref_metadata_string(name), value)).collect();
+    Some(ctx.design.add_metadata_item(&MetaItem::EnumEncoding { encoding, members }).index())
+}
+
 fn parse_metadata(t: &mut WithContext<impl Tokens<Item = char>, Context>) -> Option<()> {
     let index = parse_metadata_index(t)?;
     parse_blank(t);
@@ -487,6 +529,7 @@ fn parse_metadata(t: &mut WithContext<impl Tokens<Item = char>, Context>) -> Opt
         parse_metadata_scope(t),
         parse_metadata_ident(t),
         parse_metadata_attr(t),
+        parse_metadata_enum(t),
     )?;
     parse_blank(t);
     parse_newline(t)?;
@@ -693,6 +736,9 @@ fn parse_cell(t: &mut WithContext<impl Tokens<Item = char>, Context>) -> Option<
             "ushr" => Cell::UShr(parse_value_arg(t)?, parse_value_arg(t)?, parse_int_arg(t)? as u32),
             "sshr" => Cell::SShr(parse_value_arg(t)?, parse_value_arg(t)?, parse_int_arg(t)? as u32),
             "xshr" => Cell::XShr(parse_value_arg(t)?, parse_value_arg(t)?, parse_int_arg(t)? as u32),
+            "popcnt" => Cell::Popcnt(parse_value_arg(t)?),
+            "clz" => Cell::Clz(parse_value_arg(t)?),
+            "ctz" => Cell::Ctz(parse_value_arg(t)?),
             "mul" => Cell::Mul(parse_value_arg(t)?, parse_value_arg(t)?),
             "udiv" => Cell::UDiv(parse_value_arg(t)?, parse_value_arg(t)?),
             "umod" => Cell::UMod(parse_value_arg(t)?, parse_value_arg(t)?),
@@ -847,7 +893,7 @@ fn parse_cell(t: &mut WithContext<impl Tokens<Item = char>, Context>) -> Option<
                 parse_value_arg(t)
             })
             .unwrap_or_else(|| Value::ones(data.len()));
-        let clock = parse_control_arg(t, "clk")?;
+        let clock = t.optional(|t| parse_control_arg(t, "clk"));
         Some(MemoryWritePort { addr, data, mask, clock })
     }
 
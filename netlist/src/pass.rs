@@ -0,0 +1,139 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::Display;
+use std::time::{Duration, Instant};
+
+use crate::Design;
+
+type Pass<'a> = Box<dyn Fn(&mut Design) + 'a>;
+
+/// Check run after each pass, with the design before and after the pass.
+type PassCheck<'a> = Box<dyn Fn(&Design, &Design) -> Result<(), String> + 'a>;
+
+/// Registry of named passes, which runs pipelines of them and records how long each pass took and how it changed
+/// the design.
+///
+/// Generic passes are registered by `prjunnamed_generic::register_passes`, and target specific passes are registered
+/// by [`Target::register_passes`].
+///
+/// [`Target::register_passes`]: crate::Target::register_passes
+pub struct PassManager<'a> {
+    passes: BTreeMap<String, Pass<'a>>,
+    check: Option<PassCheck<'a>>,
+    records: Vec<PassRecord>,
+}
+
+/// Result of running a single pass.
+#[derive(Debug, Clone)]
+pub struct PassRecord {
+    pub name: String,
+    /// Wall time taken by the pass.
+    pub duration: Duration,
+    /// Change in the count of each class of cells, as returned by [`Design::statistics`]. Classes whose count did not
+    /// change are not included.
+    pub statistics: BTreeMap<String, isize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PassError {
+    UnknownPass(String),
+    CheckFailed { pass: String, message: String },
+}
+
+impl<'a> PassManager<'a> {
+    pub fn new() -> Self {
+        PassManager { passes: BTreeMap::new(), check: None, records: vec![] }
+    }
+
+    /// Registers a pass named `name`.
+    ///
+    /// Panics if a pass with the same name is already registered.
+    pub fn register(&mut self, name: impl Into<String>, pass: impl Fn(&mut Design) + 'a) {
+        let name = name.into();
+        assert!(!self.passes.contains_key(&name), "pass {name:?} is already registered");
+        self.passes.insert(name, Box::new(pass));
+    }
+
+    pub fn is_registered(&self, name: &str) -> bool {
+        self.passes.contains_key(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.passes.keys().map(String::as_str)
+    }
+
+    /// Sets a check that is run after each pass with the design before and after the pass, e.g. a check that the pass
+    /// preserved the ports of the design. If the check returns an error, the pipeline is stopped.
+    ///
+    /// Each individual change is verified with [`Design::verify`] if the `verify` feature is enabled.
+    pub fn set_check(&mut self, check: impl Fn(&Design, &Design) -> Result<(), String> + 'a) {
+        self.check = Some(Box::new(check));
+    }
+
+    /// Runs the passes named in `pipeline` in order. If any of the names are unknown, no passes are run.
+    pub fn run(&mut self, design: &mut Design, pipeline: &[impl AsRef<str>]) -> Result<(), PassError> {
+        for name in pipeline {
+            if !self.passes.contains_key(name.as_ref()) {
+                return Err(PassError::UnknownPass(name.as_ref().to_owned()));
+            }
+        }
+        for name in pipeline {
+            let name = name.as_ref();
+            if cfg!(feature = "trace") {
+                eprintln!(">pass {name}");
+            }
+            let before = self.check.as_ref().map(|_| design.clone());
+            let statistics_before = design.statistics();
+            let start = Instant::now();
+            (self.passes[name])(design);
+            let duration = start.elapsed();
+            let mut statistics = BTreeMap::new();
+            for (class, amount) in design.statistics() {
+                statistics.insert(class, amount as isize);
+            }
+            for (class, amount) in statistics_before {
+                *statistics.entry(class).or_default() -= amount as isize;
+            }
+            statistics.retain(|_, delta| *delta != 0);
+            self.records.push(PassRecord { name: name.to_owned(), duration, statistics });
+            if let (Some(check), Some(before)) = (&self.check, before)
+                && let Err(message) = check(&before, design)
+            {
+                return Err(PassError::CheckFailed { pass: name.to_owned(), message });
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the results of all passes run so far, in order.
+    pub fn records(&self) -> &[PassRecord] {
+        &self.records
+    }
+}
+
+impl Default for PassManager<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Display for PassRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:>10.3} ms {}", self.duration.as_secs_f64() * 1000.0, self.name)?;
+        for (class, delta) in &self.statistics {
+            write!(f, " {delta:+} {class}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for PassError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PassError::UnknownPass(name) => write!(f, "unknown pass {name:?}"),
+            PassError::CheckFailed { pass, message } => write!(f, "check failed after pass {pass:?}: {message}"),
+        }
+    }
+}
+
+impl Error for PassError {}
@@ -340,6 +340,10 @@ impl Design {
             Cell::SShr(arg1, arg2, stride) => write_shift(f, "sshr", arg1, arg2, *stride)?,
             Cell::XShr(arg1, arg2, stride) => write_shift(f, "xshr", arg1, arg2, *stride)?,
 
+            Cell::Popcnt(arg) => write_common(f, "popcnt", &[arg])?,
+            Cell::Clz(arg) => write_common(f, "clz", &[arg])?,
+            Cell::Ctz(arg) => write_common(f, "ctz", &[arg])?,
+
             Cell::Mul(arg1, arg2) => write_common(f, "mul", &[arg1, arg2])?,
             Cell::UDiv(arg1, arg2) => write_common(f, "udiv", &[arg1, arg2])?,
             Cell::UMod(arg1, arg2) => write_common(f, "umod", &[arg1, arg2])?,
@@ -452,7 +456,9 @@ impl Design {
                         write!(f, " mask=")?;
                         self.write_value(f, &write_port.mask)?;
                     }
-                    write_control(f, " clk", write_port.clock)?;
+                    if let Some(clock) = write_port.clock {
+                        write_control(f, " clk", clock)?;
+                    }
                     write!(f, "{newline}")?;
                 }
                 let mut port_offset = 0;
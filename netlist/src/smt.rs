@@ -1,6 +1,9 @@
 use std::{borrow::Cow, cell::RefCell, collections::BTreeMap};
 
-use crate::{AssignCell, Cell, Const, ControlNet, Design, MatchCell, Net, Trit, Value};
+use crate::{
+    AssignCell, Cell, Const, ControlNet, Design, FlipFlop, MatchCell, Memory, MemoryPortRelation, MemoryReadPort,
+    MemoryWritePort, Net, Trit, Value,
+};
 
 #[cfg(feature = "easy-smt")]
 pub mod easy_smt;
@@ -69,6 +72,24 @@ pub trait SmtEngine {
     fn get_bitvec(&self, term: &Self::BitVec) -> Result<Const, Self::Error>;
 }
 
+/// Returns whether the output bit at `offset` of `cell` is held by a flip-flop, i.e. whether its users see the value
+/// from before the current step.
+pub(crate) fn is_state(cell: &Cell, offset: usize) -> bool {
+    match cell {
+        Cell::Dff(_) => true,
+        Cell::Memory(memory) => (0..memory.read_ports.len()).any(|port_index| {
+            memory.read_ports[port_index].flip_flop.is_some()
+                && memory.read_port_output_slice(port_index).contains(&offset)
+        }),
+        _ => false,
+    }
+}
+
+/// Returns whether the port row `port_row` can be selected by an address as wide as `addr`.
+fn is_addressable(addr: &Value, port_row: usize) -> bool {
+    addr.len() >= usize::BITS as usize || port_row >> addr.len() == 0
+}
+
 impl<SMT: SmtEngine> Clone for SmtTritVec<SMT> {
     fn clone(&self) -> Self {
         Self { x: self.x.clone(), y: self.y.clone() }
@@ -285,6 +306,110 @@ impl<'a, SMT: SmtEngine> SmtBuilder<'a, SMT> {
         Ok(SmtTritVec { y: bv_y_s0, x: bv_x_s0 })
     }
 
+    fn tv_bitwise_mux(
+        &mut self,
+        tv_s: SmtTritVec<SMT>,
+        tv_a: SmtTritVec<SMT>,
+        tv_b: SmtTritVec<SMT>,
+        width: usize,
+    ) -> Result<SmtTritVec<SMT>, SMT::Error> {
+        let tv_s = self.tv_bind(tv_s, width)?;
+        let tv_a = self.tv_and(tv_s.clone(), tv_a, width)?;
+        let tv_b = self.tv_and(self.tv_not(tv_s), tv_b, width)?;
+        self.tv_or(tv_a, tv_b, width)
+    }
+
+    /// Returns whether the memory address `addr` selects the port row `port_row`.
+    fn tv_addr_match(&mut self, addr: &Value, port_row: usize) -> Result<SmtTritVec<SMT>, SMT::Error> {
+        if addr.is_empty() {
+            return Ok(self.tv_lit(Trit::from(port_row == 0)));
+        }
+        let port_row = Const::from_uint(port_row as u128, addr.len());
+        self.cell(&Value::undef(1), &Cell::Eq(addr.clone(), port_row.into()))
+    }
+
+    /// Updates `tv_rows` with the rows written by `port`. If `undef` is true, the written bits become undefined
+    /// instead of taking the value of the write data.
+    fn tv_memory_write(
+        &mut self,
+        memory: &Memory,
+        port: &MemoryWritePort,
+        tv_rows: &mut [SmtTritVec<SMT>],
+        undef: bool,
+    ) -> Result<(), SMT::Error> {
+        let wide = port.data.len() / memory.width;
+        for port_row in 0..memory.depth / wide {
+            if !is_addressable(&port.addr, port_row) {
+                break;
+            }
+            let mut tv_select = self.tv_addr_match(&port.addr, port_row)?;
+            if let Some(clock) = port.clock {
+                let tv_active_edge = self.clock_net(clock)?;
+                tv_select = self.tv_and(tv_select, tv_active_edge, 1)?;
+            }
+            for index in 0..wide {
+                let range = index * memory.width..(index + 1) * memory.width;
+                let tv_enable = self.tv_and(
+                    self.value(&port.mask.slice(range.clone()))?,
+                    self.tv_concat(vec![tv_select.clone(); memory.width]),
+                    memory.width,
+                )?;
+                let tv_data =
+                    if undef { self.tv_lit(Const::undef(memory.width)) } else { self.value(&port.data.slice(range))? };
+                let tv_row = &mut tv_rows[port_row * wide + index];
+                *tv_row = self.tv_bitwise_mux(tv_enable, tv_data, tv_row.clone(), memory.width)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the data read by `port` from `tv_rows`, ignoring the flip-flop of synchronous read ports.
+    fn tv_memory_read(
+        &mut self,
+        memory: &Memory,
+        port: &MemoryReadPort,
+        tv_rows: &[SmtTritVec<SMT>],
+    ) -> Result<SmtTritVec<SMT>, SMT::Error> {
+        let wide = port.data_len / memory.width;
+        let mut tv_data = self.tv_lit(Const::undef(port.data_len));
+        for port_row in 0..memory.depth / wide {
+            if !is_addressable(&port.addr, port_row) {
+                break;
+            }
+            let tv_select = self.tv_addr_match(&port.addr, port_row)?;
+            let tv_row = self.tv_concat(tv_rows[port_row * wide..(port_row + 1) * wide].to_vec());
+            tv_data = self.tv_mux(tv_select, tv_row, tv_data, port.data_len)?;
+        }
+        Ok(tv_data)
+    }
+
+    /// Returns the next value of the flip-flop `flip_flop` driving `output`, whose data is `data`. The data nets of
+    /// `flip_flop` are not used.
+    fn tv_flip_flop(
+        &mut self,
+        output: &Value,
+        flip_flop: &FlipFlop,
+        mut data: SmtTritVec<SMT>,
+    ) -> Result<SmtTritVec<SMT>, SMT::Error> {
+        let clear = self.control_net(flip_flop.clear)?;
+        let reset = self.control_net(flip_flop.reset)?;
+        let enable = self.control_net(flip_flop.enable)?;
+        if flip_flop.reset_over_enable {
+            data = self.tv_mux(enable, data, self.past_value(output)?, output.len())?;
+            data = self.tv_mux(reset, self.tv_lit(&flip_flop.reset_value), data, output.len())?;
+        } else {
+            data = self.tv_mux(reset, self.tv_lit(&flip_flop.reset_value), data, output.len())?;
+            data = self.tv_mux(enable, data, self.past_value(output)?, output.len())?;
+        }
+        let active_edge = self.clock_net(flip_flop.clock)?;
+        let value = self.tv_mux(active_edge, data, self.past_value(output)?, output.len())?;
+        if flip_flop.has_clear() {
+            self.tv_mux(clear, self.tv_lit(&flip_flop.clear_value), value, output.len())
+        } else {
+            Ok(value)
+        }
+    }
+
     fn curr_net(&self, net: Net) -> Result<SmtTritVec<SMT>, SMT::Error> {
         match net.as_cell_index() {
             Err(trit) => Ok(self.tv_lit(trit)),
@@ -322,7 +447,7 @@ impl<'a, SMT: SmtEngine> SmtBuilder<'a, SMT> {
             }
         }
         match self.design.find_cell(net) {
-            Ok((cell_ref, _offset)) if matches!(&*cell_ref.get(), Cell::Dff(_)) => self.past_net(net),
+            Ok((cell_ref, offset)) if is_state(&cell_ref.get(), offset) => self.past_net(net),
             _ => self.curr_net(net),
         }
     }
@@ -499,6 +624,33 @@ impl<'a, SMT: SmtEngine> SmtBuilder<'a, SMT> {
                     y: self.engine.build_bvmul(tv_a.y, tv_b.y),
                 }
             }
+            Cell::Popcnt(a) | Cell::Clz(a) | Cell::Ctz(a) => {
+                let width = output.len();
+                let tv_a = self.value(a)?;
+                let tv_a = self.tv_bind(tv_a, a.len())?;
+                let bit = |index| self.bv_to_bool(self.engine.build_extract(index, index, tv_a.y.clone()));
+                let lit = |count: usize| self.engine.build_bitvec_lit(&Const::from_uint(count as u128, width));
+                let bv_y = match cell {
+                    Cell::Popcnt(_) => (0..a.len()).fold(lit(0), |bv_sum, index| {
+                        self.engine.build_bvadd(bv_sum, self.engine.build_bitvec_ite(bit(index), lit(1), lit(0)))
+                    }),
+                    Cell::Clz(_) => (0..a.len()).fold(lit(a.len()), |bv_count, index| {
+                        self.engine.build_bitvec_ite(bit(index), lit(a.len() - 1 - index), bv_count)
+                    }),
+                    Cell::Ctz(_) => (0..a.len()).rev().fold(lit(a.len()), |bv_count, index| {
+                        self.engine.build_bitvec_ite(bit(index), lit(index), bv_count)
+                    }),
+                    _ => unreachable!(),
+                };
+                SmtTritVec {
+                    x: self.engine.build_bitvec_ite(
+                        self.bv_is_zero(tv_a.x.clone(), a.len()),
+                        lit(0),
+                        self.engine.build_bitvec_lit(&Const::ones(width)),
+                    ),
+                    y: bv_y,
+                }
+            }
             Cell::UDiv(..) => unimplemented!("lowering of udiv to SMT-LIB is not implemented"),
             Cell::UMod(..) => unimplemented!("lowering of umod to SMT-LIB is not implemented"),
             Cell::SDivTrunc(..) => unimplemented!("lowering of sdiv_trunc to SMT-LIB is not implemented"),
@@ -544,26 +696,60 @@ impl<'a, SMT: SmtEngine> SmtBuilder<'a, SMT> {
                 output.len(),
             )?,
             Cell::Dff(flip_flop) => {
-                let mut data = self.value(&flip_flop.data)?;
-                let clear = self.control_net(flip_flop.clear)?;
-                let reset = self.control_net(flip_flop.reset)?;
-                let enable = self.control_net(flip_flop.enable)?;
-                if flip_flop.reset_over_enable {
-                    data = self.tv_mux(enable, data, self.past_value(&output)?, output.len())?;
-                    data = self.tv_mux(reset, self.tv_lit(&flip_flop.reset_value), data, output.len())?;
-                } else {
-                    data = self.tv_mux(reset, self.tv_lit(&flip_flop.reset_value), data, output.len())?;
-                    data = self.tv_mux(enable, data, self.past_value(&output)?, output.len())?;
+                let data = self.value(&flip_flop.data)?;
+                self.tv_flip_flop(output, flip_flop, data)?
+            }
+            Cell::Memory(memory) => {
+                // the contents of the memory before the current step are unconstrained, like the past values
+                // of flip-flop outputs
+                let mut tv_contents_rows = vec![];
+                if memory.depth > 0 {
+                    let cell_index = output[0].as_cell_index().unwrap();
+                    let tv_contents = self.tv_const("m", cell_index, memory.depth * memory.width)?;
+                    tv_contents_rows.extend((0..memory.depth).map(|row| {
+                        self.tv_extract((row + 1) * memory.width - 1, row * memory.width, tv_contents.clone())
+                    }));
                 }
-                let active_edge = self.clock_net(flip_flop.clock)?;
-                let value = self.tv_mux(active_edge, data, self.past_value(&output)?, output.len())?;
-                if flip_flop.has_clear() {
-                    self.tv_mux(clear, self.tv_lit(&flip_flop.clear_value), value, output.len())?
-                } else {
-                    value
+                let mut tv_rows = tv_contents_rows.clone();
+                for port in &memory.write_ports {
+                    self.tv_memory_write(memory, port, &mut tv_rows, false)?;
+                }
+                let mut tv_ports = vec![];
+                for (port_index, port) in memory.read_ports.iter().enumerate() {
+                    let Some(read_flip_flop) = &port.flip_flop else {
+                        tv_ports.push(self.tv_memory_read(memory, port, &tv_rows)?);
+                        continue;
+                    };
+                    // a synchronous read port samples the contents before the writes it is not transparent to
+                    let mut tv_read_rows = tv_contents_rows.clone();
+                    for (write_port, relation) in memory.write_ports.iter().zip(&read_flip_flop.relations) {
+                        match relation {
+                            MemoryPortRelation::ReadBeforeWrite => (),
+                            MemoryPortRelation::Transparent => {
+                                self.tv_memory_write(memory, write_port, &mut tv_read_rows, false)?
+                            }
+                            MemoryPortRelation::Undefined => {
+                                self.tv_memory_write(memory, write_port, &mut tv_read_rows, true)?
+                            }
+                        }
+                    }
+                    let tv_data = self.tv_memory_read(memory, port, &tv_read_rows)?;
+                    let port_output = output.slice(memory.read_port_output_slice(port_index));
+                    let flip_flop = FlipFlop {
+                        data: port_output.clone(),
+                        clock: read_flip_flop.clock,
+                        clear: read_flip_flop.clear,
+                        reset: read_flip_flop.reset,
+                        enable: read_flip_flop.enable,
+                        reset_over_enable: read_flip_flop.reset_over_enable,
+                        clear_value: read_flip_flop.clear_value.clone(),
+                        reset_value: read_flip_flop.reset_value.clone(),
+                        init_value: read_flip_flop.init_value.clone(),
+                    };
+                    tv_ports.push(self.tv_flip_flop(&port_output, &flip_flop, tv_data)?);
                 }
+                self.tv_concat(tv_ports)
             }
-            Cell::Memory(_memory) => unimplemented!("memories are not lowered to SMT-LIB yet"),
             Cell::IoBuf(_io_buffer) => self.value(output)?, // i/en/o treated as POs/PIs
             Cell::Target(_target_cell) => unimplemented!("target cells cannot be lowered to SMT-LIB yet"),
             Cell::Other(_) => unreachable!("instances cannot be lowered to SMT-LIB"),
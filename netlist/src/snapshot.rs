@@ -0,0 +1,172 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::sync::Arc;
+
+use crate::cell::CellRepr;
+use crate::design::AnnotatedCell;
+use crate::metadata::MetadataStore;
+use crate::{Cell, IoNet, IoValue, Net, SnapshotMetaItemRef, Target, TargetCell, TargetPrototype, Trit, Value};
+
+/// Immutable copy of a [`Design`], created by [`Design::snapshot`].
+///
+/// Unlike a [`Design`], a snapshot is `Send + Sync`, so read-only analyses can be run on it from several threads at
+/// once, e.g. with [`std::thread::scope`]. The cells of a snapshot are accessed through [`SnapshotCellRef`], which
+/// parallels [`CellRef`], and use the same indices as the design they were copied from.
+///
+/// [`Design`]: crate::Design
+/// [`Design::snapshot`]: crate::Design::snapshot
+/// [`CellRef`]: crate::CellRef
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    ios: BTreeMap<String, Range<u32>>,
+    cells: Vec<AnnotatedCell>,
+    metadata: MetadataStore,
+    target: Option<Arc<dyn Target>>,
+}
+
+impl Snapshot {
+    pub(crate) fn new(
+        ios: BTreeMap<String, Range<u32>>,
+        cells: Vec<AnnotatedCell>,
+        metadata: MetadataStore,
+        target: Option<Arc<dyn Target>>,
+    ) -> Snapshot {
+        Snapshot { ios, cells, metadata, target }
+    }
+
+    pub fn get_io(&self, name: impl AsRef<str>) -> Option<IoValue> {
+        self.ios.get(name.as_ref()).map(|range| IoValue::from_range(range.clone()))
+    }
+
+    pub fn find_io(&self, io_net: IoNet) -> Option<(&str, usize)> {
+        for (name, range) in self.ios.iter() {
+            if range.contains(&io_net.index) {
+                return Some((name.as_str(), (io_net.index - range.start) as usize));
+            }
+        }
+        None
+    }
+
+    pub fn iter_ios(&self) -> impl Iterator<Item = (&str, IoValue)> {
+        self.ios.iter().map(|(name, range)| (name.as_str(), IoValue::from_range(range.clone())))
+    }
+
+    pub fn find_cell(&self, net: Net) -> Result<(SnapshotCellRef<'_>, usize), Trit> {
+        let index = net.as_cell_index()?;
+        match self.cells[index].repr {
+            CellRepr::Void => panic!("located a void cell %{index} in snapshot"),
+            CellRepr::Skip(start) => {
+                Ok((SnapshotCellRef { snapshot: self, index: start as usize }, index - start as usize))
+            }
+            _ => Ok((SnapshotCellRef { snapshot: self, index }, 0)),
+        }
+    }
+
+    pub fn iter_cells(&self) -> SnapshotCellIter<'_> {
+        SnapshotCellIter { snapshot: self, index: 0 }
+    }
+
+    pub fn target(&self) -> Option<Arc<dyn Target>> {
+        self.target.clone()
+    }
+
+    pub fn target_prototype(&self, target_cell: &TargetCell) -> &TargetPrototype {
+        self.target
+            .as_ref()
+            .expect("snapshot has no target")
+            .prototype(&target_cell.kind)
+            .expect("target prototype not defined")
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct SnapshotCellRef<'a> {
+    snapshot: &'a Snapshot,
+    index: usize,
+}
+
+impl PartialEq<SnapshotCellRef<'_>> for SnapshotCellRef<'_> {
+    fn eq(&self, other: &SnapshotCellRef<'_>) -> bool {
+        std::ptr::eq(self.snapshot, other.snapshot) && self.index == other.index
+    }
+}
+
+impl Eq for SnapshotCellRef<'_> {}
+
+impl PartialOrd<SnapshotCellRef<'_>> for SnapshotCellRef<'_> {
+    fn partial_cmp(&self, other: &SnapshotCellRef<'_>) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SnapshotCellRef<'_> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        match (self.snapshot as *const Snapshot).cmp(&(other.snapshot as *const Snapshot)) {
+            core::cmp::Ordering::Equal => self.index.cmp(&other.index),
+            ord => ord,
+        }
+    }
+}
+
+impl std::hash::Hash for SnapshotCellRef<'_> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+    }
+}
+
+impl<'a> SnapshotCellRef<'a> {
+    pub fn get(self) -> Cow<'a, Cell> {
+        self.snapshot.cells[self.index].get()
+    }
+
+    pub fn metadata(&self) -> SnapshotMetaItemRef<'a> {
+        self.snapshot.metadata.ref_snapshot_item(self.snapshot.cells[self.index].meta)
+    }
+
+    pub fn output_len(&self) -> usize {
+        self.snapshot.cells[self.index].output_len()
+    }
+
+    pub fn output(&self) -> Value {
+        Value::from_cell_range(self.index, self.output_len())
+    }
+
+    pub fn visit(&self, f: impl FnMut(Net)) {
+        self.snapshot.cells[self.index].visit(f)
+    }
+
+    /// Returns the same index as [`CellRef::debug_index`] for the corresponding cell of the design.
+    ///
+    /// [`CellRef::debug_index`]: crate::CellRef::debug_index
+    pub fn debug_index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns a reference to the underlying [`Snapshot`].
+    pub fn snapshot(self) -> &'a Snapshot {
+        self.snapshot
+    }
+}
+
+pub struct SnapshotCellIter<'a> {
+    snapshot: &'a Snapshot,
+    index: usize,
+}
+
+impl<'a> Iterator for SnapshotCellIter<'a> {
+    type Item = SnapshotCellRef<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while matches!(self.snapshot.cells.get(self.index), Some(AnnotatedCell { repr: CellRepr::Void, .. })) {
+            self.index += 1;
+        }
+        if self.index < self.snapshot.cells.len() {
+            let cell_ref = SnapshotCellRef { snapshot: self.snapshot, index: self.index };
+            self.index += self.snapshot.cells[self.index].output_len().max(1);
+            Some(cell_ref)
+        } else {
+            None
+        }
+    }
+}
@@ -5,9 +5,9 @@ use std::collections::BTreeMap;
 use std::ops::Range;
 use std::sync::{Arc, Mutex};
 
-use crate::{CellRef, Const, Design, Instance, IoValue, ParamValue, TargetCell, Trit, Value};
+use crate::{CellRef, Const, Design, EnumEncoding, Instance, IoValue, ParamValue, PassManager, TargetCell, Trit, Value};
 
-pub trait Target: Debug {
+pub trait Target: Debug + Send + Sync {
     /// Get target name. The name of the target can be used to construct a new instance of it.
     fn name(&self) -> &str;
 
@@ -24,6 +24,10 @@ pub trait Target: Debug {
     /// by `Design::validate`.
     fn validate(&self, design: &Design, cell: &TargetCell);
 
+    /// Validate target-specific constraints that involve several cells or IOs. Called by `Design::apply` when
+    /// the applied changes add IOs, or add or replace target cells.
+    fn validate_design(&self, _design: &Design) {}
+
     /// Convert generic instances into target cells.
     fn import(&self, design: &mut Design) -> Result<(), TargetImportError>;
 
@@ -32,6 +36,43 @@ pub trait Target: Debug {
 
     /// Run the complete synthesis flow.
     fn synthesize(&self, design: &mut Design) -> Result<(), ()>;
+
+    /// Register the passes used by the synthesis flow, both generic and target specific, with `passes`.
+    fn register_passes<'a>(&'a self, _passes: &mut PassManager<'a>) {}
+
+    /// Get the names of the passes that make up the synthesis flow, in order. Running these passes with
+    /// a [`PassManager`] populated by [`Target::register_passes`] is equivalent to [`Target::synthesize`].
+    fn synthesis_passes(&self) -> Vec<&'static str> {
+        vec![]
+    }
+
+    /// Get the preferred encoding of state machines whose encoding is not specified in the design.
+    fn enum_encoding(&self) -> EnumEncoding {
+        EnumEncoding::Binary
+    }
+
+    /// Get the preferred architecture of multipliers.
+    fn mul_architecture(&self) -> MulArchitecture {
+        MulArchitecture::Array
+    }
+}
+
+/// Architecture of the logic that `mul` cells are lowered to. Regardless of the architecture, multiplication
+/// by a constant is lowered to a sum of shifted operands using the canonical signed digit form of the constant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MulArchitecture {
+    /// A partial product for each bit of an operand, summed by a chain of adders.
+    #[default]
+    Array,
+    /// A partial product for each radix-4 Booth digit of an operand, summed by a chain of adders. Uses half as
+    /// many adders as `Array`, which suits fabrics with fast carry chains.
+    BoothArray,
+    /// A partial product for each bit of an operand, reduced to two by a Wallace tree of carry-save adders and
+    /// summed by a single adder. The depth is logarithmic in the width of the operands.
+    Wallace,
+    /// A partial product for each radix-4 Booth digit of an operand, reduced by a Wallace tree. Suits fabrics
+    /// with wide LUTs, which implement a full adder in a single LUT.
+    BoothWallace,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -40,6 +81,7 @@ pub enum TargetParamKind {
     Bool,
     IntEnum(Vec<i64>),
     StringEnum(Vec<String>),
+    Float,
 }
 
 impl TargetParamKind {
@@ -49,6 +91,7 @@ impl TargetParamKind {
             (TargetParamKind::Bool, ParamValue::Const(value)) => value.len() == 1 && !value.has_undef(),
             (TargetParamKind::IntEnum(items), ParamValue::Int(value)) => items.contains(value),
             (TargetParamKind::StringEnum(items), ParamValue::String(value)) => items.contains(value),
+            (TargetParamKind::Float, ParamValue::Float(_)) => true,
             _ => false,
         }
     }
@@ -85,6 +128,14 @@ impl TargetParamKind {
             (TargetParamKind::StringEnum(items), ParamValue::String(value)) if items.contains(value) => {
                 Some(ParamValue::String(value.clone()))
             }
+            (TargetParamKind::Float, ParamValue::Float(value)) => Some(ParamValue::Float(*value)),
+            (TargetParamKind::Float, ParamValue::Int(value)) if *value as f64 as i128 == *value as i128 => {
+                Some(ParamValue::from(*value as f64))
+            }
+            // Yosys represents real-valued Verilog parameters as strings
+            (TargetParamKind::Float, ParamValue::String(value)) => {
+                value.trim().parse::<f64>().ok().map(ParamValue::from)
+            }
             _ => None,
         }
     }
@@ -218,6 +269,10 @@ impl TargetPrototype {
         self.add_param_raw(name, TargetParamKind::StringEnum(variants), ParamValue::String(default))
     }
 
+    pub fn add_param_float(self, name: impl Into<String>, default: f64) -> Self {
+        self.add_param_raw(name, TargetParamKind::Float, ParamValue::from(default))
+    }
+
     fn add_input_raw(
         mut self,
         name: impl Into<String>,
@@ -344,6 +399,17 @@ impl TargetPrototype {
         }
     }
 
+    pub fn extract_param_float(&self, target_cell: &TargetCell, name: impl AsRef<str>) -> f64 {
+        let name = name.as_ref();
+        if let Some(TargetParam { index, kind, .. }) = self.get_param(name) {
+            assert_eq!(*kind, TargetParamKind::Float);
+            let ParamValue::Float(value) = target_cell.params[*index] else { unreachable!() };
+            f64::from_bits(value)
+        } else {
+            panic!("param {:?} does not exist for target cell", name);
+        }
+    }
+
     pub fn extract_input(&self, target_cell: &TargetCell, name: impl AsRef<str>) -> Value {
         let name = name.as_ref();
         if let Some(TargetInput { range, .. }) = self.get_input(name) {
@@ -430,6 +496,8 @@ pub enum TargetCellImportError {
     OutputSizeMismatch(String),
     UnknownIo(String),
     IoSizeMismatch(String),
+    /// The cell uses a feature the target cannot implement; the string describes the feature.
+    Unsupported(String),
 }
 
 impl Display for TargetCellImportError {
@@ -441,7 +509,7 @@ impl Display for TargetCellImportError {
                 let value = match value {
                     ParamValue::Const(value) => format!("{value}"),
                     ParamValue::Int(value) => format!("{value}"),
-                    ParamValue::Float(value) => format!("{value}"),
+                    ParamValue::Float(value) => format!("{:?}", f64::from_bits(*value)),
                     ParamValue::String(value) => format!("{value:?}"),
                 };
                 write!(f, "invalid value {value} for parameter {name:?}")
@@ -452,6 +520,7 @@ impl Display for TargetCellImportError {
             TargetCellImportError::OutputSizeMismatch(name) => write!(f, "size mismatch for output {name:?}"),
             TargetCellImportError::UnknownIo(name) => write!(f, "unknown io {name:?}"),
             TargetCellImportError::IoSizeMismatch(name) => write!(f, "size mismatch for io {name:?}"),
+            TargetCellImportError::Unsupported(feature) => write!(f, "{feature} not supported by the target"),
         }
     }
 }
@@ -504,6 +573,10 @@ impl TargetImportError {
     pub fn io_size_mismatch(cell_ref: CellRef, name: impl Into<String>) -> TargetImportError {
         Self::new(cell_ref, TargetCellImportError::IoSizeMismatch(name.into()))
     }
+
+    pub fn unsupported(cell_ref: CellRef, feature: impl Into<String>) -> TargetImportError {
+        Self::new(cell_ref, TargetCellImportError::Unsupported(feature.into()))
+    }
 }
 
 impl Display for TargetImportError {
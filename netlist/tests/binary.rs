@@ -0,0 +1,207 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use prjunnamed_netlist::{
+    BinaryError, Const, Design, Target, TargetCell, TargetImportError, TargetPrototype, parse, read_binary,
+    register_target, write_binary,
+};
+
+fn to_binary(design: &Design) -> Vec<u8> {
+    let mut binary = Vec::new();
+    write_binary(design, &mut binary).unwrap();
+    binary
+}
+
+#[track_caller]
+fn roundtrip(text: &str) {
+    let design = parse(None, text).unwrap();
+    let copy = read_binary(None, &to_binary(&design)[..]).unwrap();
+    assert_eq!(format!("{copy:#}"), format!("{design:#}"));
+}
+
+#[test]
+fn test_cells() {
+    roundtrip("%0:1 = buf 0\n%1:1 = not %0\n");
+    roundtrip("%0:2 = buf 00\n%2:1 = and %0+0 %0+1\n");
+    roundtrip("%0:2 = buf 00\n%2:1 = or %0+0 %0+1\n");
+    roundtrip("%0:2 = buf 00\n%2:1 = xor %0+0 %0+1\n");
+    roundtrip("%0:3 = buf 000\n%3:1 = mux %0+0 %0+1 %0+2\n");
+    roundtrip("%0:3 = buf 000\n%3:2 = adc %0+0 %0+1 %0+2\n");
+    roundtrip("%0:2 = buf 00\n%2:1 = aig %0+0 !%0+1\n");
+    roundtrip("%0:4 = buf 01X0\n%4:2 = not %0+0:2\n%6:2 = and %0+0:2 %0+2:2\n%8:3 = adc %0+0:2 %0+2:2 0\n");
+    roundtrip("%0:2 = buf 00\n%2:1 = eq %0+0 %0+1\n");
+    roundtrip("%0:2 = buf 00\n%2:1 = ult %0+0 %0+1\n");
+    roundtrip("%0:2 = buf 00\n%2:1 = slt %0+0 %0+1\n");
+    roundtrip("%0:2 = buf 00\n%2:1 = shl %0+0 %0+1 #1\n");
+    roundtrip("%0:2 = buf 00\n%2:1 = ushr %0+0 %0+1 #2\n");
+    roundtrip("%0:2 = buf 00\n%2:1 = sshr %0+0 %0+1 #3\n");
+    roundtrip("%0:2 = buf 00\n%2:1 = xshr %0+0 %0+1 #400\n");
+    roundtrip("%0:5 = buf 00000\n%5:3 = popcnt %0:5\n");
+    roundtrip("%0:5 = buf 00000\n%5:3 = clz %0:5\n");
+    roundtrip("%0:5 = buf 00000\n%5:3 = ctz %0:5\n");
+    roundtrip("%0:2 = buf 00\n%2:1 = mul %0+0 %0+1\n");
+    roundtrip("%0:2 = buf 00\n%2:1 = udiv %0+0 %0+1\n");
+    roundtrip("%0:2 = buf 00\n%2:1 = umod %0+0 %0+1\n");
+    roundtrip("%0:2 = buf 00\n%2:1 = sdiv_trunc %0+0 %0+1\n");
+    roundtrip("%0:2 = buf 00\n%2:1 = sdiv_floor %0+0 %0+1\n");
+    roundtrip("%0:2 = buf 00\n%2:1 = smod_trunc %0+0 %0+1\n");
+    roundtrip("%0:2 = buf 00\n%2:1 = smod_floor %0+0 %0+1\n");
+    roundtrip("%0:2 = buf 00\n%2:2 = match en=%0+1 %0+0 { (0 1) X }\n");
+    roundtrip("%0:4 = buf 0000\n%4:2 = assign en=%0+2 %0+0:2 %0+3 at=#1\n");
+    roundtrip("%0:5 = buf 00000\n%5:1 = dff %0+0 clk=%0+1 clr=%0+2 rst=%0+3 en=!%0+4 en/rst init=1\n");
+    roundtrip("&\"purr\":2 = io\n%0:2 = buf 00\n%2:2 = iobuf &\"purr\":2 o=%0:2 en=!%0+1\n");
+    roundtrip("%0:2 = input \"awa\"\n%2:0 = output \"bite\" %0:2\n");
+    roundtrip("%0:2 = buf 00\n%2:0 = name \"meow\" %0:2\n%3:0 = debug \"hiss\" %0:2\n");
+}
+
+#[test]
+fn test_memories() {
+    roundtrip(concat!(
+        "%0:1 = buf 0\n",
+        "%1:3 = buf 000\n",
+        "%4:4 = buf 0000\n",
+        "%8:4 = buf 0001\n",
+        "%12:1 = buf 1\n",
+        "%16:_ = memory depth=#8 width=#4 {\n",
+        "  write addr=%1:3 data=%4:4 mask=%8:4 clk=%0\n",
+        "  write addr=%1:3 data=%4:4 mask=%8:4 clk=!%12\n",
+        "  write addr=%1:3 data=%4:4 mask=%8:4\n",
+        "  %16:4 = read addr=%1:3 clk=%0 clr=%12 init=1010 [trans undef undef]\n",
+        "  %20:4 = read addr=%1:3\n",
+        "  init 0000*4\n",
+        "  init XXXX*4\n",
+        "}\n",
+    ));
+}
+
+#[test]
+fn test_instances() {
+    roundtrip(concat!(
+        "&\"pin\":1 = io\n",
+        "%0:1 = buf 0\n",
+        "%1:_ = \"TBUF\" {\n",
+        "  param \"A\" = 10X\n",
+        "  param \"B\" = #-33\n",
+        "  param \"C\" = #1.5\n",
+        "  param \"D\" = \"x\\7f\"\n",
+        "  input \"EN\" = %0\n",
+        "  %1:1 = output \"I\"\n",
+        "  %2:2 = output \"O\"\n",
+        "  io \"PIN\" = [ &_ &\"pin\" ]\n",
+        "}\n",
+    ));
+}
+
+#[test]
+fn test_metadata() {
+    roundtrip(concat!(
+        "!0 = source \"top.py\" (#1 #2) (#3 #4)\n",
+        "!1 = scope \"top\" src=!0\n",
+        "!2 = scope #-1 in=!1\n",
+        "!3 = ident \"addr\" in=!2\n",
+        "!4 = { !0 !3 }\n",
+        "!5 = attr \"foo\" #0.25\n",
+        "!6 = enum encoding=\"one-hot\" { \"IDLE\"=00 \"BUSY\"=01 }\n",
+        "%0:1 = input \"a\"\n",
+        "; source file://top.py#2\n",
+        "; drives \"y\"+0\n",
+        "%1:1 = not %0 !4\n",
+        "%2:0 = output \"y\" %1\n",
+    ));
+}
+
+#[test]
+fn test_voids() {
+    let design = parse(None, "%2:_ = \"TBUF\" {\n  %2:2 = output \"I\"\n}\n").unwrap();
+    let design = read_binary(None, &to_binary(&design)[..]).unwrap();
+    assert_eq!(design.iter_cells().count(), 1);
+    assert_eq!(format!("{design:#}"), "%2:_ = \"TBUF\" {\n  %2:2 = output \"I\"\n}\n");
+    // new cells and IOs are allocated after the existing ones
+    let mut design = parse(None, "&\"a\":2 = io\n%0:1 = buf 0\n").unwrap();
+    design = read_binary(None, &to_binary(&design)[..]).unwrap();
+    design.add_io("b", 1);
+    design.add_cell(prjunnamed_netlist::Cell::Input("c".into(), 1));
+    design.apply();
+    assert_eq!(format!("{design:#}"), "&\"a\":2 = io\n&\"b\":1 = io\n%0:1 = buf 0\n%1:1 = input \"c\"\n");
+}
+
+#[derive(Debug)]
+struct TestTarget {
+    options: BTreeMap<String, String>,
+    prototypes: BTreeMap<String, TargetPrototype>,
+}
+
+impl Target for TestTarget {
+    fn name(&self) -> &str {
+        "binary-test"
+    }
+
+    fn options(&self) -> BTreeMap<String, String> {
+        self.options.clone()
+    }
+
+    fn prototype(&self, name: &str) -> Option<&TargetPrototype> {
+        self.prototypes.get(name)
+    }
+
+    fn validate(&self, _design: &Design, _cell: &TargetCell) {}
+
+    fn import(&self, _design: &mut Design) -> Result<(), TargetImportError> {
+        Ok(())
+    }
+
+    fn export(&self, _design: &mut Design) {}
+
+    fn synthesize(&self, _design: &mut Design) -> Result<(), ()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_target() {
+    register_target("binary-test", |options| {
+        Ok(Arc::new(TestTarget {
+            options,
+            prototypes: BTreeMap::from([(
+                "OSC".into(),
+                TargetPrototype::new_has_effects()
+                    .add_param_float("FREQ", 48.0)
+                    .add_param_bits("DIV", Const::zero(2))
+                    .add_input("EN", Const::zero(1))
+                    .add_output("CLK", 1),
+            )]),
+        }))
+    });
+    let text = concat!(
+        "target \"binary-test\" \"device\"=\"example\"\n",
+        "%0:1 = input \"en\"\n",
+        "%1:1 = target \"OSC\" {\n",
+        "  param \"FREQ\" = #12.5\n",
+        "  param \"DIV\" = 10\n",
+        "  input \"EN\" = %0\n",
+        "}\n",
+    );
+    let design = parse(None, text).unwrap();
+    let design = read_binary(None, &to_binary(&design)[..]).unwrap();
+    assert_eq!(design.target().unwrap().options()["device"], "example");
+    assert_eq!(format!("{design:#}"), text);
+}
+
+#[test]
+fn test_errors() {
+    assert!(matches!(read_binary(None, &b"%0:1 = buf 0\n"[..]), Err(BinaryError::NotBinary)));
+
+    let design = parse(None, "%0:1 = input \"a\"\n").unwrap();
+    let binary = to_binary(&design);
+    let mut other_version = binary.clone();
+    other_version[8] += 1;
+    assert!(matches!(read_binary(None, &other_version[..]), Err(BinaryError::UnsupportedVersion(2))));
+    assert!(matches!(read_binary(None, &binary[..binary.len() - 1]), Err(BinaryError::Io(_))));
+    // the input cell is written last, as a tag followed by the name, the width, and the metadata
+    let mut unknown_cell = binary.clone();
+    let tag_offset = binary.len() - 5;
+    unknown_cell[tag_offset] = 0xff;
+    assert!(matches!(
+        read_binary(None, &unknown_cell[..]),
+        Err(BinaryError::Malformed(offset)) if offset == tag_offset as u64 + 1
+    ));
+}
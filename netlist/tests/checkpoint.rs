@@ -0,0 +1,133 @@
+use std::str::FromStr;
+
+use prjunnamed_netlist::{Cell, Design, MetaItem, SourcePosition};
+
+fn chain() -> Design {
+    Design::from_str(concat!(
+        "%0:1 = input \"a\"\n",
+        "%1:1 = input \"b\"\n",
+        "%2:1 = input \"c\"\n",
+        "%3:1 = input \"d\"\n",
+        "%4:1 = and %0 %1\n",
+        "%5:1 = and %4 %2\n",
+        "%6:1 = and %5 %3\n",
+        "%7:0 = output \"z\" %6\n",
+    ))
+    .unwrap()
+}
+
+#[test]
+fn test_rollback() {
+    let mut design = chain();
+    let source = design.to_string();
+    let checkpoint = design.checkpoint();
+    let and_cell = design.iter_cells().nth(4).unwrap();
+    let file = design.add_metadata_string("top.v");
+    let meta = design.add_metadata_item(&MetaItem::Source {
+        file,
+        start: SourcePosition { line: 0, column: 0 },
+        end: SourcePosition { line: 0, column: 1 },
+    });
+    let _guard = design.use_metadata(meta);
+    let x = design.add_not(and_cell.output());
+    design.replace_value(and_cell.output(), x);
+    and_cell.append_metadata(meta);
+    assert!(design.is_changed());
+    drop(_guard);
+    design.rollback(checkpoint);
+    assert!(!design.is_changed());
+    assert!(!design.apply());
+    assert_eq!(design.to_string(), source);
+}
+
+#[test]
+#[should_panic(expected = "cannot roll back while metadata added after the checkpoint is in use")]
+fn test_rollback_metadata_in_use() {
+    let design = chain();
+    let checkpoint = design.checkpoint();
+    let file = design.add_metadata_string("top.v");
+    let meta = design.add_metadata_item(&MetaItem::Source {
+        file,
+        start: SourcePosition { line: 0, column: 0 },
+        end: SourcePosition { line: 0, column: 1 },
+    });
+    let _guard = design.use_metadata(meta);
+    design.rollback(checkpoint);
+}
+
+#[test]
+fn test_rollback_metadata_restored() {
+    let mut design = chain();
+    let and_cell = design.iter_cells().nth(4).unwrap();
+    let file = design.add_metadata_string("top.v");
+    let meta = design.add_metadata_item(&MetaItem::Source {
+        file,
+        start: SourcePosition { line: 0, column: 0 },
+        end: SourcePosition { line: 0, column: 1 },
+    });
+    let guard = design.use_metadata(meta);
+    let checkpoint = design.checkpoint();
+    let nested_guard = design.use_metadata(and_cell.metadata());
+    design.add_not(and_cell.output());
+    design.rollback(checkpoint);
+    // the metadata items in use are older than the checkpoint, so they are still valid after the rollback
+    let file = design.add_metadata_string("other.v");
+    design.add_metadata_item(&MetaItem::Source {
+        file,
+        start: SourcePosition { line: 1, column: 0 },
+        end: SourcePosition { line: 1, column: 1 },
+    });
+    let x = design.add_not(and_cell.output());
+    drop(nested_guard);
+    let y = design.add_not(and_cell.output());
+    drop(guard);
+    design.add_output("x", x);
+    design.add_output("y", y);
+    design.apply();
+    let metadata = Vec::from_iter(
+        design
+            .iter_cells()
+            .filter(|cell_ref| matches!(&*cell_ref.get(), Cell::Not(_)))
+            .map(|cell_ref| cell_ref.metadata().get()),
+    );
+    assert!(matches!(metadata[0], MetaItem::None));
+    assert!(matches!(&metadata[1], MetaItem::Source { file, .. } if &*file.get() == "top.v"));
+}
+
+#[test]
+fn test_rollback_nested() {
+    let design = chain();
+    let inputs = Vec::from_iter(design.iter_cells().take(4).map(|cell_ref| cell_ref.output()));
+    design.add_or(&inputs[0], &inputs[1]);
+    let checkpoint = design.checkpoint();
+    design.add_xor(&inputs[2], &inputs[3]);
+    design.rollback(checkpoint);
+    assert_eq!(design.pending_cells().len(), 1);
+}
+
+#[test]
+#[should_panic]
+fn test_rollback_after_apply() {
+    let mut design = chain();
+    let checkpoint = design.checkpoint();
+    design.apply();
+    design.rollback(checkpoint);
+}
+
+#[test]
+fn test_pending_cell_delta() {
+    let design = chain();
+    let cells = Vec::from_iter(design.iter_cells().map(|cell_ref| cell_ref.output()));
+    // rebalance `((a & b) & c) & d` into `(a & b) & (c & d)`
+    let p = design.add_and(&cells[2], &cells[3]);
+    let q = design.add_and(&cells[4], p);
+    design.replace_value(&cells[6], &q);
+    assert_eq!(design.pending_cells().len(), 2);
+    assert_eq!(design.pending_net_replacements(), vec![(cells[6][0], q[0])]);
+    assert_eq!(design.pending_cell_delta(), 0);
+
+    // replace the output with an input, which removes every `and` cell
+    let design = chain();
+    design.replace_value(&cells[6], &cells[0]);
+    assert_eq!(design.pending_cell_delta(), -3);
+}
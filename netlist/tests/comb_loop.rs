@@ -0,0 +1,80 @@
+use std::str::FromStr;
+
+use prjunnamed_netlist::Design;
+
+#[test]
+fn test_comb_loop() {
+    let design = Design::from_str(concat!(
+        "%0:1 = input \"a\"\n",
+        "%1:1 = and %0 %2\n",
+        "%2:1 = xor %1 %0\n",
+        "%3:1 = not %3\n",
+        "%4:1 = buf %2\n",
+        "%5:0 = output \"y\" %4\n",
+        "%6:0 = name \"fb\" %1\n",
+    ))
+    .unwrap();
+    let comb_loops = design.find_comb_loops();
+    assert_eq!(comb_loops.len(), 2);
+    let cells = Vec::from_iter(comb_loops.iter().map(|comb_loop| comb_loop.cells.len()));
+    assert!(cells.contains(&2) && cells.contains(&1));
+    let and_loop = comb_loops.iter().find(|comb_loop| comb_loop.cells.len() == 2).unwrap();
+    assert_eq!(and_loop.names(), vec!["fb".to_string()]);
+}
+
+#[test]
+fn test_comb_loop_through_dff() {
+    let design = Design::from_str(concat!(
+        "%0:1 = input \"clk\"\n",
+        "%1:1 = dff %2 clk=%0\n",
+        "%2:1 = not %1\n",
+        "%3:0 = output \"q\" %1\n",
+    ))
+    .unwrap();
+    assert!(design.find_comb_loops().is_empty());
+}
+
+#[test]
+fn test_comb_loop_between_bits() {
+    // the bits of a bitwise cell are considered separately
+    let design =
+        Design::from_str(concat!("%0:1 = input \"a\"\n", "%1:2 = and [%1+0 %0] 11\n", "%3:0 = output \"y\" %1:2\n",))
+            .unwrap();
+    assert!(design.find_comb_loops().is_empty());
+
+    let design =
+        Design::from_str(concat!("%0:1 = input \"a\"\n", "%1:3 = adc [%1+0 %0] 00 0\n", "%4:0 = output \"y\" %1:3\n",))
+            .unwrap();
+    assert_eq!(design.find_comb_loops().len(), 1);
+}
+
+#[test]
+fn test_comb_loop_through_memory() {
+    // a read-modify-write through a synchronous read port is not a loop
+    let design = Design::from_str(concat!(
+        "%0:1 = input \"clk\"\n",
+        "%1:2 = input \"addr\"\n",
+        "%3:_ = memory depth=#4 width=#4 {\n",
+        "  write addr=%1:2 data=%7+0:4 mask=1111 clk=%0\n",
+        "  %3:4 = read addr=%1:2 clk=%0 [rdfirst]\n",
+        "}\n",
+        "%7:5 = adc %3:4 0001 0\n",
+        "%12:0 = output \"q\" %3:4\n",
+    ))
+    .unwrap();
+    assert!(design.find_comb_loops().is_empty());
+
+    // but it is through an asynchronous read port and an asynchronous write port
+    let design = Design::from_str(concat!(
+        "%0:1 = input \"en\"\n",
+        "%1:2 = input \"addr\"\n",
+        "%3:_ = memory depth=#4 width=#4 {\n",
+        "  write addr=%1:2 data=%7+0:4 mask=[%0 %0 %0 %0]\n",
+        "  %3:4 = read addr=%1:2\n",
+        "}\n",
+        "%7:5 = adc %3:4 0001 0\n",
+        "%12:0 = output \"q\" %3:4\n",
+    ))
+    .unwrap();
+    assert_eq!(design.find_comb_loops().len(), 1);
+}
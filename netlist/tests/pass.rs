@@ -0,0 +1,72 @@
+use std::str::FromStr;
+
+use prjunnamed_netlist::{Cell, Design, PassError, PassManager};
+
+fn design() -> Design {
+    Design::from_str(concat!(
+        "%0:2 = input \"a\"\n",
+        "%2:2 = input \"b\"\n",
+        "%4:2 = and %0:2 %2:2\n",
+        "%6:0 = output \"y\" %4:2\n",
+    ))
+    .unwrap()
+}
+
+fn and_to_or(design: &mut Design) {
+    for cell_ref in design.iter_cells() {
+        if let Cell::And(a, b) = &*cell_ref.get() {
+            cell_ref.replace(Cell::Or(a.clone(), b.clone()));
+        }
+    }
+    design.apply();
+}
+
+#[test]
+fn test_pass_manager() {
+    let mut design = design();
+    let mut passes = PassManager::new();
+    passes.register("and-to-or", and_to_or);
+    passes.register("nothing", |_| ());
+    passes.run(&mut design, &["nothing", "and-to-or"]).unwrap();
+    let records = passes.records();
+    assert_eq!(Vec::from_iter(records.iter().map(|record| record.name.as_str())), vec!["nothing", "and-to-or"]);
+    assert!(records[0].statistics.is_empty());
+    assert_eq!(records[1].statistics.get("and"), Some(&-2));
+    assert_eq!(records[1].statistics.get("or"), Some(&2));
+    assert_eq!(records[1].statistics.len(), 2);
+}
+
+#[test]
+fn test_pass_manager_unknown() {
+    let mut design = design();
+    let source = design.to_string();
+    let mut passes = PassManager::new();
+    passes.register("and-to-or", and_to_or);
+    assert_eq!(passes.run(&mut design, &["and-to-or", "or-to-and"]), Err(PassError::UnknownPass("or-to-and".into())));
+    assert!(passes.records().is_empty());
+    assert_eq!(design.to_string(), source);
+}
+
+#[test]
+fn test_pass_manager_check() {
+    let mut design = design();
+    let mut passes = PassManager::new();
+    passes.register("and-to-or", and_to_or);
+    passes.register("nothing", |_| ());
+    passes.set_check(
+        |before, after| {
+            if before.to_string() == after.to_string() { Ok(()) } else { Err("design changed".into()) }
+        },
+    );
+    let result = passes.run(&mut design, &["nothing", "and-to-or", "nothing"]);
+    assert_eq!(result, Err(PassError::CheckFailed { pass: "and-to-or".into(), message: "design changed".into() }));
+    assert_eq!(passes.records().len(), 2);
+}
+
+#[test]
+#[should_panic(expected = "pass \"and-to-or\" is already registered")]
+fn test_pass_manager_duplicate() {
+    let mut passes = PassManager::new();
+    passes.register("and-to-or", and_to_or);
+    passes.register("and-to-or", |_| ());
+}
@@ -1,6 +1,8 @@
 use std::{collections::BTreeMap, sync::Arc};
 
-use prjunnamed_netlist::{Const, Design, Target, TargetCell, TargetImportError, TargetPrototype, register_target};
+use prjunnamed_netlist::{
+    Const, Design, ParamValue, Target, TargetCell, TargetImportError, TargetParamKind, TargetPrototype, register_target,
+};
 use prjunnamed_netlist::parse;
 
 #[track_caller]
@@ -60,6 +62,11 @@ fn test_metadata() {
     roundtrip("!0 = attr \"foo\" 1101\n");
     roundtrip("!0 = attr \"foo\" #1\n");
     roundtrip("!0 = attr \"foo\" \"bar\"\n");
+    roundtrip("!0 = enum { }\n");
+    roundtrip("!0 = enum { \"IDLE\"=00 \"BUSY\"=01 \"DONE\"=10 }\n");
+    roundtrip("!0 = enum encoding=\"one-hot\" { \"IDLE\"=00 \"BUSY\"=01 }\n");
+    roundtrip("!0 = enum encoding=\"gray\" { }\n");
+    assert!(parse(None, "!0 = enum encoding=\"two-hot\" { }\n").is_err());
 }
 
 #[test]
@@ -122,6 +129,9 @@ fn test_cells() {
     roundtrip("%0:2 = buf 00\n%2:1 = sshr %0+0 %0+1 #3\n");
     roundtrip("%0:2 = buf 00\n%2:1 = xshr %0+0 %0+1 #4\n");
     roundtrip("%0:2 = buf 00\n%2:1 = mul %0+0 %0+1\n");
+    roundtrip("%0:5 = buf 00000\n%5:3 = popcnt %0:5\n");
+    roundtrip("%0:5 = buf 00000\n%5:3 = clz %0:5\n");
+    roundtrip("%0:5 = buf 00000\n%5:3 = ctz %0:5\n");
     roundtrip("%0:2 = buf 00\n%2:1 = udiv %0+0 %0+1\n");
     roundtrip("%0:2 = buf 00\n%2:1 = umod %0+0 %0+1\n");
     roundtrip("%0:2 = buf 00\n%2:1 = sdiv_trunc %0+0 %0+1\n");
@@ -177,6 +187,12 @@ fn test_memories() {
          write addr=%1:3 data=%4:4 mask=%8:4 clk=%0\n  \
          init XXXX*8\n}\n",
     );
+    roundtrip(
+        "%0:1 = buf 0\n%1:3 = buf 000\n%4:4 = buf 0000\n\
+         %8:_ = memory depth=#8 width=#4 {\n  \
+         write addr=%1:3 data=%4:4 mask=%0*4\n  \
+         init XXXX*8\n}\n",
+    );
     roundtrip(
         "%0:1 = buf 0\n%1:3 = buf 000\n\
          %8:_ = memory depth=#8 width=#4 {\n  \
@@ -282,6 +298,22 @@ fn test_instance_params() {
     roundtrip("%0:_ = \"CONFIG\" {\n  param \"A\" = #-33\n}\n");
     roundtrip("%0:_ = \"CONFIG\" {\n  param \"A\" = \"x\"\n}\n");
     roundtrip("%0:_ = \"CONFIG\" {\n  param \"A\" = \"x\\7f\"\n}\n");
+    roundtrip("%0:_ = \"CONFIG\" {\n  param \"A\" = #1.0\n}\n");
+    roundtrip("%0:_ = \"CONFIG\" {\n  param \"A\" = #-0.1\n}\n");
+    roundtrip("%0:_ = \"CONFIG\" {\n  param \"A\" = #1e100\n}\n");
+    roundtrip("%0:_ = \"CONFIG\" {\n  param \"A\" = #-inf\n}\n");
+    roundtrip("%0:_ = \"CONFIG\" {\n  param \"A\" = #NaN\n}\n");
+}
+
+#[test]
+fn test_param_float_cast() {
+    assert_eq!(TargetParamKind::Float.cast(&ParamValue::from(12.5)), Some(ParamValue::from(12.5)));
+    assert_eq!(TargetParamKind::Float.cast(&ParamValue::Int(48)), Some(ParamValue::from(48.0)));
+    assert_eq!(TargetParamKind::Float.cast(&ParamValue::Int(i64::MAX)), None);
+    assert_eq!(TargetParamKind::Float.cast(&ParamValue::from("0.5")), Some(ParamValue::from(0.5)));
+    assert_eq!(TargetParamKind::Float.cast(&ParamValue::from("fast")), None);
+    assert!(TargetParamKind::Float.is_valid(&ParamValue::from(1.0)));
+    assert!(!TargetParamKind::Float.is_valid(&ParamValue::Int(1)));
 }
 
 #[derive(Debug)]
@@ -306,6 +338,7 @@ impl TestTarget {
                         .add_output("I", 4)
                         .add_io("IO", 4),
                 ),
+                ("OSC".into(), TargetPrototype::new_pure().add_param_float("FREQ", 48.0).add_output("CLK", 1)),
                 (
                     "ADD".into(),
                     TargetPrototype::new_pure()
@@ -389,6 +422,11 @@ fn test_target() {
         "%6:0 = output \"o\" %4\n",
         "%7:0 = output \"co\" %5\n",
     ));
+    onewaytrip(
+        "target \"test\"\n%0:1 = target \"OSC\" {\n}\n",
+        "target \"test\"\n%0:1 = target \"OSC\" {\n  param \"FREQ\" = #48.0\n}\n",
+    );
+    roundtrip("target \"test\"\n%0:1 = target \"OSC\" {\n  param \"FREQ\" = #1.2e-5\n}\n");
     roundtrip(concat!(
         "target \"test\"\n",
         "!0 = source \"top.py\" (#1 #2) (#3 #4)\n",
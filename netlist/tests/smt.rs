@@ -1,6 +1,8 @@
 #[cfg(feature = "easy-smt")]
 mod smt {
-    use prjunnamed_netlist::{Design, EasySmtEngine};
+    use prjunnamed_netlist::{
+        Const, Design, EasySmtEngine, FlipFlop, Memory, MemoryReadPort, MemoryWritePort, Net, Value,
+    };
 
     #[test]
     fn test_demorgan_correct() -> Result<(), Box<dyn std::error::Error>> {
@@ -20,6 +22,71 @@ mod smt {
         Ok(())
     }
 
+    #[test]
+    fn test_popcnt_correct() -> Result<(), Box<dyn std::error::Error>> {
+        let _ = env_logger::try_init();
+
+        let mut design = Design::new();
+        let a = design.add_input("a", 2);
+        let y = design.add_popcnt(&a);
+        design.add_output("y", &y);
+        design.apply();
+
+        design.replace_value(y, design.add_adc(a.slice(..1), a.slice(1..), Net::ZERO));
+        design.verify(EasySmtEngine::z3()?)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_memory_async_write_correct() -> Result<(), Box<dyn std::error::Error>> {
+        let _ = env_logger::try_init();
+
+        let mut design = Design::new();
+        let data = design.add_input("data", 2);
+        let y = design.add_memory(Memory {
+            depth: 1,
+            width: 2,
+            init_value: Const::undef(2),
+            write_ports: vec![MemoryWritePort {
+                addr: Value::new(),
+                data: data.clone(),
+                mask: Value::ones(2),
+                clock: None,
+            }],
+            read_ports: vec![MemoryReadPort::new_asynchronous(Value::new(), 2)],
+        });
+        design.add_output("y", &y);
+        design.apply();
+
+        design.replace_value(y, data);
+        design.verify(EasySmtEngine::z3()?)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_memory_sync_read_correct() -> Result<(), Box<dyn std::error::Error>> {
+        let _ = env_logger::try_init();
+
+        let mut design = Design::new();
+        let clk = design.add_input("clk", 1);
+        let addr = design.add_input("addr", 1);
+        let rom = |read_port| Memory {
+            depth: 2,
+            width: 2,
+            init_value: Const::lit("0110"),
+            write_ports: vec![],
+            read_ports: vec![read_port],
+        };
+        let y = design.add_memory(rom(MemoryReadPort::new_clocked(addr.clone(), 2, clk[0])));
+        design.add_output("y", &y);
+        design.apply();
+
+        let data = design.add_memory(rom(MemoryReadPort::new_asynchronous(addr, 2)));
+        design.replace_value(y, design.add_dff(FlipFlop::new(data, clk[0])));
+        design.verify(EasySmtEngine::z3()?)?;
+        Ok(())
+    }
+
     #[test]
     fn test_demorgan_wrong() -> Result<(), Box<dyn std::error::Error>> {
         let _ = env_logger::try_init();
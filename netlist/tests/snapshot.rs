@@ -0,0 +1,73 @@
+use std::str::FromStr;
+
+use prjunnamed_netlist::{Cell, Design, MetaItem, Snapshot, SnapshotMetaItem, SourcePosition};
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn test_snapshot() {
+    assert_send_sync::<Snapshot>();
+
+    let mut design = Design::from_str(concat!(
+        "&\"pad\":2 = io\n",
+        "%0:4 = input \"a\"\n",
+        "%4:4 = input \"b\"\n",
+        "%8:4 = and %0:4 %4:4\n",
+        "%12:0 = output \"y\" %8:4\n",
+    ))
+    .unwrap();
+    let and_cell = design.iter_cells().nth(2).unwrap();
+    let file = design.add_metadata_string("top.v");
+    let source = design.add_metadata_item(&MetaItem::Source {
+        file,
+        start: SourcePosition { line: 1, column: 2 },
+        end: SourcePosition { line: 1, column: 3 },
+    });
+    and_cell.append_metadata(source);
+    design.apply();
+
+    let snapshot = design.snapshot();
+    // pending changes are not included
+    design.add_not(design.iter_cells().next().unwrap().output());
+    assert!(design.is_changed());
+
+    assert_eq!(snapshot.get_io("pad").unwrap().len(), 2);
+    assert_eq!(snapshot.iter_cells().count(), 4);
+    let (and_cell, bit) = snapshot.find_cell(snapshot.iter_cells().nth(2).unwrap().output()[3]).unwrap();
+    assert_eq!(bit, 3);
+    assert!(matches!(&*and_cell.get(), Cell::And(..)));
+    assert_eq!(and_cell.debug_index(), design.iter_cells().nth(2).unwrap().debug_index());
+    match and_cell.metadata().get() {
+        SnapshotMetaItem::Source { file, start, .. } => {
+            assert_eq!(file, "top.v");
+            assert_eq!(start, SourcePosition { line: 1, column: 2 });
+        }
+        item => panic!("unexpected metadata {item:?}"),
+    }
+    assert!(snapshot.iter_cells().next().unwrap().metadata().is_none());
+}
+
+#[test]
+fn test_snapshot_threads() {
+    let design = Design::from_str(concat!(
+        "%0:8 = input \"a\"\n",
+        "%8:8 = input \"b\"\n",
+        "%16:8 = and %0:8 %8:8\n",
+        "%24:8 = xor %16:8 %8:8\n",
+        "%32:8 = or %24:8 %0:8\n",
+        "%40:0 = output \"y\" %32:8\n",
+    ))
+    .unwrap();
+    let snapshot = design.snapshot();
+    let cells = Vec::from_iter(snapshot.iter_cells());
+    let counts = std::thread::scope(|scope| {
+        let threads = Vec::from_iter(cells.chunks(2).map(|chunk| {
+            scope.spawn(|| {
+                chunk.iter().map(|cell_ref| (cell_ref.debug_index(), cell_ref.output_len())).collect::<Vec<_>>()
+            })
+        }));
+        Vec::from_iter(threads.into_iter().flat_map(|thread| thread.join().unwrap()))
+    });
+    let expected = Vec::from_iter(design.iter_cells().map(|cell_ref| (cell_ref.debug_index(), cell_ref.output_len())));
+    assert_eq!(counts, expected);
+}
@@ -0,0 +1,58 @@
+use std::collections::HashMap;
+
+use prjunnamed_netlist::{Cell, Design, Net, Value};
+
+fn assert_uses_consistent(design: &Design) {
+    let mut expected: HashMap<Net, Vec<(usize, usize)>> = HashMap::new();
+    for cell_ref in design.iter_cells() {
+        let mut offset = 0;
+        cell_ref.visit(|net| {
+            expected.entry(net).or_default().push((cell_ref.debug_index(), offset));
+            offset += 1;
+        });
+    }
+    for cell_ref in design.iter_cells() {
+        for net in cell_ref.output().iter() {
+            let expected = expected.get(&net).cloned().unwrap_or_default();
+            let uses = Vec::from_iter(design.uses(net).iter().map(|(cell_ref, offset)| (cell_ref.debug_index(), *offset)));
+            assert_eq!(uses, expected, "uses of {} differ", design.display_net(net));
+            assert_eq!(design.use_count(net), expected.len());
+        }
+    }
+}
+
+#[test]
+fn test_use_index() {
+    let mut design = Design::new();
+    let a = design.add_input("a", 2);
+    let b = design.add_input("b", 2);
+    let x = design.add_and(&a, &b);
+    let y = design.add_xor(&x, &x);
+    design.add_output("y", &y);
+    design.apply();
+    assert_eq!(design.use_count(x[0]), 2);
+    assert_eq!(design.use_count(a[1]), 1);
+    assert_uses_consistent(&design);
+
+    // add a cell, and replace a net used by it and by existing cells
+    let void = design.add_void(2);
+    let z = design.add_or(&x, &void);
+    design.add_output("z", &z);
+    design.replace_value(&void, &b);
+    design.replace_value(&x, design.add_not(&a));
+    design.apply();
+    assert_eq!(design.use_count(x[0]), 0);
+    assert_uses_consistent(&design);
+
+    // replace a cell, and unalive another
+    let (or_cell, _) = design.find_cell(z[0]).unwrap();
+    or_cell.replace(Cell::And(a.clone(), b.clone()));
+    let (xor_cell, _) = design.find_cell(y[0]).unwrap();
+    xor_cell.unalive();
+    design.replace_value(&y, Value::zero(2));
+    design.apply();
+    assert_uses_consistent(&design);
+
+    design.compact();
+    assert_uses_consistent(&design);
+}
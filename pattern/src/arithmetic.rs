@@ -140,3 +140,49 @@ arithmetic_patterns! {
     PSModTrunc(_,_) => SModTrunc;
     PSModFloor(_,_) => SModFloor;
 }
+
+macro_rules! count_patterns {
+    {} => {};
+
+    { $name:ident(_) => $cstr:ident; $($rest:tt)* } => {
+        pub struct $name<P>(P);
+
+        impl<P> $name<P> {
+            pub fn new(pat: P) -> $name<P> {
+                $name(pat)
+            }
+        }
+
+        impl<P: Pattern<Value>> Pattern<Value> for $name<P> {
+            type Capture = (Value, P::Capture);
+
+            #[inline]
+            fn execute(&self, design: &dyn DesignDyn, target: &Value) -> Option<Self::Capture> {
+                if target.is_empty() { return None }
+                let cap;
+                if let Ok((cell_ref, 0)) = design.find_cell(target.iter().next().unwrap()) {
+                    if let Cell::$cstr(arg) = &*cell_ref.get() {
+                        if *target == cell_ref.output() {
+                            cap = arg.clone();
+                        } else {
+                            return None
+                        }
+                    } else {
+                        return None
+                    }
+                } else {
+                    return None
+                }
+                self.0.execute(design, &cap).and_then(|cap| Some((target.clone(), cap)))
+            }
+        }
+
+        count_patterns!{ $($rest)* }
+    }
+}
+
+count_patterns! {
+    PPopcnt(_) => Popcnt;
+    PClz(_) => Clz;
+    PCtz(_) => Ctz;
+}
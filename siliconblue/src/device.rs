@@ -0,0 +1,105 @@
+/// Device family. Families differ mostly in the set of hard IP blocks available to the fabric.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Family {
+    /// iCE65L01, iCE65L04, iCE65L08, iCE65P04.
+    ICE65,
+    /// iCE40LP and iCE40HX; the original iCE40 devices, with PLLs and block RAM only.
+    ICE40LpHx,
+    /// iCE40LM; adds oscillators and SPI/I2C hard IP.
+    ICE40Lm,
+    /// iCE40 Ultra (iCE5LP); adds DSP, LED and IR drivers.
+    ICE40Ultra,
+    /// iCE40 UltraLite (iCE40UL); adds barcode and IR400/IR500 drivers, drops SPI and DSP.
+    ICE40UltraLite,
+    /// iCE40 UltraPlus (iCE40UP); adds single-port RAM and the IR transceiver.
+    ICE40UltraPlus,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Device {
+    name: String,
+    family: Family,
+    io_banks: u32,
+    special_io_banks: &'static [u32],
+}
+
+// name, family, number of IO banks, and the banks whose IOs support IO standards other than `SB_LVCMOS` (differential
+// inputs, and on iCE65, the IO standards with a fixed VCCIO). the newer iCE40 families are not restricted here.
+const DEVICES: &[(&str, Family, u32, &[u32])] = &[
+    ("iCE65L01", Family::ICE65, 4, &[]),
+    ("iCE65L04", Family::ICE65, 4, &[3]),
+    ("iCE65L08", Family::ICE65, 4, &[3]),
+    ("iCE65P04", Family::ICE65, 4, &[3]),
+    ("iCE40LP384", Family::ICE40LpHx, 4, &[3]),
+    ("iCE40LP640", Family::ICE40LpHx, 4, &[3]),
+    ("iCE40LP1K", Family::ICE40LpHx, 4, &[3]),
+    ("iCE40LP4K", Family::ICE40LpHx, 4, &[3]),
+    ("iCE40LP8K", Family::ICE40LpHx, 4, &[3]),
+    ("iCE40HX1K", Family::ICE40LpHx, 4, &[3]),
+    ("iCE40HX4K", Family::ICE40LpHx, 4, &[3]),
+    ("iCE40HX8K", Family::ICE40LpHx, 4, &[3]),
+    ("iCE40LM1K", Family::ICE40Lm, 4, &[0, 1, 2, 3]),
+    ("iCE40LM2K", Family::ICE40Lm, 4, &[0, 1, 2, 3]),
+    ("iCE40LM4K", Family::ICE40Lm, 4, &[0, 1, 2, 3]),
+    ("iCE5LP1K", Family::ICE40Ultra, 3, &[0, 1, 2]),
+    ("iCE5LP2K", Family::ICE40Ultra, 3, &[0, 1, 2]),
+    ("iCE5LP4K", Family::ICE40Ultra, 3, &[0, 1, 2]),
+    ("iCE40UL640", Family::ICE40UltraLite, 3, &[0, 1, 2]),
+    ("iCE40UL1K", Family::ICE40UltraLite, 3, &[0, 1, 2]),
+    ("iCE40UP3K", Family::ICE40UltraPlus, 3, &[0, 1, 2]),
+    ("iCE40UP5K", Family::ICE40UltraPlus, 3, &[0, 1, 2]),
+];
+
+impl Device {
+    /// Looks up a device by its name (e.g. `iCE40UP5K`). The name is matched case-insensitively.
+    pub fn find(name: &str) -> Option<Device> {
+        DEVICES
+            .iter()
+            .find(|(device_name, ..)| device_name.eq_ignore_ascii_case(name))
+            .map(|&(name, family, io_banks, special_io_banks)| Device {
+                name: name.to_owned(),
+                family,
+                io_banks,
+                special_io_banks,
+            })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn family(&self) -> Family {
+        self.family
+    }
+
+    /// Returns the number of IO banks; the banks are numbered starting at 0.
+    pub fn io_banks(&self) -> u32 {
+        self.io_banks
+    }
+
+    /// Returns `true` if IOs in `bank` can use IO standards other than `SB_LVCMOS` (and the open-drain IOs).
+    pub fn has_special_io_standards(&self, bank: u32) -> bool {
+        self.special_io_banks.contains(&bank)
+    }
+
+    /// Returns `true` if the hard IP block `kind` (a target cell kind) exists on this device.
+    ///
+    /// Cells that are a part of the logic fabric itself (LUTs, flip-flops, IO) are always available.
+    pub fn has_primitive(&self, kind: &str) -> bool {
+        use Family::*;
+        let families: &[Family] = match kind {
+            "SB_LSOSC" | "SB_HSOSC" => &[ICE40Lm, ICE40Ultra],
+            "SB_LFOSC" | "SB_HFOSC" => &[ICE40UltraLite, ICE40UltraPlus],
+            "SB_I2C" | "SB_SPI" => &[ICE40Lm, ICE40Ultra, ICE40UltraPlus],
+            "SB_FILTER_50NS" => &[ICE40UltraPlus],
+            "SB_RGB_DRV" | "SB_LED_DRV_CUR" => &[ICE40Ultra, ICE40UltraPlus],
+            "SB_IR_DRV" | "SB_LEDD_IP" => &[ICE40Ultra],
+            "SB_RGBA_DRV" | "SB_LEDDA_IP" => &[ICE40UltraLite, ICE40UltraPlus],
+            "SB_IR400_DRV" | "SB_IR500_DRV" | "SB_BARCODE_DRV" => &[ICE40UltraLite],
+            "SB_IR_IP" | "SB_SPRAM256KA" => &[ICE40UltraPlus],
+            "SB_MAC16" => &[ICE40Ultra, ICE40UltraPlus],
+            _ => return true,
+        };
+        families.contains(&self.family)
+    }
+}
@@ -1,26 +1,54 @@
 //! This library covers the Lattice iCE65 and iCE40 FPGA families (acquired with SiliconBlue).
 
 use std::{
-    collections::{BTreeMap, BTreeSet, HashMap},
+    collections::{BTreeMap, BTreeSet},
+    error::Error,
+    fmt::Display,
     sync::Arc,
 };
 
-use prjunnamed_generic::{chain_rebalance, tree_rebalance};
 use prjunnamed_netlist::{
-    Cell, Const, Design, Instance, MetaItemRef, Net, ParamValue, Target, TargetCell, TargetImportError,
-    TargetPrototype, Trit, Value,
+    Cell, CellRef, Const, Design, EnumEncoding, FlipFlop, Instance, MetaItemRef, MulArchitecture, Net, ParamValue, PassManager,
+    Target, TargetCell, TargetImportError, TargetPrototype, Trit, Value,
 };
 
-use prjunnamed_lut::Lut;
+use prjunnamed_lut::{CarryStage, DelayModel, Lut, LutTarget};
 
+mod device;
 mod memory;
+mod validate;
+
+pub use device::{Device, Family};
 
 pub fn register() {
-    prjunnamed_netlist::register_target("siliconblue", |options| Ok(SiliconBlueTarget::new(options)));
+    prjunnamed_netlist::register_target("siliconblue", |options| Ok(SiliconBlueTarget::new(options)?));
+}
+
+/// An invalid option passed to [`SiliconBlueTarget::new`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TargetOptionError {
+    UnknownDevice(String),
+    UnknownOption(String),
+    /// The bank assigned to an IO (with a `bank.<io name>` option) is not a number, or does not exist on the device.
+    InvalidBank(String, String),
+}
+
+impl Display for TargetOptionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TargetOptionError::UnknownDevice(device) => write!(f, "unknown SiliconBlue device {device:?}"),
+            TargetOptionError::UnknownOption(name) => write!(f, "unknown SiliconBlue target option {name:?}"),
+            TargetOptionError::InvalidBank(io, bank) => write!(f, "invalid bank {bank:?} for IO {io:?}"),
+        }
+    }
 }
 
+impl Error for TargetOptionError {}
+
 #[derive(Debug)]
 pub struct SiliconBlueTarget {
+    device: Option<Device>,
+    io_banks: BTreeMap<String, u32>,
     prototypes: BTreeMap<String, TargetPrototype>,
 }
 
@@ -35,10 +63,46 @@ pub const SB_PLL40: &str = "SB_PLL40";
 pub const SB_MAC16: &str = "SB_MAC16";
 pub const SB_SPRAM256KA: &str = "SB_SPRAM256KA";
 
+// Hard IP cells whose parameters are all binary strings (`"0b0101"`) in the vendor library. These are imported
+// as bit parameters and exported back as strings.
+const BINARY_STRING_PARAM_CELLS: &[&str] = &[
+    "SB_HSOSC",
+    "SB_HFOSC",
+    "SB_I2C",
+    "SB_SPI",
+    "SB_RGB_DRV",
+    "SB_RGBA_DRV",
+    "SB_IR_DRV",
+    "SB_IR400_DRV",
+    "SB_IR500_DRV",
+    "SB_BARCODE_DRV",
+];
+
 impl SiliconBlueTarget {
-    pub fn new(options: BTreeMap<String, String>) -> Arc<Self> {
-        if !options.is_empty() {
-            panic!("SiliconBlue target doesn't take options");
+    pub fn new(options: BTreeMap<String, String>) -> Result<Arc<Self>, TargetOptionError> {
+        let mut device = None;
+        let mut io_banks = BTreeMap::new();
+        for (name, value) in options {
+            match name.as_str() {
+                "device" => match Device::find(&value) {
+                    Some(found) => device = Some(found),
+                    None => return Err(TargetOptionError::UnknownDevice(value)),
+                },
+                _ => {
+                    let Some(io_name) = name.strip_prefix("bank.") else {
+                        return Err(TargetOptionError::UnknownOption(name));
+                    };
+                    let Ok(bank) = value.parse::<u32>() else {
+                        return Err(TargetOptionError::InvalidBank(io_name.to_owned(), value));
+                    };
+                    io_banks.insert(io_name.to_owned(), bank);
+                }
+            }
+        }
+        if let Some(device) = &device
+            && let Some((io_name, bank)) = io_banks.iter().find(|&(_, &bank)| bank >= device.io_banks())
+        {
+            return Err(TargetOptionError::InvalidBank(io_name.clone(), bank.to_string()));
         }
         let mut prototypes = BTreeMap::new();
         prototypes.insert(
@@ -307,18 +371,337 @@ impl SiliconBlueTarget {
                 .add_input("POWEROFF", Const::undef(1))
                 .add_output("DATAOUT", 16),
         );
-        // TODO: SB_I2C, SB_SPI, SB_FILTER_50NS, SB_I2C_FIFO
-        // TODO: SB_LSOSC, SB_HSOSC, SB_LFOSC, SB_HFOSC
-        // TODO: SB_IR_DRV, SB_RGB_DRV, SB_LED_DRV_CUR, SB_LEDD_IP
-        // TODO: SB_BARCODE_DRV, SB_IR400_DRV, SB_IR500_DRV, SB_RGBA_DRV
-        // TODO: SB_LEDDA_IP, SB_IR_IP
+        // oscillators; the iCE40LM and iCE40 Ultra ones are called SB_[HL]SOSC, the later ones SB_[HL]FOSC
+        prototypes.insert(
+            "SB_LSOSC".into(),
+            TargetPrototype::new_has_state()
+                .add_input("CLKLFPU", Const::zero(1))
+                .add_input("CLKLFEN", Const::zero(1))
+                .add_output("CLKLF", 1),
+        );
+        prototypes.insert(
+            "SB_HSOSC".into(),
+            TargetPrototype::new_has_state()
+                .add_param_bits("CLKHF_DIV", Const::zero(2))
+                .add_input("CLKHFPU", Const::zero(1))
+                .add_input("CLKHFEN", Const::zero(1))
+                .add_output("CLKHF", 1),
+        );
+        prototypes.insert(
+            "SB_LFOSC".into(),
+            TargetPrototype::new_has_state()
+                .add_input("CLKLFPU", Const::zero(1))
+                .add_input("CLKLFEN", Const::zero(1))
+                .add_output("CLKLF", 1),
+        );
+        prototypes.insert(
+            "SB_HFOSC".into(),
+            TargetPrototype::new_has_state()
+                .add_param_bits("CLKHF_DIV", Const::zero(2))
+                .add_param_bits("TRIM_EN", Const::zero(1))
+                .add_input("CLKHFPU", Const::zero(1))
+                .add_input("CLKHFEN", Const::zero(1))
+                .add_input("TRIM", Const::zero(10)) // synthetic; vendor uses separate TRIM0-TRIM9 ports
+                .add_output("CLKHF", 1),
+        );
+        // system bus IP; the vendor cells use separate ports for each bit of SBADRI, SBDATI, SBDATO, MCSNO, MCSNOE
+        prototypes.insert(
+            "SB_I2C".into(),
+            TargetPrototype::new_has_state()
+                .add_param_bits("I2C_SLAVE_INIT_ADDR", Const::lit("1111100001"))
+                .add_param_bits("BUS_ADDR74", Const::lit("0001"))
+                .add_param_bits("SDA_INPUT_DELAYED", Const::zero(1))
+                .add_param_bits("SDA_OUTPUT_DELAYED", Const::zero(1))
+                .add_input("SBCLKI", Const::undef(1))
+                .add_input("SBRWI", Const::zero(1))
+                .add_input("SBSTBI", Const::zero(1))
+                .add_input("SBADRI", Const::zero(8)) // synthetic
+                .add_input("SBDATI", Const::zero(8)) // synthetic
+                .add_input("SCLI", Const::undef(1))
+                .add_input("SDAI", Const::undef(1))
+                .add_output("SBDATO", 8) // synthetic
+                .add_output("SBACKO", 1)
+                .add_output("I2CIRQ", 1)
+                .add_output("I2CWKUP", 1)
+                .add_output("SCLO", 1)
+                .add_output("SCLOE", 1)
+                .add_output("SDAO", 1)
+                .add_output("SDAOE", 1),
+        );
+        prototypes.insert(
+            "SB_SPI".into(),
+            TargetPrototype::new_has_state()
+                .add_param_bits("BUS_ADDR74", Const::lit("0000"))
+                .add_input("SBCLKI", Const::undef(1))
+                .add_input("SBRWI", Const::zero(1))
+                .add_input("SBSTBI", Const::zero(1))
+                .add_input("SBADRI", Const::zero(8)) // synthetic
+                .add_input("SBDATI", Const::zero(8)) // synthetic
+                .add_input("MI", Const::undef(1))
+                .add_input("SI", Const::undef(1))
+                .add_input("SCKI", Const::undef(1))
+                .add_input("SCSNI", Const::ones(1))
+                .add_output("SBDATO", 8) // synthetic
+                .add_output("SBACKO", 1)
+                .add_output("SPIIRQ", 1)
+                .add_output("SPIWKUP", 1)
+                .add_output("SO", 1)
+                .add_output("SOE", 1)
+                .add_output("MO", 1)
+                .add_output("MOE", 1)
+                .add_output("SCKO", 1)
+                .add_output("SCKOE", 1)
+                .add_output("MCSNO", 4) // synthetic
+                .add_output("MCSNOE", 4), // synthetic
+        );
+        prototypes.insert(
+            "SB_FILTER_50NS".into(),
+            TargetPrototype::new_has_state().add_input("FILTERIN", Const::undef(1)).add_output("FILTEROUT", 1),
+        );
+        // TODO: SB_I2C_FIFO
+        // LED and IR drivers; the pads they drive are dedicated and are not available as SB_IO
+        prototypes.insert(
+            "SB_LED_DRV_CUR".into(),
+            TargetPrototype::new_has_state().add_input("EN", Const::zero(1)).add_output("LEDPU", 1),
+        );
+        prototypes.insert(
+            "SB_RGB_DRV".into(),
+            TargetPrototype::new_has_effects()
+                .add_param_bits("RGB0_CURRENT", Const::zero(6))
+                .add_param_bits("RGB1_CURRENT", Const::zero(6))
+                .add_param_bits("RGB2_CURRENT", Const::zero(6))
+                .add_input("RGBPU", Const::zero(1))
+                .add_input("RGBLEDEN", Const::zero(1))
+                .add_input("RGB0PWM", Const::zero(1))
+                .add_input("RGB1PWM", Const::zero(1))
+                .add_input("RGB2PWM", Const::zero(1))
+                .add_io("RGB0", 1)
+                .add_io("RGB1", 1)
+                .add_io("RGB2", 1),
+        );
+        prototypes.insert(
+            "SB_RGBA_DRV".into(),
+            TargetPrototype::new_has_effects()
+                .add_param_bits("CURRENT_MODE", Const::zero(1))
+                .add_param_bits("RGB0_CURRENT", Const::zero(6))
+                .add_param_bits("RGB1_CURRENT", Const::zero(6))
+                .add_param_bits("RGB2_CURRENT", Const::zero(6))
+                .add_input("CURREN", Const::zero(1))
+                .add_input("RGBLEDEN", Const::zero(1))
+                .add_input("RGB0PWM", Const::zero(1))
+                .add_input("RGB1PWM", Const::zero(1))
+                .add_input("RGB2PWM", Const::zero(1))
+                .add_io("RGB0", 1)
+                .add_io("RGB1", 1)
+                .add_io("RGB2", 1),
+        );
+        prototypes.insert(
+            "SB_IR_DRV".into(),
+            TargetPrototype::new_has_effects()
+                .add_param_bits("IR_CURRENT", Const::zero(10))
+                .add_input("IRPU", Const::zero(1))
+                .add_input("IRLEDEN", Const::zero(1))
+                .add_input("IRPWM", Const::zero(1))
+                .add_io("IRLED", 1),
+        );
+        prototypes.insert(
+            "SB_IR400_DRV".into(),
+            TargetPrototype::new_has_effects()
+                .add_param_bits("CURRENT_MODE", Const::zero(1))
+                .add_param_bits("IR400_CURRENT", Const::zero(8))
+                .add_input("CURREN", Const::zero(1))
+                .add_input("IRLEDEN", Const::zero(1))
+                .add_input("IRPWM", Const::zero(1))
+                .add_io("IRLED", 1),
+        );
+        prototypes.insert(
+            "SB_IR500_DRV".into(),
+            TargetPrototype::new_has_effects()
+                .add_param_bits("CURRENT_MODE", Const::zero(1))
+                .add_param_bits("IR500_CURRENT", Const::zero(12))
+                .add_input("CURREN", Const::zero(1))
+                .add_input("IRLEDEN", Const::zero(1))
+                .add_input("IRPWM", Const::zero(1))
+                .add_io("IRLED1", 1)
+                .add_io("IRLED2", 1),
+        );
+        prototypes.insert(
+            "SB_BARCODE_DRV".into(),
+            TargetPrototype::new_has_effects()
+                .add_param_bits("CURRENT_MODE", Const::zero(1))
+                .add_param_bits("BARCODE_CURRENT", Const::zero(4))
+                .add_input("CURREN", Const::zero(1))
+                .add_input("BARCODEEN", Const::zero(1))
+                .add_input("BARCODEPWM", Const::zero(1))
+                .add_io("BARCODE", 1),
+        );
+        // LED PWM and IR transceiver IP; the vendor cells use separate ports for each bit of the buses
+        for (kind, has_reset) in [("SB_LEDD_IP", true), ("SB_LEDDA_IP", false)] {
+            let mut prototype = TargetPrototype::new_has_state()
+                .add_input("LEDDCS", Const::zero(1))
+                .add_input("LEDDCLK", Const::undef(1))
+                .add_input("LEDDDAT", Const::zero(8)) // synthetic
+                .add_input("LEDDADDR", Const::zero(4)) // synthetic
+                .add_input("LEDDDEN", Const::zero(1))
+                .add_input("LEDDEXE", Const::zero(1));
+            if has_reset {
+                prototype = prototype.add_input("LEDDRST", Const::zero(1));
+            }
+            prototypes.insert(
+                kind.into(),
+                prototype
+                    .add_output("PWMOUT", 3) // synthetic
+                    .add_output("LEDDON", 1),
+            );
+        }
+        prototypes.insert(
+            "SB_IR_IP".into(),
+            TargetPrototype::new_has_state()
+                .add_input("CLKI", Const::undef(1))
+                .add_input("CSI", Const::zero(1))
+                .add_input("DENI", Const::zero(1))
+                .add_input("EXE", Const::zero(1))
+                .add_input("LEARN", Const::zero(1))
+                .add_input("IRIN", Const::undef(1))
+                .add_input("ADRI", Const::zero(4)) // synthetic
+                .add_input("WDATA", Const::zero(8)) // synthetic
+                .add_output("RDATA", 8) // synthetic
+                .add_output("BUSY", 1)
+                .add_output("DRDY", 1)
+                .add_output("ERR", 1)
+                .add_output("IROUT", 1),
+        );
+
+        Ok(Arc::new(SiliconBlueTarget { device, io_banks, prototypes }))
+    }
 
-        Arc::new(SiliconBlueTarget { prototypes })
+    pub fn device(&self) -> Option<&Device> {
+        self.device.as_ref()
+    }
+
+    /// Returns the bank of the IO named `io_name`, if it was assigned one with a `bank.<io name>` option.
+    pub fn io_bank(&self, io_name: &str) -> Option<u32> {
+        self.io_banks.get(io_name).copied()
     }
 
     pub fn is_ice40(&self) -> bool {
-        // TODO: actually parse options and determine if we're ice40 or ice65
-        true
+        // without a device, assume the most capable family
+        self.device.as_ref().is_none_or(|device| device.family() != Family::ICE65)
+    }
+
+    // vendor cells describe buses as separate one-bit ports named `{name}0`, `{name}1`, ...; merge them into
+    // a single synthetic port, leaving the bits that aren't connected at their default value.
+    fn import_bus_input(
+        &self,
+        cell_ref: CellRef,
+        instance: &mut Instance,
+        name: &str,
+    ) -> Result<(), TargetImportError> {
+        let default = &self.prototypes[&instance.kind].get_input(name).unwrap().default;
+        if !(0..default.len()).any(|index| instance.inputs.contains_key(&format!("{name}{index}"))) {
+            return Ok(());
+        }
+        let mut value = Value::new();
+        for index in 0..default.len() {
+            let bit_name = format!("{name}{index}");
+            if let Some(input_bit) = instance.inputs.remove(&bit_name) {
+                if input_bit.len() != 1 {
+                    return Err(TargetImportError::input_size_mismatch(cell_ref, bit_name));
+                }
+                value.extend(input_bit);
+            } else {
+                value.push(default[index]);
+            }
+        }
+        if instance.inputs.insert(name.into(), value).is_some() {
+            return Err(TargetImportError::unknown_input(cell_ref, name));
+        }
+        Ok(())
+    }
+
+    // like `import_bus_input`, but for outputs; the merged output is appended to `instance_output`, since
+    // the one-bit outputs of the vendor cell are not necessarily adjacent.
+    fn import_bus_output(
+        &self,
+        design: &Design,
+        cell_ref: CellRef,
+        instance: &mut Instance,
+        instance_output: &mut Value,
+        name: &str,
+    ) -> Result<(), TargetImportError> {
+        let width = self.prototypes[&instance.kind].get_output(name).unwrap().len();
+        if !(0..width).any(|index| instance.outputs.contains_key(&format!("{name}{index}"))) {
+            return Ok(());
+        }
+        let start = instance_output.len();
+        for index in 0..width {
+            let bit_name = format!("{name}{index}");
+            if let Some(range) = instance.outputs.remove(&bit_name) {
+                if range.len() != 1 {
+                    return Err(TargetImportError::output_size_mismatch(cell_ref, bit_name));
+                }
+                instance_output.extend(instance_output.slice(range));
+            } else {
+                instance_output.extend(design.add_void(1));
+            }
+        }
+        if instance.outputs.insert(name.into(), start..start + width).is_some() {
+            return Err(TargetImportError::unknown_output(cell_ref, name));
+        }
+        Ok(())
+    }
+
+    // in mode N, the 256x16 array of SB_RAM40_4K is split into 2**N lanes: bit i of the word at address A is stored
+    // in bit `(i << N) + A[8+N-1:8]` of row `A[7:0]`, and only every 2**N-th bit of WDATA/RDATA is connected.
+    fn import_legacy_ram(
+        &self,
+        design: &Design,
+        cell_ref: CellRef,
+        instance: &mut Instance,
+        instance_output: &mut Value,
+        mode: usize,
+        init: Const,
+    ) -> Result<Const, TargetImportError> {
+        let width = 16 >> mode;
+        let lanes = 1 << mode;
+        let data_bit = |index: usize| (index << mode) + [0, 0, 1, 3][mode];
+        instance.add_param("READ_MODE", mode as i64);
+        instance.add_param("WRITE_MODE", mode as i64);
+        for name in ["RADDR", "WADDR"] {
+            if let Some(addr) = instance.inputs.remove(name) {
+                if addr.len() != 8 + mode {
+                    return Err(TargetImportError::input_size_mismatch(cell_ref, name));
+                }
+                instance.inputs.insert(name.into(), addr.zext(11));
+            }
+        }
+        if let Some(wdata) = instance.inputs.remove("WDATA") {
+            if wdata.len() != width {
+                return Err(TargetImportError::input_size_mismatch(cell_ref, "WDATA"));
+            }
+            let mut value = Value::undef(16);
+            for index in 0..width {
+                value[data_bit(index)] = wdata[index];
+            }
+            instance.inputs.insert("WDATA".into(), value);
+        }
+        if let Some(range) = instance.outputs.remove("RDATA") {
+            if range.len() != width {
+                return Err(TargetImportError::output_size_mismatch(cell_ref, "RDATA"));
+            }
+            let start = instance_output.len();
+            let mut rdata = design.add_void(16);
+            for index in 0..width {
+                rdata[data_bit(index)] = instance_output[range.start + index];
+            }
+            instance_output.extend(rdata);
+            instance.outputs.insert("RDATA".into(), start..start + 16);
+        }
+        Ok(Const::from_iter((0..0x1000).map(|index| {
+            let (row, bit) = (index / 16, index % 16);
+            let (lane, data_index) = (bit % lanes, bit / lanes);
+            init[((lane << 8) | row) * width + data_index]
+        })))
     }
 }
 
@@ -328,28 +711,44 @@ impl Target for SiliconBlueTarget {
     }
 
     fn options(&self) -> BTreeMap<String, String> {
-        BTreeMap::new()
+        let mut options = BTreeMap::new();
+        if let Some(device) = &self.device {
+            options.insert("device".into(), device.name().into());
+        }
+        for (io_name, bank) in &self.io_banks {
+            options.insert(format!("bank.{io_name}"), bank.to_string());
+        }
+        options
     }
 
     fn prototype(&self, name: &str) -> Option<&TargetPrototype> {
         self.prototypes.get(name)
     }
 
-    fn validate(&self, _design: &Design, _cell: &TargetCell) {
-        // TODO:
-        // - SB_IO:
-        //   - validate PACKAGE_PIN_B floating if IO not differential
-        //   - validate PULLUP off if IO differential or open-drain
-        // - SB_PLL40: validate ports / parameters unused according to mode
-        // - SB_MAC16: validate parameters
+    fn validate(&self, _design: &Design, cell: &TargetCell) {
+        if let Err(error) = self.check_cell(cell) {
+            panic!("invalid {} cell: {}", cell.kind, error);
+        }
+    }
+
+    fn validate_design(&self, design: &Design) {
+        if let Err(error) = self.check_design(design) {
+            panic!("invalid design: {error}");
+        }
     }
 
     fn import(&self, design: &mut Design) -> Result<(), TargetImportError> {
         for cell_ref in design.iter_cells() {
+            if let Cell::Memory(memory) = &*cell_ref.get()
+                && memory.write_ports.iter().any(|port| port.clock.is_none())
+            {
+                return Err(TargetImportError::unsupported(cell_ref, "asynchronous memory write ports are"));
+            }
             let Cell::Other(instance) = &*cell_ref.get() else { continue };
             let _guard = design.use_metadata_from(&[cell_ref]);
             let orig_kind = &instance.kind[..];
             let mut instance = instance.clone();
+            let mut instance_output = cell_ref.output();
             match orig_kind {
                 "GND" | "VCC" => {
                     let prototype = TargetPrototype::new_pure().add_output("Y", 1);
@@ -444,22 +843,23 @@ impl Target for SiliconBlueTarget {
                     instance.rename_output("DIN1", "D_IN_1");
                     instance.rename_io("PACKAGEPIN", "PACKAGE_PIN");
                 }
-                // TODO: lower SB_RAM256x16, SB_RAM512x8, SB_RAM1024x4, SB_RAM2048x2 here.
-                //
-                // you may think that it is an easy task that you can do when you have spare 30 minutes.
-                //
-                // you are horribly wrong.
-                //
-                // these cells fear neither God nor Satan.  you get to figure out the weird mapping of *ADDR bits,
-                // and the weird mapping of RDATA bits, and the weird mapping of WDATA bits, and also swizzle
-                // the entire INIT parameter.  you will end up cursing whatever gods you believe in for not striking
-                // you down with a merciful lightning bolt or perhaps burning out your eyes before you ever gazed
-                // upon the SB_RAM40 simulation model.
-                //
-                // you will also have to figure out how to implement output bit swizzling (and resizing) with our
-                // raw_import model which unfortunately is not really fit for the task.
-                "SB_RAM40_4K" | "SB_RAM40_4KNR" | "SB_RAM40_4KNW" | "SB_RAM40_4KNRNW" => {
-                    let mut kind = orig_kind.strip_prefix("SB_RAM40_4K").unwrap();
+                // the legacy fixed-geometry RAM cells are imported as SB_RAM40_4K in the corresponding mode. their
+                // INIT_* parameters describe the memory as a sequence of words (with word 0 in the low bits of
+                // INIT_0), and have to be rearranged into the physical layout.
+                "SB_RAM40_4K" | "SB_RAM40_4KNR" | "SB_RAM40_4KNW" | "SB_RAM40_4KNRNW" | "SB_RAM256x16"
+                | "SB_RAM256x16NR" | "SB_RAM256x16NW" | "SB_RAM256x16NRNW" | "SB_RAM512x8" | "SB_RAM512x8NR"
+                | "SB_RAM512x8NW" | "SB_RAM512x8NRNW" | "SB_RAM1024x4" | "SB_RAM1024x4NR" | "SB_RAM1024x4NW"
+                | "SB_RAM1024x4NRNW" | "SB_RAM2048x2" | "SB_RAM2048x2NR" | "SB_RAM2048x2NW" | "SB_RAM2048x2NRNW" => {
+                    let (mut kind, legacy_mode) = [
+                        ("SB_RAM40_4K", None),
+                        ("SB_RAM256x16", Some(0)),
+                        ("SB_RAM512x8", Some(1)),
+                        ("SB_RAM1024x4", Some(2)),
+                        ("SB_RAM2048x2", Some(3)),
+                    ]
+                    .into_iter()
+                    .find_map(|(prefix, mode)| Some((orig_kind.strip_prefix(prefix)?, mode)))
+                    .unwrap();
                     if let Some(rest) = kind.strip_prefix("NR") {
                         instance.add_param("IS_RCLK_INVERTED", true);
                         instance.rename_input("RCLKN", "RCLK");
@@ -491,6 +891,10 @@ impl Target for SiliconBlueTarget {
                             init.extend(Const::undef(256));
                         }
                     }
+                    if let Some(mode) = legacy_mode {
+                        init =
+                            self.import_legacy_ram(design, cell_ref, &mut instance, &mut instance_output, mode, init)?;
+                    }
                     instance.add_param("INIT", init);
                 }
                 "SB_PLL40_CORE" | "SB_PLL40_PAD" | "SB_PLL40_2_PAD" | "SB_PLL40_2F_CORE" | "SB_PLL40_2F_PAD" => {
@@ -503,8 +907,41 @@ impl Target for SiliconBlueTarget {
                         instance.rename_output("PLLOUTGLOBAL", "PLLOUTGLOBALA");
                     }
                 }
+                "SB_HFOSC" => {
+                    self.import_bus_input(cell_ref, &mut instance, "TRIM")?;
+                }
+                "SB_I2C" | "SB_SPI" => {
+                    self.import_bus_input(cell_ref, &mut instance, "SBADRI")?;
+                    self.import_bus_input(cell_ref, &mut instance, "SBDATI")?;
+                    self.import_bus_output(design, cell_ref, &mut instance, &mut instance_output, "SBDATO")?;
+                    if orig_kind == "SB_SPI" {
+                        self.import_bus_output(design, cell_ref, &mut instance, &mut instance_output, "MCSNO")?;
+                        self.import_bus_output(design, cell_ref, &mut instance, &mut instance_output, "MCSNOE")?;
+                    }
+                }
+                "SB_LEDD_IP" | "SB_LEDDA_IP" => {
+                    self.import_bus_input(cell_ref, &mut instance, "LEDDDAT")?;
+                    self.import_bus_input(cell_ref, &mut instance, "LEDDADDR")?;
+                    self.import_bus_output(design, cell_ref, &mut instance, &mut instance_output, "PWMOUT")?;
+                }
+                "SB_IR_IP" => {
+                    self.import_bus_input(cell_ref, &mut instance, "ADRI")?;
+                    self.import_bus_input(cell_ref, &mut instance, "WDATA")?;
+                    self.import_bus_output(design, cell_ref, &mut instance, &mut instance_output, "RDATA")?;
+                }
                 _ => {}
             }
+            if BINARY_STRING_PARAM_CELLS.contains(&orig_kind) {
+                for value in instance.params.values_mut() {
+                    let bits = match value {
+                        ParamValue::String(string) => string.strip_prefix("0b").and_then(|bits| bits.parse().ok()),
+                        _ => None,
+                    };
+                    if let Some(bits) = bits {
+                        *value = ParamValue::Const(bits);
+                    }
+                }
+            }
             if let Some(prototype) = self.prototypes.get(&instance.kind) {
                 if let Some(value) = instance.params.get_mut("IO_STANDARD") {
                     if let ParamValue::String(value) = value {
@@ -517,7 +954,7 @@ impl Target for SiliconBlueTarget {
 
                 cell_ref.unalive();
                 let (target_cell, value) = prototype
-                    .instance_to_target_cell(design, &instance, cell_ref.output())
+                    .instance_to_target_cell(design, &instance, instance_output)
                     .map_err(|cause| TargetImportError::new(cell_ref, cause))?;
                 design.replace_value(value, design.add_target(target_cell));
             }
@@ -541,6 +978,20 @@ impl Target for SiliconBlueTarget {
             }
         }
 
+        fn split_bus_input(instance: &mut Instance, name: &str) {
+            let value = instance.inputs.remove(name).unwrap();
+            for (index, net) in value.iter().enumerate() {
+                instance.inputs.insert(format!("{name}{index}"), Value::from(net));
+            }
+        }
+
+        fn split_bus_output(instance: &mut Instance, name: &str) {
+            let range = instance.outputs.remove(name).unwrap();
+            for (index, bit) in range.enumerate() {
+                instance.outputs.insert(format!("{name}{index}"), bit..bit + 1);
+            }
+        }
+
         for cell_ref in design.iter_cells() {
             let Cell::Target(target_cell) = &*cell_ref.get() else { continue };
             let _guard = design.use_metadata_from(&[cell_ref]);
@@ -642,6 +1093,8 @@ impl Target for SiliconBlueTarget {
                         removed_outputs.insert("GLOBAL_BUFFER_OUTPUT");
                     }
                 }
+                // the legacy fixed-geometry RAM cells are exported as SB_RAM40_4K in the mode they were imported with,
+                // which keeps the physical INIT layout and the data lanes they were rearranged into.
                 "SB_RAM40_4K" => {
                     for (param, suffix, name_from, name_to) in
                         [("IS_RCLK_INVERTED", "NR", "RCLK", "RCLKN"), ("IS_WCLK_INVERTED", "NW", "WCLK", "WCLKN")]
@@ -680,8 +1133,37 @@ impl Target for SiliconBlueTarget {
                     }
                     instance.kind = mode;
                 }
+                "SB_HFOSC" => {
+                    split_bus_input(&mut instance, "TRIM");
+                }
+                "SB_I2C" | "SB_SPI" => {
+                    split_bus_input(&mut instance, "SBADRI");
+                    split_bus_input(&mut instance, "SBDATI");
+                    split_bus_output(&mut instance, "SBDATO");
+                    if target_cell.kind == "SB_SPI" {
+                        split_bus_output(&mut instance, "MCSNO");
+                        split_bus_output(&mut instance, "MCSNOE");
+                    }
+                }
+                "SB_LEDD_IP" | "SB_LEDDA_IP" => {
+                    split_bus_input(&mut instance, "LEDDDAT");
+                    split_bus_input(&mut instance, "LEDDADDR");
+                    split_bus_output(&mut instance, "PWMOUT");
+                }
+                "SB_IR_IP" => {
+                    split_bus_input(&mut instance, "ADRI");
+                    split_bus_input(&mut instance, "WDATA");
+                    split_bus_output(&mut instance, "RDATA");
+                }
                 _ => (),
             }
+            if BINARY_STRING_PARAM_CELLS.contains(&target_cell.kind.as_str()) {
+                for value in instance.params.values_mut() {
+                    if let ParamValue::Const(bits) = value {
+                        *value = ParamValue::String(format!("0b{bits}"));
+                    }
+                }
+            }
             // perform output reconstruction surgery on the instance
             let mut new_output_map = vec![];
             let mut index = 0;
@@ -709,26 +1191,114 @@ impl Target for SiliconBlueTarget {
     }
 
     fn synthesize(&self, design: &mut Design) -> Result<(), ()> {
-        prjunnamed_generic::decision(design);
-        prjunnamed_generic::canonicalize(design);
-        self.lower_memories(design);
-        prjunnamed_generic::canonicalize(design);
-        design.rewrite(&[&prjunnamed_generic::LowerLt, &prjunnamed_generic::LowerMul, &prjunnamed_generic::LowerShift]);
-        prjunnamed_generic::canonicalize(design);
-        design.rewrite(&[
-            &prjunnamed_generic::Normalize,
-            &prjunnamed_generic::LowerEq,
-            &prjunnamed_generic::LowerMux,
-            &prjunnamed_generic::SimpleAigOpt,
-        ]);
-        self.lower_ffs(design);
-        self.lower_iobufs(design);
-        chain_rebalance(design);
-        prjunnamed_generic::canonicalize(design);
-        tree_rebalance(design);
-        self.lower_luts(design);
-        prjunnamed_generic::canonicalize(design);
-        Ok(())
+        let mut passes = PassManager::new();
+        self.register_passes(&mut passes);
+        passes.run(design, &self.synthesis_passes()).map_err(|_| ())
+    }
+
+    fn register_passes<'a>(&'a self, passes: &mut PassManager<'a>) {
+        prjunnamed_generic::register_passes(passes);
+        passes.register("merge-read-dffs", prjunnamed_memory::merge_read_dffs);
+        passes.register("lower-memories", |design| self.lower_memories(design));
+        passes.register("lower-ffs", |design| self.lower_ffs(design));
+        passes.register("lower-iobufs", |design| self.lower_iobufs(design));
+        passes.register("lower-luts", |design| self.lower_luts(design));
+    }
+
+    fn synthesis_passes(&self) -> Vec<&'static str> {
+        vec![
+            "fsm",
+            "memory-infer",
+            "share",
+            "decision",
+            "canonicalize",
+            "merge-read-dffs",
+            "lower-memories",
+            "canonicalize",
+            "lower-operators",
+            "canonicalize",
+            "lower-logic",
+            "fraig",
+            "lower-ffs",
+            "lower-iobufs",
+            "chain-rebalance",
+            "canonicalize",
+            "tree-rebalance",
+            "lower-luts",
+            "canonicalize",
+        ]
+    }
+
+    fn enum_encoding(&self) -> EnumEncoding {
+        // flip-flops are plentiful, and one-hot next state logic fits into few 4-input LUTs
+        EnumEncoding::OneHot
+    }
+
+    fn mul_architecture(&self) -> MulArchitecture {
+        // a full adder takes two 4-input LUTs, while an adder bit takes one LUT and the carry chain
+        MulArchitecture::BoothArray
+    }
+}
+
+// rough estimates for iCE40HX, in picoseconds
+struct SiliconBlueDelayModel;
+
+impl DelayModel for SiliconBlueDelayModel {
+    fn lut_delay(&self, _input_count: usize) -> u32 {
+        450
+    }
+
+    fn carry_delay(&self) -> u32 {
+        126
+    }
+
+    fn route_delay(&self, fanout: usize) -> u32 {
+        300 + 20 * (fanout.clamp(1, 32) as u32 - 1)
+    }
+}
+
+// the carry chain of a logic cell computes `CO = MAJ(I[1], I[2], CI)`, and its LUT can use CI as the input I[3];
+// this leaves I[0] free to implement a function of the sum and one more net.
+fn carry_stage_inputs(stage: &CarryStage) -> [Option<Net>; 4] {
+    [None, Some(stage.arg1), Some(stage.arg2), Some(stage.ci)]
+}
+
+impl LutTarget for SiliconBlueTarget {
+    fn lut_size(&self) -> usize {
+        4
+    }
+
+    fn delay_model(&self) -> &dyn DelayModel {
+        &SiliconBlueDelayModel
+    }
+
+    fn materialize_lut(&self, design: &Design, lut: &Lut, meta: MetaItemRef) -> Net {
+        let sb_lut4 = self.prototype(SB_LUT4).unwrap();
+        let lut = lut.expand_to(4).unwrap();
+        let mut target_cell = TargetCell::new(SB_LUT4, sb_lut4);
+        sb_lut4.apply_param(&mut target_cell, "LUT_INIT", lut.table());
+        sb_lut4.apply_input(&mut target_cell, "I", lut.inputs());
+        let target_output = design.add_cell_with_metadata_ref(Cell::Target(target_cell), meta);
+        sb_lut4.extract_output(&target_output, "O")[0]
+    }
+
+    fn absorb_into_carry(&self, stage: &CarryStage, lut: &Lut) -> Option<Lut> {
+        lut.expand_with_fixed(&carry_stage_inputs(stage))
+    }
+
+    fn materialize_carry(&self, design: &Design, stage: &CarryStage) -> (Net, Net) {
+        let sb_lut4_carry = self.prototype(SB_LUT4_CARRY).unwrap();
+        let lut = stage.lut.expand_with_fixed(&carry_stage_inputs(stage)).unwrap();
+        let inputs = lut.inputs();
+        let mut target_cell = TargetCell::new(SB_LUT4_CARRY, sb_lut4_carry);
+        sb_lut4_carry.apply_param(&mut target_cell, "LUT_INIT", lut.table());
+        sb_lut4_carry.apply_param(&mut target_cell, "IS_I3_CI", inputs[3] == stage.ci);
+        sb_lut4_carry.apply_input(&mut target_cell, "I", inputs);
+        sb_lut4_carry.apply_input(&mut target_cell, "CI", stage.ci);
+        let target_output = design.add_cell_with_metadata_ref(Cell::Target(target_cell), stage.meta);
+        let o = sb_lut4_carry.extract_output(&target_output, "O")[0];
+        let co = sb_lut4_carry.extract_output(&target_output, "CO")[0];
+        (o, co)
     }
 }
 
@@ -765,7 +1335,36 @@ impl SiliconBlueTarget {
         design.compact();
     }
 
+    // the flip-flops of a logic cell are always initialized to zero, so a flip-flop bit with an initial value of one
+    // is stored inverted. if a counter's bits were inverted one by one, each inverted bit of the count would need
+    // an extra LUT in front of the carry chain; instead, a counter initialized to all ones stores the complement
+    // of the count, which is a counter as well, since `~(count + step) = ~count + ~step + 1`.
+    //
+    // a counter with a mix of zero and one initial bits would need a LUT between the carry chain segments of
+    // different polarity, and is left to the per-bit inversion.
+    fn invert_counters(&self, design: &mut Design) {
+        for counter in prjunnamed_generic::counters(design) {
+            let init_value = &counter.flip_flop.init_value;
+            if !init_value.iter().all(|trit| trit != Trit::Zero) || !init_value.iter().any(|trit| trit == Trit::One) {
+                continue;
+            }
+            let output = design.add_void(counter.width());
+            let sum = design.add_adc(&output, counter.step.not(), Net::ONE);
+            let flip_flop = counter.flip_flop.clone().with_data(sum.slice(..counter.width()));
+            let inverted = design.add_dff(FlipFlop {
+                clear_value: flip_flop.clear_value.not(),
+                reset_value: flip_flop.reset_value.not(),
+                init_value: flip_flop.init_value.not(),
+                ..flip_flop
+            });
+            design.replace_value(&output, &inverted);
+            design.replace_value(&counter.output, design.add_not(&inverted));
+        }
+        design.compact();
+    }
+
     pub fn lower_ffs(&self, design: &mut Design) {
+        self.invert_counters(design);
         let prototype = self.prototype(SB_DFF).unwrap();
         for cell_ref in design.iter_cells() {
             let Cell::Dff(flip_flop) = &*cell_ref.get() else { continue };
@@ -814,239 +1413,7 @@ impl SiliconBlueTarget {
         design.compact();
     }
 
-    // a fanfic of https://people.eecs.berkeley.edu/~alanmi/publications/2007/tech07_fast.pdf
     pub fn lower_luts(&self, design: &mut Design) {
-        let mut use_count: HashMap<_, u32> = HashMap::new();
-        for cell in design.iter_cells() {
-            if matches!(&*cell.get(), Cell::Debug(..)) {
-                continue;
-            }
-            cell.visit(|net| *use_count.entry(net).or_default() += 1);
-        }
-
-        let mut adc_carries = HashMap::new();
-        for cell in design.iter_cells() {
-            if let Cell::Adc(_, _, ci) = &*cell.get() {
-                let output = cell.output();
-                let mut carry = Value::from(ci);
-                if output.len() > 1 {
-                    carry.extend(design.add_void(output.len() - 2));
-                    carry.extend([output.msb()]);
-                }
-                adc_carries.insert(cell, carry);
-            }
-        }
-
-        // TODO (later): emit void nets for mux intermediate products
-
-        #[derive(Debug, Clone)]
-        enum NetDisposition {
-            Lut(u32, Lut),
-            LutCarry(u32, Lut, Net, Net),
-            CarryOut(u32),
-        }
-
-        impl NetDisposition {
-            fn depth(&self) -> u32 {
-                match *self {
-                    NetDisposition::Lut(depth, _) => depth,
-                    NetDisposition::LutCarry(depth, _, _, _) => depth,
-                    NetDisposition::CarryOut(depth) => depth,
-                }
-            }
-        }
-
-        let mut net_dispositions: BTreeMap<Net, (NetDisposition, MetaItemRef)> = BTreeMap::new();
-        for cell in design.iter_cells_topo() {
-            match &*cell.get() {
-                Cell::Buf(..)
-                | Cell::Not(..)
-                | Cell::And(..)
-                | Cell::Or(..)
-                | Cell::Xor(..)
-                | Cell::Mux(..)
-                | Cell::Aig(..) => {
-                    let output = cell.output();
-                    'cell_bits: for index in 0..output.len() {
-                        let mut lut = if matches!(&*cell.get(), Cell::Aig(..)) {
-                            Lut::from_cell(cell.get()).unwrap()
-                        } else {
-                            Lut::from_cell(cell.get().slice(index..index + 1).unwrap()).unwrap()
-                        };
-                        let mut meta = cell.metadata();
-                        let mut full_merge_lut = lut.clone();
-                        let mut full_merge_meta = meta;
-                        for net in lut.inputs() {
-                            if let Some((NetDisposition::Lut(_depth, input_lut), input_meta)) =
-                                net_dispositions.get(&net)
-                            {
-                                let Some(input_index) = full_merge_lut.inputs().iter().position(|input| input == net)
-                                else {
-                                    // input disappeared — optimized out by earlier merge?
-                                    continue;
-                                };
-                                full_merge_lut = full_merge_lut.merge(input_index, input_lut);
-                                full_merge_meta = full_merge_meta.merge(*input_meta);
-                            }
-                        }
-                        if full_merge_lut.inputs().len() <= 4 {
-                            lut = full_merge_lut;
-                            meta = full_merge_meta;
-                        } else {
-                            let mut inputs_by_depth = Vec::from_iter(lut.inputs().iter().map(|net| {
-                                (net_dispositions.get(&net).map(|(disp, _meta)| disp.depth()).unwrap_or(0), net)
-                            }));
-
-                            inputs_by_depth.sort_by_key(|&(depth, net)| (std::cmp::Reverse(depth), net));
-                            for (_, net) in inputs_by_depth {
-                                let Some(input_disposition) = net_dispositions.get(&net) else {
-                                    continue;
-                                };
-                                match *input_disposition {
-                                    (NetDisposition::CarryOut(_), _) => (),
-                                    (NetDisposition::Lut(_depth, ref input_lut), input_meta) => {
-                                        let Some(input_index) = lut.inputs().iter().position(|input| input == net)
-                                        else {
-                                            // input disappeared — optimized out by earlier merge?
-                                            continue;
-                                        };
-                                        let merged_lut = lut.merge(input_index, input_lut);
-                                        if merged_lut.inputs().len() <= 4 {
-                                            lut = merged_lut;
-                                            meta = meta.merge(input_meta);
-                                        }
-                                    }
-                                    (NetDisposition::LutCarry(depth, ref input_lut, ci, co), input_meta) => {
-                                        if use_count[&net] != 1 {
-                                            continue;
-                                        }
-                                        let Some(input_index) = lut.inputs().iter().position(|input| input == net)
-                                        else {
-                                            // input disappeared — optimized out by earlier merge?
-                                            continue;
-                                        };
-
-                                        if let Some(merged_lut) =
-                                            lut.merge(input_index, input_lut).expand_with_fixed(&[
-                                                None,
-                                                Some(input_lut.inputs()[1]),
-                                                Some(input_lut.inputs()[2]),
-                                                if input_lut.inputs()[3] == ci {
-                                                    Some(input_lut.inputs()[3])
-                                                } else {
-                                                    None
-                                                },
-                                            ])
-                                        {
-                                            net_dispositions.remove(&net);
-                                            let depth = (merged_lut
-                                                .inputs()
-                                                .iter()
-                                                .map(|net| {
-                                                    net_dispositions
-                                                        .get(&net)
-                                                        .map(|(disp, _meta)| disp.depth())
-                                                        .unwrap_or(0)
-                                                })
-                                                .max()
-                                                .unwrap_or(0)
-                                                + 1)
-                                            .max(depth);
-                                            net_dispositions.insert(
-                                                output[index],
-                                                (
-                                                    NetDisposition::LutCarry(depth, merged_lut, ci, co),
-                                                    meta.merge(input_meta),
-                                                ),
-                                            );
-                                            continue 'cell_bits;
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                        let depth = lut
-                            .inputs()
-                            .iter()
-                            .map(|net| net_dispositions.get(&net).map(|(disp, _meta)| disp.depth()).unwrap_or(0))
-                            .max()
-                            .unwrap_or(0)
-                            + 1;
-                        net_dispositions.insert(output[index], (NetDisposition::Lut(depth, lut), meta));
-                    }
-                    cell.unalive();
-                }
-                Cell::Adc(arg1, arg2, ci) => {
-                    // TODO: check if doable in one step
-                    let output = cell.output();
-                    let carry = &adc_carries[&cell];
-                    let mut max_depth = net_dispositions.get(ci).map(|(disp, _meta)| disp.depth()).unwrap_or(0);
-                    for index in 0..output.len() - 1 {
-                        for net in [arg1[index], arg2[index]] {
-                            max_depth = max_depth
-                                .max(net_dispositions.get(&net).map(|(disp, _meta)| disp.depth()).unwrap_or(0));
-                        }
-                        net_dispositions.insert(
-                            output[index],
-                            (
-                                NetDisposition::LutCarry(
-                                    max_depth + 1,
-                                    Lut::new_fixed(
-                                        Value::from_iter([Net::UNDEF, arg1[index], arg2[index], carry[index]]),
-                                        Const::lit("1100001100111100"),
-                                    ),
-                                    carry[index],
-                                    carry[index + 1],
-                                ),
-                                cell.metadata(),
-                            ),
-                        );
-                    }
-                    net_dispositions.insert(output.msb(), (NetDisposition::CarryOut(max_depth + 1), cell.metadata()));
-                    cell.unalive();
-                }
-                _ => {}
-            }
-        }
-
-        let sb_lut4 = self.prototype(SB_LUT4).unwrap();
-        let sb_lut4_carry = self.prototype(SB_LUT4_CARRY).unwrap();
-        for (net, (disposition, metadata)) in net_dispositions {
-            match disposition {
-                NetDisposition::CarryOut(_) => {}
-                NetDisposition::Lut(_, lut) => {
-                    let lut = lut.expand_to(4).unwrap();
-                    let mut target_cell = TargetCell::new(SB_LUT4, sb_lut4);
-                    sb_lut4.apply_param(&mut target_cell, "LUT_INIT", lut.table());
-                    sb_lut4.apply_input(&mut target_cell, "I", lut.inputs());
-                    let target_output = design.add_cell_with_metadata_ref(Cell::Target(target_cell), metadata);
-                    let o = sb_lut4.extract_output(&target_output, "O");
-                    design.replace_value(net, o);
-                }
-                NetDisposition::LutCarry(_, lut, net_ci, net_co) => {
-                    let lut = lut
-                        .expand_with_fixed(&[
-                            None,
-                            Some(lut.inputs()[1]),
-                            Some(lut.inputs()[2]),
-                            if lut.inputs()[3] == net_ci { Some(lut.inputs()[3]) } else { None },
-                        ])
-                        .unwrap();
-                    let mut target_cell = TargetCell::new(SB_LUT4_CARRY, sb_lut4_carry);
-                    sb_lut4_carry.apply_param(&mut target_cell, "LUT_INIT", lut.table());
-                    let inputs = lut.inputs();
-                    sb_lut4_carry.apply_param(&mut target_cell, "IS_I3_CI", inputs[3] == net_ci);
-                    sb_lut4_carry.apply_input(&mut target_cell, "I", inputs);
-                    sb_lut4_carry.apply_input(&mut target_cell, "CI", net_ci);
-                    let target_output = design.add_cell_with_metadata_ref(Cell::Target(target_cell), metadata);
-                    let o = sb_lut4_carry.extract_output(&target_output, "O");
-                    design.replace_value(net, o);
-                    let co = sb_lut4_carry.extract_output(&target_output, "CO");
-                    design.replace_value(net_co, co);
-                }
-            }
-        }
-
-        design.compact();
+        prjunnamed_lut::lower_luts(design, self);
     }
 }
@@ -123,8 +123,9 @@ impl SiliconBlueTarget {
         assert!(memory.depth * memory.width <= 0x1000);
 
         if let Some(write_port) = memory.write_ports.get(0) {
-            prototype.apply_param(&mut target_cell, "IS_WCLK_INVERTED", write_port.clock.is_negative());
-            prototype.apply_input(&mut target_cell, "WCLK", write_port.clock.net());
+            let write_clock = write_port.clock.unwrap();
+            prototype.apply_param(&mut target_cell, "IS_WCLK_INVERTED", write_clock.is_negative());
+            prototype.apply_input(&mut target_cell, "WCLK", write_clock.net());
             prototype.apply_input(&mut target_cell, "WE", Net::ONE);
             prototype.apply_input(
                 &mut target_cell,
@@ -277,6 +278,11 @@ impl SiliconBlueTarget {
     pub fn lower_memories(&self, design: &mut Design) {
         for cell_ref in design.iter_cells() {
             let Cell::Memory(memory) = &*cell_ref.get() else { continue };
+            // rejected by `import`
+            assert!(
+                memory.write_ports.iter().all(|port| port.clock.is_some()),
+                "asynchronous memory write ports are not supported by SiliconBlue targets"
+            );
             let _guard = design.use_metadata_from(&[cell_ref]);
             let output = cell_ref.output();
             let bram_lowering = self.find_bram_lowering(memory);
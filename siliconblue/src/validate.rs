@@ -0,0 +1,346 @@
+use std::collections::BTreeMap;
+
+use prjunnamed_netlist::{Cell, Const, Design, IoValue, Net, ParamValue, Target, TargetCell, TargetPrototype};
+
+use crate::{Family, SiliconBlueTarget, SB_IO};
+
+// parameters that may legitimately contain undefined bits; every other bit parameter configures the hardware
+// and has to be fully defined.
+const UNDEF_OK_PARAMS: &[&str] = &["LUT_INIT", "INIT", "RESET_VALUE"];
+
+fn param_const<'a>(prototype: &TargetPrototype, cell: &'a TargetCell, name: &str) -> &'a Const {
+    match prototype.extract_param(cell, name) {
+        ParamValue::Const(value) => value,
+        _ => unreachable!(),
+    }
+}
+
+fn param_uint(prototype: &TargetPrototype, cell: &TargetCell, name: &str) -> u64 {
+    u64::try_from(param_const(prototype, cell, name)).unwrap()
+}
+
+fn param_string<'a>(prototype: &TargetPrototype, cell: &'a TargetCell, name: &str) -> &'a str {
+    match prototype.extract_param(cell, name) {
+        ParamValue::String(value) => value,
+        _ => unreachable!(),
+    }
+}
+
+fn extract_io(prototype: &TargetPrototype, cell: &TargetCell, name: &str) -> IoValue {
+    cell.ios.slice(prototype.get_io(name).unwrap().range.clone())
+}
+
+// the VCCIO that a bank must be powered with for an IO standard; `SB_LVCMOS` (and open-drain) IOs work at any VCCIO.
+fn io_standard_vccio(io_standard: &str) -> Option<&'static str> {
+    match io_standard {
+        "SB_LVCMOS" | "OPEN_DRAIN" => None,
+        "SB_LVDS_INPUT" => Some("2.5V"),
+        "SB_SUBLVDS_INPUT" => Some("1.8V"),
+        _ if io_standard.starts_with("SB_LVCMOS33_") => Some("3.3V"),
+        _ if io_standard.starts_with("SB_LVCMOS25_") || io_standard.starts_with("SB_SSTL2_") => Some("2.5V"),
+        _ if io_standard.starts_with("SB_LVCMOS18_")
+            || io_standard.starts_with("SB_SSTL18_")
+            || io_standard.starts_with("SB_MDDR") =>
+        {
+            Some("1.8V")
+        }
+        _ if io_standard.starts_with("SB_LVCMOS15_") => Some("1.5V"),
+        _ => unreachable!(),
+    }
+}
+
+// the bits of RDATA and WDATA used by SB_RAM40_4K in a given READ_MODE or WRITE_MODE; see also `import_legacy_ram`.
+fn ram_data_bits(mode: i64) -> Vec<usize> {
+    let mode = mode as usize;
+    Vec::from_iter((0..16 >> mode).map(|index| (index << mode) + [0, 0, 1, 3][mode]))
+}
+
+// the frequency ranges of the PLL phase-frequency detector and VCO, in MHz
+const PLL_PFD_RANGE: (u64, u64) = (10, 133);
+const PLL_VCO_RANGE: (u64, u64) = (533, 1066);
+
+impl SiliconBlueTarget {
+    /// Checks a target cell against the constraints of the hardware (and of the selected device, if any).
+    ///
+    /// Returns a description of the first violated constraint. [`Target::validate`] panics with this description.
+    ///
+    /// Each cell is checked on its own; the constraints between cells are checked by [`Self::check_design`].
+    ///
+    /// [`Target::validate`]: prjunnamed_netlist::Target::validate
+    pub fn check_cell(&self, cell: &TargetCell) -> Result<(), String> {
+        let prototype = self.prototype(&cell.kind).unwrap();
+        if let Some(device) = self.device().filter(|device| !device.has_primitive(&cell.kind)) {
+            return Err(format!("{} is not available on {}", cell.kind, device.name()));
+        }
+        for param in &prototype.params {
+            if UNDEF_OK_PARAMS.contains(&param.name.as_str()) {
+                continue;
+            }
+            match &cell.params[param.index] {
+                ParamValue::Const(value) if value.has_undef() => {
+                    return Err(format!("parameter {} must not contain undefined bits (got {value})", param.name));
+                }
+                _ => (),
+            }
+        }
+        match &cell.kind[..] {
+            "SB_LUT4_CARRY" => self.check_lut4_carry(prototype, cell),
+            "SB_IO" => self.check_io(prototype, cell),
+            "SB_RAM40_4K" => self.check_ram40_4k(prototype, cell),
+            "SB_PLL40" => self.check_pll40(prototype, cell),
+            "SB_MAC16" => self.check_mac16(prototype, cell),
+            "SB_HFOSC" => self.check_hfosc(prototype, cell),
+            "SB_I2C" | "SB_SPI" => self.check_sysbus_ip(prototype, cell),
+            "SB_RGB_DRV" | "SB_RGBA_DRV" => self.check_rgb_drv(prototype, cell),
+            _ => Ok(()),
+        }
+    }
+
+    fn check_lut4_carry(&self, prototype: &TargetPrototype, cell: &TargetCell) -> Result<(), String> {
+        // the LUT input I[3] is replaced by CI when IS_I3_CI is set; anything else connected to it would be lost.
+        if prototype.extract_param_bool(cell, "IS_I3_CI") {
+            let i3 = prototype.extract_input(cell, "I")[3];
+            let ci = prototype.extract_input(cell, "CI")[0];
+            if !(i3 == ci || i3 == Net::UNDEF) {
+                return Err("input I[3] must be connected to CI or undefined when IS_I3_CI is set".into());
+            }
+        }
+        Ok(())
+    }
+
+    fn check_io(&self, prototype: &TargetPrototype, cell: &TargetCell) -> Result<(), String> {
+        let io_standard = param_string(prototype, cell, "IO_STANDARD");
+        let pin_type = param_uint(prototype, cell, "PIN_TYPE");
+        let is_differential = matches!(io_standard, "SB_LVDS_INPUT" | "SB_SUBLVDS_INPUT");
+        let is_open_drain = io_standard == "OPEN_DRAIN";
+        if is_open_drain {
+            // open-drain IOs (SB_IO_OD) only exist on the newer iCE40 families
+            if let Some(device) = self
+                .device()
+                .filter(|device| !matches!(device.family(), Family::ICE40UltraLite | Family::ICE40UltraPlus))
+            {
+                return Err(format!("IO standard OPEN_DRAIN is not available on {}", device.name()));
+            }
+        } else if self.is_ice40() && !matches!(io_standard, "SB_LVCMOS" | "SB_LVDS_INPUT") {
+            return Err(format!("IO standard {io_standard} is only available on iCE65 devices"));
+        }
+        if matches!(pin_type >> 2, 0b0001..=0b0011) {
+            return Err(format!("PIN_TYPE {pin_type:06b} has an invalid output configuration"));
+        }
+        if is_differential && pin_type >> 2 != 0 {
+            return Err(format!("IO standard {io_standard} is input-only, but PIN_TYPE {pin_type:06b} has an output"));
+        }
+        if !is_differential && !extract_io(prototype, cell, "PACKAGE_PIN_B").iter().all(|net| net.is_floating()) {
+            return Err(format!("PACKAGE_PIN_B must be unconnected for non-differential IO standard {io_standard}"));
+        }
+        if (is_differential || is_open_drain) && prototype.extract_param_bool(cell, "PULLUP") {
+            return Err(format!("PULLUP is not available with IO standard {io_standard}"));
+        }
+        if (is_differential || is_open_drain) && prototype.extract_param_bool(cell, "IS_GB") {
+            return Err(format!("global buffer IO (IS_GB) is not available with IO standard {io_standard}"));
+        }
+        Ok(())
+    }
+
+    fn check_ram40_4k(&self, prototype: &TargetPrototype, cell: &TargetCell) -> Result<(), String> {
+        for (mode_name, addr_name, data_name) in [("READ_MODE", "RADDR", None), ("WRITE_MODE", "WADDR", Some("WDATA"))] {
+            let ParamValue::Int(mode) = *prototype.extract_param(cell, mode_name) else { unreachable!() };
+            if !self.is_ice40() && mode != 0 {
+                return Err(format!("{mode_name} must be 0 on iCE65 (only the 256x16 configuration exists)"));
+            }
+            // the inputs that a mode does not use are ignored; anything but a constant connected to them would be lost
+            let addr = prototype.extract_input(cell, addr_name);
+            if let Some(index) = (8 + mode as usize..11).find(|&index| !addr[index].is_const()) {
+                return Err(format!("{addr_name}[{index}] is not used in {mode_name} {mode} and must be constant"));
+            }
+            if let Some(data_name) = data_name {
+                let data = prototype.extract_input(cell, data_name);
+                let used_bits = ram_data_bits(mode);
+                if let Some(index) = (0..16).find(|index| !used_bits.contains(index) && !data[*index].is_const()) {
+                    return Err(format!("{data_name}[{index}] is not used in {mode_name} {mode} and must be constant"));
+                }
+                if mode != 0 && !prototype.extract_input(cell, "MASK").iter().all(|net| net.is_const()) {
+                    return Err(format!("MASK is only used in {mode_name} 0 and must be constant"));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn check_pll40(&self, prototype: &TargetPrototype, cell: &TargetCell) -> Result<(), String> {
+        let mode = param_string(prototype, cell, "MODE");
+        let feedback_path = param_string(prototype, cell, "FEEDBACK_PATH");
+        let divr = param_uint(prototype, cell, "DIVR");
+        let divf = param_uint(prototype, cell, "DIVF");
+        let divq = param_uint(prototype, cell, "DIVQ");
+        if !(1..=6).contains(&divq) {
+            return Err(format!("DIVQ must be between 1 and 6 (got {divq})"));
+        }
+        if param_uint(prototype, cell, "TEST_MODE") != 0 {
+            return Err("TEST_MODE must be 0".into());
+        }
+        if mode.ends_with("_CORE") {
+            if !extract_io(prototype, cell, "PACKAGEPIN").iter().all(|net| net.is_floating()) {
+                return Err(format!("PACKAGEPIN must be unconnected in mode {mode}; use REFERENCECLK instead"));
+            }
+        } else if !prototype.extract_input(cell, "REFERENCECLK").is_undef() {
+            return Err(format!("REFERENCECLK must be unconnected in mode {mode}; use PACKAGEPIN instead"));
+        }
+        let mut used_ports = vec![];
+        if matches!(mode, "SB_PLL40_CORE" | "SB_PLL40_PAD") {
+            used_ports.push("PORTA");
+            for (name, default) in [("PLLOUT_SELECT_PORTB", "GENCLK"), ("ENABLE_ICEGATE_PORTB", "0")] {
+                let value = match prototype.extract_param(cell, name) {
+                    ParamValue::String(value) => value.clone(),
+                    ParamValue::Const(value) => value.to_string(),
+                    _ => unreachable!(),
+                };
+                if value != default {
+                    return Err(format!("{name} must be {default} in single-output mode {mode}"));
+                }
+            }
+        } else if mode == "SB_PLL40_2_PAD" {
+            // port A of this mode is the pad input passed through
+            used_ports.push("PORTB");
+            if param_string(prototype, cell, "PLLOUT_SELECT_PORTA") != "GENCLK" {
+                return Err(format!("PLLOUT_SELECT_PORTA must be GENCLK in mode {mode}"));
+            }
+        } else {
+            used_ports.extend(["PORTA", "PORTB"]);
+        }
+        for port in used_ports {
+            let select = param_string(prototype, cell, &format!("PLLOUT_SELECT_{port}"));
+            if select.starts_with("SHIFTREG_") && feedback_path != "PHASE_AND_DELAY" {
+                return Err(format!("PLLOUT_SELECT_{port} {select} requires FEEDBACK_PATH PHASE_AND_DELAY"));
+            }
+        }
+        if feedback_path == "EXTERNAL" && prototype.extract_input(cell, "EXTFEEDBACK").is_undef() {
+            return Err("EXTFEEDBACK must be connected when FEEDBACK_PATH is EXTERNAL".into());
+        }
+        let filter_range = param_uint(prototype, cell, "FILTER_RANGE");
+        if !(1..=6).contains(&filter_range) {
+            return Err(format!("FILTER_RANGE must be between 1 and 6 (got {filter_range})"));
+        }
+        // the reference frequency is not known, but the PFD frequency must stay within its range for a reference
+        // clock of at most the maximum PFD frequency, and the VCO frequency must stay within its range for some
+        // PFD frequency; the feedback is taken after the DIVQ divider unless FEEDBACK_PATH is SIMPLE.
+        if PLL_PFD_RANGE.0 * (divr + 1) > PLL_PFD_RANGE.1 {
+            return Err(format!(
+                "DIVR {divr} divides the reference clock by {}, which puts the PFD frequency below {} MHz",
+                divr + 1,
+                PLL_PFD_RANGE.0
+            ));
+        }
+        let multiplier = if feedback_path == "SIMPLE" { divf + 1 } else { (divf + 1) << divq };
+        if PLL_PFD_RANGE.1 * multiplier < PLL_VCO_RANGE.0 || PLL_PFD_RANGE.0 * multiplier > PLL_VCO_RANGE.1 {
+            return Err(format!(
+                "DIVF {divf} multiplies the PFD frequency by {multiplier}, which puts the VCO frequency outside of \
+                 {}-{} MHz",
+                PLL_VCO_RANGE.0, PLL_VCO_RANGE.1
+            ));
+        }
+        Ok(())
+    }
+
+    /// Checks the constraints between the cells and IOs of a design that [`Self::check_cell`] cannot check.
+    ///
+    /// IOs are assigned to banks with `bank.<io name>` target options; IOs without a bank are not checked. All
+    /// `SB_IO` cells in a bank must use IO standards with the same VCCIO, and IO standards other than `SB_LVCMOS`
+    /// must be available in the bank.
+    pub fn check_design(&self, design: &Design) -> Result<(), String> {
+        let prototype = self.prototype(SB_IO).unwrap();
+        let mut bank_vccio: BTreeMap<u32, (&str, String, String)> = BTreeMap::new();
+        for cell_ref in design.iter_cells() {
+            let Cell::Target(cell) = &*cell_ref.get() else { continue };
+            if cell.kind != SB_IO {
+                continue;
+            }
+            let Some(pin) = extract_io(prototype, cell, "PACKAGE_PIN").iter().next() else { unreachable!() };
+            let Some((io_name, _)) = design.find_io(pin) else { continue };
+            let Some(bank) = self.io_bank(io_name) else { continue };
+            let io_standard = param_string(prototype, cell, "IO_STANDARD");
+            if let Some(device) = self.device()
+                && !matches!(io_standard, "SB_LVCMOS" | "OPEN_DRAIN")
+                && !device.has_special_io_standards(bank)
+            {
+                return Err(format!(
+                    "IO standard {io_standard} of IO {io_name:?} is not available in bank {bank} of {}",
+                    device.name()
+                ));
+            }
+            let Some(vccio) = io_standard_vccio(io_standard) else { continue };
+            match bank_vccio.get(&bank) {
+                None => {
+                    bank_vccio.insert(bank, (vccio, io_name.to_owned(), io_standard.to_owned()));
+                }
+                Some((other_vccio, other_io_name, other_io_standard)) if *other_vccio != vccio => {
+                    return Err(format!(
+                        "IO standard {io_standard} of IO {io_name:?} needs VCCIO {vccio}, but IO standard \
+                         {other_io_standard} of IO {other_io_name:?} in the same bank {bank} needs VCCIO {other_vccio}"
+                    ));
+                }
+                Some(_) => (),
+            }
+        }
+        Ok(())
+    }
+
+    fn check_mac16(&self, prototype: &TargetPrototype, cell: &TargetCell) -> Result<(), String> {
+        // in 8x8 mode the 16x16 multiplier is powered down, and nothing may select its output
+        if param_uint(prototype, cell, "MODE_8x8") == 1 {
+            for name in ["PIPELINE_16x16_MULT_REG1", "PIPELINE_16x16_MULT_REG2"] {
+                if param_uint(prototype, cell, name) != 0 {
+                    return Err(format!("{name} must be 0 when MODE_8x8 is set"));
+                }
+            }
+            for name in ["TOPOUTPUT_SELECT", "BOTOUTPUT_SELECT"] {
+                if param_uint(prototype, cell, name) == 0b11 {
+                    return Err(format!("{name} cannot select the 16x16 multiplier when MODE_8x8 is set"));
+                }
+            }
+            for name in ["TOPADDSUB_LOWERINPUT", "BOTADDSUB_LOWERINPUT"] {
+                if param_uint(prototype, cell, name) == 0b10 {
+                    return Err(format!("{name} cannot select the 16x16 multiplier when MODE_8x8 is set"));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn check_hfosc(&self, prototype: &TargetPrototype, cell: &TargetCell) -> Result<(), String> {
+        if param_uint(prototype, cell, "TRIM_EN") == 0
+            && !prototype.extract_input(cell, "TRIM").iter().all(|net| net.is_const())
+        {
+            return Err("TRIM is ignored unless TRIM_EN is set".into());
+        }
+        Ok(())
+    }
+
+    fn check_sysbus_ip(&self, prototype: &TargetPrototype, cell: &TargetCell) -> Result<(), String> {
+        // the upper nibble of the system bus address selects one of the two (left or right) hard IP instances
+        let bus_addr = param_uint(prototype, cell, "BUS_ADDR74");
+        let valid = match &cell.kind[..] {
+            "SB_I2C" => [0b0001, 0b0011],
+            "SB_SPI" => [0b0000, 0b0010],
+            _ => unreachable!(),
+        };
+        if !valid.contains(&bus_addr) {
+            return Err(format!(
+                "BUS_ADDR74 must be {:04b} or {:04b} for {} (got {bus_addr:04b})",
+                valid[0], valid[1], cell.kind
+            ));
+        }
+        Ok(())
+    }
+
+    fn check_rgb_drv(&self, prototype: &TargetPrototype, cell: &TargetCell) -> Result<(), String> {
+        // current settings are thermometer coded: each additional step enables one more current source
+        for name in ["RGB0_CURRENT", "RGB1_CURRENT", "RGB2_CURRENT"] {
+            let current = param_uint(prototype, cell, name);
+            if current & (current + 1) != 0 {
+                return Err(format!("{name} must be thermometer coded (0b000000, 0b000001, 0b000011, ...)"));
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,13 +1,13 @@
 use std::collections::BTreeMap;
 
-use prjunnamed_netlist::{assert_isomorphic, Target};
+use prjunnamed_netlist::{assert_isomorphic, Cell, Const, ParamValue, Target, Trit};
 
 use prjunnamed_siliconblue::SiliconBlueTarget;
 
 // I hate rustfmt.
 macro_rules! parse {
     ($source:expr) => {{
-        let target = SiliconBlueTarget::new(BTreeMap::new());
+        let target = SiliconBlueTarget::new(BTreeMap::new()).unwrap();
         let design = prjunnamed_netlist::parse(Some(target.clone()), $source).unwrap();
         (target, design)
     }};
@@ -52,3 +52,88 @@ fn test_export_lut4_carry() {
     "#};
     assert_isomorphic!(design, gold);
 }
+
+#[test]
+fn test_export_legacy_ram_roundtrip() {
+    for (kind, mode, exported_kind) in [
+        ("SB_RAM256x16", 0, "SB_RAM40_4K"),
+        ("SB_RAM512x8NW", 1, "SB_RAM40_4KNW"),
+        ("SB_RAM1024x4NR", 2, "SB_RAM40_4KNR"),
+        ("SB_RAM2048x2NRNW", 3, "SB_RAM40_4KNRNW"),
+    ] {
+        let (width, addr_width) = (16 >> mode, 8 + mode);
+        let word_bit = |word: usize, index: usize| (word * 7 + index).is_multiple_of(3);
+        let init = Vec::from_iter((0..0x1000).map(|bit| word_bit(bit / width, bit % width)));
+        let mut params = String::new();
+        for (index, chunk) in init.chunks(256).enumerate() {
+            let bits = String::from_iter(chunk.iter().rev().map(|&bit| if bit { '1' } else { '0' }));
+            params.push_str(&format!("param \"INIT_{index:X}\" = {bits}\n"));
+        }
+        let wclk = if kind.ends_with("NW") { "WCLKN" } else { "WCLK" };
+        let rclk = if kind.contains("NR") { "RCLKN" } else { "RCLK" };
+        let source = format!(
+            r#"
+            %0:{addr_width} = input "waddr"
+            %20:{width} = input "wdata"
+            %40:{addr_width} = input "raddr"
+            %60:1 = input "wclk"
+            %61:1 = input "rclk"
+            %70:_ = "{kind}" {{
+                {params}
+                input "WADDR" = %0:{addr_width}
+                input "WDATA" = %20:{width}
+                input "{wclk}" = %60
+                input "WCLKE" = 1
+                input "WE" = 1
+                input "RADDR" = %40:{addr_width}
+                input "{rclk}" = %61
+                input "RCLKE" = 1
+                input "RE" = 1
+                %70:{width} = output "RDATA"
+            }}
+            %100:0 = output "rdata" %70:{width}
+        "#
+        );
+        let (target, mut design) = parse!(&source);
+        target.import(&mut design).unwrap();
+        let (_, mut gold) = parse!(&source);
+        target.import(&mut gold).unwrap();
+
+        target.export(&mut design);
+        let data_bit = |index: usize| (index << mode) + [0, 0, 1, 3][mode];
+        let mut wdata = None;
+        let mut rdata = None;
+        for cell_ref in design.iter_cells() {
+            match &*cell_ref.get() {
+                Cell::Input(name, _) if name == "wdata" => wdata = Some(cell_ref.output()),
+                Cell::Output(name, value) if name == "rdata" => rdata = Some(value.clone()),
+                _ => (),
+            }
+        }
+        let (wdata, rdata) = (wdata.unwrap(), rdata.unwrap());
+        let cell_ref = design.iter_cells().find(|cell_ref| matches!(&*cell_ref.get(), Cell::Other(..))).unwrap();
+        let Cell::Other(instance) = &*cell_ref.get() else { unreachable!() };
+        assert_eq!(instance.kind, exported_kind);
+        assert_eq!(instance.params["READ_MODE"], ParamValue::Int(mode as i64));
+        let output = cell_ref.output();
+        for index in 0..width {
+            assert_eq!(instance.inputs["WDATA"][data_bit(index)], wdata[index]);
+            assert_eq!(output[instance.outputs["RDATA"].start + data_bit(index)], rdata[index]);
+        }
+        let mut physical = Const::new();
+        for index in 0..16 {
+            let ParamValue::Const(value) = &instance.params[&format!("INIT_{index:X}")] else { unreachable!() };
+            physical.extend(value.iter());
+        }
+        for word in 0..(0x1000 / width) {
+            let (row, lane) = (word & 0xff, word >> 8);
+            for index in 0..width {
+                let expected = if word_bit(word, index) { Trit::One } else { Trit::Zero };
+                assert_eq!(physical[row * 16 + (index << mode) + lane], expected, "{kind} word {word} bit {index}");
+            }
+        }
+
+        target.import(&mut design).unwrap();
+        assert_isomorphic!(design, gold);
+    }
+}
@@ -0,0 +1,252 @@
+use std::collections::BTreeMap;
+
+use prjunnamed_netlist::{assert_isomorphic, Target, TargetCellImportError};
+
+use prjunnamed_siliconblue::{SiliconBlueTarget, TargetOptionError};
+
+// I hate rustfmt.
+macro_rules! parse {
+    ($source:expr) => {{
+        let target = SiliconBlueTarget::new(BTreeMap::new()).unwrap();
+        let design = prjunnamed_netlist::parse(Some(target.clone()), $source).unwrap();
+        (target, design)
+    }};
+}
+
+#[test]
+fn test_import_hfosc() {
+    let (target, mut design) = parse! {r#"
+        %0:1 = input "t"
+        %1:_ = "SB_HFOSC" {
+            param "CLKHF_DIV" = "0b10"
+            param "TRIM_EN" = "0b1"
+            input "CLKHFPU" = 1
+            input "CLKHFEN" = 1
+            input "TRIM3" = %0
+            %1:1 = output "CLKHF"
+        }
+        %2:0 = output "clk" %1
+    "#};
+    target.import(&mut design).unwrap();
+    let (_, mut gold) = parse! {r#"
+        %0:1 = input "t"
+        %1:_ = target "SB_HFOSC" {
+            param "CLKHF_DIV" = 10
+            param "TRIM_EN" = 1
+            input "CLKHFPU" = 1
+            input "CLKHFEN" = 1
+            input "TRIM" = [ 0 0 0 0 0 0 %0 0 0 0 ]
+            %1:1 = output "CLKHF"
+        }
+        %2:0 = output "clk" %1
+    "#};
+    assert_isomorphic!(design, gold);
+}
+
+#[test]
+fn test_import_i2c_buses() {
+    let (target, mut design) = parse! {r#"
+        %0:2 = input "a"
+        %2:_ = "SB_I2C" {
+            input "SBCLKI" = %0+0
+            input "SBADRI1" = %0+1
+            %2:1 = output "SBDATO7"
+            %3:1 = output "SBACKO"
+            %4:1 = output "SBDATO0"
+        }
+        %5:0 = output "d" [ %2 %4 ]
+        %6:0 = output "ack" %3
+    "#};
+    target.import(&mut design).unwrap();
+    let (_, mut gold) = parse! {r#"
+        %0:2 = input "a"
+        %2:_ = target "SB_I2C" {
+            param "I2C_SLAVE_INIT_ADDR" = 1111100001
+            param "BUS_ADDR74" = 0001
+            param "SDA_INPUT_DELAYED" = 0
+            param "SDA_OUTPUT_DELAYED" = 0
+            input "SBCLKI" = %0+0
+            input "SBRWI" = 0
+            input "SBSTBI" = 0
+            input "SBADRI" = [ 0 0 0 0 0 0 %0+1 0 ]
+            input "SBDATI" = 00000000
+            input "SCLI" = X
+            input "SDAI" = X
+            %2:8 = output "SBDATO"
+            %10:1 = output "SBACKO"
+            %11:1 = output "I2CIRQ"
+            %12:1 = output "I2CWKUP"
+            %13:1 = output "SCLO"
+            %14:1 = output "SCLOE"
+            %15:1 = output "SDAO"
+            %16:1 = output "SDAOE"
+        }
+        %17:0 = output "d" [ %2+7 %2+0 ]
+        %18:0 = output "ack" %10
+    "#};
+    assert_isomorphic!(design, gold);
+}
+
+#[test]
+fn test_export_hfosc() {
+    let (target, mut design) = parse! {r#"
+        %0:1 = input "t"
+        %1:_ = target "SB_HFOSC" {
+            param "CLKHF_DIV" = 01
+            param "TRIM_EN" = 0
+            input "CLKHFPU" = 1
+            input "CLKHFEN" = %0
+            input "TRIM" = 0000000000
+            %1:1 = output "CLKHF"
+        }
+        %2:0 = output "clk" %1
+    "#};
+    target.export(&mut design);
+    let (_, mut gold) = parse! {r#"
+        %0:1 = input "t"
+        %1:_ = "SB_HFOSC" {
+            param "CLKHF_DIV" = "0b01"
+            param "TRIM_EN" = "0b0"
+            input "CLKHFPU" = 1
+            input "CLKHFEN" = %0
+            input "TRIM0" = 0
+            input "TRIM1" = 0
+            input "TRIM2" = 0
+            input "TRIM3" = 0
+            input "TRIM4" = 0
+            input "TRIM5" = 0
+            input "TRIM6" = 0
+            input "TRIM7" = 0
+            input "TRIM8" = 0
+            input "TRIM9" = 0
+            %1:1 = output "CLKHF"
+        }
+        %2:0 = output "clk" %1
+    "#};
+    assert_isomorphic!(design, gold);
+}
+
+#[test]
+fn test_device_option() {
+    let target = SiliconBlueTarget::new(BTreeMap::from([("device".to_owned(), "ice40up5k".to_owned())])).unwrap();
+    assert_eq!(target.options(), BTreeMap::from([("device".to_owned(), "iCE40UP5K".to_owned())]));
+    prjunnamed_netlist::parse(
+        Some(target),
+        r#"
+        %0:_ = target "SB_HFOSC" {
+            input "CLKHFPU" = 1
+            input "CLKHFEN" = 1
+            %0:1 = output "CLKHF"
+        }
+        %1:0 = output "clk" %0
+    "#,
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_invalid_options() {
+    let options = BTreeMap::from([("device".to_owned(), "iCE40HX9K".to_owned())]);
+    assert_eq!(SiliconBlueTarget::new(options).unwrap_err(), TargetOptionError::UnknownDevice("iCE40HX9K".into()));
+    let options = BTreeMap::from([("speed".to_owned(), "fast".to_owned())]);
+    assert_eq!(SiliconBlueTarget::new(options).unwrap_err(), TargetOptionError::UnknownOption("speed".into()));
+    let options = BTreeMap::from([("bank.led".to_owned(), "north".to_owned())]);
+    assert_eq!(
+        SiliconBlueTarget::new(options).unwrap_err(),
+        TargetOptionError::InvalidBank("led".into(), "north".into())
+    );
+    let options = BTreeMap::from([
+        ("device".to_owned(), "iCE40UP5K".to_owned()),
+        ("bank.led".to_owned(), "3".to_owned()),
+    ]);
+    assert_eq!(SiliconBlueTarget::new(options).unwrap_err(), TargetOptionError::InvalidBank("led".into(), "3".into()));
+}
+
+#[test]
+#[should_panic(expected = "SB_HFOSC is not available on iCE40HX8K")]
+fn test_device_unavailable_primitive() {
+    let target = SiliconBlueTarget::new(BTreeMap::from([("device".to_owned(), "iCE40HX8K".to_owned())])).unwrap();
+    prjunnamed_netlist::parse(
+        Some(target),
+        r#"
+        %0:_ = target "SB_HFOSC" {
+            input "CLKHFPU" = 1
+            input "CLKHFEN" = 1
+            %0:1 = output "CLKHF"
+        }
+        %1:0 = output "clk" %0
+    "#,
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_import_legacy_ram() {
+    // word 0x000 = 10, word 0x100 = 01
+    let init_0 = format!("{}10", "0".repeat(254));
+    let init_2 = format!("{}01", "0".repeat(254));
+    let (target, mut design) = parse!(&format!(
+        r#"
+        %0:11 = input "raddr"
+        %11:1 = input "clk"
+        %12:_ = "SB_RAM2048x2NR" {{
+            param "INIT_0" = {init_0}
+            param "INIT_2" = {init_2}
+            input "RADDR" = %0:11
+            input "RCLKN" = %11
+            input "RCLKE" = 1
+            input "RE" = 1
+            %12:2 = output "RDATA"
+        }}
+        %14:0 = output "d" %12:2
+    "#
+    ));
+    target.import(&mut design).unwrap();
+    let mut init = vec!['X'; 0x1000];
+    // INIT_0 covers words 0x000..0x080 (lane 0), INIT_2 covers words 0x100..0x180 (lane 1)
+    for row in 0..0x80 {
+        for bit in [0, 1, 8, 9] {
+            init[0x1000 - 1 - (row * 16 + bit)] = '0';
+        }
+    }
+    init[0x1000 - 1 - 8] = '1';
+    init[0x1000 - 1 - 1] = '1';
+    let init: String = init.into_iter().collect();
+    let (_, mut gold) = parse!(&format!(
+        r#"
+        %0:11 = input "raddr"
+        %11:1 = input "clk"
+        %12:_ = target "SB_RAM40_4K" {{
+            param "READ_MODE" = #3
+            param "WRITE_MODE" = #3
+            param "INIT" = {init}
+            param "IS_RCLK_INVERTED" = 1
+            input "RCLK" = %11
+            input "RCLKE" = 1
+            input "RE" = 1
+            input "RADDR" = %0:11
+            %12:16 = output "RDATA"
+        }}
+        %28:0 = output "d" [ %12+11 %12+3 ]
+    "#
+    ));
+    assert_isomorphic!(design, gold);
+}
+
+#[test]
+fn test_import_memory_async_write() {
+    let (target, mut design) = parse! {r#"
+        %0:4 = input "wd"
+        %4:8 = input "wa"
+        %12:1 = input "we"
+        %13:8 = input "ra"
+        %21:_ = memory depth=#256 width=#4 {
+            write addr=%4:8 data=%0:4 mask=%12*4
+            %21:4 = read addr=%13:8
+        }
+        %25:0 = output "rd" %21:4
+    "#};
+    let error = target.import(&mut design).unwrap_err();
+    assert!(matches!(error.cause, TargetCellImportError::Unsupported(_)));
+    assert!(error.to_string().ends_with(": asynchronous memory write ports are not supported by the target"));
+}
@@ -7,7 +7,7 @@ use prjunnamed_siliconblue::SiliconBlueTarget;
 // I hate rustfmt.
 macro_rules! parse {
     ($source:expr) => {{
-        let target = SiliconBlueTarget::new(BTreeMap::new());
+        let target = SiliconBlueTarget::new(BTreeMap::new()).unwrap();
         let design = prjunnamed_netlist::parse(Some(target.clone()), $source).unwrap();
         (target, design)
     }};
@@ -366,3 +366,42 @@ fn test_lower_ff_unmap_reset() {
     "#};
     assert_isomorphic!(design, gold);
 }
+
+#[test]
+fn test_lower_ff_counter_inv() {
+    let (target, mut design) = parse! {r#"
+        %0:1 = input "c"
+        %1:1 = input "r"
+        %2:3 = adc %5:2 01 0
+        %5:2 = dff %2+0:2 clk=%0 rst=%1,10 init=11
+        %7:0 = output "q" %5:2
+    "#};
+    target.lower_ffs(&mut design);
+    // the complement of the count is stored instead, so that no inverters are needed between the flip-flops and the adder
+    let (_, mut gold) = parse! {r#"
+        %0:1 = input "c"
+        %1:1 = input "r"
+        %2:3 = adc [ %6 %5 ] 10 1
+        %5:1 = target "SB_DFF" {
+            param "RESET_VALUE" = 1
+            param "IS_RESET_ASYNC" = 0
+            param "IS_C_INVERTED" = 0
+            input "D" = %2+0
+            input "C" = %0
+            input "R" = %1
+            input "E" = 1
+        }
+        %6:1 = target "SB_DFF" {
+            param "RESET_VALUE" = 0
+            param "IS_RESET_ASYNC" = 0
+            param "IS_C_INVERTED" = 0
+            input "D" = %2+1
+            input "C" = %0
+            input "R" = %1
+            input "E" = 1
+        }
+        %7:2 = not [ %6 %5 ]
+        %9:0 = output "q" %7:2
+    "#};
+    assert_isomorphic!(design, gold);
+}
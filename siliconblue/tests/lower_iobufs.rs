@@ -7,7 +7,7 @@ use prjunnamed_siliconblue::SiliconBlueTarget;
 // I hate rustfmt.
 macro_rules! parse {
     ($source:expr) => {{
-        let target = SiliconBlueTarget::new(BTreeMap::new());
+        let target = SiliconBlueTarget::new(BTreeMap::new()).unwrap();
         let design = prjunnamed_netlist::parse(Some(target.clone()), $source).unwrap();
         (target, design)
     }};
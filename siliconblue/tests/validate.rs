@@ -0,0 +1,356 @@
+use std::collections::BTreeMap;
+
+use prjunnamed_siliconblue::SiliconBlueTarget;
+
+fn parse(device: Option<&str>, source: &str) {
+    parse_with_banks(device, &[], source);
+}
+
+fn parse_with_banks(device: Option<&str>, banks: &[(&str, u32)], source: &str) {
+    let mut options = BTreeMap::from_iter(device.map(|device| ("device".to_owned(), device.to_owned())));
+    options.extend(banks.iter().map(|(io_name, bank)| (format!("bank.{io_name}"), bank.to_string())));
+    prjunnamed_netlist::parse(Some(SiliconBlueTarget::new(options).unwrap()), source).unwrap();
+}
+
+#[test]
+fn test_valid_io() {
+    parse(
+        None,
+        r#"
+        &"pad":1 = io
+        %0:_ = target "SB_IO" {
+            param "PIN_TYPE" = 011001
+            param "PULLUP" = 1
+            io "PACKAGE_PIN" = &"pad"
+            %0:1 = output "D_IN_0"
+            %1:1 = output "D_IN_1"
+            %2:1 = output "GLOBAL_BUFFER_OUTPUT"
+        }
+        %3:0 = output "d" %0
+    "#,
+    );
+}
+
+#[test]
+#[should_panic(expected = "invalid SB_IO cell: IO standard SB_LVDS_INPUT is input-only")]
+fn test_io_lvds_output() {
+    parse(
+        None,
+        r#"
+        &"pad_p":1 = io
+        &"pad_n":1 = io
+        %0:_ = target "SB_IO" {
+            param "PIN_TYPE" = 011001
+            param "IO_STANDARD" = "SB_LVDS_INPUT"
+            input "D_OUT_0" = 0
+            io "PACKAGE_PIN" = &"pad_p"
+            io "PACKAGE_PIN_B" = &"pad_n"
+            %0:1 = output "D_IN_0"
+            %1:1 = output "D_IN_1"
+            %2:1 = output "GLOBAL_BUFFER_OUTPUT"
+        }
+    "#,
+    );
+}
+
+#[test]
+#[should_panic(expected = "PACKAGE_PIN_B must be unconnected")]
+fn test_io_package_pin_b() {
+    parse(
+        None,
+        r#"
+        &"pad_p":1 = io
+        &"pad_n":1 = io
+        %0:_ = target "SB_IO" {
+            param "PIN_TYPE" = 000001
+            io "PACKAGE_PIN" = &"pad_p"
+            io "PACKAGE_PIN_B" = &"pad_n"
+            %0:1 = output "D_IN_0"
+            %1:1 = output "D_IN_1"
+            %2:1 = output "GLOBAL_BUFFER_OUTPUT"
+        }
+    "#,
+    );
+}
+
+#[test]
+#[should_panic(expected = "IO standard OPEN_DRAIN is not available on iCE40HX1K")]
+fn test_io_open_drain_unavailable() {
+    parse(
+        Some("iCE40HX1K"),
+        r#"
+        &"pad":1 = io
+        %0:_ = target "SB_IO" {
+            param "PIN_TYPE" = 101001
+            param "IO_STANDARD" = "OPEN_DRAIN"
+            io "PACKAGE_PIN" = &"pad"
+            %0:1 = output "D_IN_0"
+            %1:1 = output "D_IN_1"
+            %2:1 = output "GLOBAL_BUFFER_OUTPUT"
+        }
+    "#,
+    );
+}
+
+#[test]
+#[should_panic(expected = "READ_MODE must be 0 on iCE65")]
+fn test_ram_mode_ice65() {
+    parse(
+        Some("iCE65L04"),
+        r#"
+        %0:_ = target "SB_RAM40_4K" {
+            param "READ_MODE" = #1
+            param "WRITE_MODE" = #0
+            input "RCLK" = 0
+            %0:16 = output "RDATA"
+        }
+    "#,
+    );
+}
+
+const IO_BANK_SOURCE: &str = r#"
+        &"a":1 = io
+        &"b":1 = io
+        %0:_ = target "SB_IO" {
+            param "PIN_TYPE" = 011001
+            param "IO_STANDARD" = "SB_LVCMOS33_8"
+            input "D_OUT_0" = 0
+            io "PACKAGE_PIN" = &"a"
+            %0:1 = output "D_IN_0"
+            %1:1 = output "D_IN_1"
+            %2:1 = output "GLOBAL_BUFFER_OUTPUT"
+        }
+        %3:_ = target "SB_IO" {
+            param "PIN_TYPE" = 011001
+            param "IO_STANDARD" = "SB_LVCMOS18_2"
+            input "D_OUT_0" = 0
+            io "PACKAGE_PIN" = &"b"
+            %3:1 = output "D_IN_0"
+            %4:1 = output "D_IN_1"
+            %5:1 = output "GLOBAL_BUFFER_OUTPUT"
+        }
+    "#;
+
+#[test]
+fn test_io_bank_vccio_separate() {
+    // the IO standards are compatible if the IOs are in different banks, or if the banks are not known
+    parse(Some("iCE65L04"), IO_BANK_SOURCE);
+    parse_with_banks(Some("iCE65L04"), &[("a", 3)], IO_BANK_SOURCE);
+}
+
+#[test]
+#[should_panic(expected = "invalid design: IO standard SB_LVCMOS18_2 of IO \"b\" needs VCCIO 1.8V, but IO standard \
+                           SB_LVCMOS33_8 of IO \"a\" in the same bank 3 needs VCCIO 3.3V")]
+fn test_io_bank_vccio() {
+    parse_with_banks(Some("iCE65L04"), &[("a", 3), ("b", 3)], IO_BANK_SOURCE);
+}
+
+#[test]
+#[should_panic(expected = "invalid design: IO standard SB_LVDS_INPUT of IO \"pad_p\" is not available in bank 0 of iCE40HX8K")]
+fn test_io_bank_lvds() {
+    parse_with_banks(
+        Some("iCE40HX8K"),
+        &[("pad_p", 0)],
+        r#"
+        &"pad_p":1 = io
+        &"pad_n":1 = io
+        %0:_ = target "SB_IO" {
+            param "PIN_TYPE" = 000001
+            param "IO_STANDARD" = "SB_LVDS_INPUT"
+            io "PACKAGE_PIN" = &"pad_p"
+            io "PACKAGE_PIN_B" = &"pad_n"
+            %0:1 = output "D_IN_0"
+            %1:1 = output "D_IN_1"
+            %2:1 = output "GLOBAL_BUFFER_OUTPUT"
+        }
+    "#,
+    );
+}
+
+#[test]
+#[should_panic(expected = "WDATA[0] is not used in WRITE_MODE 3 and must be constant")]
+fn test_ram_unused_data() {
+    parse(
+        None,
+        r#"
+        %0:16 = input "wdata"
+        %16:_ = target "SB_RAM40_4K" {
+            param "READ_MODE" = #3
+            param "WRITE_MODE" = #3
+            input "RCLK" = 0
+            input "WCLK" = 0
+            input "WDATA" = %0:16
+            %16:16 = output "RDATA"
+        }
+    "#,
+    );
+}
+
+#[test]
+#[should_panic(expected = "MASK is only used in WRITE_MODE 0 and must be constant")]
+fn test_ram_unused_mask() {
+    parse(
+        None,
+        r#"
+        %0:16 = input "mask"
+        %16:_ = target "SB_RAM40_4K" {
+            param "READ_MODE" = #1
+            param "WRITE_MODE" = #1
+            input "RCLK" = 0
+            input "WCLK" = 0
+            input "MASK" = %0:16
+            %16:16 = output "RDATA"
+        }
+    "#,
+    );
+}
+
+#[test]
+#[should_panic(expected = "RADDR[9] is not used in READ_MODE 1 and must be constant")]
+fn test_ram_unused_addr() {
+    parse(
+        None,
+        r#"
+        %0:11 = input "raddr"
+        %11:_ = target "SB_RAM40_4K" {
+            param "READ_MODE" = #1
+            param "WRITE_MODE" = #1
+            input "RCLK" = 0
+            input "RADDR" = %0:11
+            %11:16 = output "RDATA"
+        }
+    "#,
+    );
+}
+
+fn pll(divr: &str, divf: &str, filter_range: &str) -> String {
+    format!(
+        r#"
+        %0:1 = input "clk"
+        %1:_ = target "SB_PLL40" {{
+            param "MODE" = "SB_PLL40_CORE"
+            param "DIVR" = {divr}
+            param "DIVF" = {divf}
+            param "DIVQ" = 100
+            param "FILTER_RANGE" = {filter_range}
+            input "REFERENCECLK" = %0
+            %1:1 = output "PLLOUTCOREA"
+            %2:1 = output "PLLOUTGLOBALA"
+            %3:1 = output "PLLOUTCOREB"
+            %4:1 = output "PLLOUTGLOBALB"
+            %5:1 = output "LOCK"
+            %6:1 = output "SDO"
+        }}
+    "#
+    )
+}
+
+#[test]
+fn test_valid_pll() {
+    // 12 MHz to 48 MHz
+    parse(None, &pll("0000", "0111111", "001"));
+}
+
+#[test]
+#[should_panic(expected = "FILTER_RANGE must be between 1 and 6 (got 7)")]
+fn test_pll_filter_range() {
+    parse(None, &pll("0000", "0111111", "111"));
+}
+
+#[test]
+#[should_panic(expected = "DIVR 13 divides the reference clock by 14, which puts the PFD frequency below 10 MHz")]
+fn test_pll_divr_range() {
+    parse(None, &pll("1101", "0111111", "001"));
+}
+
+#[test]
+#[should_panic(expected = "DIVF 1 multiplies the PFD frequency by 2, which puts the VCO frequency outside of 533-1066 MHz")]
+fn test_pll_divf_range() {
+    parse(None, &pll("0000", "0000001", "001"));
+}
+
+#[test]
+#[should_panic(expected = "DIVQ must be between 1 and 6 (got 7)")]
+fn test_pll_divq_range() {
+    parse(
+        None,
+        r#"
+        %0:1 = input "clk"
+        %1:_ = target "SB_PLL40" {
+            param "MODE" = "SB_PLL40_CORE"
+            param "DIVQ" = 111
+            input "REFERENCECLK" = %0
+            %1:1 = output "PLLOUTCOREA"
+            %2:1 = output "PLLOUTGLOBALA"
+            %3:1 = output "PLLOUTCOREB"
+            %4:1 = output "PLLOUTGLOBALB"
+            %5:1 = output "LOCK"
+            %6:1 = output "SDO"
+        }
+    "#,
+    );
+}
+
+#[test]
+#[should_panic(expected = "PLLOUT_SELECT_PORTA SHIFTREG_90deg requires FEEDBACK_PATH PHASE_AND_DELAY")]
+fn test_pll_shiftreg_feedback() {
+    parse(
+        None,
+        r#"
+        %0:1 = input "clk"
+        %1:_ = target "SB_PLL40" {
+            param "MODE" = "SB_PLL40_CORE"
+            param "DIVQ" = 100
+            param "PLLOUT_SELECT_PORTA" = "SHIFTREG_90deg"
+            input "REFERENCECLK" = %0
+            %1:1 = output "PLLOUTCOREA"
+            %2:1 = output "PLLOUTGLOBALA"
+            %3:1 = output "PLLOUTCOREB"
+            %4:1 = output "PLLOUTGLOBALB"
+            %5:1 = output "LOCK"
+            %6:1 = output "SDO"
+        }
+    "#,
+    );
+}
+
+#[test]
+#[should_panic(expected = "input I[3] must be connected to CI or undefined when IS_I3_CI is set")]
+fn test_lut4_carry_i3_ci() {
+    parse(
+        None,
+        r#"
+        %0:4 = input "i"
+        %4:1 = input "ci"
+        %5:_ = target "SB_LUT4_CARRY" {
+            param "LUT_INIT" = 0110100110010110
+            param "IS_I3_CI" = 1
+            input "I" = %0:4
+            input "CI" = %4
+            %5:1 = output "O"
+            %6:1 = output "CO"
+        }
+    "#,
+    );
+}
+
+#[test]
+#[should_panic(expected = "BUS_ADDR74 must be 0001 or 0011 for SB_I2C (got 0010)")]
+fn test_i2c_bus_addr() {
+    parse(
+        None,
+        r#"
+        %0:_ = target "SB_I2C" {
+            param "BUS_ADDR74" = 0010
+            %0:8 = output "SBDATO"
+            %8:1 = output "SBACKO"
+            %9:1 = output "I2CIRQ"
+            %10:1 = output "I2CWKUP"
+            %11:1 = output "SCLO"
+            %12:1 = output "SCLOE"
+            %13:1 = output "SDAO"
+            %14:1 = output "SDAOE"
+        }
+    "#,
+    );
+}
@@ -3,7 +3,8 @@ use std::{cell::RefCell, collections::BTreeMap, io::Write};
 
 use crate::yosys::{self, CellDetails, MetadataValue, NetDetails, PortDetails};
 use prjunnamed_netlist::{
-    Cell, Const, ControlNet, Design, IoNet, IoValue, MemoryPortRelation, MetaItem, MetaItemRef, Net, Trit, Value,
+    lower_clz, lower_ctz, lower_popcnt, Cell, Const, ControlNet, Design, IoNet, IoValue, MemoryPortRelation, MetaItem,
+    MetaItemRef, Net, Trit, Value,
 };
 
 struct Counter(usize);
@@ -116,14 +117,21 @@ fn export_module(mut design: Design) -> yosys::Module {
     let indexer = NetlistIndexer::new();
     let mut ys_module = yosys::Module::new();
 
-    // Yosys IR cannot express DFFs with both asynchronous and synchronous reset.
     for cell_ref in design.iter_cells() {
-        if let Cell::Dff(flip_flop) = &*cell_ref.get() {
-            if flip_flop.has_clear() && flip_flop.has_reset() {
+        let _guard = design.use_metadata_from(&[cell_ref]);
+        let add_cell = |cell| design.add_cell(cell);
+        match &*cell_ref.get() {
+            // Yosys IR cannot express DFFs with both asynchronous and synchronous reset.
+            Cell::Dff(flip_flop) if flip_flop.has_clear() && flip_flop.has_reset() => {
                 let mut flip_flop = flip_flop.clone();
                 flip_flop.unmap_reset(&design);
                 cell_ref.replace(Cell::Dff(flip_flop));
             }
+            // Yosys has no cells counting bits.
+            Cell::Popcnt(arg) => cell_ref.replace(Cell::Buf(lower_popcnt(&add_cell, arg))),
+            Cell::Clz(arg) => cell_ref.replace(Cell::Buf(lower_clz(&add_cell, arg))),
+            Cell::Ctz(arg) => cell_ref.replace(Cell::Buf(lower_ctz(&add_cell, arg))),
+            _ => (),
         }
     }
     design.apply();
@@ -295,6 +303,8 @@ fn export_module(mut design: Design) -> yosys::Module {
             Cell::SShr(arg1, arg2, stride) => ys_cell_shift(&mut ys_module, "$sshr", arg1, arg2, *stride, true),
             Cell::XShr(arg1, arg2, stride) => ys_cell_shift(&mut ys_module, "$shiftx", arg1, arg2, *stride, false),
 
+            Cell::Popcnt(..) | Cell::Clz(..) | Cell::Ctz(..) => unreachable!("count cells are lowered above"),
+
             Cell::Mul(arg1, arg2) => ys_cell_binary(&mut ys_module, "$mul", arg1, arg2, false),
             Cell::UDiv(arg1, arg2) => ys_cell_binary(&mut ys_module, "$div", arg1, arg2, false),
             Cell::UMod(arg1, arg2) => ys_cell_binary(&mut ys_module, "$mod", arg1, arg2, false),
@@ -371,6 +381,7 @@ fn export_module(mut design: Design) -> yosys::Module {
                     );
 
                 let mut wr_ports = 0;
+                let mut wr_clk_enable = Const::new();
                 let mut wr_clk_polarity = Const::new();
                 let mut wr_wide_continuation = Const::new();
                 let mut wr_addr = Value::new();
@@ -382,9 +393,16 @@ fn export_module(mut design: Design) -> yosys::Module {
                     let wide_log2 = port.wide_log2(memory);
                     for index in 0..(1 << wide_log2) {
                         let addr = Value::from(Const::from_uint(index, wide_log2)).concat(&port.addr).zext(abits);
-                        wr_clk.extend([port.clock.net()]);
                         wr_addr.extend(&addr);
-                        wr_clk_polarity.push(port.clock.is_positive());
+                        if let Some(clock) = port.clock {
+                            wr_clk.extend([clock.net()]);
+                            wr_clk_enable.push(true);
+                            wr_clk_polarity.push(clock.is_positive());
+                        } else {
+                            wr_clk.extend([Net::UNDEF]);
+                            wr_clk_enable.push(false);
+                            wr_clk_polarity.push(Trit::Undef);
+                        }
                         wr_wide_continuation.push(index != 0);
                         write_port_indices.push(port_index);
                     }
@@ -393,6 +411,7 @@ fn export_module(mut design: Design) -> yosys::Module {
                     wr_ports += 1 << wide_log2;
                 }
                 if wr_ports == 0 {
+                    wr_clk_enable.push(false);
                     wr_clk_polarity.push(false);
                     wr_wide_continuation.push(false);
                 }
@@ -423,7 +442,8 @@ fn export_module(mut design: Design) -> yosys::Module {
                             rd_clk_polarity.push(flip_flop.clock.is_positive());
                             rd_ce_over_srst.push(!flip_flop.reset_over_enable);
                             for &write_port_index in &write_port_indices {
-                                let (trans, col_x) = if flip_flop.clock == memory.write_ports[write_port_index].clock {
+                                let write_clock = memory.write_ports[write_port_index].clock;
+                                let (trans, col_x) = if write_clock == Some(flip_flop.clock) {
                                     match flip_flop.relations[write_port_index] {
                                         MemoryPortRelation::Undefined => (false, true),
                                         MemoryPortRelation::ReadBeforeWrite => (false, false),
@@ -499,7 +519,7 @@ fn export_module(mut design: Design) -> yosys::Module {
                     .param("ABITS", abits)
                     .param("INIT", memory.init_value.clone())
                     .param("WR_PORTS", wr_ports)
-                    .param("WR_CLK_ENABLE", Const::ones(wr_ports.max(1)))
+                    .param("WR_CLK_ENABLE", wr_clk_enable)
                     .param("WR_CLK_POLARITY", wr_clk_polarity)
                     .param("WR_PRIORITY_MASK", Const::zero(wr_ports.max(1)))
                     .param("WR_WIDE_CONTINUATION", wr_wide_continuation)
@@ -632,7 +632,6 @@ impl ModuleImporter<'_> {
                 let mut wr_port_indices = vec![];
                 if wr_ports != 0 {
                     assert!(wr_priority_mask.iter().all(|x| x == Trit::Zero));
-                    assert!(wr_clk_enable.iter().all(|x| x == Trit::One));
                 }
                 let mut index = 0;
                 while index < wr_ports {
@@ -644,10 +643,12 @@ impl ModuleImporter<'_> {
                     let wide_log2 = wide.ilog2() as usize;
                     assert!(wide.is_power_of_two());
                     wr_port_indices.push(index);
-                    let clock = if wr_clk_polarity[index] == Trit::One {
-                        ControlNet::Pos(wr_clk[index])
+                    let clock = if wr_clk_enable[index] != Trit::One {
+                        None
+                    } else if wr_clk_polarity[index] == Trit::One {
+                        Some(ControlNet::Pos(wr_clk[index]))
                     } else {
-                        ControlNet::Neg(wr_clk[index])
+                        Some(ControlNet::Neg(wr_clk[index]))
                     };
                     let addr = wr_addr.slice(index * abits..(index + 1) * abits).slice(wide_log2..);
                     let data = wr_data.slice(index * width..end_index * width);
@@ -689,7 +690,7 @@ impl ModuleImporter<'_> {
                         let mut relations = vec![];
                         for (new_index, &orig_index) in wr_port_indices.iter().enumerate() {
                             let mask_index = index * wr_ports + orig_index;
-                            let relation = if memory.write_ports[new_index].clock != clock
+                            let relation = if memory.write_ports[new_index].clock != Some(clock)
                                 || rd_collision_x_mask[mask_index] == Trit::One
                             {
                                 MemoryPortRelation::Undefined
@@ -760,6 +761,7 @@ impl ModuleImporter<'_> {
                     let val = match val {
                         yosys::MetadataValue::Const(val) => ParamValue::Const(val.clone()),
                         yosys::MetadataValue::String(val) => ParamValue::String(val.clone()),
+                        yosys::MetadataValue::Float(val) => ParamValue::from(*val),
                     };
                     parameters.insert(name.clone(), val);
                 }
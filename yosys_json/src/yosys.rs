@@ -131,6 +131,7 @@ impl From<BitVector> for JsonValue {
 pub enum MetadataValue {
     String(String),
     Const(Const),
+    Float(f64),
 }
 
 impl MetadataValue {
@@ -231,9 +232,7 @@ impl From<ParamValue> for MetadataValue {
             ParamValue::Const(value) => value.into(),
             ParamValue::Int(value) => value.into(),
             ParamValue::String(value) => value.into(),
-            ParamValue::Float(_) => {
-                unimplemented!("Yosys JSON does not define serialization for floating point parameters")
-            }
+            ParamValue::Float(value) => MetadataValue::Float(f64::from_bits(value)),
         }
     }
 }
@@ -244,6 +243,10 @@ impl From<&ParamValue> for MetadataValue {
     }
 }
 
+// Yosys JSON does not distinguish real values from strings; they are serialized as their shortest round-tripping
+// decimal representation prefixed with this marker.
+const REAL_MARKER: &str = "$real:";
+
 enum MetadataValueClass {
     Bits,
     BitsAndSpaces,
@@ -285,7 +288,10 @@ impl TryFrom<JsonValue> for MetadataValue {
                         .into(),
                 ),
                 MetadataValueClass::BitsAndSpaces => MetadataValue::String(value.strip_suffix(" ").unwrap().to_owned()),
-                MetadataValueClass::Other => MetadataValue::String(value.to_owned()),
+                MetadataValueClass::Other => match value.strip_prefix(REAL_MARKER).map(str::parse) {
+                    Some(Ok(value)) => MetadataValue::Float(value),
+                    _ => MetadataValue::String(value.to_owned()),
+                },
             })
         } else {
             Err(SyntaxError(value))
@@ -311,6 +317,7 @@ impl From<MetadataValue> for JsonValue {
                 })
                 .collect::<String>()
                 .into(),
+            MetadataValue::Float(value) => format!("{REAL_MARKER}{value:?}").into(),
         }
     }
 }